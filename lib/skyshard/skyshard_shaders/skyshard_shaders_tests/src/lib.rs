@@ -29,4 +29,35 @@ mod test {
         let vertex_shader = vs::shader();
         let fragment_shader = fs::shader();
     }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, skyshard_shaders::HasVertexLayout)]
+    struct SkinnedVertex {
+        position: [f32; 3],
+        normal: [f32; 3],
+        #[vertex(tex_coord = 1)]
+        lightmap_uv: [f32; 2],
+        joints: [u8; 4],
+        #[vertex(joint_weights, normalized)]
+        influences: [u8; 4],
+        #[vertex(skip)]
+        padding: u32,
+    }
+
+    #[test]
+    fn test_derive_vertex_layout() {
+
+        use skyshard::graphics::{HasVertexLayout, VertexFormat, VertexSemantic};
+
+        let layout = SkinnedVertex::vertex_layout();
+        let attributes = layout.attributes();
+
+        assert_eq!(layout.stride(), 44);
+        assert_eq!(attributes.len(), 5);
+        assert_eq!(attributes[2].semantic, VertexSemantic::TexCoord(1));
+        assert_eq!(attributes[2].offset, 24);
+        assert_eq!(attributes[3].format, VertexFormat::UByte4);
+        assert_eq!(attributes[4].semantic, VertexSemantic::JointWeights);
+        assert_eq!(attributes[4].format, VertexFormat::UByte4Norm);
+    }
 }
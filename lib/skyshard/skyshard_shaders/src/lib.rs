@@ -1,3 +1,5 @@
+mod vertex;
+
 use proc_macro::TokenStream;
 
 use proc_macro2::{Ident, Literal};
@@ -28,6 +30,18 @@ pub fn shader(input: TokenStream) -> TokenStream {
     tokens.into()
 }
 
+/// Derives `skyshard::graphics::HasVertexLayout` for a `#[repr(C)]` struct, see `vertex.rs` for
+/// the supported field attributes.
+#[proc_macro_derive(HasVertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+
+    let input = ::syn::parse_macro_input!(input as ::syn::DeriveInput);
+
+    vertex::derive_vertex_layout(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
 fn quote_shader_fn(shader: &ShaderSource) -> ::proc_macro2::TokenStream {
     match shader.kind {
         ::shaderc::ShaderKind::Vertex => {
@@ -51,6 +65,13 @@ fn quote_shader_fn(shader: &ShaderSource) -> ::proc_macro2::TokenStream {
                 }
             }
         }
+        ::shaderc::ShaderKind::Compute => {
+            quote! {
+                pub fn shader() -> skyshard::graphics::vulkan::shaders::ComputeShaderBinary {
+                    <skyshard::graphics::vulkan::shaders::ComputeShaderBinary>::new(&bytes)
+                }
+            }
+        }
         _ => panic!("unsupported shader type")
     }
 }
@@ -105,6 +126,7 @@ fn resolve_shader_kind(kind: &str) -> ::shaderc::ShaderKind {
         "Geometry" => ::shaderc::ShaderKind::Geometry,
         "TessControl" => ::shaderc::ShaderKind::TessControl,
         "TessEvaluation" => ::shaderc::ShaderKind::TessEvaluation,
+        "Compute" => ::shaderc::ShaderKind::Compute,
         _ => panic!("Unknown shader kind") // TODO: Don't panic
     }
 }
@@ -0,0 +1,195 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Data, DeriveInput, Expr, Field, Fields, Lit, LitInt, Type};
+
+/// Generates an implementation of `skyshard::graphics::HasVertexLayout` for a `#[repr(C)]` struct
+/// with named fields.
+///
+/// The semantic of a field is taken from a `#[vertex(...)]` attribute or derived from its name:
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy, HasVertexLayout)]
+/// struct SkinnedVertex {
+///     position: [f32; 3],
+///     normal: [f32; 3],
+///     #[vertex(tex_coord = 1)]
+///     lightmap_uv: [f32; 2],
+///     #[vertex(joint_weights, normalized)]
+///     weights: [u8; 4],
+///     #[vertex(skip)]
+///     padding: u32,
+/// }
+/// ```
+pub fn derive_vertex_layout(input: DeriveInput) -> syn::Result<TokenStream> {
+
+    let name = &input.ident;
+
+    let mut has_repr_c = false;
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            has_repr_c |= meta.path.is_ident("C");
+            // skip the arguments of e.g. align(16)
+            if meta.input.peek(::syn::token::Paren) {
+                let _content;
+                ::syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+
+    if !has_repr_c {
+        return Err(syn::Error::new_spanned(name, "HasVertexLayout requires #[repr(C)]"));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(name, "HasVertexLayout requires named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(name, "HasVertexLayout can only be derived for structs")),
+    };
+
+    let attributes = fields.iter()
+        .map(vertex_attribute)
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten();
+
+    Ok(quote! {
+        impl skyshard::graphics::HasVertexLayout for #name {
+            fn vertex_layout() -> skyshard::graphics::VertexLayout {
+                skyshard::graphics::VertexLayout::new(::std::mem::size_of::<#name>() as u32, vec![
+                    #(#attributes),*
+                ])
+            }
+        }
+    })
+}
+
+/// Options of a field given by its `#[vertex(...)]` attribute.
+#[derive(Default)]
+struct FieldOptions {
+    semantic: Option<TokenStream>,
+    normalized: bool,
+    skip: bool,
+}
+
+fn vertex_attribute(field: &Field) -> syn::Result<Option<TokenStream>> {
+
+    let ident = field.ident.as_ref().expect("named field");
+    let mut options = FieldOptions::default();
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            let semantic = |variant: &str| {
+                let variant = ::proc_macro2::Ident::new(variant, ::proc_macro2::Span::call_site());
+                Some(quote!(skyshard::graphics::VertexSemantic::#variant))
+            };
+            if meta.path.is_ident("skip") {
+                options.skip = true;
+            }
+            else if meta.path.is_ident("normalized") {
+                options.normalized = true;
+            }
+            else if meta.path.is_ident("position") {
+                options.semantic = semantic("Position");
+            }
+            else if meta.path.is_ident("normal") {
+                options.semantic = semantic("Normal");
+            }
+            else if meta.path.is_ident("tangent") {
+                options.semantic = semantic("Tangent");
+            }
+            else if meta.path.is_ident("color") {
+                options.semantic = semantic("Color");
+            }
+            else if meta.path.is_ident("joint_indices") {
+                options.semantic = semantic("JointIndices");
+            }
+            else if meta.path.is_ident("joint_weights") {
+                options.semantic = semantic("JointWeights");
+            }
+            else if meta.path.is_ident("tex_coord") {
+                let literal = meta.value()?.parse::<LitInt>()?;
+                let set = literal.base10_parse::<u8>()?;
+                if set > ::skyshard::graphics::MAX_TEX_COORD_SET {
+                    return Err(syn::Error::new_spanned(literal, format!("texture coordinate set {set} exceeds the maximum of {}", ::skyshard::graphics::MAX_TEX_COORD_SET)));
+                }
+                options.semantic = Some(quote!(skyshard::graphics::VertexSemantic::TexCoord(#set)));
+            }
+            else {
+                return Err(meta.error("unknown vertex attribute option"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if options.skip {
+        return Ok(None)
+    }
+
+    let semantic = match options.semantic {
+        Some(semantic) => semantic,
+        None => semantic_of_name(&ident.to_string())
+            .ok_or_else(|| syn::Error::new_spanned(ident, "unknown vertex semantic, add #[vertex(<semantic>)] or #[vertex(skip)]"))?,
+    };
+
+    let format = format_of_type(&field.ty, options.normalized)
+        .ok_or_else(|| syn::Error::new_spanned(&field.ty, "unsupported vertex attribute type"))?;
+    let format = ::proc_macro2::Ident::new(format, ::proc_macro2::Span::call_site());
+
+    Ok(Some(quote! {
+        skyshard::graphics::VertexAttribute {
+            semantic: #semantic,
+            format: skyshard::graphics::VertexFormat::#format,
+            offset: ::std::mem::offset_of!(Self, #ident) as u32,
+        }
+    }))
+}
+
+fn semantic_of_name(name: &str) -> Option<TokenStream> {
+    match name {
+        "position" => Some(quote!(skyshard::graphics::VertexSemantic::Position)),
+        "normal" => Some(quote!(skyshard::graphics::VertexSemantic::Normal)),
+        "tangent" => Some(quote!(skyshard::graphics::VertexSemantic::Tangent)),
+        "color" => Some(quote!(skyshard::graphics::VertexSemantic::Color)),
+        "uv" | "tex_coord" => Some(quote!(skyshard::graphics::VertexSemantic::TexCoord(0))),
+        "joints" | "joint_indices" => Some(quote!(skyshard::graphics::VertexSemantic::JointIndices)),
+        "weights" | "joint_weights" => Some(quote!(skyshard::graphics::VertexSemantic::JointWeights)),
+        _ => None,
+    }
+}
+
+fn format_of_type(ty: &Type, normalized: bool) -> Option<&'static str> {
+
+    let (element, length) = match ty {
+        Type::Array(array) => {
+            let length = match &array.len {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Int(length) => length.base10_parse::<usize>().ok()?,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            (array.elem.to_token_stream().to_string(), length)
+        }
+        ty => (ty.to_token_stream().to_string(), 1),
+    };
+
+    match (element.as_str(), length, normalized) {
+        ("f32", 1, false) => Some("Float"),
+        ("f32", 2, false) => Some("Float2"),
+        ("f32", 3, false) => Some("Float3"),
+        ("f32", 4, false) => Some("Float4"),
+        ("u32", 1, false) => Some("UInt"),
+        ("u32", 2, false) => Some("UInt2"),
+        ("u32", 3, false) => Some("UInt3"),
+        ("u32", 4, false) => Some("UInt4"),
+        ("u8", 4, false) => Some("UByte4"),
+        ("u8", 4, true) => Some("UByte4Norm"),
+        ("u16", 4, false) => Some("UShort4"),
+        _ => None,
+    }
+}
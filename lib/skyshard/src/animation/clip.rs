@@ -0,0 +1,286 @@
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+
+use crate::animation::Transform;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Holds the value of a keyframe until the next one.
+    Step,
+    /// Interpolates linearly, rotations along the shortest arc.
+    Linear,
+    /// Interpolates with a cubic Hermite spline. Every keyframe stores three values: the incoming
+    /// tangent, the value and the outgoing tangent, as in glTF.
+    CubicSpline,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelValues {
+    Translation(Vec<Vector3<f32>>),
+    /// The rotations as quaternions, which are normalized after interpolation. The tangents of a
+    /// cubic spline are not unit quaternions.
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+}
+
+impl ChannelValues {
+
+    pub fn len(&self) -> usize {
+        match self {
+            ChannelValues::Translation(values) => values.len(),
+            ChannelValues::Rotation(values) => values.len(),
+            ChannelValues::Scale(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The keyframes of one property of a transform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    /// The index of the animated transform, e.g. a joint of a skeleton.
+    pub target: usize,
+    pub interpolation: Interpolation,
+    /// The time of each keyframe in seconds, ascending.
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+impl Channel {
+
+    /// Writes the channel's value at `time` into the transform.
+    pub fn sample(&self, time: f32, transform: &mut Transform) {
+        match &self.values {
+            ChannelValues::Translation(values) => {
+                transform.translation = sample_keyframes(&self.times, values, self.interpolation, time);
+            }
+            ChannelValues::Rotation(values) => {
+                transform.rotation = UnitQuaternion::new_normalize(sample_keyframes(&self.times, values, self.interpolation, time));
+            }
+            ChannelValues::Scale(values) => {
+                transform.scale = sample_keyframes(&self.times, values, self.interpolation, time);
+            }
+        }
+    }
+}
+
+/// A set of channels animating transforms over time.
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub name: String,
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl AnimationClip {
+
+    /// Panics if a channel has no keyframes, its times are not ascending or the number of values
+    /// does not match the number of keyframes.
+    pub fn new(name: String, channels: Vec<Channel>) -> AnimationClip {
+
+        channels.iter().for_each(|channel| {
+            let values_per_key = match channel.interpolation {
+                Interpolation::CubicSpline => 3,
+                _ => 1,
+            };
+            assert!(!channel.times.is_empty(), "the channels of clip '{}' must have keyframes", name);
+            assert!(channel.times.windows(2).all(|times| times[0] <= times[1]), "the keyframes of clip '{}' must be ascending", name);
+            assert_eq!(channel.values.len(), channel.times.len() * values_per_key, "the values of clip '{}' do not match its keyframes", name);
+        });
+
+        let duration = channels.iter()
+            .map(|channel| *channel.times.last().unwrap())
+            .fold(0.0, f32::max);
+
+        AnimationClip { name, channels, duration }
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// The time of the last keyframe in seconds.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Samples the clip at `time`, clamped to the keyframes of each channel, into the transforms
+    /// targeted by its channels. Transforms which are not animated by the clip remain unchanged.
+    pub fn sample(&self, time: f32, transforms: &mut [Transform]) {
+        self.channels.iter().for_each(|channel| {
+            if let Some(transform) = transforms.get_mut(channel.target) {
+                channel.sample(time, transform);
+            }
+        });
+    }
+}
+
+trait Keyframe: Copy {
+
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+
+    /// Evaluates the Hermite spline from `self` to `other`, the tangents are scaled by the duration
+    /// of the segment.
+    fn hermite(&self, out_tangent: &Self, in_tangent: &Self, other: &Self, t: f32, delta: f32) -> Self;
+}
+
+impl Keyframe for Vector3<f32> {
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Vector3::lerp(self, other, t)
+    }
+
+    fn hermite(&self, out_tangent: &Self, in_tangent: &Self, other: &Self, t: f32, delta: f32) -> Self {
+        let [a, b, c, d] = hermite_basis(t, delta);
+        self * a + out_tangent * b + other * c + in_tangent * d
+    }
+}
+
+impl Keyframe for Quaternion<f32> {
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let from = Transform { rotation: UnitQuaternion::new_normalize(*self), ..Transform::identity() };
+        let to = Transform { rotation: UnitQuaternion::new_normalize(*other), ..Transform::identity() };
+        from.interpolate(&to, t).rotation.into_inner()
+    }
+
+    fn hermite(&self, out_tangent: &Self, in_tangent: &Self, other: &Self, t: f32, delta: f32) -> Self {
+        let [a, b, c, d] = hermite_basis(t, delta);
+        Quaternion::from(self.coords * a + out_tangent.coords * b + other.coords * c + in_tangent.coords * d)
+    }
+}
+
+fn hermite_basis(t: f32, delta: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [2.0 * t3 - 3.0 * t2 + 1.0, (t3 - 2.0 * t2 + t) * delta, -2.0 * t3 + 3.0 * t2, (t3 - t2) * delta]
+}
+
+fn sample_keyframes<T: Keyframe>(times: &[f32], values: &[T], interpolation: Interpolation, time: f32) -> T {
+
+    // the index of a keyframe's value, cubic splines store the tangents around it
+    let value = |key: usize| match interpolation {
+        Interpolation::CubicSpline => values[key * 3 + 1],
+        _ => values[key],
+    };
+
+    let next = times.partition_point(|key_time| *key_time <= time);
+
+    if next == 0 {
+        return value(0)
+    }
+    if next == times.len() {
+        return value(times.len() - 1)
+    }
+
+    let key = next - 1;
+    let delta = times[next] - times[key];
+    let t = (time - times[key]) / delta;
+
+    match interpolation {
+        Interpolation::Step => value(key),
+        Interpolation::Linear => value(key).lerp(&value(next), t),
+        Interpolation::CubicSpline => {
+            value(key).hermite(&values[key * 3 + 2], &values[next * 3], &value(next), t, delta)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+
+    use crate::animation::{AnimationClip, Channel, ChannelValues, Interpolation, Transform};
+
+    fn translation_clip(interpolation: Interpolation, values: Vec<Vector3<f32>>) -> AnimationClip {
+        AnimationClip::new(String::from("clip"), vec![
+            Channel {
+                target: 0,
+                interpolation,
+                times: vec![1.0, 3.0],
+                values: ChannelValues::Translation(values),
+            }
+        ])
+    }
+
+    fn sample(clip: &AnimationClip, time: f32) -> Transform {
+        let mut transforms = [Transform::identity()];
+        clip.sample(time, &mut transforms);
+        transforms[0]
+    }
+
+    #[test]
+    fn test_sample_step_and_linear_keyframes() {
+
+        let values = vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 2.0, 0.0)];
+        let step = translation_clip(Interpolation::Step, Clone::clone(&values));
+        let linear = translation_clip(Interpolation::Linear, values);
+
+        assert_that!(step.duration(), is(equal_to(3.0)));
+        assert_that!(sample(&step, 2.9).translation, is(equal_to(Vector3::new(0.0, 0.0, 0.0))));
+        assert_that!(sample(&step, 3.0).translation, is(equal_to(Vector3::new(4.0, 2.0, 0.0))));
+        assert_that!(sample(&linear, 1.5).translation, is(equal_to(Vector3::new(1.0, 0.5, 0.0))));
+        // clamped to the first and the last keyframe
+        assert_that!(sample(&linear, 0.0).translation, is(equal_to(Vector3::new(0.0, 0.0, 0.0))));
+        assert_that!(sample(&linear, 9.0).translation, is(equal_to(Vector3::new(4.0, 2.0, 0.0))));
+    }
+
+    #[test]
+    fn test_sample_cubic_spline_keyframes() {
+
+        // tangents of a straight line from 0 to 4 within 2 seconds match linear interpolation
+        let tangent = Vector3::new(2.0, 0.0, 0.0);
+        let clip = translation_clip(Interpolation::CubicSpline, vec![
+            tangent, Vector3::new(0.0, 0.0, 0.0), tangent,
+            tangent, Vector3::new(4.0, 0.0, 0.0), tangent,
+        ]);
+
+        assert_that!((sample(&clip, 1.5).translation - Vector3::new(1.0, 0.0, 0.0)).norm(), is(less_than(1e-5)));
+
+        // flat tangents ease in and out
+        let flat = translation_clip(Interpolation::CubicSpline, vec![
+            Vector3::zeros(), Vector3::new(0.0, 0.0, 0.0), Vector3::zeros(),
+            Vector3::zeros(), Vector3::new(4.0, 0.0, 0.0), Vector3::zeros(),
+        ]);
+
+        assert_that!(sample(&flat, 1.5).translation.x, is(less_than(1.0)));
+        assert_that!((sample(&flat, 2.0).translation.x - 2.0).abs(), is(less_than(1e-5)));
+    }
+
+    #[test]
+    fn test_sample_rotation_along_shortest_arc() {
+
+        let from = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.0);
+        let to = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+
+        // the same rotation with the opposite sign must not take the long way round
+        let clip = AnimationClip::new(String::from("rotation"), vec![
+            Channel {
+                target: 0,
+                interpolation: Interpolation::Linear,
+                times: vec![0.0, 1.0],
+                values: ChannelValues::Rotation(vec![from.into_inner(), -to.into_inner()]),
+            }
+        ]);
+
+        let expected = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_4);
+
+        assert_that!(sample(&clip, 0.5).rotation.angle_to(&expected), is(less_than(1e-3)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_clip_rejects_missing_tangents() {
+        AnimationClip::new(String::from("clip"), vec![
+            Channel {
+                target: 0,
+                interpolation: Interpolation::CubicSpline,
+                times: vec![0.0, 1.0],
+                values: ChannelValues::Rotation(vec![Quaternion::identity(); 2]),
+            }
+        ]);
+    }
+}
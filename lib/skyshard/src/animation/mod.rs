@@ -0,0 +1,7 @@
+mod clip;
+mod player;
+mod skeleton;
+
+pub use crate::animation::clip::{AnimationClip, Channel, ChannelValues, Interpolation};
+pub use crate::animation::player::{AnimationLayer, AnimationPlayer, PlaybackMode};
+pub use crate::animation::skeleton::{Joint, MAX_JOINT_INFLUENCES, Pose, Skeleton, Transform};
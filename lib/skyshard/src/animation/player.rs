@@ -0,0 +1,266 @@
+use crate::animation::{AnimationClip, Transform};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// Plays the clip once and holds its last keyframe.
+    Once,
+    /// Restarts the clip whenever it ends.
+    Loop,
+    /// Plays the clip forwards and backwards alternately.
+    PingPong,
+}
+
+/// A clip being played by an `AnimationPlayer`.
+#[derive(Clone, Debug)]
+pub struct AnimationLayer {
+    pub clip: AnimationClip,
+    pub mode: PlaybackMode,
+    /// Factor of the time advanced, negative values play the clip backwards.
+    pub speed: f32,
+    /// The influence of the layer on the transforms resulting from the layers below it, in
+    /// `[0, 1]`.
+    pub weight: f32,
+    pub paused: bool,
+    time: f32,
+    fade: Option<Fade>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    weight: f32,
+    /// Change of the weight per second.
+    rate: f32,
+}
+
+impl AnimationLayer {
+
+    /// The time played so far in seconds.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Jumps to the time, as if the clip had been played for so many seconds.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// The time at which the clip is sampled, within `[0, duration]`.
+    pub fn clip_time(&self) -> f32 {
+
+        let duration = self.clip.duration();
+
+        if duration <= 0.0 {
+            return 0.0
+        }
+
+        match self.mode {
+            PlaybackMode::Once => self.time.clamp(0.0, duration),
+            PlaybackMode::Loop => self.time.rem_euclid(duration),
+            PlaybackMode::PingPong => {
+                let time = self.time.rem_euclid(2.0 * duration);
+                if time > duration { 2.0 * duration - time } else { time }
+            }
+        }
+    }
+
+    /// Whether a clip played once has reached its end, looping clips never finish.
+    pub fn is_finished(&self) -> bool {
+        match self.mode {
+            PlaybackMode::Once if self.speed < 0.0 => self.time <= 0.0,
+            PlaybackMode::Once => self.time >= self.clip.duration(),
+            _ => false,
+        }
+    }
+
+    /// Changes the weight linearly to the given weight within `duration` seconds.
+    pub fn fade_to(&mut self, weight: f32, duration: f32) {
+        match duration > 0.0 {
+            true => self.fade = Some(Fade { weight, rate: (weight - self.weight) / duration }),
+            false => {
+                self.weight = weight;
+                self.fade = None;
+            }
+        }
+    }
+}
+
+/// Plays a stack of clips and blends them into the transforms they animate, e.g. the joints of a
+/// skeleton, the instances of a geometry or a camera. Each layer is blended over the result of the
+/// layers below by its weight.
+///
+/// The player is advanced explicitly, typically by the fixed time step of the application's
+/// clock, so that playback does not depend on the frame rate.
+#[derive(Clone, Debug, Default)]
+pub struct AnimationPlayer {
+    layers: Vec<AnimationLayer>,
+}
+
+impl AnimationPlayer {
+
+    pub fn new() -> AnimationPlayer {
+        AnimationPlayer {
+            layers: Vec::new(),
+        }
+    }
+
+    /// Plays the clip from its start on top of all other layers and returns the index of its
+    /// layer.
+    pub fn play(&mut self, clip: AnimationClip, mode: PlaybackMode) -> usize {
+        self.layers.push(AnimationLayer {
+            clip,
+            mode,
+            speed: 1.0,
+            weight: 1.0,
+            paused: false,
+            time: 0.0,
+            fade: None,
+        });
+        self.layers.len() - 1
+    }
+
+    /// Plays the clip on top of all other layers and fades it in within `duration` seconds,
+    /// while the other layers are faded out and removed afterwards.
+    pub fn cross_fade(&mut self, clip: AnimationClip, mode: PlaybackMode, duration: f32) -> usize {
+
+        self.layers.iter_mut().for_each(|layer| layer.fade_to(0.0, duration));
+
+        let index = self.play(clip, mode);
+        self.layers[index].weight = 0.0;
+        self.layers[index].fade_to(1.0, duration);
+
+        self.layers.retain(|layer| layer.weight > 0.0 || layer.fade.is_some());
+        self.layers.len() - 1
+    }
+
+    /// Removes the layer and returns it.
+    pub fn stop(&mut self, index: usize) -> AnimationLayer {
+        self.layers.remove(index)
+    }
+
+    pub fn layers(&self) -> &[AnimationLayer] {
+        &self.layers
+    }
+
+    pub fn layers_mut(&mut self) -> &mut [AnimationLayer] {
+        &mut self.layers
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.layers.iter().any(|layer| !layer.paused && !layer.is_finished())
+    }
+
+    /// Advances all layers which are not paused by `delta` seconds. Layers faded out completely
+    /// are removed.
+    pub fn advance(&mut self, delta: f32) {
+
+        self.layers.iter_mut().for_each(|layer| {
+
+            if !layer.paused {
+                layer.time += delta * layer.speed;
+            }
+
+            if let Some(fade) = layer.fade {
+                let weight = layer.weight + fade.rate * delta;
+                let reached = (fade.rate >= 0.0 && weight >= fade.weight) || (fade.rate <= 0.0 && weight <= fade.weight);
+                layer.weight = if reached { fade.weight } else { weight };
+                if reached {
+                    layer.fade = None;
+                }
+            }
+        });
+
+        self.layers.retain(|layer| layer.weight > 0.0 || layer.fade.is_some());
+    }
+
+    /// Blends the layers into the transforms, from the bottom to the top layer. Transforms which
+    /// are not animated by any layer remain unchanged.
+    pub fn apply(&self, transforms: &mut [Transform]) {
+
+        let mut sampled = transforms.to_vec();
+
+        self.layers.iter().filter(|layer| layer.weight > 0.0).for_each(|layer| {
+
+            sampled.copy_from_slice(transforms);
+            layer.clip.sample(layer.clip_time(), &mut sampled);
+
+            transforms.iter_mut().zip(&sampled).for_each(|(transform, sample)| {
+                *transform = transform.interpolate(sample, layer.weight.min(1.0));
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::animation::{AnimationClip, AnimationPlayer, Channel, ChannelValues, Interpolation, PlaybackMode, Transform};
+
+    /// Moves the target from the origin to `x` within two seconds.
+    fn clip(x: f32) -> AnimationClip {
+        AnimationClip::new(String::from("move"), vec![
+            Channel {
+                target: 0,
+                interpolation: Interpolation::Linear,
+                times: vec![0.0, 2.0],
+                values: ChannelValues::Translation(vec![Vector3::zeros(), Vector3::new(x, 0.0, 0.0)]),
+            }
+        ])
+    }
+
+    fn x_of(player: &AnimationPlayer) -> f32 {
+        let mut transforms = [Transform::identity()];
+        player.apply(&mut transforms);
+        transforms[0].translation.x
+    }
+
+    #[test]
+    fn test_playback_modes() {
+
+        let mut player = AnimationPlayer::new();
+        let once = player.play(clip(2.0), PlaybackMode::Once);
+        let looped = player.play(clip(2.0), PlaybackMode::Loop);
+        let ping_pong = player.play(clip(2.0), PlaybackMode::PingPong);
+
+        player.advance(2.5);
+
+        assert_that!(player.layers()[once].clip_time(), is(equal_to(2.0)));
+        assert_that!(player.layers()[once].is_finished(), is(true));
+        assert_that!(player.layers()[looped].clip_time(), is(equal_to(0.5)));
+        assert_that!(player.layers()[ping_pong].clip_time(), is(equal_to(1.5)));
+        assert_that!(player.is_playing(), is(true));
+    }
+
+    #[test]
+    fn test_blend_layers_by_weight() {
+
+        let mut player = AnimationPlayer::new();
+        player.play(clip(2.0), PlaybackMode::Once);
+        let top = player.play(clip(4.0), PlaybackMode::Once);
+        player.layers_mut()[top].weight = 0.5;
+
+        player.advance(2.0);
+
+        assert_that!(x_of(&player), is(equal_to(3.0)));
+    }
+
+    #[test]
+    fn test_cross_fade_removes_faded_out_layers() {
+
+        let mut player = AnimationPlayer::new();
+        player.play(clip(2.0), PlaybackMode::Loop);
+        player.advance(2.0);
+
+        player.cross_fade(clip(4.0), PlaybackMode::Once, 1.0);
+        player.advance(0.5);
+
+        assert_that!(player.layers().len(), is(equal_to(2)));
+        assert_that!(player.layers()[1].weight, is(equal_to(0.5)));
+
+        player.advance(0.5);
+
+        assert_that!(player.layers().len(), is(equal_to(1)));
+        assert_that!(x_of(&player), is(equal_to(2.0)));
+    }
+}
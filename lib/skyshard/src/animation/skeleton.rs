@@ -0,0 +1,275 @@
+use nalgebra::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3};
+
+/// The maximum number of joints influencing a vertex.
+pub const MAX_JOINT_INFLUENCES: usize = 4;
+
+/// A translation, rotation and non-uniform scale, applied in the order scale, rotation,
+/// translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+
+    pub fn identity() -> Transform {
+        Transform {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Decomposes an affine matrix without shear, as used by glTF nodes and Blender bones.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Transform {
+
+        let linear: Matrix3<f32> = matrix.fixed_view::<3, 3>(0, 0).into();
+
+        let mut scale = Vector3::new(linear.column(0).norm(), linear.column(1).norm(), linear.column(2).norm());
+
+        // a mirroring is expressed by negating one axis of the scale
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let rotation = Matrix3::from_columns(&[
+            linear.column(0) / scale.x,
+            linear.column(1) / scale.y,
+            linear.column(2) / scale.z,
+        ]);
+
+        Transform {
+            translation: matrix.fixed_view::<3, 1>(0, 3).into(),
+            rotation: UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation)),
+            scale,
+        }
+    }
+
+    /// Returns the matrix transforming column vectors.
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+
+    /// Returns the transformation laid out as `InstanceData::transformation`, which transforms row
+    /// vectors.
+    pub fn to_instance_transformation(&self) -> [f32; 16] {
+        self.to_matrix().transpose().as_slice().try_into().unwrap()
+    }
+
+    /// Interpolates between both transforms, `0.0` returns `self` and `1.0` returns `other`.
+    pub fn interpolate(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            translation: self.translation.lerp(&other.translation, t),
+            // nearly identical rotations can not be slerped, normalized lerp is precise enough then
+            rotation: self.rotation.try_slerp(&other.rotation, t, 1.0e-6)
+                .unwrap_or_else(|| self.rotation.nlerp(&other.rotation, t)),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Joint {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Transforms from model space into the joint's space at the time the mesh was bound.
+    pub inverse_bind_matrix: Matrix4<f32>,
+    /// The transformation relative to the parent, if the joint is not animated.
+    pub rest: Transform,
+}
+
+/// A hierarchy of joints deforming a skinned mesh. The joints are referenced by their index, as
+/// stored in the `JointIndices` attribute of the mesh's vertices.
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+    /// The indices of the joints, every parent ahead of its children.
+    order: Vec<usize>,
+}
+
+impl Skeleton {
+
+    /// Creates a skeleton of the joints in any order.
+    ///
+    /// Panics if a parent does not exist or the joints contain a cycle.
+    pub fn new(joints: Vec<Joint>) -> Skeleton {
+
+        let mut order = Vec::with_capacity(joints.len());
+        let mut visited = vec![false; joints.len()];
+
+        while order.len() < joints.len() {
+            let before = order.len();
+            (0..joints.len()).for_each(|index| {
+                let ready = match joints[index].parent {
+                    None => true,
+                    Some(parent) => {
+                        assert!(parent < joints.len(), "parent {} of joint '{}' does not exist", parent, joints[index].name);
+                        visited[parent]
+                    }
+                };
+                if !visited[index] && ready {
+                    visited[index] = true;
+                    order.push(index);
+                }
+            });
+            assert!(order.len() > before, "the joints of a skeleton must not contain a cycle");
+        }
+
+        Skeleton { joints, order }
+    }
+
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            local: self.joints.iter().map(|joint| joint.rest).collect(),
+        }
+    }
+
+    /// Sets the inverse bind matrices, so that the mesh is bound to the skeleton in its rest pose.
+    pub fn bind_rest_pose(&mut self) {
+        let rest = self.global_transforms(&self.rest_pose());
+        self.joints.iter_mut().zip(rest).for_each(|(joint, global)| {
+            joint.inverse_bind_matrix = global.try_inverse().unwrap_or_else(Matrix4::identity);
+        });
+    }
+
+    /// Returns the model space transformation of every joint in the pose.
+    pub fn global_transforms(&self, pose: &Pose) -> Vec<Matrix4<f32>> {
+
+        assert_eq!(pose.local.len(), self.joints.len(), "the pose does not match the skeleton");
+
+        let mut global = vec![Matrix4::identity(); self.joints.len()];
+
+        self.order.iter().for_each(|&index| {
+            let local = pose.local[index].to_matrix();
+            global[index] = match self.joints[index].parent {
+                Some(parent) => global[parent] * local,
+                None => local,
+            };
+        });
+
+        global
+    }
+
+    /// Returns the joint palette of the pose: the matrices transforming the vertices from their
+    /// bind pose into the pose, in the order of the joints.
+    pub fn joint_matrices(&self, pose: &Pose) -> Vec<Matrix4<f32>> {
+        self.global_transforms(pose).iter().zip(&self.joints).map(|(global, joint)| {
+            global * joint.inverse_bind_matrix
+        }).collect()
+    }
+}
+
+/// The local transformations of the joints of a skeleton.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub local: Vec<Transform>,
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Point3, UnitQuaternion, Vector3};
+
+    use crate::animation::{Joint, Skeleton, Transform};
+
+    fn joint(name: &str, parent: Option<usize>, translation: Vector3<f32>) -> Joint {
+        Joint {
+            name: String::from(name),
+            parent,
+            inverse_bind_matrix: Matrix4::identity(),
+            rest: Transform { translation, ..Transform::identity() },
+        }
+    }
+
+    #[test]
+    fn test_transform_matrix_roundtrip() {
+
+        let transform = Transform {
+            translation: Vector3::new(1.0, 2.0, 3.0),
+            rotation: UnitQuaternion::from_euler_angles(0.3, -0.2, 1.1),
+            scale: Vector3::new(2.0, 1.0, 0.5),
+        };
+
+        let decomposed = Transform::from_matrix(&transform.to_matrix());
+
+        assert_that!((decomposed.translation - transform.translation).norm(), is(less_than(1e-5)));
+        assert_that!(decomposed.rotation.angle_to(&transform.rotation), is(less_than(1e-3)));
+        assert_that!((decomposed.scale - transform.scale).norm(), is(less_than(1e-5)));
+    }
+
+    #[test]
+    fn test_transform_to_instance_transformation() {
+
+        let transform = Transform {
+            translation: Vector3::new(1.0, 2.0, 3.0),
+            ..Transform::identity()
+        };
+
+        let transformation = transform.to_instance_transformation();
+
+        // the matrix is transposed, the translation ends every column
+        assert_that!([transformation[3], transformation[7], transformation[11]], is(equal_to([1.0, 2.0, 3.0])));
+    }
+
+    #[test]
+    fn test_joint_matrices_are_identity_in_rest_pose() {
+
+        // the child precedes its parent
+        let mut skeleton = Skeleton::new(vec![
+            joint("forearm", Some(1), Vector3::new(0.0, 1.0, 0.0)),
+            joint("upperarm", None, Vector3::new(0.0, 2.0, 0.0)),
+        ]);
+        skeleton.bind_rest_pose();
+
+        let matrices = skeleton.joint_matrices(&skeleton.rest_pose());
+
+        matrices.iter().for_each(|matrix| {
+            assert_that!((matrix - Matrix4::identity()).norm(), is(less_than(1e-5)));
+        });
+    }
+
+    #[test]
+    fn test_joint_matrices_follow_the_parent() {
+
+        let mut skeleton = Skeleton::new(vec![
+            joint("root", None, Vector3::zeros()),
+            joint("tip", Some(0), Vector3::new(0.0, 1.0, 0.0)),
+        ]);
+        skeleton.bind_rest_pose();
+
+        let mut pose = skeleton.rest_pose();
+        pose.local[0].rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2);
+
+        let matrices = skeleton.joint_matrices(&pose);
+        let tip = matrices[1].transform_point(&Point3::new(0.0, 2.0, 0.0));
+
+        assert_that!((tip - Point3::new(-2.0, 0.0, 0.0)).norm(), is(less_than(1e-5)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_skeleton_rejects_cycles() {
+        Skeleton::new(vec![
+            joint("a", Some(1), Vector3::zeros()),
+            joint("b", Some(0), Vector3::zeros()),
+        ]);
+    }
+}
@@ -1,28 +1,326 @@
+use std::path::{Path, PathBuf};
 
+use ::base64::Engine as _;
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+use thiserror::Error;
 
-pub fn load_buffers(gltf: &gltf::Gltf) -> Vec<Vec<u8>> {
-    const OCTET_STREAM_URI: &str = "data:application/octet-stream;base64,";
+use crate::animation::{AnimationClip, Channel, ChannelValues, Interpolation, Joint, Skeleton, Transform};
+use crate::engine::SkinnedVertex;
 
-    let mut buffers = Vec::new();
+#[derive(Error, Debug)]
+pub enum GltfError {
 
-    for buffer in gltf.buffers() {
+    #[error("Failed to decode the data URI of buffer {buffer}: {cause}")]
+    InvalidDataUri { buffer: usize, cause: String },
+
+    #[error("Failed to read buffer {buffer} from '{path}': {cause}")]
+    ReadBufferError { buffer: usize, path: String, cause: String },
+
+    #[error("Buffer {buffer} refers to the binary chunk, but the glTF has none!")]
+    MissingBlob { buffer: usize },
+}
+
+/// Loads the data of all buffers, either embedded as base64 data URIs, stored in the binary chunk
+/// of a `.glb` file or in external files. The URIs of external files are resolved relative to the
+/// directory of the glTF file at `path`.
+pub fn load_buffers(gltf: &::gltf::Gltf, path: &Path) -> Result<Vec<Vec<u8>>, GltfError> {
+
+    gltf.buffers().map(|buffer| {
+        let index = buffer.index();
         match buffer.source() {
-            gltf::buffer::Source::Uri(uri) => {
-                if uri.starts_with(OCTET_STREAM_URI) {
-                    buffers.push(::base64::decode(&uri[OCTET_STREAM_URI.len()..]).expect("Failed to decode data"));
-                } else {
-                    todo!()
-                }
-            }
-            gltf::buffer::Source::Bin => {
-                if let Some(blob) = gltf.blob.as_deref() {
-                    buffers.push(blob.into());
-                } else {
-                    todo!();
-                }
+            ::gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                let (_, data) = uri.split_once(";base64,")
+                    .ok_or_else(|| GltfError::InvalidDataUri { buffer: index, cause: String::from("not base64 encoded") })?;
+                ::base64::engine::general_purpose::STANDARD.decode(data)
+                    .map_err(|error| GltfError::InvalidDataUri { buffer: index, cause: error.to_string() })
+            }
+            ::gltf::buffer::Source::Uri(uri) => {
+                let file = path.parent().unwrap_or(Path::new("")).join(decode_uri_path(uri));
+                std::fs::read(&file)
+                    .map_err(|error| GltfError::ReadBufferError { buffer: index, path: file.display().to_string(), cause: error.to_string() })
+            }
+            ::gltf::buffer::Source::Bin => {
+                gltf.blob.clone().ok_or(GltfError::MissingBlob { buffer: index })
+            }
+        }
+    }).collect()
+}
+
+/// Decodes the percent-encoded characters of a relative URI, e.g. `%20` for spaces.
+fn decode_uri_path(uri: &str) -> PathBuf {
+
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
             }
         }
     }
 
-    buffers
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Loads the joints of a skin in the order of `skin.joints()`, which is the order the vertices of
+/// skinned primitives refer to them.
+///
+/// Joints without a parent joint are placed relative to the model, the transformations of their
+/// ancestors which are not part of the skin are ignored.
+pub fn load_skeleton(skin: &::gltf::Skin, buffers: &[Vec<u8>]) -> Skeleton {
+
+    let nodes = skin.joints().map(|node| node.index()).collect::<Vec<_>>();
+
+    let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+    let inverse_bind_matrices = reader.read_inverse_bind_matrices()
+        .map(|matrices| matrices.map(|matrix| Matrix4::from(matrix)).collect::<Vec<_>>())
+        .unwrap_or_else(|| vec![Matrix4::identity(); nodes.len()]);
+
+    let mut joints = skin.joints().zip(inverse_bind_matrices).map(|(node, inverse_bind_matrix)| {
+        let (translation, rotation, scale) = node.transform().decomposed();
+        Joint {
+            name: String::from(node.name().unwrap_or_default()),
+            parent: None,
+            inverse_bind_matrix,
+            rest: Transform {
+                translation: Vector3::from(translation),
+                rotation: UnitQuaternion::new_normalize(Quaternion::from(rotation)),
+                scale: Vector3::from(scale),
+            },
+        }
+    }).collect::<Vec<_>>();
+
+    skin.joints().enumerate().for_each(|(parent, node)| {
+        node.children().for_each(|child| {
+            if let Some(joint) = nodes.iter().position(|index| *index == child.index()) {
+                joints[joint].parent = Some(parent);
+            }
+        });
+    });
+
+    Skeleton::new(joints)
+}
+
+/// Loads the channels of the animation targeting the joints of the skin. Channels of other nodes
+/// and morph target weights are skipped.
+pub fn load_animation(animation: &::gltf::Animation, skin: &::gltf::Skin, buffers: &[Vec<u8>]) -> AnimationClip {
+
+    let channels = animation.channels().filter_map(|channel| {
+
+        let target = skin.joints().position(|node| node.index() == channel.target().node().index())?;
+        let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+
+        let times = reader.read_inputs()?.collect::<Vec<_>>();
+
+        let values = match reader.read_outputs()? {
+            ::gltf::animation::util::ReadOutputs::Translations(translations) => {
+                ChannelValues::Translation(translations.map(Vector3::from).collect())
+            }
+            ::gltf::animation::util::ReadOutputs::Rotations(rotations) => {
+                ChannelValues::Rotation(rotations.into_f32().map(Quaternion::from).collect())
+            }
+            ::gltf::animation::util::ReadOutputs::Scales(scales) => {
+                ChannelValues::Scale(scales.map(Vector3::from).collect())
+            }
+            ::gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => return None,
+        };
+
+        let interpolation = match channel.sampler().interpolation() {
+            ::gltf::animation::Interpolation::Step => Interpolation::Step,
+            ::gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            ::gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+
+        Some(Channel { target, interpolation, times, values })
+    }).collect();
+
+    AnimationClip::new(String::from(animation.name().unwrap_or("animation")), channels)
+}
+
+/// Loads the indices and vertices of a primitive of a skinned mesh. Missing normals, colors and
+/// texture coordinates default to zero, white and zero, vertices without joints are bound to the
+/// first joint.
+///
+/// Panics if the primitive has no positions.
+pub fn load_skinned_mesh(primitive: &::gltf::Primitive, buffers: &[Vec<u8>]) -> (Vec<u32>, Vec<SkinnedVertex>) {
+
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+
+    let positions = reader.read_positions()
+        .expect("a primitive without positions")
+        .collect::<Vec<_>>();
+
+    let mut normals = reader.read_normals()
+        .map(|normals| normals.collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+    let mut colors = reader.read_colors(0)
+        .map(|colors| colors.into_rgb_f32().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+    let mut uvs = reader.read_tex_coords(0)
+        .map(|uvs| uvs.into_f32().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+    let mut joints = reader.read_joints(0)
+        .map(|joints| joints.into_u16().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+    let mut weights = reader.read_weights(0)
+        .map(|weights| weights.into_f32().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+
+    let vertices = positions.iter().map(|position| {
+        SkinnedVertex {
+            position: *position,
+            normal: normals.next().unwrap_or([0.0; 3]),
+            color: colors.next().unwrap_or([1.0; 3]),
+            uv: uvs.next().unwrap_or([0.0; 2]),
+            joints: joints.next().unwrap_or([0; 4]).map(u32::from),
+            weights: weights.next().unwrap_or([1.0, 0.0, 0.0, 0.0]),
+        }
+    }).collect::<Vec<_>>();
+
+    let indices = reader.read_indices()
+        .map(|indices| indices.into_u32().collect())
+        .unwrap_or_else(|| (0..vertices.len() as u32).collect());
+
+    (indices, vertices)
+}
+
+#[cfg(test)]
+mod tests {
+    use ::base64::Engine as _;
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Point3, Vector3};
+
+    use std::path::Path;
+
+    use crate::assets::gltf::{GltfError, load_animation, load_buffers, load_skeleton, load_skinned_mesh};
+
+    /// Two joints, the second one above the first, moved up by an animation, and a triangle
+    /// bound to the second joint.
+    fn document(buffer_uri: impl FnOnce(&[u8]) -> String) -> ::gltf::Gltf {
+
+        let mut data = Vec::new();
+        // inverse bind matrices
+        [Matrix4::<f32>::identity(), Matrix4::new_translation(&Vector3::new(0.0, -1.0, 0.0))].iter()
+            .for_each(|matrix| matrix.as_slice().iter().for_each(|value| data.extend(value.to_le_bytes())));
+        // keyframe times and translations
+        [0.0f32, 1.0, 0.0, 1.0, 0.0, 0.0, 3.0, 0.0].iter().for_each(|value| data.extend(value.to_le_bytes()));
+        // positions
+        [0.0f32, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0].iter().for_each(|value| data.extend(value.to_le_bytes()));
+        // joints and weights
+        (0..3).for_each(|_| data.extend([1u8, 0, 0, 0]));
+        (0..3).for_each(|_| [1.0f32, 0.0, 0.0, 0.0].iter().for_each(|value| data.extend(value.to_le_bytes())));
+
+        let json = format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "buffers": [{{ "byteLength": {length}, "uri": "{uri}" }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 128 }},
+                {{ "buffer": 0, "byteOffset": 128, "byteLength": 8 }},
+                {{ "buffer": 0, "byteOffset": 136, "byteLength": 24 }},
+                {{ "buffer": 0, "byteOffset": 160, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 196, "byteLength": 12 }},
+                {{ "buffer": 0, "byteOffset": 208, "byteLength": 48 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "MAT4" }},
+                {{ "bufferView": 1, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0.0], "max": [1.0] }},
+                {{ "bufferView": 2, "componentType": 5126, "count": 2, "type": "VEC3" }},
+                {{ "bufferView": 3, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 1.0, 0.0], "max": [1.0, 1.0, 1.0] }},
+                {{ "bufferView": 4, "componentType": 5121, "count": 3, "type": "VEC4" }},
+                {{ "bufferView": 5, "componentType": 5126, "count": 3, "type": "VEC4" }}
+            ],
+            "nodes": [
+                {{ "name": "root", "children": [1] }},
+                {{ "name": "tip", "translation": [0.0, 1.0, 0.0] }},
+                {{ "mesh": 0, "skin": 0 }}
+            ],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 3, "JOINTS_0": 4, "WEIGHTS_0": 5 }} }}] }}],
+            "skins": [{{ "joints": [0, 1], "inverseBindMatrices": 0 }}],
+            "animations": [{{
+                "name": "raise",
+                "samplers": [{{ "input": 1, "output": 2, "interpolation": "LINEAR" }}],
+                "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "translation" }} }}]
+            }}]
+        }}"#, length = data.len(), uri = buffer_uri(&data));
+
+        ::gltf::Gltf::from_slice(json.as_bytes()).expect("valid glTF")
+    }
+
+    fn embedded(data: &[u8]) -> String {
+        format!("data:application/octet-stream;base64,{}", ::base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    #[test]
+    fn test_load_skin_animation_and_mesh() {
+
+        let gltf = document(embedded);
+        let buffers = load_buffers(&gltf, Path::new("skin.gltf")).expect("embedded buffers");
+        let skin = gltf.skins().next().unwrap();
+
+        let skeleton = load_skeleton(&skin, &buffers);
+        let clip = load_animation(&gltf.animations().next().unwrap(), &skin, &buffers);
+        let (indices, vertices) = load_skinned_mesh(&gltf.meshes().next().unwrap().primitives().next().unwrap(), &buffers);
+
+        assert_that!(skeleton.joints()[1].name.as_str(), is(equal_to("tip")));
+        assert_that!(skeleton.joints()[1].parent, is(equal_to(Some(0))));
+        assert_that!(clip.name.as_str(), is(equal_to("raise")));
+        assert_that!(clip.duration(), is(equal_to(1.0)));
+        assert_that!(indices, is(equal_to(vec![0, 1, 2])));
+        assert_that!(vertices[0].joints, is(equal_to([1, 0, 0, 0])));
+
+        // the mesh is bound in the rest pose and follows the root moved up by the animation
+        let mut pose = skeleton.rest_pose();
+        clip.sample(1.0, &mut pose.local);
+        let matrices = skeleton.joint_matrices(&pose);
+        let position = Point3::from(vertices[0].position);
+
+        assert_that!((matrices[1].transform_point(&position) - Point3::new(0.0, 4.0, 0.0)).norm(), is(less_than(1e-5)));
+    }
+
+    #[test]
+    fn test_load_buffers_from_external_files() {
+
+        let directory = std::env::temp_dir().join(format!("skyshard-gltf-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("temporary directory");
+
+        let mut written = Vec::new();
+        let gltf = document(|data| {
+            written = data.to_vec();
+            String::from("skin%20data.bin")
+        });
+        std::fs::write(directory.join("skin data.bin"), &written).expect("buffer file");
+
+        let buffers = load_buffers(&gltf, &directory.join("skin.gltf"));
+        let missing = load_buffers(&gltf, Path::new("missing/skin.gltf"));
+
+        std::fs::remove_dir_all(&directory).expect("removed temporary directory");
+
+        assert_that!(buffers.expect("external buffers"), is(equal_to(vec![written])));
+        assert_that!(matches!(missing, Err(GltfError::ReadBufferError { buffer: 0, .. })), is(true));
+    }
+
+    #[test]
+    fn test_load_buffers_fails_without_blob() {
+
+        let json = r#"{ "asset": { "version": "2.0" }, "buffers": [{ "byteLength": 4 }] }"#;
+        let gltf = ::gltf::Gltf::from_slice(json.as_bytes()).expect("valid glTF");
+
+        assert_that!(matches!(load_buffers(&gltf, Path::new("scene.gltf")), Err(GltfError::MissingBlob { buffer: 0 })), is(true));
+    }
 }
@@ -1,3 +1,5 @@
+pub mod gltf;
+
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs::{read, read_dir};
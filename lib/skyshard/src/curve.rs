@@ -0,0 +1,83 @@
+/// A value which can be interpolated linearly along a [Curve].
+pub trait CurveValue: Copy {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl CurveValue for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<const N: usize> CurveValue for [f32; N] {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|index| self[index].lerp(&other[index], t))
+    }
+}
+
+/// A value over a normalized parameter from 0.0 to 1.0, e.g. the color or size of a particle
+/// over its lifetime or a color ramp. The keys are interpolated linearly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: CurveValue> Curve<T> {
+
+    /// Panics if there are no keys or their positions are not ascending.
+    pub fn new(keys: Vec<(f32, T)>) -> Curve<T> {
+        assert!(!keys.is_empty(), "a curve must have keys");
+        assert!(keys.windows(2).all(|keys| keys[0].0 <= keys[1].0), "the keys of a curve must be ascending");
+        Curve { keys }
+    }
+
+    pub fn constant(value: T) -> Curve<T> {
+        Curve::new(vec![(0.0, value)])
+    }
+
+    /// Interpolates from the first to the second value over the whole range.
+    pub fn linear(from: T, to: T) -> Curve<T> {
+        Curve::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys
+    }
+
+    /// Returns the value at the position, clamped to the first and the last key.
+    pub fn sample(&self, position: f32) -> T {
+
+        let next = self.keys.partition_point(|(key_position, _)| *key_position <= position);
+
+        if next == 0 {
+            return self.keys[0].1
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].1
+        }
+
+        let (from_position, from) = self.keys[next - 1];
+        let (to_position, to) = self.keys[next];
+
+        from.lerp(&to, (position - from_position) / (to_position - from_position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::curve::Curve;
+
+    #[test]
+    fn test_sample_interpolates_between_keys_and_clamps() {
+
+        let curve = Curve::new(vec![(0.25, [0.0, 1.0]), (0.75, [1.0, 0.0]), (1.0, [1.0, 1.0])]);
+
+        assert_that!(curve.sample(0.0), is(equal_to([0.0, 1.0])));
+        assert_that!(curve.sample(0.5), is(equal_to([0.5, 0.5])));
+        assert_that!(curve.sample(0.875), is(equal_to([1.0, 0.5])));
+        assert_that!(curve.sample(2.0), is(equal_to([1.0, 1.0])));
+        assert_that!(Curve::constant(3.0).sample(0.5), is(equal_to(3.0)));
+    }
+}
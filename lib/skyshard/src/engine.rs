@@ -5,40 +5,75 @@ use std::cell::{Ref, RefCell};
 use std::convert::TryInto;
 use std::ffi::CStr;
 use std::io::Write;
+use std::path::Path;
 use std::rc::Rc;
+use std::time::Instant;
 
 use ash::vk;
 use ash::vk::{CommandBufferResetFlags, ImageView, Offset3D};
 use log::info;
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, Vector3};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 use winit::window::Window;
 
+use crate::animation::{Pose, Skeleton};
 use crate::assets::AssetsManager;
 use crate::entity::World;
-use crate::graphics::{Extent, Geometry, Material};
+use crate::graphics::{BoundingBox, Extent, Geometry, HasVertexLayout, IndexBuffer, Skin, vertex_bytes, VertexLayout, VertexLayoutError, IndirectDraw, LevelOfDetail, LodBatch, LodMetric, LodSelection, partition_visible_instances, select_lods, Material, MaterialDescriptor, MaterialUniformBufferObject, Texture, TextureData};
+use crate::graphics::{BlendMode, sort_back_to_front, transparent_draw_order};
 use crate::graphics::Camera;
+use crate::graphics::LightsUniformBufferObject;
+use crate::graphics::{CaptureError, CapturedFrame, ChannelOrder, FrameSequence};
+use crate::graphics::{CubeMapData, Skybox};
+use crate::graphics::{DebugLines, DebugVertex, MAX_DEBUG_VERTICES};
+use crate::graphics::{FontAtlas, MAX_OVERLAY_VERTICES, Overlay, OverlayBatch, OverlayPushConstants, OverlayTexture, OverlayVertex};
+use crate::graphics::{EnvironmentLighting, EnvironmentLightingData, EnvironmentLightingPushConstants, EnvironmentLightingSettings, EnvironmentLightingShaders};
+use crate::graphics::{PostEffect, PostPass, PostPushConstants};
+use crate::graphics::{MAX_SELECTED_OBJECTS, OutlineSettings, SelectionUniformBufferObject};
+use crate::graphics::{cascade_splits, MAX_SHADOW_CASTERS, shadow_casters, SHADOW_CASCADES, SHADOW_MAP_RESOLUTION, ShadowCascade, ShadowsUniformBufferObject};
 use crate::graphics::vulkan::DebugLevel;
+use crate::graphics::vulkan::compute::{ComputePipeline, workgroup_count};
+use crate::graphics::vulkan::descriptors::{DescriptorAllocator, TextureTable};
 use crate::graphics::vulkan::device::{Device, DeviceRef};
+use crate::graphics::vulkan::fullscreen::{FullscreenPipeline, FullscreenTarget};
+use crate::graphics::vulkan::billboards::BillboardPipeline;
+use crate::graphics::vulkan::lines::LinePipeline;
+use crate::graphics::vulkan::overlay::OverlayPipeline;
 use crate::graphics::vulkan::instance::{Instance, InstanceRef};
-use crate::graphics::vulkan::queue::QueueCapabilities;
-use crate::graphics::vulkan::renderpass::create_render_pass;
+use crate::graphics::vulkan::queue::{DeviceQueue, QueueCapabilities};
+use crate::graphics::vulkan::renderpass::{create_depth_only_render_pass, create_fullscreen_render_pass, create_render_pass};
 use crate::graphics::vulkan::resources::{Buffer, CopyDestination, Image, ImageAllocationDescriptor, ImageFormat, ImageTiling, ImageUsage, Resource, ResourceManager};
 use crate::graphics::vulkan::resources::{BufferAllocationDescriptor, BufferUsage, MemoryLocation};
-use crate::graphics::vulkan::shaders::{FragmentShaderBinary, ShaderModule, VertexShaderBinary};
+use crate::graphics::vulkan::shaders::{ComputeShaderBinary, FragmentShaderBinary, LoadedShaderModule, ShaderModule, VertexShaderBinary};
 use crate::graphics::vulkan::surface::{Surface, SurfaceRef};
 use crate::graphics::vulkan::swapchain::{Swapchain, SwapchainRef};
 use crate::graphics::vulkan::VulkanObject;
+use crate::mesh::IndexData;
+use crate::particles::{billboards, MAX_PARTICLE_VERTICES, ParticleEmitter, ParticleVertex};
 use crate::util::HasBuilder;
 
 #[repr(C, align(16))]
 #[derive(Clone, Debug, Copy)]
 pub struct Vertex {
     pub position: [f32; 3],
+    pub normal: [f32; 3],
     pub color: [f32; 3],
     pub uv: [f32; 2]
 }
 
+/// A vertex deformed by up to `MAX_JOINT_INFLUENCES` joints of a skeleton. The weights of a
+/// vertex should sum up to one, unused influences have a weight of zero.
+#[repr(C, align(16))]
+#[derive(Clone, Debug, Copy)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 3],
+    pub uv: [f32; 2],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
 #[repr(C, align(16))]
 #[derive(Clone, Debug, Copy)]
 pub struct InstanceData {
@@ -46,10 +81,149 @@ pub struct InstanceData {
     pub transformation: [f32; 16],
 }
 
-#[repr(C, align(16))]
+/// Per-frame uniforms bound to `set = 0, binding = 0` of every pipeline.
+///
+/// The memory layout follows the std140 rules and must match the following GLSL declaration:
+///
+/// ```glsl
+/// layout(set = 0, binding = 0) uniform UniformBufferObject {
+///     mat4 view;
+///     mat4 projection;
+///     mat4 view_projection;
+///     mat4 inverse_view_projection;
+///     vec4 camera_position;   // xyz: world space position, w: unused
+///     vec4 viewport;          // x: width, y: height, z: 1 / width, w: 1 / height
+///     float time;             // seconds since the engine has been created
+///     float delta_time;       // seconds since the last frame
+///     uint frame;             // index of the rendered frame
+/// } ubo;
+/// ```
+///
+/// The struct is aligned to 256 bytes, so that the per swapchain image copies satisfy any
+/// `minUniformBufferOffsetAlignment` when they are bound at an offset of the uniform buffer.
+#[repr(C, align(256))]
 #[derive(Clone, Debug, Copy)]
 struct UniformBufferObject {
-    mvp: Matrix4<f32>,
+    view: Matrix4<f32>,
+    projection: Matrix4<f32>,
+    view_projection: Matrix4<f32>,
+    inverse_view_projection: Matrix4<f32>,
+    camera_position: [f32; 4],
+    viewport: [f32; 4],
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+    _padding: u32,
+}
+
+impl UniformBufferObject {
+
+    fn new() -> UniformBufferObject {
+        UniformBufferObject {
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+            view_projection: Matrix4::identity(),
+            inverse_view_projection: Matrix4::identity(),
+            camera_position: [0.0, 0.0, 0.0, 1.0],
+            viewport: [0.0, 0.0, 0.0, 0.0],
+            time: 0.0,
+            delta_time: 0.0,
+            frame: 0,
+            _padding: 0,
+        }
+    }
+}
+
+/// Push constants of the main pass, matching the following GLSL declaration:
+///
+/// ```glsl
+/// layout(push_constant) uniform GeometryConstants {
+///     uint receive_shadows;
+///     float lod_dither;       // see `LodBatch::dither`
+/// } geometry;
+/// ```
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+struct GeometryPushConstants {
+    receive_shadows: u32,
+    lod_dither: f32,
+}
+
+impl GeometryPushConstants {
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>())
+        }
+    }
+}
+
+/// Number of invocations per workgroup of the culling shader (`local_size_x`).
+const CULLING_WORKGROUP_SIZE: u32 = 64;
+
+/// Number of global bindings sampling the environment lighting, from binding 4 on.
+const ENVIRONMENT_LIGHTING_BINDINGS: u32 = 3;
+
+/// Number of invocations per workgroup of the environment lighting shaders along x and y.
+const ENVIRONMENT_LIGHTING_WORKGROUP_SIZE: u32 = 8;
+
+/// Push constants of the culling shader, matching the following GLSL declaration:
+///
+/// ```glsl
+/// layout(push_constant) uniform PushConstants {
+///     vec4 bounding_sphere;   // xyz: model space center, w: radius
+///     uint instance_count;
+/// } geometry;
+/// ```
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+struct CullingPushConstants {
+    bounding_sphere: [f32; 4],
+    instance_count: u32,
+}
+
+impl CullingPushConstants {
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>())
+        }
+    }
+}
+
+/// Computes a sphere enclosing all positions, centered at the middle of their bounding box.
+fn bounding_sphere(positions: &[Vector3<f32>]) -> [f32; 4] {
+
+    if positions.is_empty() {
+        return [0.0, 0.0, 0.0, 0.0];
+    }
+
+    let (min, max) = positions.iter().fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), position| {
+        ([
+            min[0].min(position[0]),
+            min[1].min(position[1]),
+            min[2].min(position[2]),
+        ], [
+            max[0].max(position[0]),
+            max[1].max(position[1]),
+            max[2].max(position[2]),
+        ])
+    });
+
+    let center = [
+        (min[0] + max[0]) / 2.0,
+        (min[1] + max[1]) / 2.0,
+        (min[2] + max[2]) / 2.0,
+    ];
+
+    let radius = positions.iter().map(|position| {
+        let x = position[0] - center[0];
+        let y = position[1] - center[1];
+        let z = position[2] - center[2];
+        (x * x + y * y + z * z).sqrt()
+    }).fold(0.0, f32::max);
+
+    [center[0], center[1], center[2], radius]
 }
 
 #[derive(Debug)]
@@ -67,18 +241,58 @@ pub struct Engine {
     renderpass: ash::vk::RenderPass,
     viewports: [ash::vk::Viewport; 1],
     scissors: [ash::vk::Rect2D; 1],
-    pipelines: Vec<ash::vk::Pipeline>,
+    vertex_shader_module: LoadedShaderModule<VertexShaderBinary>,
+    fragment_shader_module: LoadedShaderModule<FragmentShaderBinary>,
+    skinning_shader_module: LoadedShaderModule<VertexShaderBinary>,
+    geometry_pipelines: Vec<GeometryPipelines>,
     pipeline_layout: ash::vk::PipelineLayout,
+    culling_descriptor_set_layout: ::ash::vk::DescriptorSetLayout,
+    culling_pipeline: Option<ComputePipeline>,
+    compute_command_buffer: ash::vk::CommandBuffer,
+    compute_finished_semaphore: ash::vk::Semaphore,
+    compute_recording: bool,
     frame_buffers: Vec<ash::vk::Framebuffer>,
     command_buffers: Vec<ash::vk::CommandBuffer>,
     descriptor_pool: ::ash::vk::DescriptorPool,
+    global_descriptor_set_layout: ::ash::vk::DescriptorSetLayout,
     global_descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    descriptor_allocator: DescriptorAllocator,
     material_descriptor_set_layout: ::ash::vk::DescriptorSetLayout,
+    skin_descriptor_set_layout: ::ash::vk::DescriptorSetLayout,
+    texture_table: TextureTable,
     texture_sampler: ::ash::vk::Sampler,
+    default_textures: Vec<Texture>,
     ubo_buffer: Buffer<UniformBufferObject>,
+    lights_buffer: Buffer<LightsUniformBufferObject>,
+    shadow_renderpass: ash::vk::RenderPass,
+    shadow_maps: Vec<Image>,
+    shadow_map_views: Vec<ImageView>,
+    shadow_frame_buffers: Vec<ash::vk::Framebuffer>,
+    shadow_sampler: ::ash::vk::Sampler,
+    shadow_descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    shadow_cascades_buffer: Buffer<UniformBufferObject>,
+    shadows_buffer: Buffer<ShadowsUniformBufferObject>,
+    environment_sampler: ::ash::vk::Sampler,
+    default_environment_lighting: EnvironmentLighting,
+    /// The irradiance view of the environment lighting written into the global descriptor sets.
+    bound_environment_lighting: ImageView,
     object_id_lookup_images: Vec<Image>,
     object_id_lookup_images_views: Vec<ImageView>,
     object_id_lookup_buffer: Buffer<u32>,
+    /// The ids of the objects outlined by the post-processing.
+    selection: Vec<u32>,
+    hdr_images: Vec<Image>,
+    hdr_image_views: Vec<ImageView>,
+    post_processing: PostProcessing,
+    debug_lines: DebugLines,
+    debug_drawing: DebugDrawing,
+    particle_drawing: ParticleDrawing,
+    overlay: Overlay,
+    overlay_drawing: OverlayDrawing,
+    capture_buffer: Buffer<u8>,
+    capture_requested: bool,
+    captured_frame: Option<CapturedFrame>,
+    frame_sequence: Option<FrameSequence>,
     image_available_semaphore: ash::vk::Semaphore,
     render_finished_semaphore: ash::vk::Semaphore,
     command_buffers_completed_fence: ash::vk::Fence,
@@ -86,6 +300,9 @@ pub struct Engine {
     vertices_query_pool: ash::vk::QueryPool,
     last_timing_value: [u64; 1],
     last_swapchain_image_index: u32,
+    start_time: Instant,
+    last_frame_time: Instant,
+    frame_index: u32,
 }
 
 impl Engine {
@@ -104,18 +321,93 @@ impl Engine {
 
 impl Drop for Engine {
     fn drop(&mut self) {
+
+        let _device = (*self.device).borrow();
+
+        unsafe {
+            _device.handle().device_wait_idle()
+                .expect("Failed to wait for idle device");
+        }
+
+        let post_processing = &mut self.post_processing;
+
+        unsafe {
+            post_processing.frame_buffers.iter()
+                .chain(&post_processing.present_frame_buffers)
+                .chain(&self.frame_buffers)
+                .for_each(|frame_buffer| _device.handle().destroy_framebuffer(*frame_buffer, None));
+            post_processing.image_views.iter()
+                .chain(&self.hdr_image_views)
+                .chain(&self.object_id_lookup_images_views)
+                .for_each(|view| _device.handle().destroy_image_view(*view, None));
+            _device.handle().destroy_sampler(post_processing.sampler, None);
+            _device.handle().destroy_sampler(post_processing.object_id_sampler, None);
+        }
+
+        post_processing.images.drain(..)
+            .chain(self.hdr_images.drain(..))
+            .chain(self.object_id_lookup_images.drain(..))
+            .for_each(|image| {
+                unsafe {
+                    _device.handle().destroy_image(*image.handle(), None);
+                }
+                self.resource_manager.free(image)
+                    .expect("Failed to free render target image");
+            });
+
+        if let Some(font) = self.overlay_drawing.font.take() {
+
+            unsafe {
+                _device.handle().destroy_image_view(font.image_view, None);
+                _device.handle().destroy_image(*font.image.handle(), None);
+                _device.handle().destroy_sampler(self.overlay_drawing.sampler, None);
+            }
+
+            self.resource_manager.free(font.image)
+                .expect("Failed to free overlay font image");
+        }
+
         unsafe {
             // self.instance.destroy_instance(None);
         }
     }
 }
 
-pub fn create(
-    app_name: &str,
-    window: &Window,
-    vertex_shader: VertexShaderBinary,
-    fragment_shader: FragmentShaderBinary,
-) -> Result<Engine, EngineError> {
+/// The shaders of the engine's passes, passed to [create].
+///
+/// The skinning shader replaces the vertex shader for geometries with skinned vertices. The
+/// culling shader is optional, without it all instances are culled on the CPU.
+pub struct EngineShaders {
+    pub vertex: VertexShaderBinary,
+    pub fragment: FragmentShaderBinary,
+    pub culling: Option<ComputeShaderBinary>,
+    pub skinning: VertexShaderBinary,
+    pub post_processing_vertex: VertexShaderBinary,
+    pub post_processing_fragment: FragmentShaderBinary,
+    pub debug_vertex: VertexShaderBinary,
+    pub debug_fragment: FragmentShaderBinary,
+    pub overlay_vertex: VertexShaderBinary,
+    pub overlay_fragment: FragmentShaderBinary,
+    pub particle_vertex: VertexShaderBinary,
+    pub particle_fragment: FragmentShaderBinary,
+}
+
+pub fn create(app_name: &str, window: &Window, shaders: EngineShaders) -> Result<Engine, EngineError> {
+
+    let EngineShaders {
+        vertex: vertex_shader,
+        fragment: fragment_shader,
+        culling: culling_shader,
+        skinning: skinning_shader,
+        post_processing_vertex: post_processing_vertex_shader,
+        post_processing_fragment: post_processing_fragment_shader,
+        debug_vertex: debug_vertex_shader,
+        debug_fragment: debug_fragment_shader,
+        overlay_vertex: overlay_vertex_shader,
+        overlay_fragment: overlay_fragment_shader,
+        particle_vertex: particle_vertex_shader,
+        particle_fragment: particle_fragment_shader,
+    } = shaders;
 
     let instance = Instance::builder()
         .application_name(app_name)
@@ -139,18 +431,49 @@ pub fn create(
     let renderpass: ash::vk::RenderPass;
     let viewports: [ash::vk::Viewport; 1];
     let scissors: [ash::vk::Rect2D; 1];
-    let pipelines: Vec<ash::vk::Pipeline>;
+    let vertex_shader_module: LoadedShaderModule<VertexShaderBinary>;
+    let fragment_shader_module: LoadedShaderModule<FragmentShaderBinary>;
+    let skinning_shader_module: LoadedShaderModule<VertexShaderBinary>;
+    let geometry_pipelines: Vec<GeometryPipelines>;
     let pipeline_layout: ::ash::vk::PipelineLayout;
+    let culling_descriptor_set_layout: ::ash::vk::DescriptorSetLayout;
+    let culling_pipeline: Option<ComputePipeline>;
+    let compute_command_buffer: ash::vk::CommandBuffer;
+    let compute_finished_semaphore: ash::vk::Semaphore;
     let descriptor_pool: ::ash::vk::DescriptorPool;
+    let global_descriptor_set_layout: ::ash::vk::DescriptorSetLayout;
     let global_descriptor_sets: Vec<ash::vk::DescriptorSet>;
+    let descriptor_allocator: DescriptorAllocator;
     let material_descriptor_set_layout: ::ash::vk::DescriptorSetLayout;
+    let skin_descriptor_set_layout: ::ash::vk::DescriptorSetLayout;
+    let mut texture_table: TextureTable;
     let texture_sampler: ::ash::vk::Sampler;
+    let default_textures: Vec<Texture>;
     let ubo_buffer: Buffer<UniformBufferObject>;
+    let lights_buffer: Buffer<LightsUniformBufferObject>;
+    let shadow_renderpass: ash::vk::RenderPass;
+    let shadow_maps: Vec<Image>;
+    let shadow_map_views: Vec<ImageView>;
+    let shadow_frame_buffers: Vec<ash::vk::Framebuffer>;
+    let shadow_sampler: ::ash::vk::Sampler;
+    let shadow_descriptor_sets: Vec<ash::vk::DescriptorSet>;
+    let shadow_cascades_buffer: Buffer<UniformBufferObject>;
+    let shadows_buffer: Buffer<ShadowsUniformBufferObject>;
+    let environment_sampler: ::ash::vk::Sampler;
+    let default_environment_lighting: EnvironmentLighting;
     let index_buffer: ash::vk::Buffer;
     let vertex_buffer: ash::vk::Buffer;
     let object_id_lookup_images: Vec<Image>;
     let object_id_lookup_images_views: Vec<ImageView>;
     let object_id_lookup_buffer: Buffer<u32>;
+    let hdr_images: Vec<Image>;
+    let hdr_image_views: Vec<ImageView>;
+    let post_processing: PostProcessing;
+    let debug_drawing: DebugDrawing;
+    let particle_drawing: ParticleDrawing;
+    let overlay: Overlay;
+    let overlay_drawing: OverlayDrawing;
+    let capture_buffer: Buffer<u8>;
     let image_available_semaphore: ash::vk::Semaphore;
     let render_finished_semaphore: ash::vk::Semaphore;
     let command_buffers_completed_fence: ash::vk::Fence;
@@ -177,7 +500,8 @@ pub fn create(
         resource_manager = ResourceManager::new(
             (*instance).borrow().handle(),
             (*device).borrow().handle(),
-            (*physical_device).handle()
+            (*physical_device).handle(),
+            &(*device).borrow().queue_family_indices(),
         ).expect("Failed to create ResourceManager");
 
         assets_manager = AssetsManager::new(&String::from("./assets"))
@@ -217,90 +541,17 @@ pub fn create(
 
         let _device = (*device).borrow();
 
-        let vertex_shader_module = ShaderModule::create(vertex_shader, "main")
+        vertex_shader_module = ShaderModule::create(vertex_shader, "main")
             .and_then(|module| module.load(&_device))
             .unwrap();
 
-        let fragment_shader_module = ShaderModule::create(fragment_shader, "main")
+        fragment_shader_module = ShaderModule::create(fragment_shader, "main")
             .and_then(|module| module.load(&_device))
             .unwrap();
 
-        let shader_stage_create_infos = [
-            vertex_shader_module.create_pipeline_shader_stage_create_info(),
-            fragment_shader_module.create_pipeline_shader_stage_create_info(),
-        ];
-
-        let vertex_input_binding_descriptors = [
-            ash::vk::VertexInputBindingDescription::builder()
-                .binding(0)
-                .stride(std::mem::size_of::<Vertex>() as u32)
-                .input_rate(ash::vk::VertexInputRate::VERTEX)
-                .build(),
-            ash::vk::VertexInputBindingDescription::builder()
-                .binding(1)
-                .stride(std::mem::size_of::<InstanceData>() as u32)
-                .input_rate(ash::vk::VertexInputRate::INSTANCE)
-                .build(),
-        ];
-
-        let vertex_input_attribute_descriptors = [
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(0)
-                .location(0)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Vertex, position) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(0)
-                .location(1)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Vertex, color) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(0)
-                .location(2)
-                .format(ash::vk::Format::R32G32_SFLOAT)
-                .offset(offset_of!(Vertex, uv) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(1)
-                .location(3)
-                .format(ash::vk::Format::R32_UINT)
-                .offset(offset_of!(InstanceData, id) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(1)
-                .location(4)
-                .format(ash::vk::Format::R32G32B32A32_SFLOAT)
-                .offset((offset_of!(InstanceData, transformation) + 0) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(1)
-                .location(5)
-                .format(ash::vk::Format::R32G32B32A32_SFLOAT)
-                .offset((offset_of!(InstanceData, transformation) + 16) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(1)
-                .location(6)
-                .format(ash::vk::Format::R32G32B32A32_SFLOAT)
-                .offset((offset_of!(InstanceData, transformation) + 32) as u32)
-                .build(),
-            ash::vk::VertexInputAttributeDescription::builder()
-                .binding(1)
-                .location(7)
-                .format(ash::vk::Format::R32G32B32A32_SFLOAT)
-                .offset((offset_of!(InstanceData, transformation) + 48) as u32)
-                .build(),
-        ];
-
-        let vertex_input_state_info = ash::vk::PipelineVertexInputStateCreateInfo::builder()
-            .vertex_binding_descriptions(&vertex_input_binding_descriptors)
-            .vertex_attribute_descriptions(&vertex_input_attribute_descriptors);
-
-        let vertex_input_assembly_state_info = ash::vk::PipelineInputAssemblyStateCreateInfo::builder()
-            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
-            .primitive_restart_enable(false);
+        skinning_shader_module = ShaderModule::create(skinning_shader, "main")
+            .and_then(|module| module.load(&_device))
+            .unwrap();
 
         viewports = [vk::Viewport {
             x: 0.0,
@@ -319,126 +570,34 @@ pub fn create(
             },
         }];
 
-        let viewport_state_info = vk::PipelineViewportStateCreateInfo::builder()
-            .scissors(&scissors)
-            .viewports(&viewports);
-
-        let rasterization_info_fill_mode = ash::vk::PipelineRasterizationStateCreateInfo::builder()
-            .depth_clamp_enable(false)
-            .rasterizer_discard_enable(false)
-            .polygon_mode(ash::vk::PolygonMode::FILL)
-            .line_width(1.0)
-            .cull_mode(ash::vk::CullModeFlags::BACK)
-            .front_face(ash::vk::FrontFace::COUNTER_CLOCKWISE)
-            .depth_bias_enable(false)
-            .depth_bias_constant_factor(0.0)
-            .depth_bias_clamp(0.0)
-            .depth_bias_slope_factor(0.0)
-            .build();
-
-        let rasterization_info_line_mode = ash::vk::PipelineRasterizationStateCreateInfo::builder()
-            .depth_clamp_enable(false)
-            .rasterizer_discard_enable(false)
-            .polygon_mode(ash::vk::PolygonMode::LINE)
-            .line_width(3.0)
-            .cull_mode(ash::vk::CullModeFlags::BACK)
-            .front_face(ash::vk::FrontFace::COUNTER_CLOCKWISE)
-            .depth_bias_enable(false)
-            .depth_bias_constant_factor(0.0)
-            .depth_bias_clamp(0.0)
-            .depth_bias_slope_factor(0.0)
-            .build();
-
-        let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
-            .sample_shading_enable(false)
-            .rasterization_samples(vk::SampleCountFlags::TYPE_1)
-            .min_sample_shading(1.0)
-            .alpha_to_coverage_enable(false)
-            .alpha_to_one_enable(false)
-            .build();
 
-        let noop_stencil_state = vk::StencilOpState {
-            fail_op: vk::StencilOp::KEEP,
-            pass_op: vk::StencilOp::KEEP,
-            depth_fail_op: vk::StencilOp::KEEP,
-            compare_op: vk::CompareOp::ALWAYS,
-            ..Default::default()
+        // one global set per swapchain image and one per cascade of a shadow map and swapchain image,
+        // material sets are allocated by the descriptor allocator.
+        let global_sets = {
+            let count = (*swapchain).views().len() as u32;
+            count + count * (MAX_SHADOW_CASTERS * SHADOW_CASCADES) as u32
         };
 
-        let depth_state_info = vk::PipelineDepthStencilStateCreateInfo::builder()
-            .depth_test_enable(true)
-            .depth_write_enable(true)
-            .depth_compare_op(vk::CompareOp::LESS)
-            .depth_bounds_test_enable(false)
-            .stencil_test_enable(false)
-            .build();
-
-        // per frame buffer
-        let color_blend_attachment_states = [
-            vk::PipelineColorBlendAttachmentState::builder()
-                .color_write_mask(
-                    vk::ColorComponentFlags::R
-                        | vk::ColorComponentFlags::G
-                        | vk::ColorComponentFlags::B
-                        | vk::ColorComponentFlags::A
-                )
-                .blend_enable(false)
-                .src_color_blend_factor(ash::vk::BlendFactor::ONE)
-                .dst_color_blend_factor(ash::vk::BlendFactor::ZERO)
-                .color_blend_op(ash::vk::BlendOp::ADD)
-                .src_color_blend_factor(ash::vk::BlendFactor::ONE)
-                .dst_alpha_blend_factor(ash::vk::BlendFactor::ZERO)
-                .alpha_blend_op(ash::vk::BlendOp::ADD)
-                .build(),
-            vk::PipelineColorBlendAttachmentState::builder()
-                .color_write_mask(
-                    vk::ColorComponentFlags::R
-                        | vk::ColorComponentFlags::G
-                        | vk::ColorComponentFlags::B
-                        | vk::ColorComponentFlags::A
-                )
-                .blend_enable(false)
-                .src_color_blend_factor(ash::vk::BlendFactor::ONE)
-                .dst_color_blend_factor(ash::vk::BlendFactor::ZERO)
-                .color_blend_op(ash::vk::BlendOp::ADD)
-                .src_color_blend_factor(ash::vk::BlendFactor::ONE)
-                .dst_alpha_blend_factor(ash::vk::BlendFactor::ZERO)
-                .alpha_blend_op(ash::vk::BlendOp::ADD)
-                .build(),
-        ];
-
-        // for all frame buffers - global
-        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
-            .logic_op_enable(false)
-            .logic_op(ash::vk::LogicOp::COPY)
-            .attachments(&color_blend_attachment_states)
-            .blend_constants([0.0, 0.0, 0.0, 0.0]);
-
-        let dynamic_state = [
-            ash::vk::DynamicState::VIEWPORT,
-            ash::vk::DynamicState::SCISSOR
-        ];
+        // one set sampling the HDR target per swapchain image and one per intermediate image
+        let post_processing_sets = (*swapchain).views().len() as u32 + 2;
 
-        let dynamic_state_info = ash::vk::PipelineDynamicStateCreateInfo::builder()
-            .dynamic_states(&dynamic_state);
+        // one set with the object ids and the selection per swapchain image
+        let selection_sets = (*swapchain).views().len() as u32;
 
         descriptor_pool = {
-            let pool_sizes = {
-                let count = (*swapchain).views().len() as u32;
-                [
-                    ::ash::vk::DescriptorPoolSize::builder()
-                        .ty(ash::vk::DescriptorType::UNIFORM_BUFFER)
-                        .descriptor_count(count)
-                        .build(),
-                    ::ash::vk::DescriptorPoolSize::builder()
-                        .ty(::ash::vk::DescriptorType::SAMPLER)
-                        .descriptor_count(count * 16) // TODO: how many sampler descriptors do we need?
-                        .build(),
-                ]
-            };
+            let pool_sizes = [
+                ::ash::vk::DescriptorPoolSize::builder()
+                    .ty(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                    .descriptor_count(global_sets * 3 + selection_sets)
+                    .build(),
+                ::ash::vk::DescriptorPoolSize::builder()
+                    .ty(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(global_sets * (MAX_SHADOW_CASTERS as u32 + ENVIRONMENT_LIGHTING_BINDINGS) + post_processing_sets + selection_sets)
+                    .build(),
+            ];
 
             let pool_create_info = ash::vk::DescriptorPoolCreateInfo::builder()
-                .max_sets(1024) // TODO: how many descriptor sets do we need?
+                .max_sets(global_sets + post_processing_sets + selection_sets)
                 .pool_sizes(&pool_sizes)
                 .build();
 
@@ -448,14 +607,51 @@ pub fn create(
             }
         };
 
-        let global_descriptor_set_layout = {
+        global_descriptor_set_layout = {
             let bindings = [
                 ::ash::vk::DescriptorSetLayoutBinding::builder()
                     .binding(0)
                     .descriptor_count(1)
-                    .stage_flags(::ash::vk::ShaderStageFlags::VERTEX)
+                    .stage_flags(::ash::vk::ShaderStageFlags::VERTEX | ::ash::vk::ShaderStageFlags::FRAGMENT | ::ash::vk::ShaderStageFlags::COMPUTE)
+                    .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                    .build(),
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(1)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                    .build(),
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(2)
+                    .descriptor_count(MAX_SHADOW_CASTERS as u32)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .build(),
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(3)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
                     .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
                     .build(),
+                // the irradiance map, the specular map and the BRDF LUT of the environment lighting
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(4)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .build(),
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(5)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .build(),
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(6)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .build(),
             ];
 
             let create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
@@ -468,13 +664,39 @@ pub fn create(
             }
         };
 
+        descriptor_allocator = DescriptorAllocator::new(_device.handle(), &[
+            (::ash::vk::DescriptorType::UNIFORM_BUFFER, 1),
+            (::ash::vk::DescriptorType::STORAGE_BUFFER, 4),
+        ], 64);
+
         material_descriptor_set_layout = {
             let bindings = [
                 ::ash::vk::DescriptorSetLayoutBinding::builder()
                     .binding(0)
                     .descriptor_count(1)
                     .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
-                    .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_type(::ash::vk::DescriptorType::UNIFORM_BUFFER)
+                    .build()
+            ];
+
+            let descriptor_set_layout_create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+                .bindings(&bindings)
+                .build();
+
+            unsafe {
+                _device.handle().create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+                    .expect("Failed to create descriptor set layout!")
+            }
+        };
+
+        // the joint palette of a skinned geometry
+        skin_descriptor_set_layout = {
+            let bindings = [
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(0)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::VERTEX)
+                    .descriptor_type(::ash::vk::DescriptorType::STORAGE_BUFFER)
                     .build()
             ];
 
@@ -501,15 +723,52 @@ pub fn create(
             }
         }).collect::<Vec<_>>();
 
+        // the shadow pass uses the global layout as well, with the light's transformation as view_projection
+        shadow_descriptor_sets = (0..swapchain.views().len() * MAX_SHADOW_CASTERS * SHADOW_CASCADES).map(|_| {
+            let descriptor_set_layouts = [
+                global_descriptor_set_layout
+            ];
+            let create_info = ash::vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&descriptor_set_layouts);
+            unsafe {
+                _device.handle().allocate_descriptor_sets(&create_info)
+                    .expect("Failed to allocate descriptor set")[0]
+            }
+        }).collect::<Vec<_>>();
+
+        texture_table = {
+            // the shadow maps and the environment lighting are sampled by the same stage
+            let limits = _device.physical_device().limits().clone();
+            let max_textures = limits.max_per_stage_descriptor_samplers
+                .min(limits.max_per_stage_descriptor_sampled_images)
+                .min(limits.max_descriptor_set_samplers)
+                .saturating_sub(MAX_SHADOW_CASTERS as u32 + ENVIRONMENT_LIGHTING_BINDINGS)
+                .min(16384);
+            TextureTable::new(_device.handle(), 256, max_textures)
+                .expect("Failed to create texture table")
+        };
+
         pipeline_layout = {
 
             let descriptor_set_layouts = [
                 global_descriptor_set_layout,
                 material_descriptor_set_layout,
+                texture_table.layout(),
+                skin_descriptor_set_layout,
+            ];
+
+            let push_constant_ranges = [
+                ::ash::vk::PushConstantRange::builder()
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .offset(0)
+                    .size(std::mem::size_of::<GeometryPushConstants>() as u32)
+                    .build()
             ];
 
             let pipeline_layout_create_info = ::ash::vk::PipelineLayoutCreateInfo::builder()
                 .set_layouts(&descriptor_set_layouts)
+                .push_constant_ranges(&push_constant_ranges)
                 .build();
 
             unsafe {
@@ -518,59 +777,67 @@ pub fn create(
             }
         };
 
-        renderpass = create_render_pass(device.clone(), surface.clone());
+        // binding 0: all instances, 1: visible instances, 2: draw command, 3: draw count
+        culling_descriptor_set_layout = {
+            let bindings = (0..4).map(|binding| {
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding)
+                    .descriptor_count(1)
+                    .stage_flags(::ash::vk::ShaderStageFlags::COMPUTE)
+                    .descriptor_type(::ash::vk::DescriptorType::STORAGE_BUFFER)
+                    .build()
+            }).collect::<Vec<_>>();
 
-        let default_graphic_pipeline_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
-            .flags(::ash::vk::PipelineCreateFlags::ALLOW_DERIVATIVES)
-            .stages(&shader_stage_create_infos)
-            .vertex_input_state(&vertex_input_state_info)
-            .input_assembly_state(&vertex_input_assembly_state_info)
-            .viewport_state(&viewport_state_info)
-            .rasterization_state(&rasterization_info_fill_mode)
-            .multisample_state(&multisample_state_info)
-            .depth_stencil_state(&depth_state_info)
-            .color_blend_state(&color_blend_state)
-            .dynamic_state(&dynamic_state_info)
-            .layout(pipeline_layout)
-            .render_pass(renderpass)
-            .subpass(0)
-            .build();
+            let descriptor_set_layout_create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+                .bindings(&bindings)
+                .build();
 
-        let line_mode_graphic_pipeline_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
-            .flags(::ash::vk::PipelineCreateFlags::DERIVATIVE)
-            .base_pipeline_index(0)
-            .stages(&shader_stage_create_infos)
-            .vertex_input_state(&vertex_input_state_info)
-            .input_assembly_state(&vertex_input_assembly_state_info)
-            .viewport_state(&viewport_state_info)
-            .rasterization_state(&rasterization_info_line_mode)
-            .multisample_state(&multisample_state_info)
-            .depth_stencil_state(&depth_state_info)
-            .color_blend_state(&color_blend_state)
-            .dynamic_state(&dynamic_state_info)
-            .layout(pipeline_layout)
-            .render_pass(renderpass)
-            .subpass(0)
-            .build();
+            unsafe {
+                _device.handle().create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+                    .expect("Failed to create descriptor set layout!")
+            }
+        };
 
-        pipelines = unsafe {
-            _device.handle().create_graphics_pipelines(
-                ::ash::vk::PipelineCache::null(),
-                &[
-                    default_graphic_pipeline_info,
-                    line_mode_graphic_pipeline_info,
-                ],
-                None,
+        // bounding sphere (vec4) followed by the number of instances (uint) as push constants
+        culling_pipeline = culling_shader.map(|culling_shader| {
+            ComputePipeline::new(
+                &_device,
+                culling_shader,
+                &[global_descriptor_set_layout, culling_descriptor_set_layout],
+                std::mem::size_of::<CullingPushConstants>() as u32,
+            ).expect("Failed to create culling pipeline")
+        });
+
+        renderpass = create_render_pass(device.clone(), ImageFormat::RGBA16F.into());
+
+        shadow_renderpass = create_depth_only_render_pass(device.clone());
+
+        Vertex::vertex_layout().validate(&vertex_shader_module.shader().input_locations())
+            .expect("Failed to match the vertex shader's inputs");
+
+        // further pipelines are created along with the first geometry of another vertex layout
+        geometry_pipelines = vec![
+            create_geometry_pipelines(
+                &_device,
+                &vertex_shader_module,
+                &fragment_shader_module,
+                &Vertex::vertex_layout(),
+                pipeline_layout,
+                renderpass,
+                shadow_renderpass,
             )
-        }.expect("Failed to create graphic pipelines");
+        ];
 
         object_id_lookup_images = swapchain.views().iter().enumerate().map(|(index, _)| {
             resource_manager.create_image(format!("object-id-lookup-{index:?}"), &ImageAllocationDescriptor {
-                usage: [ImageUsage::ColorAttachment, ImageUsage::TransferSource],
+                usage: [ImageUsage::ColorAttachment, ImageUsage::TransferSource, ImageUsage::Sampled],
                 extent: Extent::from(window.inner_size().width, window.inner_size().height, 1),
                 format: ImageFormat::UInt32,
                 tiling: ImageTiling::Linear,
-                memory: MemoryLocation::GpuOnly
+                memory: MemoryLocation::GpuOnly,
+                mip_levels: 1,
+                array_layers: 1,
+                cube_compatible: false,
             }).expect("Failed to create object-id-lookup image")
         }).collect::<Vec<_>>();
 
@@ -599,7 +866,24 @@ pub fn create(
             }.unwrap()
         }).collect::<Vec<_>>();
 
-        frame_buffers = swapchain.views().iter().zip(&object_id_lookup_images_views).map(|(view, object_id_lookup)| {
+        hdr_images = swapchain.views().iter().enumerate().map(|(index, _)| {
+            resource_manager.create_image(format!("hdr-{index:?}"), &ImageAllocationDescriptor {
+                usage: [ImageUsage::ColorAttachment, ImageUsage::Sampled],
+                extent: Extent::from(window.inner_size().width, window.inner_size().height, 1),
+                format: ImageFormat::RGBA16F,
+                tiling: ImageTiling::Optimal,
+                memory: MemoryLocation::GpuOnly,
+                mip_levels: 1,
+                array_layers: 1,
+                cube_compatible: false,
+            }).expect("Failed to create hdr image")
+        }).collect::<Vec<_>>();
+
+        hdr_image_views = hdr_images.iter().map(|image| {
+            create_color_image_view(&_device, image, ImageFormat::RGBA16F.into())
+        }).collect::<Vec<_>>();
+
+        frame_buffers = hdr_image_views.iter().zip(&object_id_lookup_images_views).map(|(view, object_id_lookup)| {
 
             let attachments = [*view, *swapchain.depth_image_view(), *object_id_lookup];
 
@@ -615,8 +899,502 @@ pub fn create(
             }.unwrap()
         }).collect::<Vec<_>>();
 
-        command_buffers = (0..swapchain.views().len()).map(|_| {
-            let create_info = ash::vk::CommandBufferAllocateInfo::builder()
+        shadow_maps = (0..MAX_SHADOW_CASTERS).map(|index| {
+            resource_manager.create_image(format!("shadow-map-{index:?}"), &ImageAllocationDescriptor {
+                usage: [ImageUsage::DepthStencilAttachment, ImageUsage::Sampled],
+                extent: Extent::from(2 * SHADOW_MAP_RESOLUTION, 2 * SHADOW_MAP_RESOLUTION, 1), // 2x2 cascades
+                format: ImageFormat::Depth,
+                tiling: ImageTiling::Optimal,
+                memory: MemoryLocation::GpuOnly,
+                mip_levels: 1,
+                array_layers: 1,
+                cube_compatible: false,
+            }).expect("Failed to create shadow map image")
+        }).collect::<Vec<_>>();
+
+        shadow_map_views = shadow_maps.iter().map(|image| {
+
+            let create_image_view_info = vk::ImageViewCreateInfo::builder()
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(ImageFormat::Depth.into())
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::DEPTH,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image(*image.handle());
+
+            unsafe {
+                _device.handle().create_image_view(&create_image_view_info, None)
+            }.unwrap()
+        }).collect::<Vec<_>>();
+
+        shadow_frame_buffers = shadow_map_views.iter().map(|view| {
+
+            let attachments = [*view];
+
+            let create_info = ash::vk::FramebufferCreateInfo::builder()
+                .render_pass(shadow_renderpass)
+                .attachments(&attachments)
+                .width(2 * SHADOW_MAP_RESOLUTION)
+                .height(2 * SHADOW_MAP_RESOLUTION)
+                .layers(1);
+
+            unsafe {
+                _device.handle().create_framebuffer(&create_info, None)
+            }.unwrap()
+        }).collect::<Vec<_>>();
+
+        post_processing = {
+
+            let renderpass = create_fullscreen_render_pass(device.clone(), ImageFormat::RGBA16F.into(), ::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+            let present_renderpass = create_fullscreen_render_pass(device.clone(), swapchain.format(), ::ash::vk::ImageLayout::PRESENT_SRC_KHR);
+
+            let descriptor_set_layout = {
+                let bindings = [
+                    ::ash::vk::DescriptorSetLayoutBinding::builder()
+                        .binding(0)
+                        .descriptor_count(1)
+                        .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                        .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .build()
+                ];
+
+                let descriptor_set_layout_create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+                    .bindings(&bindings)
+                    .build();
+
+                unsafe {
+                    _device.handle().create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+                        .expect("Failed to create descriptor set layout!")
+                }
+            };
+
+            // the object ids and the selection read by the outline pass
+            let selection_descriptor_set_layout = {
+                let bindings = [
+                    ::ash::vk::DescriptorSetLayoutBinding::builder()
+                        .binding(0)
+                        .descriptor_count(1)
+                        .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                        .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .build(),
+                    ::ash::vk::DescriptorSetLayoutBinding::builder()
+                        .binding(1)
+                        .descriptor_count(1)
+                        .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                        .descriptor_type(::ash::vk::DescriptorType::UNIFORM_BUFFER)
+                        .build(),
+                ];
+
+                let descriptor_set_layout_create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+                    .bindings(&bindings)
+                    .build();
+
+                unsafe {
+                    _device.handle().create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+                        .expect("Failed to create descriptor set layout!")
+                }
+            };
+
+            let vertex_shader_module = ShaderModule::create(post_processing_vertex_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            let fragment_shader_module = ShaderModule::create(post_processing_fragment_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            let [pipeline, present_pipeline] = [renderpass, present_renderpass].map(|renderpass| {
+                FullscreenPipeline::new(
+                    &_device,
+                    &vertex_shader_module,
+                    &fragment_shader_module,
+                    &[descriptor_set_layout, selection_descriptor_set_layout],
+                    std::mem::size_of::<PostPushConstants>() as u32,
+                    renderpass,
+                    &FullscreenTarget::default(),
+                ).expect("Failed to create post-processing pipeline")
+            });
+
+            // the modules are not needed anymore, once the pipelines have been created
+            let _ = vertex_shader_module.unload(&_device);
+            let _ = fragment_shader_module.unload(&_device);
+
+            let images = (0..2).map(|index| {
+                resource_manager.create_image(format!("post-processing-{index:?}"), &ImageAllocationDescriptor {
+                    usage: [ImageUsage::ColorAttachment, ImageUsage::Sampled],
+                    extent: Extent::from(window.inner_size().width, window.inner_size().height, 1),
+                    format: ImageFormat::RGBA16F,
+                    tiling: ImageTiling::Optimal,
+                    memory: MemoryLocation::GpuOnly,
+                    mip_levels: 1,
+                    array_layers: 1,
+                    cube_compatible: false,
+                }).expect("Failed to create post-processing image")
+            }).collect::<Vec<_>>();
+
+            let image_views = images.iter().map(|image| {
+                create_color_image_view(&_device, image, ImageFormat::RGBA16F.into())
+            }).collect::<Vec<_>>();
+
+            let create_frame_buffer = |renderpass: ash::vk::RenderPass, view: &ImageView| {
+
+                let attachments = [*view];
+
+                let create_info = ash::vk::FramebufferCreateInfo::builder()
+                    .render_pass(renderpass)
+                    .attachments(&attachments)
+                    .width(window.inner_size().width)
+                    .height(window.inner_size().height)
+                    .layers(1);
+
+                unsafe {
+                    _device.handle().create_framebuffer(&create_info, None)
+                }.unwrap()
+            };
+
+            let frame_buffers = image_views.iter()
+                .map(|view| create_frame_buffer(renderpass, view))
+                .collect::<Vec<_>>();
+
+            let present_frame_buffers = swapchain.views().iter()
+                .map(|view| create_frame_buffer(present_renderpass, view))
+                .collect::<Vec<_>>();
+
+            let sampler = {
+                let sampler_create_info = ::ash::vk::SamplerCreateInfo::builder()
+                    .mag_filter(::ash::vk::Filter::LINEAR)
+                    .min_filter(::ash::vk::Filter::LINEAR)
+                    .address_mode_u(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+                unsafe {
+                    _device.handle().create_sampler(&sampler_create_info, None)
+                        .expect("Failed to create post-processing sampler")
+                }
+            };
+
+            let [hdr_descriptor_sets, descriptor_sets] = [&hdr_image_views, &image_views].map(|views| {
+                views.iter().map(|view| {
+
+                    let descriptor_set_layouts = [
+                        descriptor_set_layout
+                    ];
+                    let create_info = ash::vk::DescriptorSetAllocateInfo::builder()
+                        .descriptor_pool(descriptor_pool)
+                        .set_layouts(&descriptor_set_layouts);
+                    let descriptor_set = unsafe {
+                        _device.handle().allocate_descriptor_sets(&create_info)
+                            .expect("Failed to allocate descriptor set")[0]
+                    };
+
+                    let image_info = [
+                        ::ash::vk::DescriptorImageInfo::builder()
+                            .sampler(sampler)
+                            .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                            .image_view(*view)
+                            .build()
+                    ];
+                    let descriptor_writes = [
+                        ::ash::vk::WriteDescriptorSet::builder()
+                            .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .dst_set(descriptor_set)
+                            .dst_binding(0)
+                            .image_info(&image_info)
+                            .build(),
+                    ];
+                    unsafe {
+                        _device.handle().update_descriptor_sets(&descriptor_writes, &[])
+                    }
+
+                    descriptor_set
+                }).collect::<Vec<_>>()
+            });
+
+            // object ids must not be interpolated
+            let object_id_sampler = {
+                let sampler_create_info = ::ash::vk::SamplerCreateInfo::builder()
+                    .mag_filter(::ash::vk::Filter::NEAREST)
+                    .min_filter(::ash::vk::Filter::NEAREST)
+                    .address_mode_u(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+                unsafe {
+                    _device.handle().create_sampler(&sampler_create_info, None)
+                        .expect("Failed to create object id sampler")
+                }
+            };
+
+            let selection_buffer = {
+                let count = swapchain.views().len(); // one selection buffer per swapchain image
+
+                let mut buffer = resource_manager.create_buffer(String::from("selection-uniform-buffer"), &BufferAllocationDescriptor {
+                    usage: [BufferUsage::UniformBuffer],
+                    memory: MemoryLocation::CpuToGpu
+                }, count).expect("Failed to create selection uniform buffer");
+
+                let selections = (0..count).map(|_| {
+                    SelectionUniformBufferObject::new(&OutlineSettings::default(), &[])
+                }).collect::<Vec<_>>();
+
+                unsafe {
+                    resource_manager.copy(&selections, &mut buffer, 0, count)
+                        .expect("Failed to copy selection");
+                    resource_manager.flush(&mut buffer, 0, count)
+                        .expect("Failed to flush selection uniform buffer");
+                }
+
+                buffer
+            };
+
+            let selection_descriptor_sets = object_id_lookup_images_views.iter().enumerate().map(|(index, view)| {
+
+                let descriptor_set_layouts = [
+                    selection_descriptor_set_layout
+                ];
+                let create_info = ash::vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&descriptor_set_layouts);
+                let descriptor_set = unsafe {
+                    _device.handle().allocate_descriptor_sets(&create_info)
+                        .expect("Failed to allocate descriptor set")[0]
+                };
+
+                let size = std::mem::size_of::<SelectionUniformBufferObject>();
+                let image_info = [
+                    ::ash::vk::DescriptorImageInfo::builder()
+                        .sampler(object_id_sampler)
+                        .image_layout(::ash::vk::ImageLayout::GENERAL)
+                        .image_view(*view)
+                        .build()
+                ];
+                let buffer_info = [
+                    ::ash::vk::DescriptorBufferInfo::builder()
+                        .buffer(*selection_buffer.handle())
+                        .offset((index * size) as u64)
+                        .range(size as u64)
+                        .build()
+                ];
+                let descriptor_writes = [
+                    ::ash::vk::WriteDescriptorSet::builder()
+                        .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .dst_set(descriptor_set)
+                        .dst_binding(0)
+                        .image_info(&image_info)
+                        .build(),
+                    ::ash::vk::WriteDescriptorSet::builder()
+                        .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                        .dst_set(descriptor_set)
+                        .dst_binding(1)
+                        .buffer_info(&buffer_info)
+                        .build(),
+                ];
+                unsafe {
+                    _device.handle().update_descriptor_sets(&descriptor_writes, &[])
+                }
+
+                descriptor_set
+            }).collect::<Vec<_>>();
+
+            let srgb_output = matches!(swapchain.format(), ash::vk::Format::B8G8R8A8_SRGB | ash::vk::Format::R8G8B8A8_SRGB);
+
+            PostProcessing {
+                renderpass,
+                present_renderpass,
+                pipeline,
+                present_pipeline,
+                images,
+                image_views,
+                frame_buffers,
+                present_frame_buffers,
+                sampler,
+                hdr_descriptor_sets,
+                descriptor_sets,
+                object_id_sampler,
+                selection_buffer,
+                selection_descriptor_sets,
+                srgb_output,
+            }
+        };
+
+        debug_drawing = {
+
+            let vertex_shader_module = ShaderModule::create(debug_vertex_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            let fragment_shader_module = ShaderModule::create(debug_fragment_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            // the lines are not written into the object ids, so that they cannot be picked
+            let [pipeline, overlay_pipeline] = [true, false].map(|depth_test| {
+                LinePipeline::new(
+                    &_device,
+                    &vertex_shader_module,
+                    &fragment_shader_module,
+                    &[global_descriptor_set_layout],
+                    &DebugVertex::vertex_layout(),
+                    renderpass,
+                    2,
+                    depth_test,
+                ).expect("Failed to create debug line pipeline")
+            });
+
+            let _ = vertex_shader_module.unload(&_device);
+            let _ = fragment_shader_module.unload(&_device);
+
+            let vertex_buffer = resource_manager.create_buffer(String::from("debug-vertex-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::VertexBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, swapchain.views().len() * MAX_DEBUG_VERTICES).expect("Failed to create debug vertex buffer"); // one range per swapchain image
+
+            DebugDrawing {
+                pipeline,
+                overlay_pipeline,
+                vertex_buffer,
+            }
+        };
+
+        particle_drawing = {
+
+            let vertex_shader_module = ShaderModule::create(particle_vertex_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            let fragment_shader_module = ShaderModule::create(particle_fragment_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            // like additive geometries, particles are not written into the object ids
+            let [alpha_pipeline, additive_pipeline] = [false, true].map(|additive| {
+                BillboardPipeline::new(
+                    &_device,
+                    &vertex_shader_module,
+                    &fragment_shader_module,
+                    &[global_descriptor_set_layout],
+                    &ParticleVertex::vertex_layout(),
+                    renderpass,
+                    2,
+                    additive,
+                ).expect("Failed to create particle pipeline")
+            });
+
+            let _ = vertex_shader_module.unload(&_device);
+            let _ = fragment_shader_module.unload(&_device);
+
+            let vertex_buffer = resource_manager.create_buffer(String::from("particle-vertex-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::VertexBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, swapchain.views().len() * MAX_PARTICLE_VERTICES).expect("Failed to create particle vertex buffer"); // one range per swapchain image
+
+            ParticleDrawing {
+                alpha_pipeline,
+                additive_pipeline,
+                vertex_buffer,
+                vertices: Vec::new(),
+            }
+        };
+
+        (overlay_drawing, overlay) = {
+
+            let vertex_shader_module = ShaderModule::create(overlay_vertex_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            let fragment_shader_module = ShaderModule::create(overlay_fragment_shader, "main")
+                .and_then(|module| module.load(&_device))
+                .unwrap();
+
+            // the overlay is drawn after the post-processing, directly into the swapchain image
+            let pipeline = OverlayPipeline::new(
+                &_device,
+                &vertex_shader_module,
+                &fragment_shader_module,
+                &[texture_table.layout()],
+                std::mem::size_of::<OverlayPushConstants>() as u32,
+                &OverlayVertex::vertex_layout(),
+                post_processing.present_renderpass,
+            ).expect("Failed to create overlay pipeline");
+
+            let _ = vertex_shader_module.unload(&_device);
+            let _ = fragment_shader_module.unload(&_device);
+
+            // glyphs are drawn at integral scales, they must stay crisp
+            let sampler = {
+                let sampler_create_info = ::ash::vk::SamplerCreateInfo::builder()
+                    .mag_filter(::ash::vk::Filter::NEAREST)
+                    .min_filter(::ash::vk::Filter::NEAREST)
+                    .address_mode_u(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+                unsafe {
+                    _device.handle().create_sampler(&sampler_create_info, None)
+                        .expect("Failed to create overlay sampler")
+                }
+            };
+
+            let font = FontAtlas::builtin();
+            let queue = Rc::clone(&_device.queues()[0]);
+
+            let font_image = upload_image(&_device, &queue, &mut resource_manager, "overlay-font", &ImageAllocationDescriptor {
+                usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+                extent: font.texture.extent,
+                format: ImageFormat::RGBA8,
+                tiling: ImageTiling::Optimal,
+                memory: MemoryLocation::GpuOnly,
+                mip_levels: 1,
+                array_layers: 1,
+                cube_compatible: false,
+            }, &[font.texture.data.clone()]);
+
+            let font_image_view = create_color_image_view(&_device, &font_image, ImageFormat::RGBA8.into());
+
+            let font_texture = texture_table.insert(font_image_view, sampler)
+                .expect("Failed to add the overlay font to the texture table");
+
+            let overlay = Overlay::new(&font, font_texture);
+
+            let font = OverlayTexture {
+                image: font_image,
+                image_view: font_image_view,
+                index: font_texture,
+            };
+
+            let vertex_buffer = resource_manager.create_buffer(String::from("overlay-vertex-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::VertexBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, swapchain.views().len() * MAX_OVERLAY_VERTICES).expect("Failed to create overlay vertex buffer"); // one range per swapchain image
+
+            (OverlayDrawing {
+                pipeline,
+                sampler,
+                font: Some(font),
+                vertex_buffer,
+            }, overlay)
+        };
+
+        capture_buffer = {
+
+            let count = {
+                let extent = swapchain.extent();
+                (extent.width * extent.height * 4) as usize
+            };
+
+            resource_manager.create_buffer(String::from("capture-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::TransferDestinationBuffer],
+                memory: MemoryLocation::GpuToCpu
+            }, count).expect("Failed to create capture-buffer")
+        };
+
+        command_buffers = (0..swapchain.views().len()).map(|_| {
+            let create_info = ash::vk::CommandBufferAllocateInfo::builder()
                 .command_pool(_device.command_pool().handle())
                 .command_buffer_count(1)
                 .level(ash::vk::CommandBufferLevel::PRIMARY)
@@ -638,6 +1416,23 @@ pub fn create(
             _device.handle().create_semaphore(&semaphore_create_info, None)
         }.unwrap();
 
+        compute_finished_semaphore = unsafe {
+            _device.handle().create_semaphore(&semaphore_create_info, None)
+        }.unwrap();
+
+        // dispatches are submitted to the async compute queue, if the device has one
+        compute_command_buffer = {
+            let create_info = ash::vk::CommandBufferAllocateInfo::builder()
+                .command_pool(_device.compute_command_pool().unwrap_or_else(|| _device.command_pool().handle()))
+                .command_buffer_count(1)
+                .level(ash::vk::CommandBufferLevel::PRIMARY)
+                .build();
+
+            unsafe {
+                _device.handle().allocate_command_buffers(&create_info)
+            }.unwrap()[0]
+        };
+
         command_buffers_completed_fence = {
             let fence_create_info = ::ash::vk::FenceCreateInfo::builder()
                 .build();
@@ -658,9 +1453,7 @@ pub fn create(
             }, count).expect("Failed to create uniform buffer");
 
             let ubos = (0..count).map(|_| {
-                UniformBufferObject {
-                    mvp: Matrix4::identity(),
-                }
+                UniformBufferObject::new()
             }).collect::<Vec<_>>();
 
             unsafe {
@@ -671,6 +1464,72 @@ pub fn create(
             buffer
         };
 
+        lights_buffer = {
+            let count = swapchain.views().len(); // one light buffer per swapchain image
+
+            let mut buffer = resource_manager.create_buffer(String::from("lights-uniform-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::UniformBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, count).expect("Failed to create lights uniform buffer");
+
+            let lights = (0..count).map(|_| {
+                LightsUniformBufferObject::new(&[0.0, 0.0, 0.0], &[])
+            }).collect::<Vec<_>>();
+
+            unsafe {
+                resource_manager.copy(&lights, &mut buffer, 0, count)
+                    .expect("Failed to copy lights");
+                resource_manager.flush(&mut buffer, 0, count)
+                    .expect("Failed to flush lights uniform buffer");
+            }
+
+            buffer
+        };
+
+        shadow_cascades_buffer = {
+            let count = swapchain.views().len() * MAX_SHADOW_CASTERS * SHADOW_CASCADES; // one ubo per cascade and swapchain image
+
+            let mut buffer = resource_manager.create_buffer(String::from("shadow-cascades-uniform-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::UniformBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, count).expect("Failed to create shadow cascades uniform buffer");
+
+            let ubos = (0..count).map(|_| {
+                UniformBufferObject::new()
+            }).collect::<Vec<_>>();
+
+            unsafe {
+                resource_manager.copy(&ubos, &mut buffer, 0, count)
+                    .expect("Failed to copy shadow cascades");
+                resource_manager.flush(&mut buffer, 0, count)
+                    .expect("Failed to flush shadow cascades uniform buffer");
+            }
+
+            buffer
+        };
+
+        shadows_buffer = {
+            let count = swapchain.views().len(); // one shadow buffer per swapchain image
+
+            let mut buffer = resource_manager.create_buffer(String::from("shadows-uniform-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::UniformBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, count).expect("Failed to create shadows uniform buffer");
+
+            let shadows = (0..count).map(|_| {
+                ShadowsUniformBufferObject::new(&[0.0; SHADOW_CASCADES], &[], &Default::default())
+            }).collect::<Vec<_>>();
+
+            unsafe {
+                resource_manager.copy(&shadows, &mut buffer, 0, count)
+                    .expect("Failed to copy shadows");
+                resource_manager.flush(&mut buffer, 0, count)
+                    .expect("Failed to flush shadows uniform buffer");
+            }
+
+            buffer
+        };
+
         object_id_lookup_buffer = {
 
             let count = {
@@ -706,8 +1565,68 @@ pub fn create(
             }
         };
 
+        default_textures = [
+            ("default-base-color-texture", MaterialDescriptor::DEFAULT_BASE_COLOR, ImageFormat::SRGBA8),
+            ("default-metallic-roughness-texture", MaterialDescriptor::DEFAULT_METALLIC_ROUGHNESS, ImageFormat::RGBA8),
+            ("default-normal-texture", MaterialDescriptor::DEFAULT_NORMAL, ImageFormat::RGBA8),
+            ("default-occlusion-texture", MaterialDescriptor::DEFAULT_OCCLUSION, ImageFormat::RGBA8),
+            ("default-emissive-texture", MaterialDescriptor::DEFAULT_EMISSIVE, ImageFormat::SRGBA8),
+        ].iter().map(|(name, color, format)| {
+            create_texture(&_device, &mut resource_manager, &mut texture_table, texture_sampler, name, &TextureData::solid(*color), *format)
+        }).collect::<Vec<_>>();
+
+        shadow_sampler = {
+            let sampler_create_info = ::ash::vk::SamplerCreateInfo::builder()
+                .mag_filter(::ash::vk::Filter::LINEAR)
+                .min_filter(::ash::vk::Filter::LINEAR)
+                .address_mode_u(::ash::vk::SamplerAddressMode::CLAMP_TO_BORDER)
+                .address_mode_v(::ash::vk::SamplerAddressMode::CLAMP_TO_BORDER)
+                .address_mode_w(::ash::vk::SamplerAddressMode::CLAMP_TO_BORDER)
+                .border_color(::ash::vk::BorderColor::FLOAT_OPAQUE_WHITE)
+                .compare_enable(true)
+                .compare_op(::ash::vk::CompareOp::LESS_OR_EQUAL);
+
+            unsafe {
+                _device.handle().create_sampler(&sampler_create_info, None)
+                    .expect("Failed to create shadow sampler")
+            }
+        };
+
+        // the specular map is sampled between its levels by roughness
+        environment_sampler = {
+            let sampler_create_info = ::ash::vk::SamplerCreateInfo::builder()
+                .mag_filter(::ash::vk::Filter::LINEAR)
+                .min_filter(::ash::vk::Filter::LINEAR)
+                .mipmap_mode(::ash::vk::SamplerMipmapMode::LINEAR)
+                .address_mode_u(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_v(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_w(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .max_lod(::ash::vk::LOD_CLAMP_NONE);
+
+            unsafe {
+                _device.handle().create_sampler(&sampler_create_info, None)
+                    .expect("Failed to create environment sampler")
+            }
+        };
+
+        default_environment_lighting = upload_environment_lighting(
+            &_device,
+            &_device.queues()[0],
+            &mut resource_manager,
+            &EnvironmentLightingData::none(),
+        );
+
         {
             let size: usize = std::mem::size_of::<UniformBufferObject>();
+            let lights_size: usize = std::mem::size_of::<LightsUniformBufferObject>();
+            let shadows_size: usize = std::mem::size_of::<ShadowsUniformBufferObject>();
+            let shadow_map_infos = shadow_map_views.iter().map(|view| {
+                ::ash::vk::DescriptorImageInfo::builder()
+                    .sampler(shadow_sampler)
+                    .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .image_view(*view)
+                    .build()
+            }).collect::<Vec<_>>();
             global_descriptor_sets.iter().enumerate().for_each(|(index, descriptor_set)| {
                 let buffer_info = [
                     ::ash::vk::DescriptorBufferInfo::builder()
@@ -716,6 +1635,20 @@ pub fn create(
                         .range(size as u64)
                         .build()
                 ];
+                let lights_buffer_info = [
+                    ::ash::vk::DescriptorBufferInfo::builder()
+                        .buffer(*lights_buffer.handle())
+                        .offset((index * lights_size) as u64)
+                        .range(lights_size as u64)
+                        .build()
+                ];
+                let shadows_buffer_info = [
+                    ::ash::vk::DescriptorBufferInfo::builder()
+                        .buffer(*shadows_buffer.handle())
+                        .offset((index * shadows_size) as u64)
+                        .range(shadows_size as u64)
+                        .build()
+                ];
                 let descriptor_writes = [
                     ::ash::vk::WriteDescriptorSet::builder()
                         .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
@@ -723,37 +1656,118 @@ pub fn create(
                         .dst_binding(0)
                         .buffer_info(&buffer_info)
                         .build(),
+                    ::ash::vk::WriteDescriptorSet::builder()
+                        .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                        .dst_set(*descriptor_set)
+                        .dst_binding(1)
+                        .buffer_info(&lights_buffer_info)
+                        .build(),
+                    ::ash::vk::WriteDescriptorSet::builder()
+                        .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .dst_set(*descriptor_set)
+                        .dst_binding(2)
+                        .image_info(&shadow_map_infos)
+                        .build(),
+                    ::ash::vk::WriteDescriptorSet::builder()
+                        .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                        .dst_set(*descriptor_set)
+                        .dst_binding(3)
+                        .buffer_info(&shadows_buffer_info)
+                        .build(),
                 ];
                 let descriptor_copies: [ash::vk::CopyDescriptorSet; 0] = [];
                 unsafe {
                     _device.handle().update_descriptor_sets(&descriptor_writes, &descriptor_copies)
                 }
             });
-        }
-    }
 
-    return Ok(Engine {
-        instance,
-        device,
-        resource_manager,
-        assets_manager,
-        surface,
-        swapchain,
-        frame_buffers,
-        command_buffers,
-        renderpass,
-        viewports,
-        scissors,
-        pipelines,
+            write_environment_lighting(&_device, &global_descriptor_sets, environment_sampler, &default_environment_lighting);
+
+            // the shadow pass only reads the uniform buffer in its vertex shader
+            shadow_descriptor_sets.iter().enumerate().for_each(|(index, descriptor_set)| {
+                let buffer_info = [
+                    ::ash::vk::DescriptorBufferInfo::builder()
+                        .buffer(*shadow_cascades_buffer.handle())
+                        .offset((index * size) as u64)
+                        .range(size as u64)
+                        .build()
+                ];
+                let descriptor_writes = [
+                    ::ash::vk::WriteDescriptorSet::builder()
+                        .descriptor_type(ash::vk::DescriptorType::UNIFORM_BUFFER)
+                        .dst_set(*descriptor_set)
+                        .dst_binding(0)
+                        .buffer_info(&buffer_info)
+                        .build(),
+                ];
+                let descriptor_copies: [ash::vk::CopyDescriptorSet; 0] = [];
+                unsafe {
+                    _device.handle().update_descriptor_sets(&descriptor_writes, &descriptor_copies)
+                }
+            });
+        }
+    }
+
+    return Ok(Engine {
+        instance,
+        device,
+        resource_manager,
+        assets_manager,
+        surface,
+        swapchain,
+        frame_buffers,
+        command_buffers,
+        renderpass,
+        viewports,
+        scissors,
+        vertex_shader_module,
+        fragment_shader_module,
+        skinning_shader_module,
+        geometry_pipelines,
         pipeline_layout,
+        culling_descriptor_set_layout,
+        culling_pipeline,
+        compute_command_buffer,
+        compute_finished_semaphore,
+        compute_recording: false,
         descriptor_pool,
+        global_descriptor_set_layout,
         global_descriptor_sets,
+        descriptor_allocator,
         material_descriptor_set_layout,
+        skin_descriptor_set_layout,
+        texture_table,
         texture_sampler,
+        default_textures,
         ubo_buffer,
+        lights_buffer,
+        shadow_renderpass,
+        shadow_maps,
+        shadow_map_views,
+        shadow_frame_buffers,
+        shadow_sampler,
+        shadow_descriptor_sets,
+        shadow_cascades_buffer,
+        shadows_buffer,
+        environment_sampler,
+        bound_environment_lighting: default_environment_lighting.irradiance_view,
+        default_environment_lighting,
         object_id_lookup_images,
         object_id_lookup_images_views,
         object_id_lookup_buffer,
+        selection: Vec::new(),
+        hdr_images,
+        hdr_image_views,
+        post_processing,
+        debug_lines: DebugLines::default(),
+        debug_drawing,
+        particle_drawing,
+        overlay,
+        overlay_drawing,
+        capture_buffer,
+        capture_requested: false,
+        captured_frame: None,
+        frame_sequence: None,
         image_available_semaphore,
         render_finished_semaphore,
         command_buffers_completed_fence,
@@ -761,41 +1775,499 @@ pub fn create(
         vertices_query_pool,
         last_timing_value: [0],
         last_swapchain_image_index: 0,
+        start_time: Instant::now(),
+        last_frame_time: Instant::now(),
+        frame_index: 0,
     });
 }
 
-pub fn create_geometry(
+fn create_color_image_view(device: &Device, image: &Image, format: ash::vk::Format) -> ImageView {
+
+    let create_image_view_info = vk::ImageViewCreateInfo::builder()
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(format)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+        .image(*image.handle());
+
+    unsafe {
+        device.handle().create_image_view(&create_image_view_info, None)
+    }.unwrap()
+}
+
+/// Creates a view of all layers of the levels `base_mip_level..base_mip_level + level_count`,
+/// e.g. of cube maps or of single levels of them to be written by compute shaders.
+fn create_layered_image_view(
+    device: &Device,
+    image: &Image,
+    view_type: ash::vk::ImageViewType,
+    format: ash::vk::Format,
+    base_mip_level: u32,
+    level_count: u32,
+    layer_count: u32,
+) -> ImageView {
+
+    let create_image_view_info = vk::ImageViewCreateInfo::builder()
+        .view_type(view_type)
+        .format(format)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count,
+        })
+        .image(*image.handle());
+
+    unsafe {
+        device.handle().create_image_view(&create_image_view_info, None)
+    }.unwrap()
+}
+
+/// The pipelines and the vertices of the debug lines drawn at the end of the main pass.
+struct DebugDrawing {
+    pipeline: LinePipeline,
+    /// Draws the lines without depth test.
+    overlay_pipeline: LinePipeline,
+    /// Holds [MAX_DEBUG_VERTICES] per swapchain image, the depth-tested lines first.
+    vertex_buffer: Buffer<DebugVertex>,
+}
+
+/// The pipelines and the billboards of the particles drawn after the transparent geometries.
+struct ParticleDrawing {
+    alpha_pipeline: BillboardPipeline,
+    additive_pipeline: BillboardPipeline,
+    /// Holds [MAX_PARTICLE_VERTICES] per swapchain image, the additive billboards first.
+    vertex_buffer: Buffer<ParticleVertex>,
+    /// The billboards of the current frame, kept to reuse the allocation.
+    vertices: Vec<ParticleVertex>,
+}
+
+/// The pipeline, the builtin font and the vertices of the overlay drawn at the end of the
+/// present pass.
+struct OverlayDrawing {
+    pipeline: OverlayPipeline,
+    /// Samples the font and the textures created by [create_overlay_texture].
+    sampler: ::ash::vk::Sampler,
+    /// Taken when the engine is dropped.
+    font: Option<OverlayTexture>,
+    /// Holds [MAX_OVERLAY_VERTICES] per swapchain image.
+    vertex_buffer: Buffer<OverlayVertex>,
+}
+
+/// The resources of the fullscreen passes post-processing the HDR target. The passes render
+/// alternately into two intermediate images, the last pass copies the result into the swapchain.
+struct PostProcessing {
+    renderpass: ash::vk::RenderPass,
+    present_renderpass: ash::vk::RenderPass,
+    pipeline: FullscreenPipeline,
+    present_pipeline: FullscreenPipeline,
+    images: Vec<Image>,
+    image_views: Vec<ImageView>,
+    frame_buffers: Vec<ash::vk::Framebuffer>,
+    /// One per swapchain image.
+    present_frame_buffers: Vec<ash::vk::Framebuffer>,
+    sampler: ::ash::vk::Sampler,
+    /// Sample the HDR target of every swapchain image.
+    hdr_descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    /// Sample the intermediate images.
+    descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    object_id_sampler: ::ash::vk::Sampler,
+    /// One selection per swapchain image.
+    selection_buffer: Buffer<SelectionUniformBufferObject>,
+    /// Sample the object ids and read the selection of every swapchain image.
+    selection_descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    /// Whether the swapchain encodes the colors to sRGB, so that no gamma pass is needed.
+    srgb_output: bool,
+}
+
+/// The pipelines drawing the geometries with a particular vertex layout.
+struct GeometryPipelines {
+    vertex_layout: VertexLayout,
+    /// The filled and the wireframe pipeline of the main pass.
+    pipelines: Vec<ash::vk::Pipeline>,
+    /// The pipelines of transparent geometries, blending by alpha and additively.
+    transparent_pipelines: Vec<ash::vk::Pipeline>,
+    shadow_pipeline: ash::vk::Pipeline,
+}
+
+impl GeometryPipelines {
+
+    /// Returns the pipeline drawing the geometry filled.
+    fn fill_pipeline(&self, blend_mode: BlendMode) -> ash::vk::Pipeline {
+        match blend_mode {
+            BlendMode::Opaque | BlendMode::Mask { .. } => self.pipelines[0],
+            BlendMode::Blend => self.transparent_pipelines[0],
+            BlendMode::Additive => self.transparent_pipelines[1],
+        }
+    }
+}
+
+fn create_geometry_pipelines(
+    device: &Device,
+    vertex_shader_module: &LoadedShaderModule<VertexShaderBinary>,
+    fragment_shader_module: &LoadedShaderModule<FragmentShaderBinary>,
+    vertex_layout: &VertexLayout,
+    pipeline_layout: ash::vk::PipelineLayout,
+    renderpass: ash::vk::RenderPass,
+    shadow_renderpass: ash::vk::RenderPass,
+) -> GeometryPipelines {
+
+    let shader_stage_create_infos = [
+        vertex_shader_module.create_pipeline_shader_stage_create_info(),
+        fragment_shader_module.create_pipeline_shader_stage_create_info(),
+    ];
+
+    let (vertex_input_binding_descriptors, vertex_input_attribute_descriptors) = vertex_layout.input_descriptions();
+
+    let vertex_input_state_info = ash::vk::PipelineVertexInputStateCreateInfo::builder()
+        .vertex_binding_descriptions(&vertex_input_binding_descriptors)
+        .vertex_attribute_descriptions(&vertex_input_attribute_descriptors);
+
+    let vertex_input_assembly_state_info = ash::vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .primitive_restart_enable(false);
+
+    // viewport and scissor are dynamic state, only their count is needed
+    let viewport_state_info = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let rasterization_info_fill_mode = ash::vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(ash::vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(ash::vk::CullModeFlags::BACK)
+        .front_face(ash::vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false)
+        .depth_bias_constant_factor(0.0)
+        .depth_bias_clamp(0.0)
+        .depth_bias_slope_factor(0.0)
+        .build();
+
+    let rasterization_info_line_mode = ash::vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(ash::vk::PolygonMode::LINE)
+        .line_width(3.0)
+        .cull_mode(ash::vk::CullModeFlags::BACK)
+        .front_face(ash::vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false)
+        .depth_bias_constant_factor(0.0)
+        .depth_bias_clamp(0.0)
+        .depth_bias_slope_factor(0.0)
+        .build();
+
+    let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+        .min_sample_shading(1.0)
+        .alpha_to_coverage_enable(false)
+        .alpha_to_one_enable(false)
+        .build();
+
+    let noop_stencil_state = vk::StencilOpState {
+        fail_op: vk::StencilOp::KEEP,
+        pass_op: vk::StencilOp::KEEP,
+        depth_fail_op: vk::StencilOp::KEEP,
+        compare_op: vk::CompareOp::ALWAYS,
+        ..Default::default()
+    };
+
+    let depth_state_info = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false)
+        .build();
+
+    // per frame buffer
+    let color_blend_attachment_states = [
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(
+                vk::ColorComponentFlags::R
+                    | vk::ColorComponentFlags::G
+                    | vk::ColorComponentFlags::B
+                    | vk::ColorComponentFlags::A
+            )
+            .blend_enable(false)
+            .src_color_blend_factor(ash::vk::BlendFactor::ONE)
+            .dst_color_blend_factor(ash::vk::BlendFactor::ZERO)
+            .color_blend_op(ash::vk::BlendOp::ADD)
+            .src_color_blend_factor(ash::vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(ash::vk::BlendFactor::ZERO)
+            .alpha_blend_op(ash::vk::BlendOp::ADD)
+            .build(),
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(
+                vk::ColorComponentFlags::R
+                    | vk::ColorComponentFlags::G
+                    | vk::ColorComponentFlags::B
+                    | vk::ColorComponentFlags::A
+            )
+            .blend_enable(false)
+            .src_color_blend_factor(ash::vk::BlendFactor::ONE)
+            .dst_color_blend_factor(ash::vk::BlendFactor::ZERO)
+            .color_blend_op(ash::vk::BlendOp::ADD)
+            .src_color_blend_factor(ash::vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(ash::vk::BlendFactor::ZERO)
+            .alpha_blend_op(ash::vk::BlendOp::ADD)
+            .build(),
+    ];
+
+    // for all frame buffers - global
+    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(ash::vk::LogicOp::COPY)
+        .attachments(&color_blend_attachment_states)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    let dynamic_state = [
+        ash::vk::DynamicState::VIEWPORT,
+        ash::vk::DynamicState::SCISSOR
+    ];
+
+    let dynamic_state_info = ash::vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(&dynamic_state);
+
+    let default_graphic_pipeline_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
+        .flags(::ash::vk::PipelineCreateFlags::ALLOW_DERIVATIVES)
+        .stages(&shader_stage_create_infos)
+        .vertex_input_state(&vertex_input_state_info)
+        .input_assembly_state(&vertex_input_assembly_state_info)
+        .viewport_state(&viewport_state_info)
+        .rasterization_state(&rasterization_info_fill_mode)
+        .multisample_state(&multisample_state_info)
+        .depth_stencil_state(&depth_state_info)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(renderpass)
+        .subpass(0)
+        .build();
+
+    // transparent geometries are tested against the depth of the opaque ones, but do not occlude
+    // each other, as they are sorted from back to front
+    let transparent_depth_state_info = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(false)
+        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false)
+        .build();
+
+    // blended geometries write their object id, so that they can be picked, additive ones are
+    // considered effects and keep the id of what is behind them
+    let transparent_color_blend_attachment_states = [
+        (ash::vk::BlendFactor::ONE_MINUS_SRC_ALPHA, vk::ColorComponentFlags::R | vk::ColorComponentFlags::G | vk::ColorComponentFlags::B | vk::ColorComponentFlags::A),
+        (ash::vk::BlendFactor::ONE, vk::ColorComponentFlags::empty()),
+    ].map(|(dst_color_blend_factor, object_id_write_mask)| [
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(
+                vk::ColorComponentFlags::R
+                    | vk::ColorComponentFlags::G
+                    | vk::ColorComponentFlags::B
+                    | vk::ColorComponentFlags::A
+            )
+            .blend_enable(true)
+            .src_color_blend_factor(ash::vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(dst_color_blend_factor)
+            .color_blend_op(ash::vk::BlendOp::ADD)
+            .src_alpha_blend_factor(ash::vk::BlendFactor::ZERO)
+            .dst_alpha_blend_factor(ash::vk::BlendFactor::ONE)
+            .alpha_blend_op(ash::vk::BlendOp::ADD)
+            .build(),
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(object_id_write_mask)
+            .blend_enable(false)
+            .build(),
+    ]);
+
+    let transparent_color_blend_states = transparent_color_blend_attachment_states.iter().map(|attachment_states| {
+        vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .logic_op(ash::vk::LogicOp::COPY)
+            .attachments(attachment_states)
+            .blend_constants([0.0, 0.0, 0.0, 0.0])
+            .build()
+    }).collect::<Vec<_>>();
+
+    let transparent_graphic_pipeline_infos = transparent_color_blend_states.iter().map(|color_blend_state| {
+        ::ash::vk::GraphicsPipelineCreateInfo::builder()
+            .flags(::ash::vk::PipelineCreateFlags::DERIVATIVE)
+            .base_pipeline_index(0)
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&vertex_input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info_fill_mode)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&transparent_depth_state_info)
+            .color_blend_state(color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(pipeline_layout)
+            .render_pass(renderpass)
+            .subpass(0)
+            .build()
+    }).collect::<Vec<_>>();
+
+    let line_mode_graphic_pipeline_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
+        .flags(::ash::vk::PipelineCreateFlags::DERIVATIVE)
+        .base_pipeline_index(0)
+        .stages(&shader_stage_create_infos)
+        .vertex_input_state(&vertex_input_state_info)
+        .input_assembly_state(&vertex_input_assembly_state_info)
+        .viewport_state(&viewport_state_info)
+        .rasterization_state(&rasterization_info_line_mode)
+        .multisample_state(&multisample_state_info)
+        .depth_stencil_state(&depth_state_info)
+        .color_blend_state(&color_blend_state)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(renderpass)
+        .subpass(0)
+        .build();
+
+    let mut pipelines = unsafe {
+        device.handle().create_graphics_pipelines(
+            ::ash::vk::PipelineCache::null(),
+            &[
+                default_graphic_pipeline_info,
+                line_mode_graphic_pipeline_info,
+                transparent_graphic_pipeline_infos[0],
+                transparent_graphic_pipeline_infos[1],
+            ],
+            None,
+        )
+    }.expect("Failed to create graphic pipelines");
+
+    let transparent_pipelines = pipelines.split_off(2);
+
+    let shadow_pipeline = {
+
+        let shader_stage_create_infos = [
+            vertex_shader_module.create_pipeline_shader_stage_create_info(),
+        ];
+
+        let rasterization_info = ash::vk::PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(ash::vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(ash::vk::CullModeFlags::NONE)
+            .front_face(ash::vk::FrontFace::COUNTER_CLOCKWISE)
+            .depth_bias_enable(true)
+            .depth_bias_constant_factor(1.25)
+            .depth_bias_clamp(0.0)
+            .depth_bias_slope_factor(1.75)
+            .build();
+
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .logic_op(ash::vk::LogicOp::COPY)
+            .attachments(&[])
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        let shadow_graphic_pipeline_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&vertex_input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(pipeline_layout)
+            .render_pass(shadow_renderpass)
+            .subpass(0)
+            .build();
+
+        unsafe {
+            device.handle().create_graphics_pipelines(
+                ::ash::vk::PipelineCache::null(),
+                &[shadow_graphic_pipeline_info],
+                None,
+            )
+        }.expect("Failed to create shadow pipeline")[0]
+    };
+
+    info!("Created pipelines for vertex layout with {} attributes.", vertex_layout.attributes().len());
+
+    GeometryPipelines {
+        vertex_layout: Clone::clone(vertex_layout),
+        pipelines,
+        transparent_pipelines,
+        shadow_pipeline,
+    }
+}
+
+pub fn create_geometry<V: HasVertexLayout>(
     engine: &mut Engine,
-    indices: &Vec<u32>,
-    vertices: &Vec<Vertex>,
+    indices: &IndexData,
+    vertices: &[V],
     texture_data: &Vec<u8>,
     texture_extent: Extent,
     instances: &Vec<InstanceData>,
-) -> Geometry {
+) -> Result<Geometry, VertexLayoutError> {
 
-    let _device = (*engine.device).borrow();
-    let mut resource_manager = &mut engine.resource_manager;
+    // before the material is created, which would leak otherwise
+    prepare_geometry_pipelines(engine, &V::vertex_layout())?;
 
-    let index_buffer = {
+    let material = create_material(engine, &MaterialDescriptor {
+        base_color_texture: Some(TextureData::new(Clone::clone(texture_data), texture_extent)),
+        ..MaterialDescriptor::principled()
+    });
 
-        let size: usize = (indices.len() * std::mem::size_of::<u32>());
+    Ok(create_prepared_geometry(engine, indices, vertices, material, instances))
+}
 
-        let mut buffer = resource_manager.create_buffer(String::from("geometry-index-buffer"), &BufferAllocationDescriptor {
-            usage: [BufferUsage::IndexBuffer],
-            memory: MemoryLocation::CpuToGpu
-        }, indices.len()).expect("geometry index buffer");
+/// Creates a geometry of vertices of any type with a vertex layout. The vertex shader has to read
+/// its inputs at the locations of the layout's semantics.
+///
+/// Fails if the layout has no attribute at one of the vertex shader's input locations, the
+/// material is destroyed then.
+pub fn create_geometry_with_material<V: HasVertexLayout>(
+    engine: &mut Engine,
+    indices: &IndexData,
+    vertices: &[V],
+    material: Material,
+    instances: &Vec<InstanceData>,
+) -> Result<Geometry, VertexLayoutError> {
 
-        unsafe {
-            resource_manager.copy(&indices, &mut buffer, 0, indices.len());
-            resource_manager.flush(&mut buffer, 0, indices.len());
-        }
+    if let Err(error) = prepare_geometry_pipelines(engine, &V::vertex_layout()) {
+        destroy_material(engine, material);
+        return Err(error)
+    }
 
-        buffer
-    };
+    Ok(create_prepared_geometry(engine, indices, vertices, material, instances))
+}
 
-    let vertex_buffer = {
+/// Creates a geometry, once the pipelines for its vertex layout have been prepared.
+fn create_prepared_geometry<V: HasVertexLayout>(
+    engine: &mut Engine,
+    indices: &IndexData,
+    vertices: &[V],
+    material: Material,
+    instances: &Vec<InstanceData>,
+) -> Geometry {
 
-        let size: usize = (vertices.len() * std::mem::size_of::<Vertex>());
+    let vertex_layout = V::vertex_layout();
+    let vertices = vertex_bytes(vertices);
+    let positions = vertex_layout.positions(vertices);
+
+    let resource_manager = &mut engine.resource_manager;
+
+    let index_buffer = create_index_buffer(resource_manager, String::from("geometry-index-buffer"), indices);
+
+    let vertex_buffer = {
 
         let mut buffer = resource_manager.create_buffer(String::from("geometry-vertex-buffer"), &BufferAllocationDescriptor {
             usage: [BufferUsage::VertexBuffer],
@@ -810,123 +2282,1173 @@ pub fn create_geometry(
         buffer
     };
 
-    let texture_transfere_buffer = {
-
-        let size: usize = texture_data.len() * std::mem::size_of::<u8>();
+    let instances_buffer = {
 
-        let mut buffer = resource_manager.create_buffer(String::from("texture-transfer-buffer"), &BufferAllocationDescriptor {
-            usage: [BufferUsage::TransferSourceBuffer],
-            memory: MemoryLocation::CpuToGpu,
-        }, size).expect("Failed to create texture data transfer buffer");
+        let mut buffer = resource_manager.create_buffer(String::from("geometry-instance-data-buffer"), &BufferAllocationDescriptor {
+            usage: [BufferUsage::VertexBuffer, BufferUsage::StorageBuffer],
+            memory: MemoryLocation::CpuToGpu
+        }, instances.len()).expect("geometry instance data buffer");
 
         unsafe {
-            resource_manager.copy(&texture_data, &mut buffer, 0, texture_data.len());
-            resource_manager.flush(&mut buffer, 0, texture_data.len());
+            resource_manager.copy(&instances, &mut buffer, 0, instances.len())
+                .expect("Failed to copy instance data");
+            resource_manager.flush(&mut buffer, 0, instances.len())
+                .expect("Failed to flush geometry instance data buffer");
         }
 
         buffer
     };
 
-    let texture_image: Image = {
+    // levels of detail are added later on, see create_geometry_lod
+    let indirect_draw = engine.culling_pipeline.as_ref().filter(|_| is_culled_on_gpu(material.blend_mode, 0)).map(|_| {
 
-        resource_manager.create_image(String::from("texture-image"), &ImageAllocationDescriptor {
-            usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
-            extent: texture_extent,
-            format: ImageFormat::RGBA8,
-            tiling: ImageTiling::Optimal,
+        let visible_instances_buffer: Buffer<InstanceData> = resource_manager.create_buffer(String::from("geometry-visible-instance-data-buffer"), &BufferAllocationDescriptor {
+            usage: [BufferUsage::VertexBuffer, BufferUsage::StorageBuffer],
             memory: MemoryLocation::GpuOnly
-        }).expect("Failed to create texture image")
-    };
+        }, instances.len()).expect("geometry visible instance data buffer");
+
+        // the instance count is reset and incremented by the culling shader, the rest stays as is
+        let draw_command_buffer = {
+
+            let command = [
+                ::ash::vk::DrawIndexedIndirectCommand {
+                    index_count: indices.len() as u32,
+                    instance_count: 0,
+                    first_index: 0,
+                    vertex_offset: 0,
+                    first_instance: 0,
+                }
+            ];
 
-    let texture_image_view = {
-        let image_view_create_info = ::ash::vk::ImageViewCreateInfo::builder()
-            .image(*texture_image.handle())
-            .view_type(::ash::vk::ImageViewType::TYPE_2D)
-            .format(::ash::vk::Format::R8G8B8A8_UNORM)
-            .subresource_range(::ash::vk::ImageSubresourceRange::builder()
-                .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
-                .base_mip_level(0)
-                .level_count(1)
-                .base_array_layer(0)
-                .layer_count(1)
+            let mut buffer = resource_manager.create_buffer(String::from("geometry-draw-command-buffer"), &BufferAllocationDescriptor {
+                usage: [BufferUsage::IndirectBuffer, BufferUsage::StorageBuffer, BufferUsage::TransferDestinationBuffer],
+                memory: MemoryLocation::CpuToGpu
+            }, command.len()).expect("geometry draw command buffer");
+
+            unsafe {
+                resource_manager.copy(&command, &mut buffer, 0, command.len())
+                    .expect("Failed to copy draw command");
+                resource_manager.flush(&mut buffer, 0, command.len())
+                    .expect("Failed to flush geometry draw command buffer");
+            }
+
+            buffer
+        };
+
+        let draw_count_buffer: Buffer<u32> = resource_manager.create_buffer(String::from("geometry-draw-count-buffer"), &BufferAllocationDescriptor {
+            usage: [BufferUsage::IndirectBuffer, BufferUsage::StorageBuffer, BufferUsage::TransferDestinationBuffer],
+            memory: MemoryLocation::GpuOnly
+        }, 1).expect("geometry draw count buffer");
+
+        let descriptor_set = engine.descriptor_allocator.allocate(engine.culling_descriptor_set_layout)
+            .expect("Failed to allocate culling descriptor set");
+
+        let buffer_infos = [
+            *instances_buffer.handle(),
+            *visible_instances_buffer.handle(),
+            *draw_command_buffer.handle(),
+            *draw_count_buffer.handle(),
+        ].map(|buffer| {
+            [
+                ::ash::vk::DescriptorBufferInfo::builder()
+                    .buffer(buffer)
+                    .offset(0)
+                    .range(::ash::vk::WHOLE_SIZE)
+                    .build()
+            ]
+        });
+
+        let descriptor_writes = buffer_infos.iter().enumerate().map(|(binding, buffer_info)| {
+            ::ash::vk::WriteDescriptorSet::builder()
+                .dst_set(descriptor_set)
+                .dst_binding(binding as u32)
+                .dst_array_element(0)
+                .descriptor_type(::ash::vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(buffer_info)
                 .build()
-            );
+        }).collect::<Vec<_>>();
+
+        let descriptor_copies: [ash::vk::CopyDescriptorSet; 0] = [];
 
         unsafe {
-            _device.handle().create_image_view(&image_view_create_info, None)
-                .expect("Failed to create image view")
+            (*engine.device).borrow().handle().update_descriptor_sets(&descriptor_writes, &descriptor_copies)
         }
-    };
 
-    let instances_buffer = {
+        IndirectDraw {
+            visible_instances_buffer,
+            draw_command_buffer,
+            draw_count_buffer,
+            descriptor_set,
+        }
+    });
 
-        let size: usize = (instances.len() * std::mem::size_of::<InstanceData>());
+    Geometry {
+        index_buffer: index_buffer,
+        vertex_buffer: vertex_buffer,
+        vertex_layout,
+        instances_buffer: instances_buffer,
+        instances: Clone::clone(instances),
+        visible_instances: instances.len(),
+        material: material,
+        cast_shadows: true,
+        receive_shadows: true,
+        frustum_culling: true,
+        lods: Vec::new(),
+        lod_selection: LodSelection::default(),
+        lod_batches: Vec::new(),
+        bounding_box: BoundingBox::from_points(&positions),
+        bounding_sphere: bounding_sphere(&positions),
+        indirect_draw,
+        skin: None,
+    }
+}
 
-        let mut buffer = resource_manager.create_buffer(String::from("geometry-instance-data-buffer"), &BufferAllocationDescriptor {
+/// Adds a coarser mesh to the geometry, used for instances passing the threshold according to the
+/// geometry's `lod_selection`. Levels are kept ordered by their threshold.
+///
+/// Panics if the vertices are laid out differently than the geometry's vertices.
+pub fn create_geometry_lod<V: HasVertexLayout>(
+    engine: &mut Engine,
+    geometry: &mut Geometry,
+    indices: &IndexData,
+    vertices: &[V],
+    threshold: f32,
+) {
+    assert!(V::vertex_layout() == geometry.vertex_layout, "the vertex layout of a level of detail must match the geometry's");
+
+    let vertices = vertex_bytes(vertices);
+    let resource_manager = &mut engine.resource_manager;
+
+    let index_buffer = create_index_buffer(resource_manager, String::from("geometry-lod-index-buffer"), indices);
+
+    let vertex_buffer = {
+
+        let mut buffer = resource_manager.create_buffer(String::from("geometry-lod-vertex-buffer"), &BufferAllocationDescriptor {
             usage: [BufferUsage::VertexBuffer],
             memory: MemoryLocation::CpuToGpu
-        }, instances.len()).expect("geometry instance data buffer");
+        }, vertices.len()).expect("geometry lod vertex buffer");
 
         unsafe {
-            resource_manager.copy(&instances, &mut buffer, 0, instances.len());
-            resource_manager.flush(&mut buffer, 0, instances.len());
+            resource_manager.copy(&vertices, &mut buffer, 0, vertices.len())
+                .expect("Failed to copy level of detail vertices");
+            resource_manager.flush(&mut buffer, 0, vertices.len())
+                .expect("Failed to flush geometry lod vertex buffer");
         }
 
         buffer
     };
 
-    let descriptor_set = {
-        let pool = engine.descriptor_pool;
-        let descriptor_set_layouts = [
-            engine.material_descriptor_set_layout
-        ];
-        let create_info = ash::vk::DescriptorSetAllocateInfo::builder()
-            .descriptor_pool(pool)
-            .set_layouts(&descriptor_set_layouts);
-        unsafe {
-            _device.handle().allocate_descriptor_sets(&create_info)
-                .expect("Failed to allocate descriptor set")[0]
-        }
+    let position = match geometry.lod_selection.metric {
+        LodMetric::Distance => geometry.lods.iter().take_while(|lod| lod.threshold < threshold).count(),
+        LodMetric::ScreenSize => geometry.lods.iter().take_while(|lod| lod.threshold > threshold).count(),
     };
 
-    {
-        let sampler = engine.texture_sampler;
+    geometry.lods.insert(position, LevelOfDetail {
+        index_buffer,
+        vertex_buffer,
+        threshold,
+    });
+
+    // the culling shader does not select levels of detail
+    if let Some(indirect_draw) = geometry.indirect_draw.take() {
+        destroy_indirect_draw(engine, indirect_draw);
+    }
+}
+
+/// Frees the buffers written by the culling shader, once a geometry is culled on the CPU.
+fn destroy_indirect_draw(engine: &mut Engine, indirect_draw: IndirectDraw) {
+
+    let _device = (*engine.device).borrow();
+    let resource_manager = &mut engine.resource_manager;
+
+    engine.descriptor_allocator.free(indirect_draw.descriptor_set)
+        .expect("Failed to free culling descriptor set");
+
+    unsafe {
+        _device.handle().destroy_buffer(*indirect_draw.visible_instances_buffer.handle(), None);
+        _device.handle().destroy_buffer(*indirect_draw.draw_command_buffer.handle(), None);
+        _device.handle().destroy_buffer(*indirect_draw.draw_count_buffer.handle(), None);
+    }
+
+    resource_manager.free(indirect_draw.visible_instances_buffer)
+        .expect("Failed to free visible instance data buffer");
+    resource_manager.free(indirect_draw.draw_command_buffer)
+        .expect("Failed to free draw command buffer");
+    resource_manager.free(indirect_draw.draw_count_buffer)
+        .expect("Failed to free draw count buffer");
+}
+
+/// Binds the geometry's skinned vertices to the skeleton, posed in its rest pose. The pose is
+/// uploaded to the joint palette every frame, change `skin.pose` to animate the geometry.
+///
+/// Panics if the geometry's vertices have no joint indices and weights.
+pub fn create_skin(engine: &mut Engine, geometry: &mut Geometry, skeleton: Skeleton) {
+
+    assert!(geometry.vertex_layout.is_skinned(), "a skin requires vertices with joint indices and weights");
+
+    let pose = skeleton.rest_pose();
+    let resource_manager = &mut engine.resource_manager;
+
+    let joint_palette_buffer = {
+
+        let joint_matrices = joint_palette(&skeleton, &pose);
+
+        let mut buffer = resource_manager.create_buffer(String::from("geometry-joint-palette-buffer"), &BufferAllocationDescriptor {
+            usage: [BufferUsage::StorageBuffer],
+            memory: MemoryLocation::CpuToGpu
+        }, joint_matrices.len().max(1)).expect("geometry joint palette buffer");
+
+        unsafe {
+            resource_manager.copy(&joint_matrices, &mut buffer, 0, joint_matrices.len())
+                .expect("Failed to copy joint palette");
+            resource_manager.flush(&mut buffer, 0, joint_matrices.len())
+                .expect("Failed to flush joint palette buffer");
+        }
+
+        buffer
+    };
+
+    let descriptor_set = engine.descriptor_allocator.allocate(engine.skin_descriptor_set_layout)
+        .expect("Failed to allocate skin descriptor set");
+
+    let buffer_info = [
+        ::ash::vk::DescriptorBufferInfo::builder()
+            .buffer(*joint_palette_buffer.handle())
+            .offset(0)
+            .range(::ash::vk::WHOLE_SIZE)
+            .build()
+    ];
+
+    let descriptor_writes = [
+        ::ash::vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(::ash::vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&buffer_info)
+            .build()
+    ];
+
+    let descriptor_copies: [ash::vk::CopyDescriptorSet; 0] = [];
+
+    unsafe {
+        (*engine.device).borrow().handle().update_descriptor_sets(&descriptor_writes, &descriptor_copies)
+    }
+
+    geometry.skin = Some(Skin {
+        skeleton,
+        pose,
+        joint_palette_buffer,
+        descriptor_set,
+    });
+}
+
+/// Returns the joint matrices of the pose, laid out like a GLSL `mat4` array.
+fn joint_palette(skeleton: &Skeleton, pose: &Pose) -> Vec<[f32; 16]> {
+    skeleton.joint_matrices(pose).iter().map(|matrix| {
+        matrix.as_slice().try_into().unwrap()
+    }).collect()
+}
+
+/// Creates the pipelines for the vertex layout, unless the engine has some already. Skinned
+/// vertices are transformed by the skinning shader. Fails if the layout does not match the
+/// shader's inputs.
+fn prepare_geometry_pipelines(engine: &mut Engine, vertex_layout: &VertexLayout) -> Result<(), VertexLayoutError> {
+
+    if engine.geometry_pipelines.iter().any(|pipelines| pipelines.vertex_layout == *vertex_layout) {
+        return Ok(())
+    }
+
+    let vertex_shader_module = match vertex_layout.is_skinned() {
+        true => &engine.skinning_shader_module,
+        false => &engine.vertex_shader_module,
+    };
+
+    vertex_layout.validate(&vertex_shader_module.shader().input_locations())?;
+
+    let pipelines = create_geometry_pipelines(
+        &(*engine.device).borrow(),
+        vertex_shader_module,
+        &engine.fragment_shader_module,
+        vertex_layout,
+        engine.pipeline_layout,
+        engine.renderpass,
+        engine.shadow_renderpass,
+    );
+
+    engine.geometry_pipelines.push(pipelines);
+
+    Ok(())
+}
+
+fn create_index_buffer(resource_manager: &mut ResourceManager, name: String, indices: &IndexData) -> IndexBuffer {
+    match indices {
+        IndexData::U16(indices) => IndexBuffer::U16(create_filled_index_buffer(resource_manager, name, indices)),
+        IndexData::U32(indices) => IndexBuffer::U32(create_filled_index_buffer(resource_manager, name, indices)),
+    }
+}
+
+fn create_filled_index_buffer<A>(resource_manager: &mut ResourceManager, name: String, indices: &Vec<A>) -> Buffer<A> {
+
+    let mut buffer = resource_manager.create_buffer(name, &BufferAllocationDescriptor {
+        usage: [BufferUsage::IndexBuffer],
+        memory: MemoryLocation::CpuToGpu
+    }, indices.len()).expect("geometry index buffer");
+
+    unsafe {
+        resource_manager.copy(&indices, &mut buffer, 0, indices.len())
+            .expect("Failed to copy indices");
+        resource_manager.flush(&mut buffer, 0, indices.len())
+            .expect("Failed to flush index buffer");
+    }
+
+    buffer
+}
+
+pub fn create_material(engine: &mut Engine, descriptor: &MaterialDescriptor) -> Material {
+
+    let _device = (*engine.device).borrow();
+    let resource_manager = &mut engine.resource_manager;
+    let texture_table = &mut engine.texture_table;
+    let sampler = engine.texture_sampler;
+
+    let mut texture = |name: &str, texture: &Option<TextureData>, format: ImageFormat| {
+        texture.as_ref().map(|texture| {
+            create_texture(&_device, resource_manager, texture_table, sampler, name, texture, format)
+        })
+    };
+
+    let base_color_texture = texture("material-base-color-texture", &descriptor.base_color_texture, ImageFormat::SRGBA8);
+    let metallic_roughness_texture = texture("material-metallic-roughness-texture", &descriptor.metallic_roughness_texture, ImageFormat::RGBA8);
+    let normal_texture = texture("material-normal-texture", &descriptor.normal_texture, ImageFormat::RGBA8);
+    let occlusion_texture = texture("material-occlusion-texture", &descriptor.occlusion_texture, ImageFormat::RGBA8);
+    let emissive_texture = texture("material-emissive-texture", &descriptor.emissive_texture, ImageFormat::SRGBA8);
+
+    let texture_indices = {
+        let mut result = [0u32; 5];
+        [
+            &base_color_texture,
+            &metallic_roughness_texture,
+            &normal_texture,
+            &occlusion_texture,
+            &emissive_texture,
+        ].iter().zip(&engine.default_textures).enumerate().for_each(|(index, (texture, default))| {
+            result[index] = texture.as_ref().unwrap_or(default).index;
+        });
+        result
+    };
+
+    let parameters_buffer = {
+
+        let parameters = [MaterialUniformBufferObject::new(descriptor, &texture_indices)];
+
+        let mut buffer = resource_manager.create_buffer(String::from("material-parameters-buffer"), &BufferAllocationDescriptor {
+            usage: [BufferUsage::UniformBuffer],
+            memory: MemoryLocation::CpuToGpu
+        }, 1).expect("Failed to create material parameters buffer");
+
+        unsafe {
+            resource_manager.copy(&parameters, &mut buffer, 0, 1)
+                .expect("Failed to copy material parameters");
+            resource_manager.flush(&mut buffer, 0, 1)
+                .expect("Failed to flush material parameters buffer");
+        }
+
+        buffer
+    };
+
+    let descriptor_set = engine.descriptor_allocator.allocate(engine.material_descriptor_set_layout)
+        .expect("Failed to allocate material descriptor set");
+
+    {
+        let buffer_info = [
+            ::ash::vk::DescriptorBufferInfo::builder()
+                .buffer(*parameters_buffer.handle())
+                .offset(0)
+                .range(std::mem::size_of::<MaterialUniformBufferObject>() as u64)
+                .build()
+        ];
+
+        let descriptor_writes = [
+            ::ash::vk::WriteDescriptorSet::builder()
+                .descriptor_type(::ash::vk::DescriptorType::UNIFORM_BUFFER)
+                .dst_set(descriptor_set)
+                .dst_binding(0)
+                .buffer_info(&buffer_info)
+                .build()
+        ];
+
+        let descriptor_copies: [ash::vk::CopyDescriptorSet; 0] = [];
+
+        unsafe {
+            _device.handle().update_descriptor_sets(&descriptor_writes, &descriptor_copies)
+        }
+    }
+
+    Material {
+        descriptor_set,
+        blend_mode: descriptor.blend_mode,
+        parameters_buffer,
+        base_color_texture,
+        metallic_roughness_texture,
+        normal_texture,
+        occlusion_texture,
+        emissive_texture,
+    }
+}
+
+/// Releases the descriptor set, the textures and the buffers of a material. The material must
+/// not be used by any geometry which is rendered afterwards.
+pub fn destroy_material(engine: &mut Engine, material: Material) {
+
+    let _device = (*engine.device).borrow();
+    let resource_manager = &mut engine.resource_manager;
+
+    engine.descriptor_allocator.free(material.descriptor_set)
+        .expect("Failed to free material descriptor set");
+
+    unsafe {
+        _device.handle().destroy_buffer(*material.parameters_buffer.handle(), None);
+    }
+
+    resource_manager.free(material.parameters_buffer)
+        .expect("Failed to free material parameters buffer");
+
+    [
+        material.base_color_texture,
+        material.metallic_roughness_texture,
+        material.normal_texture,
+        material.occlusion_texture,
+        material.emissive_texture,
+    ].into_iter().flatten().for_each(|texture| {
+
+        engine.texture_table.remove(texture.index);
+
+        unsafe {
+            _device.handle().destroy_image_view(texture.image_view, None);
+            _device.handle().destroy_image(*texture.image.handle(), None);
+            _device.handle().destroy_buffer(*texture.buffer.handle(), None);
+        }
+
+        resource_manager.free(texture.image)
+            .expect("Failed to free texture image");
+        resource_manager.free(texture.buffer)
+            .expect("Failed to free texture transfer buffer");
+    });
+}
+
+/// Creates a texture whose data gets transferred to the GPU by [prepare] and adds it to the
+/// texture table.
+fn create_texture(device: &Device, resource_manager: &mut ResourceManager, texture_table: &mut TextureTable, sampler: ::ash::vk::Sampler, name: &str, texture: &TextureData, format: ImageFormat) -> Texture {
+
+    let buffer = {
+
+        let size: usize = texture.data.len() * std::mem::size_of::<u8>();
+
+        let mut buffer = resource_manager.create_buffer(format!("{name}-transfer-buffer"), &BufferAllocationDescriptor {
+            usage: [BufferUsage::TransferSourceBuffer],
+            memory: MemoryLocation::CpuToGpu,
+        }, size).expect("Failed to create texture data transfer buffer");
+
+        unsafe {
+            resource_manager.copy(&texture.data, &mut buffer, 0, texture.data.len())
+                .expect("Failed to copy texture");
+            resource_manager.flush(&mut buffer, 0, texture.data.len())
+                .expect("Failed to flush texture transfer buffer");
+        }
+
+        buffer
+    };
+
+    let image: Image = {
+
+        resource_manager.create_image(String::from(name), &ImageAllocationDescriptor {
+            usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+            extent: texture.extent,
+            format,
+            tiling: ImageTiling::Optimal,
+            memory: MemoryLocation::GpuOnly,
+            mip_levels: 1,
+            array_layers: 1,
+            cube_compatible: false,
+        }).expect("Failed to create texture image")
+    };
+
+    let image_view = {
+        let image_view_create_info = ::ash::vk::ImageViewCreateInfo::builder()
+            .image(*image.handle())
+            .view_type(::ash::vk::ImageViewType::TYPE_2D)
+            .format(format.into())
+            .subresource_range(::ash::vk::ImageSubresourceRange::builder()
+                .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build()
+            );
+
+        unsafe {
+            device.handle().create_image_view(&image_view_create_info, None)
+                .expect("Failed to create image view")
+        }
+    };
+
+    let index = texture_table.insert(image_view, sampler)
+        .expect("Failed to add texture to the texture table");
+
+    Texture {
+        buffer,
+        image,
+        image_view,
+        index,
+    }
+}
+
+/// Uploads the environment as cube map and creates the pipeline drawing it behind all
+/// geometries. The skybox is drawn once it is assigned to `World::skybox`.
+///
+/// The vertex shader is expected to derive a triangle covering the viewport at the far plane from
+/// `gl_VertexIndex` and the fragment shader to sample the cube map bound to `set = 1, binding = 0`.
+/// Both have access to the global uniforms at `set = 0, binding = 0`.
+pub fn create_skybox(
+    engine: &mut Engine,
+    vertex_shader: VertexShaderBinary,
+    fragment_shader: FragmentShaderBinary,
+    environment: &CubeMapData,
+) -> Skybox {
+
+    let _device = (*engine.device).borrow();
+    let queue = Rc::clone(&_device.queues()[0]);
+    let resource_manager = &mut engine.resource_manager;
+    let format = ImageFormat::RGBA32F;
+
+    let image = upload_image(&_device, &queue, resource_manager, "skybox", &ImageAllocationDescriptor {
+        usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+        extent: Extent::from(environment.size, environment.size, 1),
+        format,
+        tiling: ImageTiling::Optimal,
+        memory: MemoryLocation::GpuOnly,
+        mip_levels: 1,
+        array_layers: 6,
+        cube_compatible: true,
+    }, &[environment.bytes()]);
+
+    let image_view = create_layered_image_view(&_device, &image, ::ash::vk::ImageViewType::CUBE, format.into(), 0, 1, 6);
+
+    let sampler = {
+        let sampler_create_info = ::ash::vk::SamplerCreateInfo::builder()
+            .mag_filter(::ash::vk::Filter::LINEAR)
+            .min_filter(::ash::vk::Filter::LINEAR)
+            .address_mode_u(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(::ash::vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+        unsafe {
+            _device.handle().create_sampler(&sampler_create_info, None)
+                .expect("Failed to create skybox sampler")
+        }
+    };
+
+    let descriptor_set_layout = {
+        let bindings = [
+            ::ash::vk::DescriptorSetLayoutBinding::builder()
+                .binding(0)
+                .descriptor_count(1)
+                .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .build()
+        ];
+
+        let descriptor_set_layout_create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&bindings)
+            .build();
+
+        unsafe {
+            _device.handle().create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+                .expect("Failed to create descriptor set layout!")
+        }
+    };
+
+    // every skybox brings its own pool, as there are rarely more than a few
+    let descriptor_pool = {
+        let pool_sizes = [
+            ::ash::vk::DescriptorPoolSize::builder()
+                .ty(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .build(),
+        ];
+
+        let pool_create_info = ash::vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(1)
+            .pool_sizes(&pool_sizes);
+
+        unsafe {
+            _device.handle().create_descriptor_pool(&pool_create_info, None)
+                .expect("Failed to create skybox descriptor pool")
+        }
+    };
+
+    let descriptor_set = {
+        let descriptor_set_layouts = [
+            descriptor_set_layout
+        ];
+        let create_info = ash::vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&descriptor_set_layouts);
+        let descriptor_set = unsafe {
+            _device.handle().allocate_descriptor_sets(&create_info)
+                .expect("Failed to allocate descriptor set")[0]
+        };
+
+        let image_info = [
+            ::ash::vk::DescriptorImageInfo::builder()
+                .sampler(sampler)
+                .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(image_view)
+                .build()
+        ];
+        let descriptor_writes = [
+            ::ash::vk::WriteDescriptorSet::builder()
+                .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .dst_set(descriptor_set)
+                .dst_binding(0)
+                .image_info(&image_info)
+                .build(),
+        ];
+        unsafe {
+            _device.handle().update_descriptor_sets(&descriptor_writes, &[])
+        }
+
+        descriptor_set
+    };
+
+    let pipeline = {
+
+        let vertex_shader_module = ShaderModule::create(vertex_shader, "main")
+            .and_then(|module| module.load(&_device))
+            .unwrap();
+
+        let fragment_shader_module = ShaderModule::create(fragment_shader, "main")
+            .and_then(|module| module.load(&_device))
+            .unwrap();
+
+        // the object id of the background is written as well
+        let pipeline = FullscreenPipeline::new(
+            &_device,
+            &vertex_shader_module,
+            &fragment_shader_module,
+            &[engine.global_descriptor_set_layout, descriptor_set_layout],
+            0,
+            engine.renderpass,
+            &FullscreenTarget {
+                color_attachments: 2,
+                depth_test: true,
+            },
+        ).expect("Failed to create skybox pipeline");
+
+        let _ = vertex_shader_module.unload(&_device);
+        let _ = fragment_shader_module.unload(&_device);
+
+        pipeline
+    };
+
+    Skybox {
+        image,
+        image_view,
+        sampler,
+        descriptor_pool,
+        descriptor_set_layout,
+        descriptor_set,
+        pipeline,
+    }
+}
+
+/// Destroys the skybox, it must not be used by the world anymore.
+pub fn destroy_skybox(engine: &mut Engine, skybox: Skybox) {
+
+    let _device = (*engine.device).borrow();
+
+    skybox.pipeline.destroy(&_device);
+
+    unsafe {
+        _device.handle().destroy_descriptor_pool(skybox.descriptor_pool, None);
+        _device.handle().destroy_descriptor_set_layout(skybox.descriptor_set_layout, None);
+        _device.handle().destroy_sampler(skybox.sampler, None);
+        _device.handle().destroy_image_view(skybox.image_view, None);
+        _device.handle().destroy_image(*skybox.image.handle(), None);
+    }
+
+    engine.resource_manager.free(skybox.image)
+        .expect("Failed to free skybox image");
+}
+
+/// Precomputes the lighting of the environment sampled by the lit shader: the irradiance map,
+/// the specular map prefiltered into one level per roughness and the BRDF LUT. The lighting takes
+/// effect once it is assigned to `World::environment_lighting`.
+///
+/// With shaders the lighting is computed on the GPU, otherwise on the CPU by
+/// `EnvironmentLightingData::compute`, which takes considerably longer.
+pub fn create_environment_lighting(
+    engine: &mut Engine,
+    environment: &CubeMapData,
+    settings: &EnvironmentLightingSettings,
+    shaders: Option<EnvironmentLightingShaders>,
+) -> EnvironmentLighting {
+
+    let _device = (*engine.device).borrow();
+    let queue = Rc::clone(&_device.queues()[0]);
+
+    match shaders {
+        Some(shaders) => compute_environment_lighting(&_device, &queue, &mut engine.resource_manager, engine.environment_sampler, environment, settings, shaders),
+        None => upload_environment_lighting(&_device, &queue, &mut engine.resource_manager, &EnvironmentLightingData::compute(environment, settings)),
+    }
+}
+
+/// Destroys the environment lighting, it must not be used by the world anymore.
+pub fn destroy_environment_lighting(engine: &mut Engine, lighting: EnvironmentLighting) {
+
+    let _device = (*engine.device).borrow();
+
+    if engine.bound_environment_lighting == lighting.irradiance_view {
+        write_environment_lighting(&_device, &engine.global_descriptor_sets, engine.environment_sampler, &engine.default_environment_lighting);
+        engine.bound_environment_lighting = engine.default_environment_lighting.irradiance_view;
+    }
+
+    unsafe {
+        _device.handle().destroy_image_view(lighting.irradiance_view, None);
+        _device.handle().destroy_image_view(lighting.specular_view, None);
+        _device.handle().destroy_image_view(lighting.brdf_lut_view, None);
+        _device.handle().destroy_image(*lighting.irradiance.handle(), None);
+        _device.handle().destroy_image(*lighting.specular.handle(), None);
+        _device.handle().destroy_image(*lighting.brdf_lut.handle(), None);
+    }
+
+    [lighting.irradiance, lighting.specular, lighting.brdf_lut].into_iter().for_each(|image| {
+        engine.resource_manager.free(image)
+            .expect("Failed to free environment lighting image");
+    });
+}
+
+fn upload_environment_lighting(
+    device: &Device,
+    queue: &DeviceQueue,
+    resource_manager: &mut ResourceManager,
+    data: &EnvironmentLightingData,
+) -> EnvironmentLighting {
+
+    let format = ImageFormat::RGBA32F;
+
+    let cube_map = |size: u32, mip_levels: u32| ImageAllocationDescriptor {
+        usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+        extent: Extent::from(size, size, 1),
+        format,
+        tiling: ImageTiling::Optimal,
+        memory: MemoryLocation::GpuOnly,
+        mip_levels,
+        array_layers: 6,
+        cube_compatible: true,
+    };
+
+    let irradiance = upload_image(device, queue, resource_manager, "environment-irradiance",
+        &cube_map(data.irradiance.size, 1),
+        &[data.irradiance.bytes()]);
+
+    let specular = upload_image(device, queue, resource_manager, "environment-specular",
+        &cube_map(data.specular[0].size, data.specular.len() as u32),
+        &data.specular.iter().map(CubeMapData::bytes).collect::<Vec<_>>());
+
+    let brdf_lut = upload_image(device, queue, resource_manager, "environment-brdf-lut", &ImageAllocationDescriptor {
+        usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+        extent: Extent::from(data.brdf_lut.size, data.brdf_lut.size, 1),
+        format,
+        tiling: ImageTiling::Optimal,
+        memory: MemoryLocation::GpuOnly,
+        mip_levels: 1,
+        array_layers: 1,
+        cube_compatible: false,
+    }, &[data.brdf_lut.bytes()]);
+
+    EnvironmentLighting {
+        irradiance_view: create_layered_image_view(device, &irradiance, ::ash::vk::ImageViewType::CUBE, format.into(), 0, 1, 6),
+        specular_view: create_layered_image_view(device, &specular, ::ash::vk::ImageViewType::CUBE, format.into(), 0, data.specular.len() as u32, 6),
+        brdf_lut_view: create_color_image_view(device, &brdf_lut, format.into()),
+        irradiance,
+        specular,
+        brdf_lut,
+    }
+}
+
+/// Computes the environment lighting with one dispatch per image, respectively per level of the
+/// specular map, all submitted at once.
+fn compute_environment_lighting(
+    device: &Device,
+    queue: &DeviceQueue,
+    resource_manager: &mut ResourceManager,
+    sampler: ::ash::vk::Sampler,
+    environment: &CubeMapData,
+    settings: &EnvironmentLightingSettings,
+    shaders: EnvironmentLightingShaders,
+) -> EnvironmentLighting {
+
+    let format = ImageFormat::RGBA32F;
+
+    let environment_image = upload_image(device, queue, resource_manager, "environment", &ImageAllocationDescriptor {
+        usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+        extent: Extent::from(environment.size, environment.size, 1),
+        format,
+        tiling: ImageTiling::Optimal,
+        memory: MemoryLocation::GpuOnly,
+        mip_levels: 1,
+        array_layers: 6,
+        cube_compatible: true,
+    }, &[environment.bytes()]);
+
+    let environment_view = create_layered_image_view(device, &environment_image, ::ash::vk::ImageViewType::CUBE, format.into(), 0, 1, 6);
+
+    let mut create_target = |name: &str, size: u32, mip_levels: u32, array_layers: u32| {
+        resource_manager.create_image(String::from(name), &ImageAllocationDescriptor {
+            usage: [ImageUsage::Storage, ImageUsage::Sampled],
+            extent: Extent::from(size, size, 1),
+            format,
+            tiling: ImageTiling::Optimal,
+            memory: MemoryLocation::GpuOnly,
+            mip_levels,
+            array_layers,
+            cube_compatible: array_layers == 6,
+        }).expect("Failed to create environment lighting image")
+    };
+
+    let irradiance = create_target("environment-irradiance", settings.irradiance_size, 1, 6);
+    let specular = create_target("environment-specular", settings.specular_size, settings.specular_levels, 6);
+    let brdf_lut = create_target("environment-brdf-lut", settings.brdf_lut_size, 1, 1);
+
+    // every dispatch writes one view: the irradiance map, each level of the specular map and the LUT
+    let storage_views = std::iter::once(create_layered_image_view(device, &irradiance, ::ash::vk::ImageViewType::TYPE_2D_ARRAY, format.into(), 0, 1, 6))
+        .chain((0..settings.specular_levels).map(|level| {
+            create_layered_image_view(device, &specular, ::ash::vk::ImageViewType::TYPE_2D_ARRAY, format.into(), level, 1, 6)
+        }))
+        .chain(std::iter::once(create_color_image_view(device, &brdf_lut, format.into())))
+        .collect::<Vec<_>>();
+
+    // binding 0: the environment, 1: the written image
+    let descriptor_set_layout = {
+        let bindings = [
+            ::ash::vk::DescriptorSetLayoutBinding::builder()
+                .binding(0)
+                .descriptor_count(1)
+                .stage_flags(::ash::vk::ShaderStageFlags::COMPUTE)
+                .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .build(),
+            ::ash::vk::DescriptorSetLayoutBinding::builder()
+                .binding(1)
+                .descriptor_count(1)
+                .stage_flags(::ash::vk::ShaderStageFlags::COMPUTE)
+                .descriptor_type(::ash::vk::DescriptorType::STORAGE_IMAGE)
+                .build(),
+        ];
+
+        let descriptor_set_layout_create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&bindings)
+            .build();
+
+        unsafe {
+            device.handle().create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+                .expect("Failed to create descriptor set layout!")
+        }
+    };
+
+    let descriptor_pool = {
+        let pool_sizes = [
+            ::ash::vk::DescriptorPoolSize::builder()
+                .ty(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(storage_views.len() as u32)
+                .build(),
+            ::ash::vk::DescriptorPoolSize::builder()
+                .ty(::ash::vk::DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(storage_views.len() as u32)
+                .build(),
+        ];
+
+        let pool_create_info = ash::vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(storage_views.len() as u32)
+            .pool_sizes(&pool_sizes);
+
+        unsafe {
+            device.handle().create_descriptor_pool(&pool_create_info, None)
+                .expect("Failed to create environment lighting descriptor pool")
+        }
+    };
+
+    let descriptor_sets = storage_views.iter().map(|storage_view| {
+        let descriptor_set_layouts = [
+            descriptor_set_layout
+        ];
+        let create_info = ash::vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&descriptor_set_layouts);
+        let descriptor_set = unsafe {
+            device.handle().allocate_descriptor_sets(&create_info)
+                .expect("Failed to allocate descriptor set")[0]
+        };
+
+        let environment_info = [
+            ::ash::vk::DescriptorImageInfo::builder()
+                .sampler(sampler)
+                .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(environment_view)
+                .build()
+        ];
+        let storage_info = [
+            ::ash::vk::DescriptorImageInfo::builder()
+                .image_layout(::ash::vk::ImageLayout::GENERAL)
+                .image_view(*storage_view)
+                .build()
+        ];
+        let descriptor_writes = [
+            ::ash::vk::WriteDescriptorSet::builder()
+                .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .dst_set(descriptor_set)
+                .dst_binding(0)
+                .image_info(&environment_info)
+                .build(),
+            ::ash::vk::WriteDescriptorSet::builder()
+                .descriptor_type(ash::vk::DescriptorType::STORAGE_IMAGE)
+                .dst_set(descriptor_set)
+                .dst_binding(1)
+                .image_info(&storage_info)
+                .build(),
+        ];
+        unsafe {
+            device.handle().update_descriptor_sets(&descriptor_writes, &[])
+        }
+
+        descriptor_set
+    }).collect::<Vec<_>>();
+
+    let push_constants_size = std::mem::size_of::<EnvironmentLightingPushConstants>() as u32;
+    let irradiance_pipeline = ComputePipeline::new(device, shaders.irradiance, &[descriptor_set_layout], push_constants_size)
+        .expect("Failed to create irradiance pipeline");
+    let prefilter_pipeline = ComputePipeline::new(device, shaders.prefilter, &[descriptor_set_layout], push_constants_size)
+        .expect("Failed to create prefilter pipeline");
+    let brdf_pipeline = ComputePipeline::new(device, shaders.brdf, &[descriptor_set_layout], push_constants_size)
+        .expect("Failed to create BRDF pipeline");
+
+    // the pipeline, size, roughness and layers of every dispatch in the order of the views
+    let dispatches = std::iter::once((&irradiance_pipeline, settings.irradiance_size, 0.0, 6))
+        .chain((0..settings.specular_levels).map(|level| {
+            (&prefilter_pipeline, settings.specular_level_size(level), settings.specular_roughness(level), 6)
+        }))
+        .chain(std::iter::once((&brdf_pipeline, settings.brdf_lut_size, 0.0, 1)))
+        .collect::<Vec<_>>();
+
+    let ranges = [(&irradiance, 1, 6), (&specular, settings.specular_levels, 6), (&brdf_lut, 1, 1)].map(|(image, level_count, layer_count)| {
+        (*image.handle(), ::ash::vk::ImageSubresourceRange::builder()
+            .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(level_count)
+            .base_array_layer(0)
+            .layer_count(layer_count)
+            .build())
+    });
+
+    submit_immediately(device, queue, |command_buffer| {
+
+        let barriers = ranges.map(|(image, range)| {
+            ::ash::vk::ImageMemoryBarrier::builder()
+                .image(image)
+                .old_layout(::ash::vk::ImageLayout::UNDEFINED)
+                .new_layout(::ash::vk::ImageLayout::GENERAL)
+                .subresource_range(range)
+                .src_access_mask(::ash::vk::AccessFlags::NONE)
+                .dst_access_mask(::ash::vk::AccessFlags::SHADER_WRITE)
+                .build()
+        });
+
+        unsafe {
+            device.handle().cmd_pipeline_barrier(command_buffer, ::ash::vk::PipelineStageFlags::TOP_OF_PIPE, ::ash::vk::PipelineStageFlags::COMPUTE_SHADER, ::ash::vk::DependencyFlags::empty(), &[], &[], &barriers);
+        }
+
+        dispatches.iter().zip(descriptor_sets.iter()).for_each(|((pipeline, size, roughness, layers), descriptor_set)| {
+            let push_constants = EnvironmentLightingPushConstants {
+                roughness: *roughness,
+                size: *size,
+                samples: settings.samples,
+            };
+            let groups = workgroup_count(*size, ENVIRONMENT_LIGHTING_WORKGROUP_SIZE);
+            pipeline.record_dispatch(device, &command_buffer, &[*descriptor_set], push_constants.as_bytes(), [groups, groups, *layers]);
+        });
+
+        let barriers = ranges.map(|(image, range)| {
+            ::ash::vk::ImageMemoryBarrier::builder()
+                .image(image)
+                .old_layout(::ash::vk::ImageLayout::GENERAL)
+                .new_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .subresource_range(range)
+                .src_access_mask(::ash::vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(::ash::vk::AccessFlags::SHADER_READ)
+                .build()
+        });
+
+        unsafe {
+            device.handle().cmd_pipeline_barrier(command_buffer, ::ash::vk::PipelineStageFlags::COMPUTE_SHADER, ::ash::vk::PipelineStageFlags::FRAGMENT_SHADER, ::ash::vk::DependencyFlags::empty(), &[], &[], &barriers);
+        }
+    });
+
+    irradiance_pipeline.destroy(device);
+    prefilter_pipeline.destroy(device);
+    brdf_pipeline.destroy(device);
+
+    unsafe {
+        device.handle().destroy_descriptor_pool(descriptor_pool, None);
+        device.handle().destroy_descriptor_set_layout(descriptor_set_layout, None);
+        storage_views.iter().for_each(|view| device.handle().destroy_image_view(*view, None));
+        device.handle().destroy_image_view(environment_view, None);
+        device.handle().destroy_image(*environment_image.handle(), None);
+    }
+
+    resource_manager.free(environment_image)
+        .expect("Failed to free environment image");
+
+    EnvironmentLighting {
+        irradiance_view: create_layered_image_view(device, &irradiance, ::ash::vk::ImageViewType::CUBE, format.into(), 0, 1, 6),
+        specular_view: create_layered_image_view(device, &specular, ::ash::vk::ImageViewType::CUBE, format.into(), 0, settings.specular_levels, 6),
+        brdf_lut_view: create_color_image_view(device, &brdf_lut, format.into()),
+        irradiance,
+        specular,
+        brdf_lut,
+    }
+}
+
+/// Writes the environment lighting into the bindings 4 to 6 of the global descriptor sets, which
+/// must not be in use by the device.
+fn write_environment_lighting(
+    device: &Device,
+    descriptor_sets: &[ash::vk::DescriptorSet],
+    sampler: ::ash::vk::Sampler,
+    lighting: &EnvironmentLighting,
+) {
+    let image_infos = [lighting.irradiance_view, lighting.specular_view, lighting.brdf_lut_view].map(|view| {
+        [
+            ::ash::vk::DescriptorImageInfo::builder()
+                .sampler(sampler)
+                .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(view)
+                .build()
+        ]
+    });
+
+    let descriptor_writes = descriptor_sets.iter().flat_map(|descriptor_set| {
+        image_infos.iter().enumerate().map(|(index, image_info)| {
+            ::ash::vk::WriteDescriptorSet::builder()
+                .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .dst_set(*descriptor_set)
+                .dst_binding(4 + index as u32)
+                .image_info(image_info)
+                .build()
+        })
+    }).collect::<Vec<_>>();
+
+    unsafe {
+        device.handle().update_descriptor_sets(&descriptor_writes, &[])
+    }
+}
+
+/// Uploads the levels of the image, each holding all of its layers one after another, and
+/// transitions the image to be sampled.
+fn upload_image<const N: usize>(
+    device: &Device,
+    queue: &DeviceQueue,
+    resource_manager: &mut ResourceManager,
+    name: &str,
+    descriptor: &ImageAllocationDescriptor<N>,
+    levels: &[Vec<u8>],
+) -> Image {
+
+    let data = levels.concat();
+
+    let mut buffer = resource_manager.create_buffer(format!("{name}-transfer-buffer"), &BufferAllocationDescriptor {
+        usage: [BufferUsage::TransferSourceBuffer],
+        memory: MemoryLocation::CpuToGpu,
+    }, data.len()).expect("Failed to create transfer buffer");
+
+    unsafe {
+        resource_manager.copy(&data, &mut buffer, 0, data.len())
+            .expect("Failed to copy the image into the transfer buffer");
+        resource_manager.flush(&mut buffer, 0, data.len())
+            .expect("Failed to flush transfer buffer");
+    }
+
+    let image = resource_manager.create_image(String::from(name), descriptor)
+        .unwrap_or_else(|_| panic!("Failed to create {name} image"));
+
+    let range = ::ash::vk::ImageSubresourceRange::builder()
+        .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(descriptor.mip_levels)
+        .base_array_layer(0)
+        .layer_count(descriptor.array_layers)
+        .build();
+
+    let buffer_copies = levels.iter().enumerate().scan(0, |offset, (level, bytes)| {
+        let buffer_copy = ::ash::vk::BufferImageCopy::builder()
+            .buffer_offset(*offset as u64)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(::ash::vk::ImageSubresourceLayers::builder()
+                .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
+                .mip_level(level as u32)
+                .base_array_layer(0)
+                .layer_count(descriptor.array_layers)
+                .build()
+            )
+            .image_offset(Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(::ash::vk::Extent3D {
+                width: (descriptor.extent.width >> level).max(1),
+                height: (descriptor.extent.height >> level).max(1),
+                depth: 1,
+            })
+            .build();
+        *offset += bytes.len();
+        Some(buffer_copy)
+    }).collect::<Vec<_>>();
+
+    submit_immediately(device, queue, |command_buffer| {
 
-        let descriptor_image_info = [
-            ::ash::vk::DescriptorImageInfo::builder()
-                .sampler(sampler)
-                .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                .image_view(texture_image_view)
-                .build()
-        ];
+        let barrier = ::ash::vk::ImageMemoryBarrier::builder()
+            .image(*image.handle())
+            .old_layout(::ash::vk::ImageLayout::UNDEFINED)
+            .new_layout(::ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .subresource_range(range)
+            .src_access_mask(::ash::vk::AccessFlags::NONE)
+            .dst_access_mask(::ash::vk::AccessFlags::TRANSFER_WRITE);
 
-        let descriptor_writes = [
-            ::ash::vk::WriteDescriptorSet::builder()
-                .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-                .dst_set(descriptor_set)
-                .dst_binding(0)
-                .image_info(&descriptor_image_info)
-                .build()
-        ];
+        unsafe {
+            device.handle().cmd_pipeline_barrier(command_buffer, ::ash::vk::PipelineStageFlags::TOP_OF_PIPE, ::ash::vk::PipelineStageFlags::TRANSFER, ::ash::vk::DependencyFlags::empty(), &[], &[], &[*barrier]);
+            device.handle().cmd_copy_buffer_to_image(command_buffer, *buffer.handle(), *image.handle(), ::ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL, &buffer_copies);
+        }
 
-        let descriptor_copies: [ash::vk::CopyDescriptorSet; 0] = [];
+        let barrier = ::ash::vk::ImageMemoryBarrier::builder()
+            .image(*image.handle())
+            .old_layout(::ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .subresource_range(range)
+            .src_access_mask(::ash::vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(::ash::vk::AccessFlags::SHADER_READ);
 
         unsafe {
-            _device.handle().update_descriptor_sets(&descriptor_writes, &descriptor_copies)
+            device.handle().cmd_pipeline_barrier(command_buffer, ::ash::vk::PipelineStageFlags::TRANSFER, ::ash::vk::PipelineStageFlags::FRAGMENT_SHADER | ::ash::vk::PipelineStageFlags::COMPUTE_SHADER, ::ash::vk::DependencyFlags::empty(), &[], &[], &[*barrier]);
         }
-    }
+    });
 
-    Geometry {
-        index_buffer: index_buffer,
-        vertex_buffer: vertex_buffer,
-        instances_buffer: instances_buffer,
-        material: Material {
-            descriptor_set: descriptor_set,
-            texture_buffer: texture_transfere_buffer,
-            texture_image: texture_image,
-            texture_image_view: texture_image_view
-        },
+    unsafe {
+        device.handle().destroy_buffer(*buffer.handle(), None);
     }
+
+    resource_manager.free(buffer)
+        .expect("Failed to free transfer buffer");
+
+    image
 }
 
 pub fn update_geometry(
@@ -940,6 +3462,100 @@ pub fn update_geometry(
         resource_manager.copy(&instances, buffer, 0, instances.len());
         resource_manager.flush(buffer, 0, instances.len());
     }
+    geometry.instances = Clone::clone(instances);
+    geometry.visible_instances = instances.len();
+}
+
+pub fn create_compute_pipeline(
+    engine: &mut Engine,
+    shader: ComputeShaderBinary,
+    descriptor_set_layouts: &[::ash::vk::DescriptorSetLayout],
+    push_constants_size: u32,
+) -> ComputePipeline {
+    ComputePipeline::new(&(*engine.device).borrow(), shader, descriptor_set_layouts, push_constants_size)
+        .expect("Failed to create compute pipeline")
+}
+
+/// Records a dispatch of the pipeline. All dispatches recorded until the next call of `render`
+/// are submitted as one batch, to the async compute queue if the device provides one, and the
+/// frame waits for their completion before it reads any vertex or indirect data.
+pub fn dispatch_compute(
+    engine: &mut Engine,
+    pipeline: &ComputePipeline,
+    descriptor_sets: &[::ash::vk::DescriptorSet],
+    push_constants: &[u8],
+    group_counts: [u32; 3],
+) {
+    let _device = (*engine.device).borrow();
+
+    if !engine.compute_recording {
+
+        let begin_info = ash::vk::CommandBufferBeginInfo::builder()
+            .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        unsafe {
+            _device.handle().reset_command_buffer(engine.compute_command_buffer, CommandBufferResetFlags::RELEASE_RESOURCES)
+                .expect("Failed to reset compute command buffer");
+            _device.handle().begin_command_buffer(engine.compute_command_buffer, &begin_info)
+                .expect("Failed to begin compute command buffer");
+        }
+
+        engine.compute_recording = true;
+    }
+    else {
+        // consecutive dispatches may depend on each other's results
+        let barriers = [
+            ::ash::vk::MemoryBarrier::builder()
+                .src_access_mask(::ash::vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(::ash::vk::AccessFlags::SHADER_READ | ::ash::vk::AccessFlags::SHADER_WRITE)
+                .build()
+        ];
+
+        unsafe {
+            _device.handle().cmd_pipeline_barrier(
+                engine.compute_command_buffer,
+                ::ash::vk::PipelineStageFlags::COMPUTE_SHADER,
+                ::ash::vk::PipelineStageFlags::COMPUTE_SHADER,
+                ::ash::vk::DependencyFlags::empty(),
+                &barriers,
+                &[],
+                &[],
+            );
+        }
+    }
+
+    pipeline.record_dispatch(&_device, &engine.compute_command_buffer, descriptor_sets, push_constants, group_counts);
+}
+
+/// Submits the dispatches recorded by `dispatch_compute` and returns whether the frame has to
+/// wait for the compute finished semaphore.
+fn submit_compute(engine: &mut Engine) -> bool {
+
+    if !engine.compute_recording {
+        return false;
+    }
+
+    let _device = (*engine.device).borrow();
+    let queue = _device.compute_queue()
+        .map(Rc::clone)
+        .unwrap_or_else(|| Rc::clone(&_device.queues()[0]));
+
+    let command_buffers = [engine.compute_command_buffer];
+    let signal_semaphores = [engine.compute_finished_semaphore];
+    let submit_info = ash::vk::SubmitInfo::builder()
+        .command_buffers(&command_buffers)
+        .signal_semaphores(&signal_semaphores);
+
+    unsafe {
+        _device.handle().end_command_buffer(engine.compute_command_buffer)
+            .expect("Failed to end compute command buffer");
+        _device.handle().queue_submit(*queue.handle(), &[*submit_info], ash::vk::Fence::null())
+            .expect("Failed to submit compute queue");
+    }
+
+    engine.compute_recording = false;
+
+    true
 }
 
 pub fn pick_object(engine: &mut Engine, x: i32, y: i32) -> Option<u32> {
@@ -1011,7 +3627,7 @@ pub fn pick_object(engine: &mut Engine, x: i32, y: i32) -> Option<u32> {
 
     let image_to_buffer_copy = ::ash::vk::CopyImageToBufferInfo2::builder()
         .src_image(*image.handle())
-        .src_image_layout(::ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+        .src_image_layout(::ash::vk::ImageLayout::GENERAL)
         .dst_buffer(*buffer.handle())
         .regions(&[
             ::ash::vk::BufferImageCopy2::builder()
@@ -1094,6 +3710,312 @@ pub fn pick_object(engine: &mut Engine, x: i32, y: i32) -> Option<u32> {
     }
 }
 
+/// Replaces the objects outlined by the post-processing with the given instance ids, e.g. the
+/// ones returned by [pick_object]. The background id 0 is ignored and only the first
+/// [MAX_SELECTED_OBJECTS] ids are kept, an empty selection skips the outline pass.
+pub fn set_selection(engine: &mut Engine, ids: &[u32]) {
+    engine.selection = ids.iter()
+        .copied()
+        .filter(|id| *id != 0)
+        .take(MAX_SELECTED_OBJECTS)
+        .collect();
+}
+
+/// Returns the ids of the objects outlined by the post-processing.
+pub fn selection(engine: &Engine) -> &[u32] {
+    &engine.selection
+}
+
+/// Returns the debug lines drawn by the next call of [render], which clears them afterwards.
+pub fn debug_lines(engine: &mut Engine) -> &mut DebugLines {
+    &mut engine.debug_lines
+}
+
+/// Returns the overlay drawn over the next call of [render], which clears it afterwards.
+pub fn overlay(engine: &mut Engine) -> &mut Overlay {
+    &mut engine.overlay
+}
+
+/// Uploads an sRGB texture to be sampled by overlay triangles, see [Overlay::triangles].
+pub fn create_overlay_texture(engine: &mut Engine, texture: &TextureData) -> OverlayTexture {
+
+    let _device = (*engine.device).borrow();
+    let queue = Rc::clone(&_device.queues()[0]);
+
+    let image = upload_image(&_device, &queue, &mut engine.resource_manager, "overlay-texture", &ImageAllocationDescriptor {
+        usage: [ImageUsage::Sampled, ImageUsage::TransferDestination],
+        extent: texture.extent,
+        format: ImageFormat::SRGBA8,
+        tiling: ImageTiling::Optimal,
+        memory: MemoryLocation::GpuOnly,
+        mip_levels: 1,
+        array_layers: 1,
+        cube_compatible: false,
+    }, &[texture.data.clone()]);
+
+    let image_view = create_color_image_view(&_device, &image, ImageFormat::SRGBA8.into());
+
+    let index = engine.texture_table.insert(image_view, engine.overlay_drawing.sampler)
+        .expect("Failed to add overlay texture to the texture table");
+
+    OverlayTexture {
+        image,
+        image_view,
+        index,
+    }
+}
+
+/// Destroys the texture, it must not be drawn by the overlay anymore.
+pub fn destroy_overlay_texture(engine: &mut Engine, texture: OverlayTexture) {
+
+    let _device = (*engine.device).borrow();
+
+    engine.texture_table.remove(texture.index);
+
+    unsafe {
+        _device.handle().destroy_image_view(texture.image_view, None);
+        _device.handle().destroy_image(*texture.image.handle(), None);
+    }
+
+    engine.resource_manager.free(texture.image)
+        .expect("Failed to free overlay texture image");
+}
+
+/// Requests the next rendered frame to be captured. The frame is copied before it is presented
+/// and returned by [take_captured_frame] after [render].
+///
+/// The frame is captured as presented, i.e. after the post-processing, and converted into RGBA.
+pub fn request_frame_capture(engine: &mut Engine) -> Result<(), CaptureError> {
+    capture_channel_order(engine)?;
+    engine.capture_requested = true;
+    Ok(())
+}
+
+/// Returns the frame captured by the last [render] after [request_frame_capture], if any.
+pub fn take_captured_frame(engine: &mut Engine) -> Option<CapturedFrame> {
+    engine.captured_frame.take()
+}
+
+/// Renders a frame and returns it as presented. A shorthand for [request_frame_capture],
+/// [render] and [take_captured_frame], for applications that do not render the frame anyway.
+pub fn capture_frame(engine: &mut Engine, world: &mut World, camera: &Camera) -> Result<CapturedFrame, CaptureError> {
+    request_frame_capture(engine)?;
+    render(engine, world, camera);
+    Ok(take_captured_frame(engine).expect("the requested frame has been captured"))
+}
+
+/// Returns the order of the channels of the swapchain's images, if they can be captured.
+fn capture_channel_order(engine: &Engine) -> Result<ChannelOrder, CaptureError> {
+
+    let order = match engine.swapchain.format() {
+        ash::vk::Format::R8G8B8A8_UNORM | ash::vk::Format::R8G8B8A8_SRGB => Ok(ChannelOrder::Rgba),
+        ash::vk::Format::B8G8R8A8_UNORM | ash::vk::Format::B8G8R8A8_SRGB => Ok(ChannelOrder::Bgra),
+        format => Err(CaptureError::UnsupportedFormat { format: format!("{format:?}") }),
+    }?;
+
+    if !engine.swapchain.is_capturable() {
+        return Err(CaptureError::NotCapturable)
+    }
+
+    Ok(order)
+}
+
+/// Records the copy of the swapchain image into the capture buffer, after the frame has been
+/// rendered into it and before it is presented.
+fn record_capture_commands(
+    device: &Device,
+    command_buffer: &ash::vk::CommandBuffer,
+    image: ash::vk::Image,
+    buffer: &Buffer<u8>,
+    extent: ash::vk::Extent2D,
+) {
+
+    let subresource_range = ::ash::vk::ImageSubresourceRange::builder()
+        .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build();
+
+    // the rendered image is copied before it is handed over to the presentation
+    let transition = |old_layout, new_layout, src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask| {
+
+        let barrier = ::ash::vk::ImageMemoryBarrier::builder()
+            .src_queue_family_index(::ash::vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(::ash::vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .subresource_range(subresource_range)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build();
+
+        unsafe {
+            device.handle().cmd_pipeline_barrier(
+                *command_buffer,
+                src_stage_mask,
+                dst_stage_mask,
+                ::ash::vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            )
+        }
+    };
+
+    transition(
+        ::ash::vk::ImageLayout::PRESENT_SRC_KHR,
+        ::ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ::ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        ::ash::vk::AccessFlags::TRANSFER_READ,
+        ::ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        ::ash::vk::PipelineStageFlags::TRANSFER,
+    );
+
+    let region = ::ash::vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(extent.width)
+        .buffer_image_height(extent.height)
+        .image_offset(::ash::vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(::ash::vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+        .image_subresource(::ash::vk::ImageSubresourceLayers::builder()
+            .aspect_mask(::ash::vk::ImageAspectFlags::COLOR)
+            .base_array_layer(0)
+            .layer_count(1)
+            .mip_level(0)
+            .build())
+        .build();
+
+    unsafe {
+        device.handle().cmd_copy_image_to_buffer(*command_buffer, image, ::ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL, *buffer.handle(), &[region])
+    }
+
+    transition(
+        ::ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ::ash::vk::ImageLayout::PRESENT_SRC_KHR,
+        ::ash::vk::AccessFlags::TRANSFER_READ,
+        ::ash::vk::AccessFlags::empty(),
+        ::ash::vk::PipelineStageFlags::TRANSFER,
+        ::ash::vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+    );
+
+    let barrier = ::ash::vk::BufferMemoryBarrier::builder()
+        .buffer(*buffer.handle())
+        .offset(0)
+        .size(::ash::vk::WHOLE_SIZE)
+        .src_access_mask(::ash::vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(::ash::vk::AccessFlags::HOST_READ)
+        .src_queue_family_index(::ash::vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(::ash::vk::QUEUE_FAMILY_IGNORED)
+        .build();
+
+    unsafe {
+        device.handle().cmd_pipeline_barrier(
+            *command_buffer,
+            ::ash::vk::PipelineStageFlags::TRANSFER,
+            ::ash::vk::PipelineStageFlags::HOST,
+            ::ash::vk::DependencyFlags::empty(),
+            &[],
+            &[barrier],
+            &[],
+        )
+    }
+}
+
+/// Reads the frame copied by [record_capture_commands] back, once the frame's commands have
+/// been executed.
+fn read_captured_frame(engine: &Engine, order: ChannelOrder) -> CapturedFrame {
+
+    let extent = engine.swapchain.extent();
+    let buffer = &engine.capture_buffer;
+    let mut raw = vec![0u8; buffer.capacity()];
+
+    unsafe {
+        engine.resource_manager.invalidate(buffer, 0, buffer.capacity())
+            .expect("Failed to invalidate capture-buffer");
+        engine.resource_manager.copy(buffer, &mut raw, 0, buffer.capacity())
+            .expect("Failed to read capture-buffer");
+    }
+
+    CapturedFrame::from_raw(extent.width, extent.height, &raw, order, engine.post_processing.srgb_output)
+}
+
+/// Records commands with the closure and submits them, blocking until they have been executed.
+fn submit_immediately<F>(device: &Device, queue: &DeviceQueue, record: F)
+    where F: FnOnce(ash::vk::CommandBuffer) {
+
+    let command_buffer = {
+        let create_info = ::ash::vk::CommandBufferAllocateInfo::builder()
+            .command_pool(device.command_pool().handle())
+            .command_buffer_count(1)
+            .level(ash::vk::CommandBufferLevel::PRIMARY)
+            .build();
+
+        unsafe {
+            device.handle().allocate_command_buffers(&create_info)
+                .expect("Failed to create command buffer")[0]
+        }
+    };
+
+    let begin_info = ash::vk::CommandBufferBeginInfo::builder()
+        .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+    unsafe {
+        device.handle().begin_command_buffer(command_buffer, &begin_info)
+            .expect("Failed to begin command buffer");
+    }
+
+    record(command_buffer);
+
+    unsafe {
+        device.handle().end_command_buffer(command_buffer)
+            .expect("Failed to end command buffer");
+    }
+
+    let completion_fence = {
+        let fence_create_info = ::ash::vk::FenceCreateInfo::builder()
+            .build();
+        unsafe {
+            device.handle().create_fence(&fence_create_info, None)
+                .expect("Expected successfull fence creation!")
+        }
+    };
+
+    let command_buffers = [command_buffer];
+    let submit_info = ash::vk::SubmitInfo::builder()
+        .command_buffers(&command_buffers);
+
+    unsafe {
+        device.handle().queue_submit(*queue.handle(), &[*submit_info], completion_fence)
+            .expect("Failed to submit queue");
+    }
+
+    unsafe {
+        let fences = [completion_fence];
+        device.handle().wait_for_fences(&fences, true, 5_000_000_000)
+            .expect("Failed to wait for command buffer completion fence!");
+        device.handle().destroy_fence(completion_fence, None);
+        device.handle().free_command_buffers(device.command_pool().handle(), &command_buffers);
+    }
+}
+
+/// Starts writing every rendered frame into the directory as `frame-00000.png`,
+/// `frame-00001.png` and so on, e.g. for bug reports or turntables of assets. A running
+/// recording is replaced.
+pub fn start_frame_sequence(engine: &mut Engine, directory: &Path) -> Result<(), CaptureError> {
+    capture_channel_order(engine)?;
+    engine.frame_sequence = Some(FrameSequence::new(directory)?);
+    Ok(())
+}
+
+/// Stops the recording of the image sequence, waits for the recorded frames to be written and
+/// returns their number.
+pub fn stop_frame_sequence(engine: &mut Engine) -> usize {
+    engine.frame_sequence.take().map_or(0, FrameSequence::finish)
+}
+
 pub fn prepare(engine: &mut Engine, world: &mut World) {
 
     let _device: Ref<Device> = (*engine.device).borrow();
@@ -1191,14 +4113,15 @@ pub fn prepare(engine: &mut Engine, world: &mut World) {
         }
     }
 
-    world.geometries.iter().for_each(|geometry| {
+    let textures = engine.default_textures.iter()
+        .chain(world.geometries.iter().flat_map(|geometry| geometry.material.textures()));
 
-        let material = &geometry.material;
+    textures.for_each(|texture| {
 
         layout_transition(
             _device.borrow(),
             command_buffer,
-            *material.texture_image.handle(),
+            *texture.image.handle(),
             range,
             ::ash::vk::ImageLayout::UNDEFINED,
             ::ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL
@@ -1215,13 +4138,13 @@ pub fn prepare(engine: &mut Engine, world: &mut World) {
                 .build()
             )
             .image_offset(Offset3D { x: 0, y: 0, z: 0 })
-            .image_extent(material.texture_image.extent().into());
+            .image_extent(texture.image.extent().into());
 
         unsafe {
             _device.handle().cmd_copy_buffer_to_image(
                 command_buffer,
-                *material.texture_buffer.handle(),
-                *material.texture_image.handle(),
+                *texture.buffer.handle(),
+                *texture.image.handle(),
                 ::ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
                 &[*buffer_copy]
             )
@@ -1230,7 +4153,7 @@ pub fn prepare(engine: &mut Engine, world: &mut World) {
         layout_transition(
             _device.borrow(),
             command_buffer,
-            *material.texture_image.handle(),
+            *texture.image.handle(),
             range,
             ::ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
             ::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
@@ -1266,6 +4189,14 @@ pub fn prepare(engine: &mut Engine, world: &mut World) {
 
 pub fn render(engine: &mut Engine, world: &mut World, camera: &Camera) {
 
+    let wait_for_compute = submit_compute(engine);
+
+    // the frame is captured on request and for the recorded image sequence
+    let capture_order = match engine.capture_requested || engine.frame_sequence.is_some() {
+        true => capture_channel_order(engine).ok(),
+        false => None,
+    };
+
     let _device = (*engine.device).borrow();
     let (index, suboptimal) = engine.swapchain.acquire_next_image(engine.image_available_semaphore);
     let queue = Rc::clone(&_device.queues()[0]);
@@ -1273,6 +4204,14 @@ pub fn render(engine: &mut Engine, world: &mut World, camera: &Camera) {
     let swapchains = [*engine.swapchain.handle()];
     let indices = [index];
     let mut resource_manager = &mut engine.resource_manager;
+    let now = Instant::now();
+
+    // the previous frame has completed, so that the global descriptor sets are not in use
+    let environment_lighting = world.environment_lighting.as_ref().unwrap_or(&engine.default_environment_lighting);
+    if environment_lighting.irradiance_view != engine.bound_environment_lighting {
+        write_environment_lighting(&_device, &engine.global_descriptor_sets, engine.environment_sampler, environment_lighting);
+        engine.bound_environment_lighting = environment_lighting.irradiance_view;
+    }
 
     update_ubo(
         index as usize,
@@ -1280,34 +4219,104 @@ pub fn render(engine: &mut Engine, world: &mut World, camera: &Camera) {
         &mut resource_manager,
         &mut engine.ubo_buffer,
         camera,
+        &engine.viewports[0],
+        (now - engine.start_time).as_secs_f32(),
+        (now - engine.last_frame_time).as_secs_f32(),
+        engine.frame_index,
     );
 
-    record_commands(
-        engine.device.clone(),
-        &engine.command_buffers[index as usize],
-        &engine.global_descriptor_sets[index as usize],
-        &engine.frame_buffers[index as usize],
-        &engine.renderpass,
-        &engine.viewports[0],
-        &engine.scissors[0],
-        &engine.pipelines,
-        &engine.pipeline_layout,
-        &engine.timings_query_pool,
-        &engine.vertices_query_pool,
-        &world.geometries,
+    update_lights(
+        index as usize,
+        &mut resource_manager,
+        &mut engine.lights_buffer,
+        world,
+    );
+
+    update_selection(
+        index as usize,
+        &mut resource_manager,
+        &mut engine.post_processing.selection_buffer,
+        &engine.selection,
+        world,
+    );
+
+    cull_geometries(
+        &mut resource_manager,
+        &mut world.geometries,
+        camera,
+    );
+
+    update_skins(
+        &mut resource_manager,
+        &mut world.geometries,
+    );
+
+    let debug_vertex_counts = update_debug_lines(
+        index as usize,
+        &mut resource_manager,
+        &mut engine.debug_drawing.vertex_buffer,
+        &engine.debug_lines,
+    );
+
+    let particle_vertex_counts = update_particles(
+        index as usize,
+        &mut resource_manager,
+        &mut engine.particle_drawing,
+        &world.particles,
+        camera,
+    );
+
+    let overlay_vertex_count = update_overlay(
+        index as usize,
+        &mut resource_manager,
+        &mut engine.overlay_drawing.vertex_buffer,
+        &engine.overlay,
+    );
+
+    let shadow_casters = update_shadows(
+        index as usize,
+        &mut resource_manager,
+        &mut engine.shadow_cascades_buffer,
+        &mut engine.shadows_buffer,
+        camera,
+        world,
     );
 
-    let wait_semaphores = [
-        engine.image_available_semaphore
+    record_commands(engine, &FrameState {
+        swapchain_image_index: index as usize,
+        geometries: &world.geometries,
+        transparent_draw_order: &transparent_draw_order(&world.geometries, &camera.position()),
+        skybox: world.skybox.as_ref(),
+        shadow_casters,
+        particle_vertex_counts,
+        debug_vertex_counts,
+        overlay_vertex_count,
+        post_passes: &world.post_processing.passes(engine.post_processing.srgb_output, !engine.selection.is_empty()),
+        capture: capture_order.is_some(),
+    });
+
+    let mut wait_semaphores = vec![
+        engine.image_available_semaphore
+    ];
+
+    let mut wait_stages = vec![
+        ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
     ];
 
+    if wait_for_compute {
+        wait_semaphores.push(engine.compute_finished_semaphore);
+        wait_stages.push(ash::vk::PipelineStageFlags::DRAW_INDIRECT
+            | ash::vk::PipelineStageFlags::VERTEX_INPUT
+            | ash::vk::PipelineStageFlags::COMPUTE_SHADER);
+    }
+
     let signal_semaphores = [
         engine.render_finished_semaphore
     ];
 
     let submit_info = ash::vk::SubmitInfo::builder()
         .wait_semaphores(&wait_semaphores)
-        .wait_dst_stage_mask(&[ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
+        .wait_dst_stage_mask(&wait_stages)
         .command_buffers(&command_buffer)
         .signal_semaphores(&signal_semaphores);
 
@@ -1352,17 +4361,63 @@ pub fn render(engine: &mut Engine, world: &mut World, camera: &Camera) {
 
     // println!("draw time: {} ns", timing_data[1] - timing_data[0]);
     // println!("vert. invocations: {}", vertices_data[0]);
-    engine.last_swapchain_image_index = index
+    engine.last_swapchain_image_index = index;
+    engine.last_frame_time = now;
+    engine.frame_index = engine.frame_index.wrapping_add(1);
+    engine.debug_lines.clear();
+    engine.overlay.clear();
+
+    drop(_device);
+
+    if let Some(order) = capture_order {
+        let frame = read_captured_frame(engine, order);
+        if std::mem::take(&mut engine.capture_requested) {
+            engine.captured_frame = Some(frame.clone());
+        }
+        record_frame_sequence(engine, frame);
+    }
+}
+
+/// Queues the frame to be written into the image sequence, if one is recorded. The recording
+/// stops once writing a frame failed.
+fn record_frame_sequence(engine: &mut Engine, frame: CapturedFrame) {
+
+    let failed = engine.frame_sequence.as_ref()
+        .map_or(false, |sequence| !sequence.push(frame));
+
+    if failed {
+        stop_frame_sequence(engine);
+    }
 }
 
-fn update_ubo(index: usize, device: DeviceRef, resource_manager: &mut ResourceManager, buffer: &mut Buffer<UniformBufferObject>, camera: &Camera) {
+fn update_ubo(
+    index: usize,
+    device: DeviceRef,
+    resource_manager: &mut ResourceManager,
+    buffer: &mut Buffer<UniformBufferObject>,
+    camera: &Camera,
+    viewport: &ash::vk::Viewport,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+) {
 
-    let size = std::mem::size_of::<UniformBufferObject>();
-    let mvp: Matrix4<f32> = *camera.as_matrix();
+    let view_projection: Matrix4<f32> = *camera.as_matrix();
+    let position = camera.position();
 
     let ubo = [
         UniformBufferObject {
-            mvp: mvp,
+            view: *camera.view(),
+            projection: *camera.projection(),
+            view_projection,
+            inverse_view_projection: view_projection.try_inverse()
+                .unwrap_or_else(Matrix4::identity),
+            camera_position: [position.x, position.y, position.z, 1.0],
+            viewport: [viewport.width, viewport.height, 1.0 / viewport.width, 1.0 / viewport.height],
+            time,
+            delta_time,
+            frame,
+            _padding: 0,
         }
     ];
 
@@ -1372,22 +4427,323 @@ fn update_ubo(index: usize, device: DeviceRef, resource_manager: &mut ResourceMa
     }
 }
 
-fn record_commands(
-    device: DeviceRef,
-    command_buffer: &ash::vk::CommandBuffer,
-    descriptor_set: &ash::vk::DescriptorSet,
-    frame_buffer: &ash::vk::Framebuffer,
-    renderpass: &ash::vk::RenderPass,
-    viewport: &ash::vk::Viewport,
-    scissor: &ash::vk::Rect2D,
-    pipelines: &Vec<ash::vk::Pipeline>,
-    pipeline_layout: &ash::vk::PipelineLayout,
-    timings_query_pool: &ash::vk::QueryPool,
-    vertices_query_pool: &ash::vk::QueryPool,
-    geometries: &Vec<Geometry>,
-) {
+fn update_skins(resource_manager: &mut ResourceManager, geometries: &mut Vec<Geometry>) {
+    geometries.iter_mut().for_each(|geometry| {
+        if let Some(skin) = &mut geometry.skin {
+            let joint_matrices = joint_palette(&skin.skeleton, &skin.pose);
+            unsafe {
+                resource_manager.copy(&joint_matrices, &mut skin.joint_palette_buffer, 0, joint_matrices.len())
+                    .expect("Failed to copy joint palette");
+                resource_manager.flush(&mut skin.joint_palette_buffer, 0, joint_matrices.len())
+                    .expect("Failed to flush joint palette buffer");
+            }
+        }
+    });
+}
+
+fn update_lights(index: usize, resource_manager: &mut ResourceManager, buffer: &mut Buffer<LightsUniformBufferObject>, world: &World) {
+
+    let lights = [
+        LightsUniformBufferObject::new(&world.ambient_light, &world.lights)
+    ];
+
+    unsafe {
+        resource_manager.copy(&lights, buffer, index, 1)
+            .expect("Failed to copy lights");
+        resource_manager.flush(buffer, index, 1)
+            .expect("Failed to flush lights uniform buffer");
+    }
+}
+
+/// Copies the debug lines into the range of the swapchain image and returns the number of
+/// depth-tested and overlay vertices, truncated to [MAX_DEBUG_VERTICES] together.
+fn update_debug_lines(index: usize, resource_manager: &mut ResourceManager, buffer: &mut Buffer<DebugVertex>, lines: &DebugLines) -> (u32, u32) {
+
+    let depth_tested = lines.vertices().len().min(MAX_DEBUG_VERTICES);
+    let overlay = lines.overlay_vertices().len().min(MAX_DEBUG_VERTICES - depth_tested);
+    let offset = index * MAX_DEBUG_VERTICES;
+
+    unsafe {
+        if depth_tested > 0 {
+            resource_manager.copy(&lines.vertices(), buffer, offset, depth_tested)
+                .expect("Failed to copy debug vertices");
+        }
+        if overlay > 0 {
+            resource_manager.copy(&lines.overlay_vertices(), buffer, offset + depth_tested, overlay)
+                .expect("Failed to copy debug vertices");
+        }
+        // the whole range is flushed, as the vertices are not aligned to the atom size
+        if depth_tested + overlay > 0 {
+            resource_manager.flush(buffer, offset, MAX_DEBUG_VERTICES)
+                .expect("Failed to flush debug vertex buffer");
+        }
+    }
+
+    (depth_tested as u32, overlay as u32)
+}
+
+fn update_particles(index: usize, resource_manager: &mut ResourceManager, particle_drawing: &mut ParticleDrawing, emitters: &[ParticleEmitter], camera: &Camera) -> (u32, u32) {
+
+    // the camera's axes are the columns of the view matrix applied to row vectors
+    let right = camera.view().fixed_view::<3, 1>(0, 0).into_owned();
+    let down = camera.view().fixed_view::<3, 1>(0, 1).into_owned();
+
+    let (additive, blended) = billboards(emitters, &camera.position(), &right, &down, MAX_PARTICLE_VERTICES, &mut particle_drawing.vertices);
+    let count = particle_drawing.vertices.len();
+    let offset = index * MAX_PARTICLE_VERTICES;
+
+    if count > 0 {
+        unsafe {
+            resource_manager.copy(&particle_drawing.vertices, &mut particle_drawing.vertex_buffer, offset, count)
+                .expect("Failed to copy particle vertices");
+            // the whole range is flushed, as the vertices are not aligned to the atom size
+            resource_manager.flush(&mut particle_drawing.vertex_buffer, offset, MAX_PARTICLE_VERTICES)
+                .expect("Failed to flush particle vertex buffer");
+        }
+    }
+
+    (additive, blended)
+}
+
+fn update_overlay(index: usize, resource_manager: &mut ResourceManager, buffer: &mut Buffer<OverlayVertex>, overlay: &Overlay) -> u32 {
+
+    let count = overlay.vertices().len().min(MAX_OVERLAY_VERTICES);
+    let offset = index * MAX_OVERLAY_VERTICES;
+
+    if count > 0 {
+        unsafe {
+            resource_manager.copy(&overlay.vertices(), buffer, offset, count)
+                .expect("Failed to copy overlay vertices");
+            // the whole range is flushed, as the vertices are not aligned to the atom size
+            resource_manager.flush(buffer, offset, MAX_OVERLAY_VERTICES)
+                .expect("Failed to flush overlay vertex buffer");
+        }
+    }
+
+    count as u32
+}
+
+fn update_selection(index: usize, resource_manager: &mut ResourceManager, buffer: &mut Buffer<SelectionUniformBufferObject>, selection: &[u32], world: &World) {
+
+    let settings = world.post_processing.outline.unwrap_or_default();
+    let selections = [
+        SelectionUniformBufferObject::new(&settings, selection)
+    ];
+
+    unsafe {
+        resource_manager.copy(&selections, buffer, index, 1)
+            .expect("Failed to copy selection");
+        resource_manager.flush(buffer, index, 1)
+            .expect("Failed to flush selection uniform buffer");
+    }
+}
+
+/// Moves the instances intersecting the frustum to the front of each geometry's instances buffer,
+/// grouped by their level of detail, so that the main pass draws only those, while the shadow
+/// passes still draw all instances. Geometries culled on the GPU are skipped and always drawn with
+/// their own mesh.
+fn cull_geometries(resource_manager: &mut ResourceManager, geometries: &mut Vec<Geometry>, camera: &Camera) {
+
+    geometries.iter_mut()
+        .filter(|geometry| gpu_culling(geometry).is_none())
+        .for_each(|geometry| {
+
+            let thresholds = geometry.lods.iter()
+                .map(|lod| lod.threshold)
+                .collect::<Vec<_>>();
+
+            (geometry.visible_instances, geometry.lod_batches) = cull_instances(
+                &mut geometry.instances,
+                geometry.frustum_culling.then_some(&geometry.bounding_box),
+                &geometry.bounding_sphere,
+                geometry.material.blend_mode,
+                &thresholds,
+                &geometry.lod_selection,
+                camera,
+            );
+
+            unsafe {
+                resource_manager.copy(&geometry.instances, &mut geometry.instances_buffer, 0, geometry.instances.len())
+                    .expect("Failed to copy instance data");
+                resource_manager.flush(&mut geometry.instances_buffer, 0, geometry.instances.len())
+                    .expect("Failed to flush geometry instance data buffer");
+            }
+        });
+}
+
+/// Moves the instances intersecting the camera's frustum to the front, unless no bounding box is
+/// passed, sorts the visible instances of transparent geometries from back to front and selects
+/// their levels of detail. Returns the number of visible instances and the batches drawing them.
+fn cull_instances(
+    instances: &mut Vec<InstanceData>,
+    bounding_box: Option<&BoundingBox>,
+    bounding_sphere: &[f32; 4],
+    blend_mode: BlendMode,
+    thresholds: &[f32],
+    lod_selection: &LodSelection,
+    camera: &Camera,
+) -> (usize, Vec<LodBatch>) {
+
+    let camera_position = camera.position();
+
+    let visible_instances = match bounding_box {
+        Some(bounding_box) => partition_visible_instances(instances, bounding_box, &camera.frustum()),
+        None => instances.len(),
+    };
+
+    if blend_mode.is_transparent() {
+        sort_back_to_front(&mut instances[..visible_instances], bounding_sphere, &camera_position);
+    }
+
+    let lod_batches = select_lods(
+        &mut instances[..visible_instances],
+        thresholds,
+        lod_selection,
+        bounding_sphere,
+        &camera_position,
+        camera.projection()[(1, 1)],
+        blend_mode.is_transparent(),
+    );
+
+    (visible_instances, lod_batches)
+}
+
+/// Whether geometries are culled by the culling shader. It draws the geometry's own mesh only and
+/// compacts the visible instances in no particular order, so geometries with levels of detail and
+/// transparent geometries, which are sorted, are culled on the CPU.
+fn is_culled_on_gpu(blend_mode: BlendMode, lod_count: usize) -> bool {
+    lod_count == 0 && !blend_mode.is_transparent()
+}
+
+/// Returns the buffers of the geometry written by the culling shader, if the geometry is culled
+/// on the GPU.
+fn gpu_culling(geometry: &Geometry) -> Option<&IndirectDraw> {
+    geometry.indirect_draw.as_ref()
+        .filter(|_| is_culled_on_gpu(geometry.material.blend_mode, geometry.lods.len()))
+}
+
+/// Updates the shadow parameters and the uniforms of every cascade rendered by the shadow pass.
+/// Returns the number of lights casting shadows.
+fn update_shadows(
+    index: usize,
+    resource_manager: &mut ResourceManager,
+    cascades_buffer: &mut Buffer<UniformBufferObject>,
+    buffer: &mut Buffer<ShadowsUniformBufferObject>,
+    camera: &Camera,
+    world: &World,
+) -> usize {
+
+    let settings = &world.shadows;
+    let splits = cascade_splits(camera.near(), camera.far().min(settings.distance), settings.split_lambda);
+
+    let casters = shadow_casters(&world.lights)
+        .map(|(_, light)| ShadowCascade::cascades(camera, &splits, &light.direction, settings.distance))
+        .collect::<Vec<_>>();
+
+    let ubos = casters.iter().flat_map(|cascades| cascades.iter()).map(|cascade| {
+        let view_projection = cascade.view_projection();
+        UniformBufferObject {
+            view: cascade.view,
+            projection: cascade.projection,
+            view_projection,
+            inverse_view_projection: view_projection.try_inverse()
+                .unwrap_or_else(Matrix4::identity),
+            ..UniformBufferObject::new()
+        }
+    }).collect::<Vec<_>>();
+
+    let shadows = [
+        ShadowsUniformBufferObject::new(&splits, &casters, settings)
+    ];
+
+    unsafe {
+        resource_manager.copy(&ubos, cascades_buffer, index * MAX_SHADOW_CASTERS * SHADOW_CASCADES, ubos.len())
+            .expect("Failed to copy shadow cascades");
+        resource_manager.flush(cascades_buffer, index * MAX_SHADOW_CASTERS * SHADOW_CASCADES, ubos.len())
+            .expect("Failed to flush shadow cascades uniform buffer");
+        resource_manager.copy(&shadows, buffer, index, 1)
+            .expect("Failed to copy shadows");
+        resource_manager.flush(buffer, index, 1)
+            .expect("Failed to flush shadows uniform buffer");
+    }
+
+    casters.len()
+}
+
+/// Returns the pipelines created for the vertex layout of the geometry.
+fn pipelines_of<'a>(geometry_pipelines: &'a [GeometryPipelines], geometry: &Geometry) -> &'a GeometryPipelines {
+    geometry_pipelines.iter()
+        .find(|pipelines| pipelines.vertex_layout == geometry.vertex_layout)
+        .expect("pipelines for the geometry's vertex layout")
+}
 
-    let _device = (*device).borrow();
+/// Geometries with skinned vertices can not be drawn before they are bound to a skeleton.
+fn is_drawable(geometry: &Geometry) -> bool {
+    geometry.skin.is_some() || !geometry.vertex_layout.is_skinned()
+}
+
+/// Binds the joint palette of a skinned geometry to set 3.
+fn bind_skin(device: &Device, command_buffer: &ash::vk::CommandBuffer, pipeline_layout: &ash::vk::PipelineLayout, geometry: &Geometry) {
+    if let Some(skin) = &geometry.skin {
+        let descriptor_sets = [skin.descriptor_set];
+        unsafe {
+            device.handle().cmd_bind_descriptor_sets(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, *pipeline_layout, 3, &descriptor_sets, &[]);
+        }
+    }
+}
+
+/// The state of a frame recorded by [record_commands], which is not kept by the engine.
+struct FrameState<'a> {
+    swapchain_image_index: usize,
+    geometries: &'a [Geometry],
+    /// The indices of the transparent geometries, see [transparent_draw_order].
+    transparent_draw_order: &'a [usize],
+    skybox: Option<&'a Skybox>,
+    shadow_casters: usize,
+    particle_vertex_counts: (u32, u32),
+    debug_vertex_counts: (u32, u32),
+    overlay_vertex_count: u32,
+    post_passes: &'a [PostPass],
+    /// Whether the presented image is copied into the capture buffer.
+    capture: bool,
+}
+
+fn record_commands(engine: &Engine, frame: &FrameState) {
+
+    let swapchain_image_index = frame.swapchain_image_index;
+    let shadow_descriptor_sets_per_image = MAX_SHADOW_CASTERS * SHADOW_CASCADES;
+
+    let command_buffer = &engine.command_buffers[swapchain_image_index];
+    let descriptor_set = &engine.global_descriptor_sets[swapchain_image_index];
+    let texture_table_descriptor_set = &engine.texture_table.descriptor_set();
+    let frame_buffer = &engine.frame_buffers[swapchain_image_index];
+    let renderpass = &engine.renderpass;
+    let viewport = &engine.viewports[0];
+    let scissor = &engine.scissors[0];
+    let geometry_pipelines = &engine.geometry_pipelines;
+    let pipeline_layout = &engine.pipeline_layout;
+    let culling_pipeline = engine.culling_pipeline.as_ref();
+    let timings_query_pool = &engine.timings_query_pool;
+    let vertices_query_pool = &engine.vertices_query_pool;
+    let geometries = frame.geometries;
+    let shadow_renderpass = &engine.shadow_renderpass;
+    let shadow_frame_buffers = &engine.shadow_frame_buffers;
+    let shadow_descriptor_sets = &engine.shadow_descriptor_sets[swapchain_image_index * shadow_descriptor_sets_per_image..][..shadow_descriptor_sets_per_image];
+    let shadow_casters = frame.shadow_casters;
+    let post_processing = &engine.post_processing;
+    let skybox = frame.skybox;
+    let transparent_draw_order = frame.transparent_draw_order;
+    let particle_drawing = &engine.particle_drawing;
+    let particle_vertex_counts = frame.particle_vertex_counts;
+    let debug_drawing = &engine.debug_drawing;
+    let debug_vertex_counts = frame.debug_vertex_counts;
+    let overlay_drawing = &engine.overlay_drawing;
+    let overlay_batches = engine.overlay.batches();
+    let overlay_vertex_count = frame.overlay_vertex_count;
+    let post_passes = frame.post_passes;
+    let capture = frame.capture.then(|| (engine.swapchain.images()[swapchain_image_index], &engine.capture_buffer, engine.swapchain.extent()));
+
+    let _device = (*engine.device).borrow();
 
     unsafe {
         _device.handle().reset_command_buffer(*command_buffer, CommandBufferResetFlags::RELEASE_RESOURCES);
@@ -1404,6 +4760,103 @@ fn record_commands(
         _device.handle().cmd_reset_query_pool(*command_buffer, *vertices_query_pool, 0, 1);
     }
 
+    if let Some(culling_pipeline) = culling_pipeline {
+        record_culling_commands(&_device, command_buffer, descriptor_set, culling_pipeline, geometries);
+    }
+
+    // Every shadow map is cleared, even if there is no light casting shadows into it, so that all
+    // shadow maps are in the layout expected by the global descriptor set.
+    shadow_frame_buffers.iter().enumerate().for_each(|(caster, frame_buffer)| {
+
+        let renderpass_begin_info = ash::vk::RenderPassBeginInfo::builder()
+            .render_pass(*shadow_renderpass)
+            .framebuffer(*frame_buffer)
+            .render_area(ash::vk::Rect2D {
+                offset: ash::vk::Offset2D { x: 0, y: 0 },
+                extent: ash::vk::Extent2D {
+                    width: 2 * SHADOW_MAP_RESOLUTION,
+                    height: 2 * SHADOW_MAP_RESOLUTION,
+                },
+            })
+            .clear_values(&[
+                ash::vk::ClearValue {
+                    depth_stencil: ash::vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 }
+                },
+            ]);
+
+        unsafe {
+            _device.handle().cmd_begin_render_pass(*command_buffer, &renderpass_begin_info, ash::vk::SubpassContents::INLINE);
+        }
+
+        if caster < shadow_casters {
+
+            (0..SHADOW_CASCADES).for_each(|cascade| {
+
+                let offset = ash::vk::Offset2D {
+                    x: ((cascade % 2) as u32 * SHADOW_MAP_RESOLUTION) as i32,
+                    y: ((cascade / 2) as u32 * SHADOW_MAP_RESOLUTION) as i32,
+                };
+
+                let viewports = [ash::vk::Viewport {
+                    x: offset.x as f32,
+                    y: offset.y as f32,
+                    width: SHADOW_MAP_RESOLUTION as f32,
+                    height: SHADOW_MAP_RESOLUTION as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                }];
+
+                let scissors = [ash::vk::Rect2D {
+                    offset,
+                    extent: ash::vk::Extent2D {
+                        width: SHADOW_MAP_RESOLUTION,
+                        height: SHADOW_MAP_RESOLUTION,
+                    },
+                }];
+
+                let descriptor_sets = [
+                    shadow_descriptor_sets[caster * SHADOW_CASCADES + cascade]
+                ];
+                let descriptor_sets_offsets = [];
+
+                unsafe {
+                    _device.handle().cmd_set_viewport(*command_buffer, 0, &viewports);
+                    _device.handle().cmd_set_scissor(*command_buffer, 0, &scissors);
+                    _device.handle().cmd_bind_descriptor_sets(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, *pipeline_layout, 0, &descriptor_sets, &descriptor_sets_offsets)
+                }
+
+                geometries.iter().filter(|geometry| geometry.cast_shadows && is_drawable(geometry)).for_each(|geometry| {
+
+                    let shadow_pipeline = pipelines_of(geometry_pipelines, geometry).shadow_pipeline;
+                    let vertex_buffers = [*geometry.vertex_buffer.handle()];
+                    let instance_data_buffers = [*geometry.instances_buffer.handle()];
+                    let buffer_offsets: [u64; 1] = [0];
+
+                    bind_skin(&_device, command_buffer, pipeline_layout, geometry);
+
+                    unsafe {
+                        _device.handle().cmd_bind_pipeline(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, shadow_pipeline);
+                        _device.handle().cmd_bind_vertex_buffers(*command_buffer, 0, &vertex_buffers, &buffer_offsets);
+                        _device.handle().cmd_bind_vertex_buffers(*command_buffer, 1, &instance_data_buffers, &buffer_offsets);
+                        _device.handle().cmd_bind_index_buffer(*command_buffer, *geometry.index_buffer.handle(), 0, geometry.index_buffer.index_type());
+                        _device.handle().cmd_draw_indexed(
+                            *command_buffer,
+                            geometry.index_buffer.capacity() as u32,
+                            geometry.instances_buffer.capacity() as u32,
+                            0,
+                            0,
+                            0,
+                        );
+                    }
+                });
+            });
+        }
+
+        unsafe {
+            _device.handle().cmd_end_render_pass(*command_buffer);
+        }
+    });
+
     let renderpass_begin_info = ash::vk::RenderPassBeginInfo::builder()
         .render_pass(*renderpass)
         .framebuffer(*frame_buffer)
@@ -1442,42 +4895,113 @@ fn record_commands(
         _device.handle().cmd_begin_render_pass(*command_buffer, &renderpass_begin_info, ash::vk::SubpassContents::INLINE);
     }
 
-    pipelines.iter().for_each(| pipeline | {
+    let draw = |geometry: &Geometry, pipeline: ash::vk::Pipeline| {
+
+        let vertex_buffers = [*geometry.vertex_buffer.handle()];
+        let instance_data_buffers = [*geometry.instances_buffer.handle()];
+        let buffer_offsets: [u64; 1] = [0];
+        let descriptor_sets = [
+            *descriptor_set,
+            geometry.material.descriptor_set,
+            *texture_table_descriptor_set,
+        ];
+        let descriptor_sets_offsets = [];
+        let push_constants = GeometryPushConstants {
+            receive_shadows: geometry.receive_shadows as u32,
+            lod_dither: 0.0,
+        };
 
         unsafe {
-            _device.handle().cmd_bind_pipeline(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, *pipeline);
+            _device.handle().cmd_bind_pipeline(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, pipeline);
+            _device.handle().cmd_bind_vertex_buffers(*command_buffer, 0, &vertex_buffers, &buffer_offsets);
+            _device.handle().cmd_bind_vertex_buffers(*command_buffer, 1, &instance_data_buffers, &buffer_offsets);
+            _device.handle().cmd_bind_index_buffer(*command_buffer, *geometry.index_buffer.handle(), 0, geometry.index_buffer.index_type());
+            _device.handle().cmd_bind_descriptor_sets(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, *pipeline_layout, 0, &descriptor_sets, &descriptor_sets_offsets);
+            _device.handle().cmd_push_constants(*command_buffer, *pipeline_layout, ::ash::vk::ShaderStageFlags::FRAGMENT, 0, push_constants.as_bytes())
         }
 
-        geometries.iter().for_each(|geometry| {
+        bind_skin(&_device, command_buffer, pipeline_layout, geometry);
 
-            let vertex_buffers = [*geometry.vertex_buffer.handle()];
-            let instance_data_buffers = [*geometry.instances_buffer.handle()];
-            let buffer_offsets: [u64; 1] = [0];
-            let descriptor_sets = [
-                *descriptor_set,
-                geometry.material.descriptor_set
-            ];
-            let descriptor_sets_offsets = [];
-
-            unsafe {
-                _device.handle().cmd_bind_vertex_buffers(*command_buffer, 0, &vertex_buffers, &buffer_offsets);
-                _device.handle().cmd_bind_vertex_buffers(*command_buffer, 1, &instance_data_buffers, &buffer_offsets);
-                _device.handle().cmd_bind_index_buffer(*command_buffer, *geometry.index_buffer.handle(), 0, ::ash::vk::IndexType::UINT32);
-                _device.handle().cmd_bind_descriptor_sets(*command_buffer, ash::vk::PipelineBindPoint::GRAPHICS, *pipeline_layout, 0, &descriptor_sets, &descriptor_sets_offsets)
-            }
-
-            unsafe {
-                _device.handle().cmd_draw_indexed(
+        match gpu_culling(geometry) {
+            Some(indirect_draw) => unsafe {
+                let visible_instances_buffers = [*indirect_draw.visible_instances_buffer.handle()];
+                _device.handle().cmd_bind_vertex_buffers(*command_buffer, 1, &visible_instances_buffers, &buffer_offsets);
+                _device.handle().cmd_draw_indexed_indirect_count(
                     *command_buffer,
-                    geometry.index_buffer.capacity() as u32,
-                    geometry.instances_buffer.capacity() as u32,
-                    0,
+                    *indirect_draw.draw_command_buffer.handle(),
                     0,
+                    *indirect_draw.draw_count_buffer.handle(),
                     0,
+                    1,
+                    std::mem::size_of::<::ash::vk::DrawIndexedIndirectCommand>() as u32,
                 );
             }
-        });
+            None => geometry.lod_batches.iter().for_each(|batch| {
+
+                let (vertex_buffer, index_buffer) = match batch.level {
+                    0 => (&geometry.vertex_buffer, &geometry.index_buffer),
+                    level => (&geometry.lods[level - 1].vertex_buffer, &geometry.lods[level - 1].index_buffer),
+                };
+
+                let vertex_buffers = [*vertex_buffer.handle()];
+                let push_constants = GeometryPushConstants {
+                    lod_dither: batch.dither,
+                    ..push_constants
+                };
+
+                unsafe {
+                    _device.handle().cmd_bind_vertex_buffers(*command_buffer, 0, &vertex_buffers, &buffer_offsets);
+                    _device.handle().cmd_bind_index_buffer(*command_buffer, *index_buffer.handle(), 0, index_buffer.index_type());
+                    _device.handle().cmd_push_constants(*command_buffer, *pipeline_layout, ::ash::vk::ShaderStageFlags::FRAGMENT, 0, push_constants.as_bytes());
+                    _device.handle().cmd_draw_indexed(
+                        *command_buffer,
+                        index_buffer.capacity() as u32,
+                        batch.instance_count,
+                        0,
+                        0,
+                        batch.first_instance,
+                    );
+                }
+            })
+        }
+    };
+
+    // the opaque geometries are drawn filled first and as wireframe afterwards
+    (0..2).for_each(|mode| {
+        geometries.iter()
+            .filter(|geometry| is_drawable(geometry) && !geometry.material.blend_mode.is_transparent())
+            .for_each(|geometry| draw(geometry, pipelines_of(geometry_pipelines, geometry).pipelines[mode]));
     });
+
+    // the skybox is drawn at the far plane, only where no geometry has been drawn
+    if let Some(skybox) = skybox {
+        skybox.pipeline.record_draw(&_device, command_buffer, &[*descriptor_set, skybox.descriptor_set], &[]);
+    }
+
+    // transparent geometries are blended over the opaque ones and the skybox, from back to front
+    transparent_draw_order.iter()
+        .map(|index| &geometries[*index])
+        .filter(|geometry| is_drawable(geometry))
+        .for_each(|geometry| draw(geometry, pipelines_of(geometry_pipelines, geometry).fill_pipeline(geometry.material.blend_mode)));
+
+    // the additive particles are independent of their order, the alpha-blended ones are sorted
+    let (additive, blended) = particle_vertex_counts;
+    let first_vertex = (swapchain_image_index * MAX_PARTICLE_VERTICES) as u32;
+    [(&particle_drawing.additive_pipeline, first_vertex, additive), (&particle_drawing.alpha_pipeline, first_vertex + additive, blended)].iter()
+        .filter(|(_, _, count)| *count > 0)
+        .for_each(|(pipeline, first_vertex, count)| {
+            pipeline.record_draw(&_device, command_buffer, &[*descriptor_set], particle_drawing.vertex_buffer.handle(), *first_vertex, *count);
+        });
+
+    // the debug lines are drawn last, the overlay ones follow the depth-tested ones in the buffer
+    let (depth_tested, overlay) = debug_vertex_counts;
+    let first_vertex = (swapchain_image_index * MAX_DEBUG_VERTICES) as u32;
+    [(&debug_drawing.pipeline, first_vertex, depth_tested), (&debug_drawing.overlay_pipeline, first_vertex + depth_tested, overlay)].iter()
+        .filter(|(_, _, count)| *count > 0)
+        .for_each(|(pipeline, first_vertex, count)| {
+            pipeline.record_draw(&_device, command_buffer, &[*descriptor_set], debug_drawing.vertex_buffer.handle(), *first_vertex, *count);
+        });
+
     unsafe {
         _device.handle().cmd_end_render_pass(*command_buffer);
     }
@@ -1490,8 +5014,306 @@ fn record_commands(
         _device.handle().cmd_write_timestamp(*command_buffer, ash::vk::PipelineStageFlags::VERTEX_SHADER, *timings_query_pool, 1)
     }
 
+    record_post_processing_commands(&_device, command_buffer, post_processing, post_passes, swapchain_image_index, scissor, |command_buffer| {
+        record_overlay_commands(&_device, command_buffer, texture_table_descriptor_set, overlay_drawing, overlay_batches, overlay_vertex_count, post_processing.srgb_output, swapchain_image_index, scissor);
+    });
+
+    if let Some((image, buffer, extent)) = capture {
+        record_capture_commands(&_device, command_buffer, image, buffer, extent);
+    }
+
     unsafe {
         _device.handle().end_command_buffer(*command_buffer);
     }
 
 }
+
+/// Records the post-processing passes, each reading the result of the previous one starting with
+/// the HDR target, followed by the pass copying the result into the swapchain image. The
+/// overlay is recorded into the latter, after the copy.
+fn record_post_processing_commands(
+    device: &Device,
+    command_buffer: &ash::vk::CommandBuffer,
+    post_processing: &PostProcessing,
+    passes: &[PostPass],
+    swapchain_image_index: usize,
+    scissor: &ash::vk::Rect2D,
+    record_overlay: impl FnOnce(&ash::vk::CommandBuffer),
+) {
+
+    let present = PostPass {
+        effect: PostEffect::Copy,
+        parameters: [0.0; 4],
+    };
+
+    let mut record_overlay = Some(record_overlay);
+    let mut input = post_processing.hdr_descriptor_sets[swapchain_image_index];
+    let selection = post_processing.selection_descriptor_sets[swapchain_image_index];
+
+    passes.iter().chain([&present]).enumerate().for_each(|(index, pass)| {
+
+        let (renderpass, frame_buffer, pipeline) = match index < passes.len() {
+            true => (post_processing.renderpass, post_processing.frame_buffers[index % 2], &post_processing.pipeline),
+            false => (post_processing.present_renderpass, post_processing.present_frame_buffers[swapchain_image_index], &post_processing.present_pipeline),
+        };
+
+        let renderpass_begin_info = ash::vk::RenderPassBeginInfo::builder()
+            .render_pass(renderpass)
+            .framebuffer(frame_buffer)
+            .render_area(*scissor);
+
+        let push_constants = PostPushConstants::new(pass, scissor.extent.width, scissor.extent.height);
+
+        unsafe {
+            device.handle().cmd_begin_render_pass(*command_buffer, &renderpass_begin_info, ash::vk::SubpassContents::INLINE);
+        }
+
+        pipeline.record_draw(device, command_buffer, &[input, selection], push_constants.as_bytes());
+
+        if index == passes.len() {
+            if let Some(record_overlay) = record_overlay.take() {
+                record_overlay(command_buffer);
+            }
+        }
+
+        unsafe {
+            device.handle().cmd_end_render_pass(*command_buffer);
+        }
+
+        input = post_processing.descriptor_sets[index % 2];
+    });
+}
+
+/// Records the batches of the overlay sampling the texture table, clipped to the vertices
+/// copied into the range of the swapchain image.
+fn record_overlay_commands(
+    device: &Device,
+    command_buffer: &ash::vk::CommandBuffer,
+    texture_table_descriptor_set: &ash::vk::DescriptorSet,
+    overlay_drawing: &OverlayDrawing,
+    batches: &[OverlayBatch],
+    vertex_count: u32,
+    srgb_output: bool,
+    swapchain_image_index: usize,
+    scissor: &ash::vk::Rect2D,
+) {
+
+    if vertex_count == 0 {
+        return
+    }
+
+    overlay_drawing.pipeline.record_bind(device, command_buffer, &[*texture_table_descriptor_set], overlay_drawing.vertex_buffer.handle());
+
+    let offset = (swapchain_image_index * MAX_OVERLAY_VERTICES) as u32;
+
+    batches.iter()
+        .filter(|batch| batch.first_vertex < vertex_count)
+        .for_each(|batch| {
+
+            let push_constants = OverlayPushConstants {
+                screen_size: [scissor.extent.width as f32, scissor.extent.height as f32],
+                texture: batch.texture,
+                srgb_output: srgb_output as u32,
+            };
+
+            let count = batch.vertex_count.min(vertex_count - batch.first_vertex);
+
+            overlay_drawing.pipeline.record_draw(device, command_buffer, push_constants.as_bytes(), offset + batch.first_vertex, count);
+        });
+}
+
+/// Records the dispatches of the culling shader, which writes the instances of every geometry
+/// inside the camera's frustum into its visible instances buffer and updates the indirect draw
+/// command accordingly.
+///
+/// The shadow passes still draw all instances, as casters outside of the camera's frustum may
+/// throw shadows into it.
+fn record_culling_commands(
+    device: &Device,
+    command_buffer: &ash::vk::CommandBuffer,
+    descriptor_set: &ash::vk::DescriptorSet,
+    culling_pipeline: &ComputePipeline,
+    geometries: &[Geometry],
+) {
+
+    let instance_count_offset = offset_of!(::ash::vk::DrawIndexedIndirectCommand, instance_count) as u64;
+    let instance_count_size = std::mem::size_of::<u32>() as u64;
+
+    geometries.iter().filter_map(gpu_culling).for_each(|indirect_draw| {
+        unsafe {
+            device.handle().cmd_fill_buffer(*command_buffer, *indirect_draw.draw_command_buffer.handle(), instance_count_offset, instance_count_size, 0);
+            device.handle().cmd_fill_buffer(*command_buffer, *indirect_draw.draw_count_buffer.handle(), 0, instance_count_size, 0);
+        }
+    });
+
+    let reset_barriers = [
+        ::ash::vk::MemoryBarrier::builder()
+            .src_access_mask(::ash::vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(::ash::vk::AccessFlags::SHADER_READ | ::ash::vk::AccessFlags::SHADER_WRITE)
+            .build()
+    ];
+
+    unsafe {
+        device.handle().cmd_pipeline_barrier(
+            *command_buffer,
+            ::ash::vk::PipelineStageFlags::TRANSFER,
+            ::ash::vk::PipelineStageFlags::COMPUTE_SHADER,
+            ::ash::vk::DependencyFlags::empty(),
+            &reset_barriers,
+            &[],
+            &[],
+        );
+    }
+
+    geometries.iter().for_each(|geometry| {
+
+        let indirect_draw = match gpu_culling(geometry) {
+            Some(indirect_draw) => indirect_draw,
+            None => return,
+        };
+
+        let instance_count = geometry.instances_buffer.capacity() as u32;
+        let push_constants = CullingPushConstants {
+            bounding_sphere: geometry.bounding_sphere,
+            instance_count,
+        };
+        let descriptor_sets = [
+            *descriptor_set,
+            indirect_draw.descriptor_set,
+        ];
+
+        culling_pipeline.record_dispatch(
+            device,
+            command_buffer,
+            &descriptor_sets,
+            push_constants.as_bytes(),
+            [workgroup_count(instance_count, CULLING_WORKGROUP_SIZE), 1, 1],
+        );
+    });
+
+    let culling_barriers = [
+        ::ash::vk::MemoryBarrier::builder()
+            .src_access_mask(::ash::vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(::ash::vk::AccessFlags::INDIRECT_COMMAND_READ | ::ash::vk::AccessFlags::VERTEX_ATTRIBUTE_READ)
+            .build()
+    ];
+
+    unsafe {
+        device.handle().cmd_pipeline_barrier(
+            *command_buffer,
+            ::ash::vk::PipelineStageFlags::COMPUTE_SHADER,
+            ::ash::vk::PipelineStageFlags::DRAW_INDIRECT | ::ash::vk::PipelineStageFlags::VERTEX_INPUT,
+            ::ash::vk::DependencyFlags::empty(),
+            &culling_barriers,
+            &[],
+            &[],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::engine::{bounding_sphere, cull_instances, CullingPushConstants, InstanceData, is_culled_on_gpu, UniformBufferObject};
+    use crate::graphics::{BlendMode, BoundingBox, Camera, LodBatch, LodSelection, Projection};
+
+    #[test]
+    fn test_uniform_buffer_object_has_std140_layout() {
+        assert_that!(offset_of!(UniformBufferObject, view), is(equal_to(0)));
+        assert_that!(offset_of!(UniformBufferObject, projection), is(equal_to(64)));
+        assert_that!(offset_of!(UniformBufferObject, view_projection), is(equal_to(128)));
+        assert_that!(offset_of!(UniformBufferObject, inverse_view_projection), is(equal_to(192)));
+        assert_that!(offset_of!(UniformBufferObject, camera_position), is(equal_to(256)));
+        assert_that!(offset_of!(UniformBufferObject, viewport), is(equal_to(272)));
+        assert_that!(offset_of!(UniformBufferObject, time), is(equal_to(288)));
+        assert_that!(offset_of!(UniformBufferObject, delta_time), is(equal_to(292)));
+        assert_that!(offset_of!(UniformBufferObject, frame), is(equal_to(296)));
+        assert_that!(std::mem::size_of::<UniformBufferObject>() % 256, is(equal_to(0)));
+    }
+
+    #[test]
+    fn test_culling_push_constants_layout() {
+        assert_that!(offset_of!(CullingPushConstants, bounding_sphere), is(equal_to(0)));
+        assert_that!(offset_of!(CullingPushConstants, instance_count), is(equal_to(16)));
+        assert_that!(std::mem::size_of::<CullingPushConstants>(), is(equal_to(20)));
+    }
+
+    #[test]
+    fn test_bounding_sphere_encloses_all_vertices() {
+
+        let sphere = bounding_sphere(&[
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(3.0, 2.0, 0.0),
+            Vector3::new(2.0, 1.0, 2.0),
+        ]);
+
+        assert_that!(&sphere[0..3], is(equal_to(&[2.0f32, 1.0, 1.0][..])));
+        assert_that!(sphere[3], is(equal_to(3.0f32.sqrt())));
+        assert_that!(bounding_sphere(&[]), is(equal_to([0.0f32; 4])));
+    }
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(Projection::PerspectiveProjection {
+            fovy: std::f32::consts::FRAC_PI_2,
+            aspect: 1.0,
+            near: 0.1,
+            far: 100.0,
+        });
+        camera.view_direction(&Vector3::zeros(), &Vector3::z(), &Vector3::new(0.0, -1.0, 0.0));
+        camera
+    }
+
+    fn instances(depths: &[(u32, f32)]) -> Vec<InstanceData> {
+        depths.iter().map(|(id, z)| InstanceData {
+            id: *id,
+            transformation: Matrix4::<f32>::identity()
+                .append_translation(&Vector3::new(0.0, 0.0, *z))
+                .transpose()
+                .as_slice()
+                .try_into()
+                .unwrap(),
+        }).collect()
+    }
+
+    fn ids(instances: &[InstanceData]) -> Vec<u32> {
+        instances.iter().map(|instance| instance.id).collect()
+    }
+
+    #[test]
+    fn test_blended_geometries_are_sorted_on_the_cpu_with_a_culling_shader() {
+
+        assert_that!(is_culled_on_gpu(BlendMode::Opaque, 0), is(true));
+        assert_that!(is_culled_on_gpu(BlendMode::Mask { cutoff: 0.5 }, 0), is(true));
+        assert_that!(is_culled_on_gpu(BlendMode::Opaque, 1), is(false));
+        assert_that!(is_culled_on_gpu(BlendMode::Blend, 0), is(false));
+        assert_that!(is_culled_on_gpu(BlendMode::Additive, 0), is(false));
+
+        let mut instances = instances(&[(1, 5.0), (2, -10.0), (3, 20.0), (4, 10.0)]);
+        let bounds = BoundingBox { min: Vector3::repeat(-1.0), max: Vector3::repeat(1.0) };
+
+        let (visible, batches) = cull_instances(&mut instances, Some(&bounds), &[0.0, 0.0, 0.0, 1.0], BlendMode::Blend, &[], &LodSelection::default(), &camera());
+
+        assert_that!(visible, is(equal_to(3)));
+        assert_that!(ids(&instances), is(equal_to(vec![3, 4, 1, 2])));
+        assert_that!(batches.len(), is(equal_to(1)));
+    }
+
+    #[test]
+    fn test_blended_instances_at_different_levels_of_detail_stay_sorted() {
+
+        let mut instances = instances(&[(1, 5.0), (2, 30.0), (3, 20.0), (4, 1.0), (5, 50.0)]);
+
+        let (visible, batches) = cull_instances(&mut instances, None, &[0.0, 0.0, 0.0, 1.0], BlendMode::Blend, &[10.0, 40.0], &LodSelection::default(), &camera());
+
+        assert_that!(visible, is(equal_to(5)));
+        assert_that!(ids(&instances), is(equal_to(vec![5, 2, 3, 1, 4])));
+        assert_that!(batches, is(equal_to(vec![
+            LodBatch { level: 2, first_instance: 0, instance_count: 1, dither: 0.0 },
+            LodBatch { level: 1, first_instance: 1, instance_count: 2, dither: 0.0 },
+            LodBatch { level: 0, first_instance: 3, instance_count: 2, dither: 0.0 },
+        ])));
+    }
+}
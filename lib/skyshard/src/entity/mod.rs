@@ -1,16 +1,34 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::graphics::Geometry;
+use crate::graphics::{EnvironmentLighting, Geometry, Light, PostProcessingSettings, ShadowSettings, Skybox};
+use crate::particles::ParticleEmitter;
 
 pub struct World {
     pub geometries: Vec<Geometry>,
+    pub lights: Vec<Light>,
+    pub ambient_light: [f32; 3],
+    pub shadows: ShadowSettings,
+    pub post_processing: PostProcessingSettings,
+    /// Drawn behind all geometries, the clear color remains visible without.
+    pub skybox: Option<Skybox>,
+    /// The image based lighting added to the ambient light, e.g. of the skybox's environment.
+    pub environment_lighting: Option<EnvironmentLighting>,
+    /// Drawn after the transparent geometries, the emitters are advanced by the application.
+    pub particles: Vec<ParticleEmitter>,
 }
 
 impl World {
 
     pub fn new() -> Self {
         World {
-            geometries: Vec::new()
+            geometries: Vec::new(),
+            lights: Vec::new(),
+            ambient_light: [0.03, 0.03, 0.03],
+            shadows: ShadowSettings::default(),
+            post_processing: PostProcessingSettings::default(),
+            skybox: None,
+            environment_lighting: None,
+            particles: Vec::new(),
         }
     }
 }
@@ -1,7 +1,12 @@
 use nalgebra::{Matrix4, Vector3};
 
+use crate::animation::Transform;
+use crate::graphics::Frustum;
+
 pub struct Camera {
     projection: Matrix4<f32>,
+    near: f32,
+    far: f32,
     view: Matrix4<f32>,
     translation: Vector3<f32>,
     rotation: Vector3<f32>,
@@ -15,8 +20,15 @@ impl Camera {
 
     pub fn new(projection: Projection) -> Self {
 
+        let (near, far) = match projection {
+            Projection::OrthographicProjection { near, far, .. } => (near, far),
+            Projection::PerspectiveProjection { near, far, .. } => (near, far),
+        };
+
         Self {
             projection: projection.matrix(),
+            near,
+            far,
             view: Matrix4::<f32>::identity(),
             translation: Vector3::new(0.0, 0.0, -5.0),
             rotation: Vector3::new(0.0, 0.0, 0.0),
@@ -32,20 +44,7 @@ impl Camera {
         let u: Vector3<f32> = w.cross(&up).normalize();
         let v: Vector3<f32> = w.cross(&u);
 
-        self.view[(0, 0)] = u.x;
-        self.view[(1, 0)] = u.y;
-        self.view[(2, 0)] = u.z;
-        self.view[(0, 1)] = v.x;
-        self.view[(1, 1)] = v.y;
-        self.view[(2, 1)] = v.z;
-        self.view[(0, 2)] = w.x;
-        self.view[(1, 2)] = w.y;
-        self.view[(2, 2)] = w.z;
-        self.view[(3, 0)] = -u.dot(&translation);
-        self.view[(3, 1)] = -v.dot(&translation);
-        self.view[(3, 2)] = -w.dot(&translation);
-
-        self.update();
+        self.view_basis(translation, &u, &v, &w);
     }
 
     pub fn view_target(&mut self, translation: &Vector3<f32>, target: &Vector3<f32>, up: &Vector3<f32>) {
@@ -65,6 +64,22 @@ impl Camera {
         let v: Vector3<f32> = Vector3::<f32>::new(c3 * s1 * s2 - c1 * s3, c2 * c3, c1 * c3 * s2 + s1 * s3);
         let w: Vector3<f32> = Vector3::<f32>::new(c2 * s1, -s2, c1 * c2);
 
+        self.view_basis(translation, &u, &v, &w);
+    }
+
+    /// Places the camera by a transform, e.g. sampled from an animation clip. At the identity
+    /// rotation the camera looks along +z with +x to the right and +y down.
+    ///
+    /// The scale of the transform is ignored.
+    pub fn view_transform(&mut self, transform: &Transform) {
+        let u: Vector3<f32> = transform.rotation * Vector3::x();
+        let v: Vector3<f32> = transform.rotation * Vector3::y();
+        let w: Vector3<f32> = transform.rotation * Vector3::z();
+
+        self.view_basis(&transform.translation, &u, &v, &w);
+    }
+
+    fn view_basis(&mut self, translation: &Vector3<f32>, u: &Vector3<f32>, v: &Vector3<f32>, w: &Vector3<f32>) {
         self.view[(0, 0)] = u.x;
         self.view[(1, 0)] = u.y;
         self.view[(2, 0)] = u.z;
@@ -96,6 +111,36 @@ impl Camera {
     pub fn as_matrix(&self) -> &Matrix4<f32> {
         &self.matrix
     }
+
+    pub fn view(&self) -> &Matrix4<f32> {
+        &self.view
+    }
+
+    pub fn projection(&self) -> &Matrix4<f32> {
+        &self.projection
+    }
+
+    /// Returns the distance of the near plane.
+    pub fn near(&self) -> f32 {
+        self.near
+    }
+
+    /// Returns the distance of the far plane.
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+
+    /// Returns the planes of the volume visible to the camera in world space.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::new(&self.matrix)
+    }
+
+    /// Returns the camera's position in world space derived from the view matrix.
+    pub fn position(&self) -> Vector3<f32> {
+        let inverse = self.view.try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        Vector3::new(inverse[(3, 0)], inverse[(3, 1)], inverse[(3, 2)])
+    }
 }
 
 
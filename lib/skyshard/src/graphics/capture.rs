@@ -0,0 +1,210 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::JoinHandle;
+
+use log::error;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CaptureError {
+
+    #[error("The swapchain's images can not be copied from!")]
+    NotCapturable,
+
+    #[error("The swapchain format '{format}' can not be captured!")]
+    UnsupportedFormat { format: String },
+
+    #[error("Failed to create '{path}'!")]
+    CreateFileError { path: String },
+
+    #[error("Failed to encode png: {0}")]
+    EncodingError(#[from] png::EncodingError),
+}
+
+/// The order of the channels of the pixels read back from an image with 8 bits per channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ChannelOrder {
+    Rgba,
+    Bgra,
+}
+
+/// A rendered frame read back from the GPU, as RGBA with 8 bits per channel and the rows from top
+/// to bottom.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    srgb: bool,
+}
+
+impl CapturedFrame {
+
+    /// Converts the pixels as read back from an image into RGBA. The alpha channel is made opaque,
+    /// as presented images are not blended with anything.
+    ///
+    /// `srgb` tells whether the image's format encoded the colors to sRGB.
+    pub(crate) fn from_raw(width: u32, height: u32, raw: &[u8], order: ChannelOrder, srgb: bool) -> CapturedFrame {
+
+        let pixels = raw[..(width * height * 4) as usize].chunks_exact(4).flat_map(|pixel| match order {
+            ChannelOrder::Rgba => [pixel[0], pixel[1], pixel[2], u8::MAX],
+            ChannelOrder::Bgra => [pixel[2], pixel[1], pixel[0], u8::MAX],
+        }).collect();
+
+        CapturedFrame { width, height, pixels, srgb }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * self.width + x) * 4) as usize;
+        self.pixels[offset..offset + 4].try_into().unwrap()
+    }
+
+    /// Encodes the frame as png. Frames of sRGB swapchains are tagged as sRGB, others are written
+    /// as they are, assuming that the post-processing applied the gamma.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), CaptureError> {
+
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        if self.srgb {
+            encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CaptureError> {
+
+        let file = File::create(path.as_ref()).map_err(|_| CaptureError::CreateFileError {
+            path: path.as_ref().display().to_string()
+        })?;
+
+        self.write_png(BufWriter::new(file))
+    }
+}
+
+/// The number of frames waiting to be written, before [FrameSequence::push] blocks.
+const FRAME_SEQUENCE_QUEUE_LENGTH: usize = 4;
+
+/// Writes the frames of an image sequence into a directory, numbered `frame-00000.png`,
+/// `frame-00001.png` and so on. The frames are encoded on a thread of their own, so that the
+/// rendering is not held up by it.
+pub(crate) struct FrameSequence {
+    sender: SyncSender<CapturedFrame>,
+    writer: JoinHandle<usize>,
+}
+
+impl FrameSequence {
+
+    /// Creates the directory, if it does not exist yet.
+    pub fn new(directory: &Path) -> Result<FrameSequence, CaptureError> {
+
+        std::fs::create_dir_all(directory).map_err(|_| CaptureError::CreateFileError {
+            path: directory.display().to_string()
+        })?;
+
+        let directory = directory.to_path_buf();
+        let (sender, receiver) = sync_channel::<CapturedFrame>(FRAME_SEQUENCE_QUEUE_LENGTH);
+
+        // stops on the first failure, which fails the following pushes
+        let writer = std::thread::spawn(move || {
+            let mut frames = 0;
+            for frame in receiver {
+                if let Err(cause) = frame.save(directory.join(format!("frame-{:05}.png", frames))) {
+                    error!("Failed to record frame {} of the image sequence: {}", frames, cause);
+                    break
+                }
+                frames += 1;
+            }
+            frames
+        });
+
+        Ok(FrameSequence {
+            sender,
+            writer,
+        })
+    }
+
+    /// Queues the frame to be written, blocking while the writer is behind by
+    /// [FRAME_SEQUENCE_QUEUE_LENGTH] frames. Returns false if the writer has stopped on a failure.
+    pub fn push(&self, frame: CapturedFrame) -> bool {
+        self.sender.send(frame).is_ok()
+    }
+
+    /// Waits for the queued frames to be written and returns the number of frames written.
+    pub fn finish(self) -> usize {
+        drop(self.sender);
+        self.writer.join().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::capture::{CapturedFrame, ChannelOrder, FrameSequence};
+
+    #[test]
+    fn test_bgra_is_converted_to_opaque_rgba() {
+
+        let raw = [10, 20, 30, 0, 40, 50, 60, 128];
+
+        let frame = CapturedFrame::from_raw(2, 1, &raw, ChannelOrder::Bgra, false);
+
+        assert_that!(frame.pixel(0, 0), is(equal_to([30, 20, 10, 255])));
+        assert_that!(frame.pixel(1, 0), is(equal_to([60, 50, 40, 255])));
+    }
+
+    #[test]
+    fn test_write_png_roundtrip() {
+
+        let raw = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+        let frame = CapturedFrame::from_raw(2, 2, &raw, ChannelOrder::Rgba, true);
+
+        let mut encoded = Vec::new();
+        frame.write_png(&mut encoded).unwrap();
+
+        let mut reader = png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+
+        assert_that!((info.width, info.height), is(equal_to((2, 2))));
+        assert_that!(reader.info().srgb.is_some(), is(true));
+        assert_that!(&decoded[..info.buffer_size()], is(equal_to(&raw[..])));
+    }
+
+    #[test]
+    fn test_frame_sequence_writes_numbered_frames() {
+
+        let directory = std::env::temp_dir().join(format!("skyshard-frame-sequence-{}", std::process::id()));
+        let frame = CapturedFrame::from_raw(1, 1, &[1, 2, 3, 4], ChannelOrder::Rgba, false);
+
+        let sequence = FrameSequence::new(&directory).unwrap();
+        assert_that!(sequence.push(frame.clone()), is(true));
+        assert_that!(sequence.push(frame), is(true));
+
+        assert_that!(sequence.finish(), is(equal_to(2)));
+        assert_that!(directory.join("frame-00001.png").is_file(), is(true));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
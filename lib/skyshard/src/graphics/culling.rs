@@ -0,0 +1,219 @@
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+use crate::engine::{InstanceData, Vertex};
+
+/// An axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl BoundingBox {
+
+    /// Returns the smallest box enclosing all points, or an empty box at the origin if there are
+    /// no points.
+    pub fn from_points<'a, I>(points: I) -> BoundingBox
+    where I: IntoIterator<Item = &'a Vector3<f32>> {
+
+        let mut points = points.into_iter().peekable();
+
+        if points.peek().is_none() {
+            return BoundingBox { min: Vector3::zeros(), max: Vector3::zeros() };
+        }
+
+        points.fold(BoundingBox {
+            min: Vector3::repeat(f32::MAX),
+            max: Vector3::repeat(f32::MIN),
+        }, |bounds, point| BoundingBox {
+            min: bounds.min.inf(point),
+            max: bounds.max.sup(point),
+        })
+    }
+
+    pub fn from_vertices(vertices: &[Vertex]) -> BoundingBox {
+        let positions = vertices.iter()
+            .map(|vertex| Vector3::from(vertex.position))
+            .collect::<Vec<_>>();
+        Self::from_points(&positions)
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        [
+            Vector3::new(self.min.x, self.min.y, self.min.z),
+            Vector3::new(self.max.x, self.min.y, self.min.z),
+            Vector3::new(self.min.x, self.max.y, self.min.z),
+            Vector3::new(self.max.x, self.max.y, self.min.z),
+            Vector3::new(self.min.x, self.min.y, self.max.z),
+            Vector3::new(self.max.x, self.min.y, self.max.z),
+            Vector3::new(self.min.x, self.max.y, self.max.z),
+            Vector3::new(self.max.x, self.max.y, self.max.z),
+        ]
+    }
+
+    /// Returns the box enclosing this box transformed by a matrix applied to row vectors
+    /// (`vec4(p, 1) * transformation`), like the instance transformations.
+    pub fn transform(&self, transformation: &Matrix4<f32>) -> BoundingBox {
+        let corners = self.corners().map(|corner| {
+            (corner.push(1.0).transpose() * transformation).transpose().xyz()
+        });
+        Self::from_points(&corners)
+    }
+}
+
+/// The six planes bounding the volume visible to a camera, pointing inwards.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+
+    /// Extracts the planes of a view projection matrix applied to row vectors, which maps the
+    /// visible volume to `-w <= x, y <= w` and `0 <= z <= w`.
+    pub fn new(view_projection: &Matrix4<f32>) -> Frustum {
+
+        let column = |index: usize| -> Vector4<f32> { view_projection.column(index).into_owned() };
+
+        let planes = [
+            column(3) + column(0),
+            column(3) - column(0),
+            column(3) + column(1),
+            column(3) - column(1),
+            column(2),
+            column(3) - column(2),
+        ].map(|plane| {
+            let length = plane.xyz().norm();
+            if length > 0.0 { plane / length } else { plane }
+        });
+
+        Frustum { planes }
+    }
+
+    pub fn intersects_sphere(&self, center: &Vector3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.xyz().dot(center) + plane.w >= -radius)
+    }
+
+    pub fn intersects_box(&self, bounds: &BoundingBox) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane's normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { bounds.max.x } else { bounds.min.x },
+                if plane.y >= 0.0 { bounds.max.y } else { bounds.min.y },
+                if plane.z >= 0.0 { bounds.max.z } else { bounds.min.z },
+            );
+            plane.xyz().dot(&corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// Reorders the instances so that the ones intersecting the frustum come first and returns the
+/// number of those. The relative order of the visible instances is preserved.
+pub(crate) fn partition_visible_instances(instances: &mut Vec<InstanceData>, bounds: &BoundingBox, frustum: &Frustum) -> usize {
+
+    let (visible, culled): (Vec<InstanceData>, Vec<InstanceData>) = instances.drain(..).partition(|instance| {
+        let transformation = Matrix4::from_column_slice(&instance.transformation);
+        frustum.intersects_box(&bounds.transform(&transformation))
+    });
+
+    let count = visible.len();
+
+    instances.extend(visible);
+    instances.extend(culled);
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::engine::InstanceData;
+    use crate::graphics::{BoundingBox, Camera, Projection};
+    use crate::graphics::culling::partition_visible_instances;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(Projection::PerspectiveProjection {
+            fovy: std::f32::consts::FRAC_PI_2,
+            aspect: 1.0,
+            near: 0.1,
+            far: 100.0,
+        });
+        camera.view_direction(&Vector3::zeros(), &Vector3::z(), &Vector3::new(0.0, -1.0, 0.0));
+        camera
+    }
+
+    fn instance(id: u32, translation: Vector3<f32>) -> InstanceData {
+        InstanceData {
+            id,
+            transformation: Matrix4::<f32>::identity()
+                .append_translation(&translation)
+                .transpose()
+                .as_slice()
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_frustum_intersects_sphere() {
+
+        let frustum = camera().frustum();
+
+        assert_that!(frustum.intersects_sphere(&Vector3::new(0.0, 0.0, 10.0), 1.0), is(true));
+        assert_that!(frustum.intersects_sphere(&Vector3::new(0.0, 0.0, -10.0), 1.0), is(false));
+        assert_that!(frustum.intersects_sphere(&Vector3::new(0.0, 0.0, 200.0), 1.0), is(false));
+        assert_that!(frustum.intersects_sphere(&Vector3::new(30.0, 0.0, 10.0), 1.0), is(false));
+        assert_that!(frustum.intersects_sphere(&Vector3::new(10.5, 0.0, 10.0), 1.0), is(true));
+    }
+
+    #[test]
+    fn test_frustum_intersects_box() {
+
+        let frustum = camera().frustum();
+
+        let visible = BoundingBox { min: Vector3::new(-1.0, -1.0, 5.0), max: Vector3::new(1.0, 1.0, 6.0) };
+        let straddling = BoundingBox { min: Vector3::new(-1.0, -1.0, -5.0), max: Vector3::new(1.0, 1.0, 5.0) };
+        let behind = BoundingBox { min: Vector3::new(-1.0, -1.0, -6.0), max: Vector3::new(1.0, 1.0, -5.0) };
+
+        assert_that!(frustum.intersects_box(&visible), is(true));
+        assert_that!(frustum.intersects_box(&straddling), is(true));
+        assert_that!(frustum.intersects_box(&behind), is(false));
+    }
+
+    #[test]
+    fn test_bounding_box_transform() {
+
+        let bounds = BoundingBox { min: Vector3::new(-1.0, -1.0, -1.0), max: Vector3::new(1.0, 1.0, 1.0) };
+        let transformation = Matrix4::<f32>::identity()
+            .append_translation(&Vector3::new(5.0, 0.0, 0.0))
+            .transpose();
+
+        let transformed = bounds.transform(&transformation);
+
+        assert_that!(transformed.min, is(equal_to(Vector3::new(4.0, -1.0, -1.0))));
+        assert_that!(transformed.max, is(equal_to(Vector3::new(6.0, 1.0, 1.0))));
+    }
+
+    #[test]
+    fn test_partition_visible_instances() {
+
+        let bounds = BoundingBox { min: Vector3::new(-1.0, -1.0, -1.0), max: Vector3::new(1.0, 1.0, 1.0) };
+        let mut instances = vec![
+            instance(1, Vector3::new(0.0, 0.0, -10.0)),
+            instance(2, Vector3::new(0.0, 0.0, 10.0)),
+            instance(3, Vector3::new(0.0, 0.0, -20.0)),
+            instance(4, Vector3::new(2.0, 0.0, 20.0)),
+        ];
+
+        let visible = partition_visible_instances(&mut instances, &bounds, &camera().frustum());
+
+        assert_that!(visible, is(equal_to(2)));
+        assert_that!(instances.iter().map(|instance| instance.id).collect::<Vec<_>>(), is(equal_to(vec![2, 4, 1, 3])));
+    }
+}
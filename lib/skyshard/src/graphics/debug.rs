@@ -0,0 +1,252 @@
+use std::f32::consts::PI;
+
+use nalgebra::{Matrix4, Vector3};
+
+use crate::graphics::{BoundingBox, HasVertexLayout, VertexAttribute, VertexFormat, VertexLayout, VertexSemantic};
+
+/// The maximum number of debug vertices, i.e. twice the number of lines, drawn per frame.
+/// Additional lines are ignored, the depth-tested lines take precedence over the overlay.
+pub const MAX_DEBUG_VERTICES: usize = 65536;
+
+/// The number of segments of the circles making up spheres.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// A corner of a debug line, read by the debug vertex shader from binding 0.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl HasVertexLayout for DebugVertex {
+    fn vertex_layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<DebugVertex>() as u32, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: offset_of!(DebugVertex, position) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Color, format: VertexFormat::Float4, offset: offset_of!(DebugVertex, color) as u32 },
+        ])
+    }
+}
+
+/// Lines in world space drawn on top of the next frame, e.g. to visualize bounds, cameras or
+/// picking rays. The lines are collected anew every frame, the engine clears them once they have
+/// been rendered. Colors are linear RGBA, blended by alpha.
+#[derive(Debug, Clone)]
+pub struct DebugLines {
+    depth_test: bool,
+    vertices: Vec<DebugVertex>,
+    overlay_vertices: Vec<DebugVertex>,
+}
+
+impl Default for DebugLines {
+    fn default() -> Self {
+        DebugLines {
+            depth_test: true,
+            vertices: Vec::new(),
+            overlay_vertices: Vec::new(),
+        }
+    }
+}
+
+impl DebugLines {
+
+    /// Sets whether the lines added afterwards are hidden behind geometries. Lines without depth
+    /// test are drawn over everything. Lines are depth-tested by default.
+    pub fn depth_test(&mut self, depth_test: bool) -> &mut DebugLines {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn line(&mut self, from: &Vector3<f32>, to: &Vector3<f32>, color: [f32; 4]) -> &mut DebugLines {
+        let vertices = match self.depth_test {
+            true => &mut self.vertices,
+            false => &mut self.overlay_vertices,
+        };
+        vertices.push(DebugVertex { position: (*from).into(), color });
+        vertices.push(DebugVertex { position: (*to).into(), color });
+        self
+    }
+
+    /// Draws the twelve edges of the box.
+    pub fn aabb(&mut self, bounds: &BoundingBox, color: [f32; 4]) -> &mut DebugLines {
+        self.box_edges(&bounds.corners(), color)
+    }
+
+    /// Draws a circle around each axis through the center.
+    pub fn sphere(&mut self, center: &Vector3<f32>, radius: f32, color: [f32; 4]) -> &mut DebugLines {
+        self.circle(center, &(Vector3::x() * radius), &(Vector3::y() * radius), color)
+            .circle(center, &(Vector3::y() * radius), &(Vector3::z() * radius), color)
+            .circle(center, &(Vector3::z() * radius), &(Vector3::x() * radius), color)
+    }
+
+    /// Draws the edges of the volume visible through a view projection matrix applied to row
+    /// vectors, like [Camera::as_matrix](crate::graphics::Camera::as_matrix). Nothing is drawn if
+    /// the matrix is not invertible.
+    pub fn frustum(&mut self, view_projection: &Matrix4<f32>, color: [f32; 4]) -> &mut DebugLines {
+
+        let inverse = match view_projection.try_inverse() {
+            Some(inverse) => inverse,
+            None => return self,
+        };
+
+        // ordered like the corners of a bounding box, with the near plane at z = 0
+        let corners = BoundingBox { min: Vector3::new(-1.0, -1.0, 0.0), max: Vector3::new(1.0, 1.0, 1.0) }
+            .corners()
+            .map(|corner| {
+                let position = corner.push(1.0).transpose() * inverse;
+                position.fixed_columns::<3>(0).transpose() / position[3]
+            });
+
+        self.box_edges(&corners, color)
+    }
+
+    /// Draws the x (red), y (green) and z (blue) axes of a transformation applied to row vectors,
+    /// like the transformation of an instance.
+    pub fn axes(&mut self, transformation: &Matrix4<f32>, size: f32) -> &mut DebugLines {
+
+        let transform = |point: Vector3<f32>| (point.push(1.0).transpose() * transformation).transpose().xyz();
+        let origin = transform(Vector3::zeros());
+
+        self.line(&origin, &transform(Vector3::x() * size), [1.0, 0.0, 0.0, 1.0])
+            .line(&origin, &transform(Vector3::y() * size), [0.0, 1.0, 0.0, 1.0])
+            .line(&origin, &transform(Vector3::z() * size), [0.0, 0.0, 1.0, 1.0])
+    }
+
+    /// Draws a square grid of `size` in the xz-plane around the center, divided into `divisions`
+    /// cells along each side.
+    pub fn grid(&mut self, center: &Vector3<f32>, size: f32, divisions: u32, color: [f32; 4]) -> &mut DebugLines {
+
+        let divisions = divisions.max(1);
+        let half = size / 2.0;
+
+        (0..=divisions).for_each(|index| {
+            let offset = index as f32 * size / divisions as f32 - half;
+            self.line(&(center + Vector3::new(offset, 0.0, -half)), &(center + Vector3::new(offset, 0.0, half)), color);
+            self.line(&(center + Vector3::new(-half, 0.0, offset)), &(center + Vector3::new(half, 0.0, offset)), color);
+        });
+
+        self
+    }
+
+    /// Draws a cross of `size` along the axes through the position.
+    pub fn marker(&mut self, position: &Vector3<f32>, size: f32, color: [f32; 4]) -> &mut DebugLines {
+
+        let half = size / 2.0;
+
+        [Vector3::x(), Vector3::y(), Vector3::z()].iter().for_each(|axis| {
+            self.line(&(position - axis * half), &(position + axis * half), color);
+        });
+
+        self
+    }
+
+    /// Removes all lines, the depth test setting is kept.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.overlay_vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty() && self.overlay_vertices.is_empty()
+    }
+
+    /// Returns the vertices of the depth-tested lines, two per line.
+    pub fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    /// Returns the vertices of the lines drawn over everything, two per line.
+    pub fn overlay_vertices(&self) -> &[DebugVertex] {
+        &self.overlay_vertices
+    }
+
+    /// Connects eight corners ordered like [BoundingBox::corners].
+    fn box_edges(&mut self, corners: &[Vector3<f32>; 8], color: [f32; 4]) -> &mut DebugLines {
+
+        const EDGES: [(usize, usize); 12] = [
+            (0, 1), (2, 3), (4, 5), (6, 7),
+            (0, 2), (1, 3), (4, 6), (5, 7),
+            (0, 4), (1, 5), (2, 6), (3, 7),
+        ];
+
+        EDGES.iter().for_each(|(from, to)| {
+            self.line(&corners[*from], &corners[*to], color);
+        });
+
+        self
+    }
+
+    /// Draws a circle spanned by two perpendicular radius vectors.
+    fn circle(&mut self, center: &Vector3<f32>, u: &Vector3<f32>, v: &Vector3<f32>, color: [f32; 4]) -> &mut DebugLines {
+
+        let point = |index: usize| {
+            let angle = 2.0 * PI * index as f32 / CIRCLE_SEGMENTS as f32;
+            center + u * angle.cos() + v * angle.sin()
+        };
+
+        (0..CIRCLE_SEGMENTS).for_each(|index| {
+            self.line(&point(index), &point(index + 1), color);
+        });
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::graphics::debug::{CIRCLE_SEGMENTS, DebugLines};
+    use crate::graphics::BoundingBox;
+
+    const WHITE: [f32; 4] = [1.0; 4];
+
+    #[test]
+    fn test_lines_are_split_by_depth_test() {
+
+        let mut lines = DebugLines::default();
+
+        lines.line(&Vector3::zeros(), &Vector3::x(), WHITE)
+            .depth_test(false)
+            .marker(&Vector3::zeros(), 1.0, WHITE)
+            .sphere(&Vector3::zeros(), 1.0, WHITE);
+
+        assert_that!(lines.vertices().len(), is(equal_to(2)));
+        assert_that!(lines.vertices()[1].position, is(equal_to([1.0, 0.0, 0.0])));
+        assert_that!(lines.overlay_vertices().len(), is(equal_to(2 * (3 + 3 * CIRCLE_SEGMENTS))));
+
+        lines.clear();
+
+        assert_that!(lines.is_empty(), is(true));
+    }
+
+    #[test]
+    fn test_aabb_and_grid_vertex_counts() {
+
+        let mut lines = DebugLines::default();
+
+        lines.aabb(&BoundingBox { min: Vector3::zeros(), max: Vector3::repeat(1.0) }, WHITE);
+        assert_that!(lines.vertices().len(), is(equal_to(24)));
+
+        lines.clear();
+        lines.grid(&Vector3::zeros(), 10.0, 4, WHITE);
+        assert_that!(lines.vertices().len(), is(equal_to(2 * 2 * 5)));
+        assert_that!(lines.vertices()[0].position, is(equal_to([-5.0, 0.0, -5.0])));
+    }
+
+    #[test]
+    fn test_frustum_of_an_orthographic_projection_is_its_box() {
+
+        // maps x, y from [-2, 2] to [-1, 1] and z from [0, 4] to [0, 1]
+        let view_projection = Matrix4::new_nonuniform_scaling(&Vector3::new(0.5, 0.5, 0.25));
+
+        let mut frustum = DebugLines::default();
+        frustum.frustum(&view_projection, WHITE);
+
+        let mut expected = DebugLines::default();
+        expected.aabb(&BoundingBox { min: Vector3::new(-2.0, -2.0, 0.0), max: Vector3::new(2.0, 2.0, 4.0) }, WHITE);
+
+        assert_that!(frustum.vertices(), is(equal_to(expected.vertices())));
+    }
+}
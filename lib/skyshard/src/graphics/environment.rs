@@ -0,0 +1,446 @@
+use std::f32::consts::PI;
+use std::io::BufRead;
+
+use nalgebra::Vector3;
+use thiserror::Error;
+
+use crate::graphics::vulkan::fullscreen::FullscreenPipeline;
+use crate::graphics::vulkan::resources::Image;
+
+#[derive(Error, Debug)]
+pub enum EnvironmentError {
+
+    #[error("Failed to read the image: {0}")]
+    ReadError(#[from] std::io::Error),
+
+    #[error("The image is not a Radiance HDR image!")]
+    InvalidHeader,
+
+    #[error("The image's resolution '{resolution}' is not supported, only '-Y <height> +X <width>'!")]
+    UnsupportedResolution { resolution: String },
+
+    #[error("The image's pixel data is corrupt!")]
+    CorruptPixelData,
+
+    #[error("The faces of a cube map have to be squares of the same size!")]
+    InvalidFaces,
+}
+
+/// A high dynamic range image with linear RGBA colors, the rows from top to bottom.
+#[derive(Debug, Clone)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+impl HdrImage {
+
+    /// Reads an image in Radiance's RGBE format (`.hdr`), either flat or run-length encoded.
+    pub fn read_radiance<R: BufRead>(mut reader: R) -> Result<HdrImage, EnvironmentError> {
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        if !line.starts_with("#?") {
+            return Err(EnvironmentError::InvalidHeader)
+        }
+
+        // the header's variables end with an empty line
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(EnvironmentError::InvalidHeader)
+            }
+            match line.trim() {
+                "" => break,
+                variable if variable.starts_with("FORMAT=") && variable != "FORMAT=32-bit_rle_rgbe" => {
+                    return Err(EnvironmentError::InvalidHeader)
+                }
+                _ => {}
+            }
+        }
+
+        line.clear();
+        reader.read_line(&mut line)?;
+
+        let (width, height) = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["-Y", height, "+X", width] => height.parse::<u32>().ok()
+                .zip(width.parse::<u32>().ok())
+                .map(|(height, width)| (width, height)),
+            _ => None,
+        }.ok_or_else(|| EnvironmentError::UnsupportedResolution {
+            resolution: String::from(line.trim())
+        })?;
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let mut rgbe = Vec::with_capacity((width * height) as usize);
+        let mut offset = 0;
+
+        for _ in 0..height {
+            offset += read_scanline(&data[offset..], width as usize, &mut rgbe)?;
+        }
+
+        Ok(HdrImage {
+            width,
+            height,
+            pixels: rgbe.iter().map(|&texel| rgbe_to_float(texel)).collect(),
+        })
+    }
+
+    /// Samples the image bilinearly, wrapping around horizontally and clamping vertically.
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let texel = |x: f32, y: f32| {
+            let x = (x as i64).rem_euclid(self.width as i64) as u32;
+            let y = (y as u32).min(self.height - 1);
+            self.pixels[(y * self.width + x) as usize]
+        };
+
+        let (a, b, c, d) = (texel(x0, y0), texel(x0 + 1.0, y0), texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+
+        std::array::from_fn(|channel| {
+            let top = a[channel] + (b[channel] - a[channel]) * fx;
+            let bottom = c[channel] + (d[channel] - c[channel]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+/// Reads a scanline of RGBE texels and returns the number of bytes consumed.
+fn read_scanline(data: &[u8], width: usize, rgbe: &mut Vec<[u8; 4]>) -> Result<usize, EnvironmentError> {
+
+    let run_length_encoded = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2 && data[1] == 2
+        && ((data[2] as usize) << 8 | data[3] as usize) == width;
+
+    if !run_length_encoded {
+        let bytes = data.get(..width * 4).ok_or(EnvironmentError::CorruptPixelData)?;
+        rgbe.extend(bytes.chunks_exact(4).map(|texel| [texel[0], texel[1], texel[2], texel[3]]));
+        return Ok(width * 4)
+    }
+
+    // the channels are stored one after another, each as runs or literals
+    let start = rgbe.len();
+    rgbe.resize(start + width, [0; 4]);
+    let mut offset = 4;
+
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(offset).ok_or(EnvironmentError::CorruptPixelData)? as usize;
+            offset += 1;
+            match count > 128 {
+                true => {
+                    let count = count - 128;
+                    let value = *data.get(offset).ok_or(EnvironmentError::CorruptPixelData)?;
+                    offset += 1;
+                    if count == 0 || x + count > width {
+                        return Err(EnvironmentError::CorruptPixelData)
+                    }
+                    (x..x + count).for_each(|x| rgbe[start + x][channel] = value);
+                    x += count;
+                }
+                false => {
+                    if count == 0 || x + count > width {
+                        return Err(EnvironmentError::CorruptPixelData)
+                    }
+                    let values = data.get(offset..offset + count).ok_or(EnvironmentError::CorruptPixelData)?;
+                    values.iter().enumerate().for_each(|(index, &value)| rgbe[start + x + index][channel] = value);
+                    offset += count;
+                    x += count;
+                }
+            }
+        }
+    }
+
+    Ok(offset)
+}
+
+fn rgbe_to_float(texel: [u8; 4]) -> [f32; 4] {
+    match texel[3] {
+        0 => [0.0, 0.0, 0.0, 1.0],
+        exponent => {
+            let factor = 2f32.powi(exponent as i32 - (128 + 8));
+            [texel[0] as f32 * factor, texel[1] as f32 * factor, texel[2] as f32 * factor, 1.0]
+        }
+    }
+}
+
+/// The faces of a cube map in the order of its layers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX = 0,
+    NegativeX = 1,
+    PositiveY = 2,
+    NegativeY = 3,
+    PositiveZ = 4,
+    NegativeZ = 5,
+}
+
+impl CubeFace {
+
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// Returns the face sampled in the direction and the coordinates `(s, t)` in `[-1, 1]` on it,
+    /// the inverse of `direction`.
+    pub fn of(direction: &Vector3<f32>) -> (CubeFace, f32, f32) {
+
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+        let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+            match x >= 0.0 {
+                true => (CubeFace::PositiveX, -z, -y, ax),
+                false => (CubeFace::NegativeX, z, -y, ax),
+            }
+        }
+        else if ay >= az {
+            match y >= 0.0 {
+                true => (CubeFace::PositiveY, x, z, ay),
+                false => (CubeFace::NegativeY, x, -z, ay),
+            }
+        }
+        else {
+            match z >= 0.0 {
+                true => (CubeFace::PositiveZ, x, -y, az),
+                false => (CubeFace::NegativeZ, -x, -y, az),
+            }
+        };
+
+        (face, sc / ma, tc / ma)
+    }
+
+    /// Returns the direction sampling the face at `(s, t)` in `[-1, 1]`, with `s` to the right
+    /// and `t` downwards on the face, as defined by Vulkan.
+    pub fn direction(&self, s: f32, t: f32) -> Vector3<f32> {
+        match self {
+            CubeFace::PositiveX => Vector3::new(1.0, -t, -s),
+            CubeFace::NegativeX => Vector3::new(-1.0, -t, s),
+            CubeFace::PositiveY => Vector3::new(s, 1.0, t),
+            CubeFace::NegativeY => Vector3::new(s, -1.0, -t),
+            CubeFace::PositiveZ => Vector3::new(s, -t, 1.0),
+            CubeFace::NegativeZ => Vector3::new(-s, -t, -1.0),
+        }.normalize()
+    }
+}
+
+/// An environment as seen from a point, stored as the six square faces of a cube.
+///
+/// The faces are sampled with world space directions. As the y axis of the world points
+/// downwards, the `PositiveY` face shows the ground and the `NegativeY` face shows the sky.
+#[derive(Debug, Clone)]
+pub struct CubeMapData {
+    pub size: u32,
+    pub faces: [Vec<[f32; 4]>; 6],
+}
+
+impl CubeMapData {
+
+    /// Creates a cube map of six images in the order of `CubeFace::ALL`.
+    pub fn from_faces(faces: [HdrImage; 6]) -> Result<CubeMapData, EnvironmentError> {
+
+        let size = faces[0].width;
+
+        if faces.iter().any(|face| face.width != size || face.height != size) {
+            return Err(EnvironmentError::InvalidFaces)
+        }
+
+        Ok(CubeMapData {
+            size,
+            faces: faces.map(|face| face.pixels),
+        })
+    }
+
+    /// Projects an equirectangular (latitude-longitude) panorama onto the faces of a cube with
+    /// the given size. The panorama's center looks along +z and its top row is the zenith.
+    pub fn from_equirectangular(panorama: &HdrImage, size: u32) -> CubeMapData {
+
+        let faces = CubeFace::ALL.map(|face| {
+            (0..size * size).map(|index| {
+                let s = 2.0 * ((index % size) as f32 + 0.5) / size as f32 - 1.0;
+                let t = 2.0 * ((index / size) as f32 + 0.5) / size as f32 - 1.0;
+                let (u, v) = equirectangular_coordinates(&face.direction(s, t));
+                panorama.sample(u, v)
+            }).collect()
+        });
+
+        CubeMapData { size, faces }
+    }
+
+    pub fn texel(&self, face: CubeFace, x: u32, y: u32) -> [f32; 4] {
+        self.faces[face as usize][(y * self.size + x) as usize]
+    }
+
+    /// Samples the cube map in the direction bilinearly, as Vulkan samples a cube map without
+    /// filtering across the edges of the faces.
+    pub fn sample(&self, direction: &Vector3<f32>) -> [f32; 4] {
+
+        let (face, s, t) = CubeFace::of(direction);
+
+        let x = ((s + 1.0) * 0.5 * self.size as f32 - 0.5).clamp(0.0, (self.size - 1) as f32);
+        let y = ((t + 1.0) * 0.5 * self.size as f32 - 0.5).clamp(0.0, (self.size - 1) as f32);
+
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+        let (a, b, c, d) = (self.texel(face, x0, y0), self.texel(face, x1, y0), self.texel(face, x0, y1), self.texel(face, x1, y1));
+
+        std::array::from_fn(|channel| {
+            let top = a[channel] + (b[channel] - a[channel]) * fx;
+            let bottom = c[channel] + (d[channel] - c[channel]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+
+    /// Returns a copy with faces of the given size, averaging the texels covered by a texel of
+    /// the copy. The size has to divide the cube map's size.
+    pub fn downsample(&self, size: u32) -> CubeMapData {
+
+        assert_eq!(self.size % size, 0, "{} does not divide the cube map's size {}", size, self.size);
+
+        let factor = self.size / size;
+        let weight = 1.0 / (factor * factor) as f32;
+
+        let faces = CubeFace::ALL.map(|face| {
+            (0..size * size).map(|index| {
+                let (x, y) = (index % size * factor, index / size * factor);
+                (0..factor * factor).fold([0.0; 4], |sum, offset| {
+                    let texel = self.texel(face, x + offset % factor, y + offset / factor);
+                    std::array::from_fn(|channel| sum[channel] + texel[channel] * weight)
+                })
+            }).collect()
+        });
+
+        CubeMapData { size, faces }
+    }
+
+    /// Returns the texels of all faces as they are uploaded, one layer per face.
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        self.faces.iter()
+            .flat_map(|face| face.iter())
+            .flat_map(|texel| texel.iter().flat_map(|channel| channel.to_ne_bytes()))
+            .collect()
+    }
+}
+
+/// Returns the texture coordinates of the direction in an equirectangular panorama.
+fn equirectangular_coordinates(direction: &Vector3<f32>) -> (f32, f32) {
+    let u = 0.5 + direction.x.atan2(direction.z) / (2.0 * PI);
+    // up is -y
+    let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+    (u, v)
+}
+
+/// A cube map drawn behind all geometries, created by `create_skybox`.
+pub struct Skybox {
+    pub image: Image,
+    pub image_view: ::ash::vk::ImageView,
+    pub(crate) sampler: ::ash::vk::Sampler,
+    pub(crate) descriptor_pool: ::ash::vk::DescriptorPool,
+    pub(crate) descriptor_set_layout: ::ash::vk::DescriptorSetLayout,
+    pub(crate) descriptor_set: ::ash::vk::DescriptorSet,
+    pub(crate) pipeline: FullscreenPipeline,
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::environment::{CubeFace, CubeMapData, HdrImage};
+
+    fn radiance(resolution: &str, pixels: &[u8]) -> Vec<u8> {
+        let mut data = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n").into_bytes();
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    #[test]
+    fn test_read_flat_radiance_image() {
+
+        // 1.0 is stored as a mantissa of 128 with an exponent of 129
+        let data = radiance("-Y 1 +X 2", &[128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let image = HdrImage::read_radiance(data.as_slice()).unwrap();
+
+        assert_that!((image.width, image.height), is(equal_to((2, 1))));
+        assert_that!(image.pixels[0], is(equal_to([1.0, 0.5, 0.0, 1.0])));
+        assert_that!(image.pixels[1], is(equal_to([0.0, 0.0, 0.0, 1.0])));
+    }
+
+    #[test]
+    fn test_read_run_length_encoded_radiance_image() {
+
+        // 8 texels, red as a run, green as literals, blue and exponent as runs
+        let mut pixels = vec![2, 2, 0, 8];
+        pixels.extend_from_slice(&[128 + 8, 128]);
+        pixels.extend_from_slice(&[8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        pixels.extend_from_slice(&[128 + 8, 0]);
+        pixels.extend_from_slice(&[128 + 8, 129]);
+
+        let image = HdrImage::read_radiance(radiance("-Y 1 +X 8", &pixels).as_slice()).unwrap();
+
+        assert_that!(image.pixels.len(), is(equal_to(8)));
+        assert_that!(image.pixels[7], is(equal_to([1.0, 7.0 / 128.0, 0.0, 1.0])));
+    }
+
+    #[test]
+    fn test_reject_unsupported_orientation() {
+        assert_that!(HdrImage::read_radiance(radiance("+Y 1 +X 1", &[0; 4]).as_slice()).is_err(), is(true));
+    }
+
+    #[test]
+    fn test_equirectangular_projection() {
+
+        // the sky in the upper half is bright, the ground is dark
+        let panorama = HdrImage {
+            width: 8,
+            height: 4,
+            pixels: (0..32).map(|index| if index < 16 { [1.0; 4] } else { [0.0, 0.0, 0.0, 1.0] }).collect(),
+        };
+
+        let cube = CubeMapData::from_equirectangular(&panorama, 4);
+
+        assert_that!(cube.texel(CubeFace::NegativeY, 1, 1)[0], is(equal_to(1.0)));
+        assert_that!(cube.texel(CubeFace::PositiveY, 1, 1)[0], is(equal_to(0.0)));
+        // the rows of the side faces run along +y, i.e. from the sky down to the ground
+        assert_that!(cube.texel(CubeFace::PositiveZ, 1, 0)[0], is(equal_to(0.0)));
+        assert_that!(cube.texel(CubeFace::PositiveZ, 1, 3)[0], is(equal_to(1.0)));
+    }
+
+    #[test]
+    fn test_face_of_direction_inverts_direction() {
+
+        CubeFace::ALL.iter().for_each(|&face| {
+            let (sampled, s, t) = CubeFace::of(&face.direction(0.25, -0.5));
+            assert_that!(sampled, is(equal_to(face)));
+            assert_that!((s - 0.25).abs() + (t + 0.5).abs(), is(less_than(1e-5)));
+        });
+    }
+
+    #[test]
+    fn test_faces_have_to_be_squares_of_the_same_size() {
+
+        let face = |size| HdrImage { width: size, height: size, pixels: vec![[0.0; 4]; (size * size) as usize] };
+
+        assert_that!(CubeMapData::from_faces([face(2), face(2), face(2), face(2), face(2), face(2)]).is_ok(), is(true));
+        assert_that!(CubeMapData::from_faces([face(2), face(2), face(1), face(2), face(2), face(2)]).is_err(), is(true));
+    }
+}
@@ -0,0 +1,386 @@
+use std::f32::consts::PI;
+
+use nalgebra::Vector3;
+
+use crate::graphics::vulkan::resources::Image;
+use crate::graphics::vulkan::shaders::ComputeShaderBinary;
+use crate::graphics::{CubeFace, CubeMapData};
+
+/// The resolutions and quality of the precomputed environment lighting.
+#[derive(Debug, Copy, Clone)]
+pub struct EnvironmentLightingSettings {
+    pub irradiance_size: u32,
+    /// The size of the sharpest level of the specular map.
+    pub specular_size: u32,
+    /// The number of levels of the specular map, from roughness 0 to 1.
+    pub specular_levels: u32,
+    pub brdf_lut_size: u32,
+    /// The number of samples per texel of the specular map and the BRDF LUT.
+    pub samples: u32,
+}
+
+impl Default for EnvironmentLightingSettings {
+    fn default() -> Self {
+        EnvironmentLightingSettings {
+            irradiance_size: 32,
+            specular_size: 128,
+            specular_levels: 5,
+            brdf_lut_size: 64,
+            samples: 256,
+        }
+    }
+}
+
+impl EnvironmentLightingSettings {
+
+    pub fn specular_level_size(&self, level: u32) -> u32 {
+        (self.specular_size >> level).max(1)
+    }
+
+    /// The roughness the level of the specular map is prefiltered for, increasing linearly from
+    /// 0 for the first to 1 for the last level.
+    pub fn specular_roughness(&self, level: u32) -> f32 {
+        match self.specular_levels {
+            1 => 0.0,
+            levels => level as f32 / (levels - 1) as f32,
+        }
+    }
+}
+
+/// The scale and bias applied to F0 by the split-sum approximation of the specular BRDF, indexed
+/// by `n·v` along x and the roughness along y.
+#[derive(Debug, Clone)]
+pub struct BrdfLut {
+    pub size: u32,
+    pub values: Vec<[f32; 2]>,
+}
+
+impl BrdfLut {
+
+    pub fn compute(size: u32, samples: u32) -> BrdfLut {
+
+        let values = (0..size * size).map(|index| {
+            let n_dot_v = ((index % size) as f32 + 0.5) / size as f32;
+            let roughness = ((index / size) as f32 + 0.5) / size as f32;
+            integrate_brdf(n_dot_v, roughness, samples)
+        }).collect();
+
+        BrdfLut { size, values }
+    }
+
+    pub fn value(&self, x: u32, y: u32) -> [f32; 2] {
+        self.values[(y * self.size + x) as usize]
+    }
+
+    /// Returns the values as RGBA texels, as they are uploaded.
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        self.values.iter()
+            .flat_map(|[scale, bias]| [*scale, *bias, 0.0, 1.0])
+            .flat_map(|channel| channel.to_ne_bytes())
+            .collect()
+    }
+}
+
+/// The ambient lighting of an environment map, as sampled by the lit shader: the irradiance for
+/// the diffuse part, the specular map prefiltered for increasing roughness and the BRDF LUT.
+#[derive(Debug, Clone)]
+pub struct EnvironmentLightingData {
+    /// The cosine weighted irradiance divided by π, multiplied with the albedo by the shader.
+    pub irradiance: CubeMapData,
+    /// One cube map per level, each half the size of the previous one.
+    pub specular: Vec<CubeMapData>,
+    pub brdf_lut: BrdfLut,
+}
+
+impl EnvironmentLightingData {
+
+    /// Precomputes the lighting on the CPU, which is slow but does not need a device. The
+    /// environment is downsampled for the irradiance, so that its size should be a power of two.
+    pub fn compute(environment: &CubeMapData, settings: &EnvironmentLightingSettings) -> EnvironmentLightingData {
+
+        let specular = (0..settings.specular_levels).map(|level| {
+            prefilter_specular(environment, settings.specular_level_size(level), settings.specular_roughness(level), settings.samples)
+        }).collect();
+
+        EnvironmentLightingData {
+            irradiance: convolve_irradiance(environment, settings.irradiance_size),
+            specular,
+            brdf_lut: BrdfLut::compute(settings.brdf_lut_size, settings.samples),
+        }
+    }
+
+    /// Lighting without any contribution, bound while the world has no environment lighting.
+    pub(crate) fn none() -> EnvironmentLightingData {
+
+        let black = CubeMapData {
+            size: 1,
+            faces: std::array::from_fn(|_| vec![[0.0, 0.0, 0.0, 1.0]]),
+        };
+
+        EnvironmentLightingData {
+            irradiance: black.clone(),
+            specular: vec![black],
+            brdf_lut: BrdfLut {
+                size: 1,
+                values: vec![[0.0, 0.0]],
+            },
+        }
+    }
+}
+
+/// Integrates the environment weighted by the cosine to the normal of every texel. The
+/// irradiance varies slowly, so the environment is downsampled to at most 16 texels per edge.
+fn convolve_irradiance(environment: &CubeMapData, size: u32) -> CubeMapData {
+
+    let mut source_size = environment.size;
+    while source_size > 16 && source_size % 2 == 0 {
+        source_size /= 2;
+    }
+    let source = environment.downsample(source_size);
+
+    // the direction and solid angle of every texel of the source
+    let texels = CubeFace::ALL.iter().flat_map(|&face| {
+        let source = &source;
+        (0..source_size * source_size).map(move |index| {
+            let s = 2.0 * ((index % source_size) as f32 + 0.5) / source_size as f32 - 1.0;
+            let t = 2.0 * ((index / source_size) as f32 + 0.5) / source_size as f32 - 1.0;
+            let texel_size = 2.0 / source_size as f32;
+            let solid_angle = texel_size * texel_size / (1.0 + s * s + t * t).powf(1.5);
+            (face.direction(s, t), solid_angle, source.texel(face, index % source_size, index / source_size))
+        })
+    }).collect::<Vec<_>>();
+
+    let faces = CubeFace::ALL.map(|face| {
+        (0..size * size).map(|index| {
+
+            let s = 2.0 * ((index % size) as f32 + 0.5) / size as f32 - 1.0;
+            let t = 2.0 * ((index / size) as f32 + 0.5) / size as f32 - 1.0;
+            let normal = face.direction(s, t);
+
+            let irradiance = texels.iter().fold([0.0; 3], |sum, (direction, solid_angle, radiance)| {
+                let weight = normal.dot(direction).max(0.0) * solid_angle;
+                [sum[0] + radiance[0] * weight, sum[1] + radiance[1] * weight, sum[2] + radiance[2] * weight]
+            });
+
+            [irradiance[0] / PI, irradiance[1] / PI, irradiance[2] / PI, 1.0]
+        }).collect()
+    });
+
+    CubeMapData { size, faces }
+}
+
+/// Convolves the environment with the GGX distribution of the roughness, assuming that the
+/// view direction equals the normal.
+fn prefilter_specular(environment: &CubeMapData, size: u32, roughness: f32, samples: u32) -> CubeMapData {
+
+    let faces = CubeFace::ALL.map(|face| {
+        (0..size * size).map(|index| {
+
+            let s = 2.0 * ((index % size) as f32 + 0.5) / size as f32 - 1.0;
+            let t = 2.0 * ((index / size) as f32 + 0.5) / size as f32 - 1.0;
+            let normal = face.direction(s, t);
+
+            if roughness == 0.0 {
+                return environment.sample(&normal)
+            }
+
+            let (sum, total_weight) = (0..samples).fold(([0.0; 3], 0.0), |(sum, total_weight), sample| {
+
+                let half = importance_sample_ggx(hammersley(sample, samples), &normal, roughness);
+                let light = 2.0 * normal.dot(&half) * half - normal;
+                let weight = normal.dot(&light);
+
+                match weight > 0.0 {
+                    true => {
+                        let radiance = environment.sample(&light);
+                        ([sum[0] + radiance[0] * weight, sum[1] + radiance[1] * weight, sum[2] + radiance[2] * weight], total_weight + weight)
+                    }
+                    false => (sum, total_weight),
+                }
+            });
+
+            match total_weight > 0.0 {
+                true => [sum[0] / total_weight, sum[1] / total_weight, sum[2] / total_weight, 1.0],
+                false => [0.0, 0.0, 0.0, 1.0],
+            }
+        }).collect()
+    });
+
+    CubeMapData { size, faces }
+}
+
+/// Integrates the specular BRDF over the hemisphere for F0 = 1 and F0 = 0, see Karis, "Real
+/// Shading in Unreal Engine 4".
+fn integrate_brdf(n_dot_v: f32, roughness: f32, samples: u32) -> [f32; 2] {
+
+    let normal = Vector3::new(0.0, 0.0, 1.0);
+    let view = Vector3::new((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+
+    let (scale, bias) = (0..samples).fold((0.0, 0.0), |(scale, bias), sample| {
+
+        let half = importance_sample_ggx(hammersley(sample, samples), &normal, roughness);
+        let light = 2.0 * view.dot(&half) * half - view;
+
+        let n_dot_l = light.z.max(0.0);
+        let n_dot_h = half.z.max(0.0);
+        let v_dot_h = view.dot(&half).max(0.0);
+
+        match n_dot_l > 0.0 {
+            true => {
+                let visibility = geometry_smith(n_dot_v, n_dot_l, roughness) * v_dot_h / (n_dot_h * n_dot_v);
+                let fresnel = (1.0 - v_dot_h).powi(5);
+                (scale + (1.0 - fresnel) * visibility, bias + fresnel * visibility)
+            }
+            false => (scale, bias),
+        }
+    });
+
+    [scale / samples as f32, bias / samples as f32]
+}
+
+/// The i-th point of the Hammersley sequence of n points in `[0, 1)²`.
+fn hammersley(i: u32, n: u32) -> (f32, f32) {
+    (i as f32 / n as f32, i.reverse_bits() as f32 * 2.328_306_4e-10)
+}
+
+/// Returns a half vector around the normal, distributed by GGX for the roughness.
+fn importance_sample_ggx((u, v): (f32, f32), normal: &Vector3<f32>, roughness: f32) -> Vector3<f32> {
+
+    let alpha = roughness * roughness;
+
+    let phi = 2.0 * PI * u;
+    let cos_theta = ((1.0 - v) / (1.0 + (alpha * alpha - 1.0) * v)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+    let up = match normal.z.abs() < 0.999 {
+        true => Vector3::new(0.0, 0.0, 1.0),
+        false => Vector3::new(1.0, 0.0, 0.0),
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(&tangent);
+
+    (tangent * phi.cos() * sin_theta + bitangent * phi.sin() * sin_theta + normal * cos_theta).normalize()
+}
+
+/// Smith's geometry term with Schlick's approximation, remapped for image based lighting.
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let schlick = |n_dot_x: f32| n_dot_x / (n_dot_x * (1.0 - k) + k);
+    schlick(n_dot_v) * schlick(n_dot_l)
+}
+
+/// The precomputed environment lighting on the GPU, created by `create_environment_lighting`.
+/// The lighting takes effect once it is assigned to `World::environment_lighting`.
+pub struct EnvironmentLighting {
+    pub irradiance: Image,
+    pub irradiance_view: ::ash::vk::ImageView,
+    pub specular: Image,
+    pub specular_view: ::ash::vk::ImageView,
+    pub brdf_lut: Image,
+    pub brdf_lut_view: ::ash::vk::ImageView,
+}
+
+/// The compute shaders precomputing the environment lighting on the GPU. Each is dispatched in
+/// workgroups of 8x8 invocations, one per texel, and for the cube maps one workgroup layer per face.
+///
+/// The irradiance and prefilter shaders sample the environment cube at `set = 0, binding = 0` and
+/// write the `image2DArray` at `set = 0, binding = 1`, the BRDF shader writes the `image2D` at the
+/// same binding. All receive `EnvironmentLightingPushConstants`.
+pub struct EnvironmentLightingShaders {
+    pub irradiance: ComputeShaderBinary,
+    pub prefilter: ComputeShaderBinary,
+    pub brdf: ComputeShaderBinary,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct EnvironmentLightingPushConstants {
+    /// The roughness of the level of the specular map being prefiltered.
+    pub roughness: f32,
+    /// The size of the written image.
+    pub size: u32,
+    pub samples: u32,
+}
+
+impl EnvironmentLightingPushConstants {
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::{CubeFace, CubeMapData, EnvironmentLightingData, EnvironmentLightingSettings};
+    use crate::graphics::ibl::BrdfLut;
+
+    fn uniform(size: u32, radiance: f32) -> CubeMapData {
+        CubeMapData {
+            size,
+            faces: std::array::from_fn(|_| vec![[radiance, radiance, radiance, 1.0]; (size * size) as usize]),
+        }
+    }
+
+    fn settings() -> EnvironmentLightingSettings {
+        EnvironmentLightingSettings {
+            irradiance_size: 4,
+            specular_size: 8,
+            specular_levels: 3,
+            brdf_lut_size: 8,
+            samples: 64,
+        }
+    }
+
+    #[test]
+    fn test_uniform_environment_is_preserved() {
+
+        let lighting = EnvironmentLightingData::compute(&uniform(8, 2.0), &settings());
+
+        // the cosine weighted integral of a constant radiance L is π L
+        CubeFace::ALL.iter().for_each(|&face| {
+            assert_that!((lighting.irradiance.texel(face, 1, 2)[0] - 2.0).abs(), is(less_than(0.02)));
+        });
+
+        assert_that!(lighting.specular.iter().map(|level| level.size).collect::<Vec<_>>(), is(equal_to(vec![8, 4, 2])));
+        lighting.specular.iter().for_each(|level| {
+            assert_that!((level.texel(CubeFace::PositiveZ, 0, 0)[1] - 2.0).abs(), is(less_than(1e-3)));
+        });
+    }
+
+    #[test]
+    fn test_irradiance_faces_the_light() {
+
+        // only the sky, i.e. -y, is bright
+        let mut environment = uniform(8, 0.0);
+        environment.faces[CubeFace::NegativeY as usize].iter_mut().for_each(|texel| *texel = [1.0; 4]);
+
+        let lighting = EnvironmentLightingData::compute(&environment, &settings());
+
+        let up = lighting.irradiance.texel(CubeFace::NegativeY, 2, 2)[0];
+        let side = lighting.irradiance.texel(CubeFace::PositiveX, 2, 2)[0];
+        let down = lighting.irradiance.texel(CubeFace::PositiveY, 2, 2)[0];
+
+        assert_that!(up, is(greater_than(side)));
+        assert_that!(side, is(greater_than(down)));
+        assert_that!(down, is(equal_to(0.0)));
+    }
+
+    #[test]
+    fn test_brdf_lut() {
+
+        let lut = BrdfLut::compute(8, 256);
+
+        // smooth surfaces seen head-on reflect F0, rough surfaces at grazing angles much less
+        let [scale, bias] = lut.value(7, 0);
+        assert_that!(scale, is(greater_than(0.9)));
+        assert_that!(bias, is(less_than(0.05)));
+
+        lut.values.iter().for_each(|[scale, bias]| {
+            assert_that!(scale + bias, is(less_than_or_equal_to(1.01)));
+        });
+    }
+}
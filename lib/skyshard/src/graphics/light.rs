@@ -0,0 +1,237 @@
+use nalgebra::Vector3;
+
+use crate::graphics::shadow::shadow_casters;
+
+/// The maximum number of lights which are uploaded to the GPU per frame. Additional lights of a
+/// [World](crate::entity::World) are ignored.
+pub const MAX_LIGHTS: usize = 16;
+
+#[derive(Debug, Copy, Clone)]
+pub enum Light {
+    Directional(DirectionalLight),
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+/// A light infinitely far away, illuminating everything from the same direction (e.g. the sun).
+/// See [MAX_SHADOW_CASTERS](crate::graphics::MAX_SHADOW_CASTERS) for the number of lights which
+/// may cast shadows at once.
+#[derive(Debug, Copy, Clone)]
+pub struct DirectionalLight {
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub cast_shadows: bool,
+}
+
+/// A light emitting in all directions from a single point, attenuated until `range`.
+#[derive(Debug, Copy, Clone)]
+pub struct PointLight {
+    pub position: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+}
+
+/// A point light restricted to a cone. Angles are given in radians, measured from the cone's axis.
+#[derive(Debug, Copy, Clone)]
+pub struct SpotLight {
+    pub position: Vector3<f32>,
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+}
+
+impl Light {
+
+    const DIRECTIONAL: f32 = 0.0;
+    const POINT: f32 = 1.0;
+    const SPOT: f32 = 2.0;
+    const NO_SHADOW: f32 = -1.0;
+}
+
+/// GPU representation of a [Light] matching the std140 layout of the following GLSL struct:
+///
+/// ```glsl
+/// struct Light {
+///     vec4 position;  // xyz: world space position, w: type (0: directional, 1: point, 2: spot)
+///     vec4 direction; // xyz: normalized direction, w: range
+///     vec4 color;     // rgb: color, a: intensity
+///     vec4 cone;      // x: cosine of the inner cone angle, y: cosine of the outer cone angle,
+///                     // z: index of the shadow map or -1
+/// };
+/// ```
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct LightData {
+    position: [f32; 4],
+    direction: [f32; 4],
+    color: [f32; 4],
+    cone: [f32; 4],
+}
+
+impl LightData {
+
+    pub(crate) fn empty() -> LightData {
+        LightData {
+            position: [0.0; 4],
+            direction: [0.0; 4],
+            color: [0.0; 4],
+            cone: [0.0, 0.0, Light::NO_SHADOW, 0.0],
+        }
+    }
+}
+
+impl From<&Light> for LightData {
+    fn from(light: &Light) -> Self {
+        match light {
+            Light::Directional(light) => {
+                let direction = light.direction.normalize();
+                LightData {
+                    position: [0.0, 0.0, 0.0, Light::DIRECTIONAL],
+                    direction: [direction.x, direction.y, direction.z, 0.0],
+                    color: [light.color[0], light.color[1], light.color[2], light.intensity],
+                    cone: [0.0, 0.0, Light::NO_SHADOW, 0.0],
+                }
+            }
+            Light::Point(light) => {
+                LightData {
+                    position: [light.position.x, light.position.y, light.position.z, Light::POINT],
+                    direction: [0.0, 0.0, 0.0, light.range],
+                    color: [light.color[0], light.color[1], light.color[2], light.intensity],
+                    cone: [0.0, 0.0, Light::NO_SHADOW, 0.0],
+                }
+            }
+            Light::Spot(light) => {
+                let direction = light.direction.normalize();
+                LightData {
+                    position: [light.position.x, light.position.y, light.position.z, Light::SPOT],
+                    direction: [direction.x, direction.y, direction.z, light.range],
+                    color: [light.color[0], light.color[1], light.color[2], light.intensity],
+                    cone: [light.inner_cone_angle.cos(), light.outer_cone_angle.cos(), Light::NO_SHADOW, 0.0],
+                }
+            }
+        }
+    }
+}
+
+/// Lights bound to `set = 0, binding = 1` of every pipeline. The layout matches the GLSL declaration:
+///
+/// ```glsl
+/// layout(set = 0, binding = 1) uniform LightsUniformBufferObject {
+///     vec4 ambient;       // rgb: ambient color, a: unused
+///     uint count;         // number of valid entries in `lights`
+///     Light lights[16];   // see MAX_LIGHTS
+/// } lights;
+/// ```
+#[repr(C, align(256))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct LightsUniformBufferObject {
+    ambient: [f32; 4],
+    count: u32,
+    _padding: [u32; 3],
+    lights: [LightData; MAX_LIGHTS],
+}
+
+impl LightsUniformBufferObject {
+
+    pub(crate) fn new(ambient: &[f32; 3], lights: &[Light]) -> LightsUniformBufferObject {
+
+        let mut result = LightsUniformBufferObject {
+            ambient: [ambient[0], ambient[1], ambient[2], 0.0],
+            count: lights.len().min(MAX_LIGHTS) as u32,
+            _padding: [0; 3],
+            lights: [LightData::empty(); MAX_LIGHTS],
+        };
+
+        lights.iter()
+            .take(MAX_LIGHTS)
+            .enumerate()
+            .for_each(|(index, light)| {
+                result.lights[index] = LightData::from(light);
+            });
+
+        shadow_casters(lights)
+            .enumerate()
+            .for_each(|(caster, (index, _))| {
+                result.lights[index].cone[2] = caster as f32;
+            });
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::graphics::light::{DirectionalLight, Light, LightData, LightsUniformBufferObject, MAX_LIGHTS, PointLight};
+
+    #[test]
+    fn test_light_data_has_std140_layout() {
+        assert_that!(offset_of!(LightData, position), is(equal_to(0)));
+        assert_that!(offset_of!(LightData, direction), is(equal_to(16)));
+        assert_that!(offset_of!(LightData, color), is(equal_to(32)));
+        assert_that!(offset_of!(LightData, cone), is(equal_to(48)));
+        assert_that!(std::mem::size_of::<LightData>(), is(equal_to(64)));
+        assert_that!(offset_of!(LightsUniformBufferObject, ambient), is(equal_to(0)));
+        assert_that!(offset_of!(LightsUniformBufferObject, count), is(equal_to(16)));
+        assert_that!(offset_of!(LightsUniformBufferObject, lights), is(equal_to(32)));
+        assert_that!(std::mem::size_of::<LightsUniformBufferObject>() % 256, is(equal_to(0)));
+    }
+
+    #[test]
+    fn test_lights_uniform_buffer_object_truncates_lights() {
+
+        let light = Light::Point(PointLight {
+            position: Vector3::new(1.0, 2.0, 3.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 2.0,
+            range: 10.0,
+        });
+
+        let ubo = LightsUniformBufferObject::new(&[0.1, 0.1, 0.1], &vec![light; MAX_LIGHTS + 4]);
+
+        assert_that!(ubo.count, is(equal_to(MAX_LIGHTS as u32)));
+        assert_that!(ubo.lights[0].position, is(equal_to([1.0, 2.0, 3.0, 1.0])));
+        assert_that!(ubo.lights[0].direction[3], is(equal_to(10.0)));
+    }
+
+    #[test]
+    fn test_directional_light_direction_gets_normalized() {
+
+        let light = Light::Directional(DirectionalLight {
+            direction: Vector3::new(0.0, 4.0, 0.0),
+            color: [1.0, 0.5, 0.25],
+            intensity: 3.0,
+            cast_shadows: false,
+        });
+
+        let data = LightData::from(&light);
+
+        assert_that!(data.direction, is(equal_to([0.0, 1.0, 0.0, 0.0])));
+        assert_that!(data.color, is(equal_to([1.0, 0.5, 0.25, 3.0])));
+        assert_that!(data.cone[2], is(equal_to(-1.0)));
+    }
+
+    #[test]
+    fn test_lights_uniform_buffer_object_assigns_shadow_maps() {
+
+        let directional = |cast_shadows| Light::Directional(DirectionalLight {
+            direction: Vector3::new(0.0, 1.0, 0.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            cast_shadows,
+        });
+
+        let ubo = LightsUniformBufferObject::new(&[0.0, 0.0, 0.0], &[directional(false), directional(true), directional(true)]);
+
+        assert_that!(ubo.lights[0].cone[2], is(equal_to(-1.0)));
+        assert_that!(ubo.lights[1].cone[2], is(equal_to(0.0)));
+        assert_that!(ubo.lights[2].cone[2], is(equal_to(1.0)));
+    }
+}
@@ -0,0 +1,232 @@
+use nalgebra::{Matrix4, Vector3};
+
+use crate::engine::InstanceData;
+use crate::graphics::IndexBuffer;
+use crate::graphics::vulkan::resources::Buffer;
+
+/// A coarser mesh of a geometry, used for instances passing the level's threshold.
+pub struct LevelOfDetail {
+    pub index_buffer: IndexBuffer,
+    /// The vertices laid out like the ones of the geometry.
+    pub vertex_buffer: Buffer<u8>,
+    /// For `LodMetric::Distance` the distance to the camera from which on the level is used, for
+    /// `LodMetric::ScreenSize` the projected size below which the level is used.
+    pub threshold: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LodMetric {
+    /// Distance between the camera and the center of the instance's bounding sphere.
+    Distance,
+    /// Radius of the instance's bounding sphere projected onto the screen, relative to half of
+    /// the viewport's height. Approximates the screen space error of a coarser mesh.
+    ScreenSize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LodSelection {
+    pub metric: LodMetric,
+    /// Fraction of a threshold before it, in which instances are cross-faded between both levels
+    /// by dithering. A value of `0.0` switches levels instantly.
+    pub dither_range: f32,
+}
+
+impl Default for LodSelection {
+    fn default() -> Self {
+        LodSelection {
+            metric: LodMetric::Distance,
+            dither_range: 0.0,
+        }
+    }
+}
+
+/// A range of the instances buffer drawn with one level of detail.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodBatch {
+    /// `0` for the geometry's own mesh, `n` for `lods[n - 1]`.
+    pub level: usize,
+    pub first_instance: u32,
+    pub instance_count: u32,
+    /// `0.0` draws all fragments. A positive value keeps the fragments whose dither threshold is
+    /// below it, a negative one the complementary fragments.
+    pub dither: f32,
+}
+
+/// Reorders the instances, so that the instances of each level are adjacent, and returns the
+/// batches to draw them. Instances within a transition are moved behind all others and are drawn
+/// once per level with complementary dither patterns.
+///
+/// With `keep_order`, e.g. for instances sorted from back to front, the instances are not
+/// reordered and every run of adjacent instances at the same level is drawn by its own batch.
+///
+/// `projection_scale` is the projection's `(1, 1)` element, i.e. `1 / tan(fovy / 2)`.
+pub(crate) fn select_lods(
+    instances: &mut [InstanceData],
+    thresholds: &[f32],
+    selection: &LodSelection,
+    bounding_sphere: &[f32; 4],
+    camera_position: &Vector3<f32>,
+    projection_scale: f32,
+    keep_order: bool,
+) -> Vec<LodBatch> {
+
+    if thresholds.is_empty() {
+        return vec![LodBatch { level: 0, first_instance: 0, instance_count: instances.len() as u32, dither: 0.0 }]
+            .into_iter()
+            .filter(|batch| batch.instance_count > 0)
+            .collect();
+    }
+
+    // the metric is turned into a value growing with the distance for both kinds of thresholds
+    let thresholds = thresholds.iter().map(|threshold| match selection.metric {
+        LodMetric::Distance => *threshold,
+        LodMetric::ScreenSize => 1.0 / threshold.max(f32::EPSILON),
+    }).collect::<Vec<_>>();
+
+    let center = Vector3::new(bounding_sphere[0], bounding_sphere[1], bounding_sphere[2]);
+
+    let mut selections = instances.iter().map(|instance| {
+
+        let transformation = Matrix4::from_column_slice(&instance.transformation);
+        let world_center = (center.push(1.0).transpose() * transformation).transpose().xyz();
+        let distance = (world_center - camera_position).norm();
+
+        let value = match selection.metric {
+            LodMetric::Distance => distance,
+            LodMetric::ScreenSize => {
+                let scale = (0..3)
+                    .map(|row| transformation.fixed_view::<1, 3>(row, 0).norm())
+                    .fold(0.0, f32::max);
+                let size = bounding_sphere[3] * scale * projection_scale / distance.max(f32::EPSILON);
+                1.0 / size.max(f32::EPSILON)
+            }
+        };
+
+        let level = thresholds.iter().take_while(|threshold| value >= **threshold).count();
+
+        // the fraction of the way through the transition into the next level, if any
+        let fade = thresholds.get(level)
+            .filter(|_| selection.dither_range > 0.0)
+            .map(|threshold| {
+                let start = threshold * (1.0 - selection.dither_range);
+                (value - start) / (threshold - start)
+            })
+            .filter(|fade| *fade >= 0.0)
+            .map(|fade| fade.clamp(f32::EPSILON, 1.0));
+
+        (*instance, level, fade)
+    }).collect::<Vec<_>>();
+
+    if !keep_order {
+
+        selections.sort_by_key(|(_, level, fade)| (fade.is_some(), *level));
+
+        instances.iter_mut().zip(selections.iter()).for_each(|(instance, (selected, _, _))| {
+            *instance = *selected;
+        });
+    }
+
+    let mut batches: Vec<LodBatch> = Vec::new();
+
+    selections.iter().enumerate().for_each(|(index, (_, level, fade))| {
+        match fade {
+            None => match batches.last_mut() {
+                Some(batch) if batch.level == *level && batch.dither == 0.0 => batch.instance_count += 1,
+                _ => batches.push(LodBatch { level: *level, first_instance: index as u32, instance_count: 1, dither: 0.0 }),
+            },
+            Some(fade) => {
+                batches.push(LodBatch { level: *level, first_instance: index as u32, instance_count: 1, dither: -fade });
+                batches.push(LodBatch { level: *level + 1, first_instance: index as u32, instance_count: 1, dither: *fade });
+            }
+        }
+    });
+
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::engine::InstanceData;
+    use crate::graphics::{LodBatch, LodMetric, LodSelection};
+    use crate::graphics::lod::select_lods;
+
+    fn instance(id: u32, z: f32) -> InstanceData {
+        InstanceData {
+            id,
+            transformation: Matrix4::<f32>::identity()
+                .append_translation(&Vector3::new(0.0, 0.0, z))
+                .transpose()
+                .as_slice()
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    fn ids(instances: &[InstanceData]) -> Vec<u32> {
+        instances.iter().map(|instance| instance.id).collect()
+    }
+
+    #[test]
+    fn test_select_lods_by_distance() {
+
+        let mut instances = vec![instance(1, 50.0), instance(2, 5.0), instance(3, 15.0), instance(4, 1.0)];
+
+        let batches = select_lods(&mut instances, &[10.0, 40.0], &LodSelection::default(), &[0.0, 0.0, 0.0, 1.0], &Vector3::zeros(), 1.0, false);
+
+        assert_that!(ids(&instances), is(equal_to(vec![2, 4, 3, 1])));
+        assert_that!(batches, is(equal_to(vec![
+            LodBatch { level: 0, first_instance: 0, instance_count: 2, dither: 0.0 },
+            LodBatch { level: 1, first_instance: 2, instance_count: 1, dither: 0.0 },
+            LodBatch { level: 2, first_instance: 3, instance_count: 1, dither: 0.0 },
+        ])));
+    }
+
+    #[test]
+    fn test_select_lods_by_screen_size() {
+
+        let mut instances = vec![instance(1, 100.0), instance(2, 2.0)];
+        let selection = LodSelection { metric: LodMetric::ScreenSize, dither_range: 0.0 };
+
+        // projected sizes: 1 / 100 and 1 / 2
+        let batches = select_lods(&mut instances, &[0.1], &selection, &[0.0, 0.0, 0.0, 1.0], &Vector3::zeros(), 1.0, false);
+
+        assert_that!(ids(&instances), is(equal_to(vec![2, 1])));
+        assert_that!(batches.iter().map(|batch| batch.level).collect::<Vec<_>>(), is(equal_to(vec![0, 1])));
+    }
+
+    #[test]
+    fn test_select_lods_dithers_transitions() {
+
+        let mut instances = vec![instance(1, 9.5), instance(2, 5.0)];
+        let selection = LodSelection { metric: LodMetric::Distance, dither_range: 0.1 };
+
+        let batches = select_lods(&mut instances, &[10.0], &selection, &[0.0, 0.0, 0.0, 1.0], &Vector3::zeros(), 1.0, false);
+
+        assert_that!(ids(&instances), is(equal_to(vec![2, 1])));
+        assert_that!(batches.len(), is(equal_to(3)));
+        assert_that!(batches[1], is(equal_to(LodBatch { level: 0, first_instance: 1, instance_count: 1, dither: -0.5 })));
+        assert_that!(batches[2], is(equal_to(LodBatch { level: 1, first_instance: 1, instance_count: 1, dither: 0.5 })));
+    }
+
+    #[test]
+    fn test_select_lods_keeps_the_order_of_sorted_instances() {
+
+        // sorted from back to front, alternating between the levels
+        let mut instances = vec![instance(1, 50.0), instance(2, 45.0), instance(3, 20.0), instance(4, 9.5), instance(5, 5.0), instance(6, 1.0)];
+        let selection = LodSelection { metric: LodMetric::Distance, dither_range: 0.1 };
+
+        let batches = select_lods(&mut instances, &[10.0, 40.0], &selection, &[0.0, 0.0, 0.0, 1.0], &Vector3::zeros(), 1.0, true);
+
+        assert_that!(ids(&instances), is(equal_to(vec![1, 2, 3, 4, 5, 6])));
+        assert_that!(batches, is(equal_to(vec![
+            LodBatch { level: 2, first_instance: 0, instance_count: 2, dither: 0.0 },
+            LodBatch { level: 1, first_instance: 2, instance_count: 1, dither: 0.0 },
+            LodBatch { level: 0, first_instance: 3, instance_count: 1, dither: -0.5 },
+            LodBatch { level: 1, first_instance: 3, instance_count: 1, dither: 0.5 },
+            LodBatch { level: 0, first_instance: 4, instance_count: 2, dither: 0.0 },
+        ])));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::graphics::Extent;
+use crate::graphics::vulkan::resources::{Buffer, Image};
+
+/// Uncompressed RGBA8 texture data with four bytes per texel.
+#[derive(Debug, Clone)]
+pub struct TextureData {
+    pub data: Vec<u8>,
+    pub extent: Extent,
+}
+
+impl TextureData {
+
+    pub fn new(data: Vec<u8>, extent: Extent) -> TextureData {
+        debug_assert!(data.len() >= (extent.width * extent.height * extent.depth * 4) as usize,
+            "Expected at least {} bytes of texture data, but got {}", extent.width * extent.height * extent.depth * 4, data.len());
+        TextureData {
+            data,
+            extent,
+        }
+    }
+
+    /// Creates a 1x1 texture of the specified color.
+    pub fn solid(color: [u8; 4]) -> TextureData {
+        TextureData {
+            data: Vec::from(color),
+            extent: Extent::from(1, 1, 1),
+        }
+    }
+}
+
+/// How the fragments of a material are combined with what has been drawn before.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlendMode {
+    /// The fragments replace what is behind, the alpha of the base color is ignored.
+    Opaque,
+    /// Fragments whose alpha is below the cutoff are discarded, the others are opaque, e.g. for
+    /// foliage or fences.
+    Mask { cutoff: f32 },
+    /// The fragments are blended over what is behind by their alpha, e.g. for glass. Fragments
+    /// which are entirely transparent are discarded, so that they neither write their object id.
+    Blend,
+    /// The fragments' colors weighted by their alpha are added to what is behind, e.g. for
+    /// glows or decals lighting up a surface. Additive geometries do not write object ids.
+    Additive,
+}
+
+impl BlendMode {
+
+    /// Whether geometries of the mode are drawn after all opaque geometries, sorted from back to
+    /// front and without writing depth.
+    pub fn is_transparent(&self) -> bool {
+        matches!(self, BlendMode::Blend | BlendMode::Additive)
+    }
+
+    /// The alpha below which the fragment shader discards fragments.
+    pub(crate) fn alpha_cutoff(&self) -> f32 {
+        match self {
+            BlendMode::Opaque | BlendMode::Additive => 0.0,
+            BlendMode::Mask { cutoff } => *cutoff,
+            BlendMode::Blend => 1.0 / 255.0,
+        }
+    }
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Opaque
+    }
+}
+
+/// Describes a physically based material using the metallic-roughness workflow.
+///
+/// The parameters follow the semantic of glTF 2.0's `pbrMetallicRoughness` which is also what
+/// Blender's Principled BSDF exports. Every texture is optional, missing textures are replaced by
+/// a neutral 1x1 texture shared by all materials, so that only the corresponding factor takes
+/// effect:
+///
+/// | texture              | channels used                      | default     |
+/// |----------------------|------------------------------------|-------------|
+/// | `base_color`         | rgba (sRGB)                        | white       |
+/// | `metallic_roughness` | g: roughness, b: metallic (linear) | white       |
+/// | `normal`             | rgb: tangent space normal (linear) | (0.5, 0.5, 1.0) |
+/// | `occlusion`          | r (linear)                         | white       |
+/// | `emissive`           | rgb (sRGB)                         | white       |
+#[derive(Debug, Clone)]
+pub struct MaterialDescriptor {
+    pub base_color_factor: [f32; 4],
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub blend_mode: BlendMode,
+    pub base_color_texture: Option<TextureData>,
+    pub metallic_roughness_texture: Option<TextureData>,
+    pub normal_texture: Option<TextureData>,
+    pub occlusion_texture: Option<TextureData>,
+    pub emissive_texture: Option<TextureData>,
+}
+
+impl MaterialDescriptor {
+
+    pub const DEFAULT_BASE_COLOR: [u8; 4] = [255, 255, 255, 255];
+    pub const DEFAULT_METALLIC_ROUGHNESS: [u8; 4] = [255, 255, 255, 255];
+    pub const DEFAULT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+    pub const DEFAULT_OCCLUSION: [u8; 4] = [255, 255, 255, 255];
+    pub const DEFAULT_EMISSIVE: [u8; 4] = [255, 255, 255, 255];
+
+    /// Returns a descriptor with the defaults of Blender's Principled BSDF: a dielectric surface
+    /// (metallic 0.0) with a roughness of 0.5.
+    pub fn principled() -> MaterialDescriptor {
+        MaterialDescriptor {
+            metallic_factor: 0.0,
+            roughness_factor: 0.5,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for MaterialDescriptor {
+
+    /// Returns a descriptor with the defaults of the glTF 2.0 specification.
+    fn default() -> Self {
+        MaterialDescriptor {
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            emissive_factor: [0.0, 0.0, 0.0],
+            blend_mode: BlendMode::Opaque,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+        }
+    }
+}
+
+/// Material parameters bound to `set = 1, binding = 0`. The textures are referenced by their
+/// index into the texture table bound to `set = 2, binding = 0`. The layout matches the GLSL
+/// declaration:
+///
+/// ```glsl
+/// layout(set = 1, binding = 0) uniform MaterialUniformBufferObject {
+///     vec4 base_color_factor;
+///     vec4 emissive_factor;       // rgb: emissive factor, a: unused
+///     float metallic_factor;
+///     float roughness_factor;
+///     float normal_scale;
+///     float occlusion_strength;
+///     uint base_color_texture;
+///     uint metallic_roughness_texture;
+///     uint normal_texture;
+///     uint occlusion_texture;
+///     uint emissive_texture;
+///     float alpha_cutoff;         // fragments with a lower alpha are discarded
+/// } material;
+///
+/// layout(set = 2, binding = 0) uniform sampler2D textures[];
+/// ```
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct MaterialUniformBufferObject {
+    base_color_factor: [f32; 4],
+    emissive_factor: [f32; 4],
+    metallic_factor: f32,
+    roughness_factor: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    base_color_texture: u32,
+    metallic_roughness_texture: u32,
+    normal_texture: u32,
+    occlusion_texture: u32,
+    emissive_texture: u32,
+    alpha_cutoff: f32,
+}
+
+impl MaterialUniformBufferObject {
+
+    /// Creates the parameters of a material whose textures are located at the specified indices
+    /// of the texture table, in the order: base color, metallic-roughness, normal, occlusion and
+    /// emissive.
+    pub(crate) fn new(descriptor: &MaterialDescriptor, textures: &[u32; 5]) -> MaterialUniformBufferObject {
+        MaterialUniformBufferObject {
+            base_color_factor: descriptor.base_color_factor,
+            emissive_factor: [descriptor.emissive_factor[0], descriptor.emissive_factor[1], descriptor.emissive_factor[2], 0.0],
+            metallic_factor: descriptor.metallic_factor,
+            roughness_factor: descriptor.roughness_factor,
+            normal_scale: descriptor.normal_scale,
+            occlusion_strength: descriptor.occlusion_strength,
+            base_color_texture: textures[0],
+            metallic_roughness_texture: textures[1],
+            normal_texture: textures[2],
+            occlusion_texture: textures[3],
+            emissive_texture: textures[4],
+            alpha_cutoff: descriptor.blend_mode.alpha_cutoff(),
+        }
+    }
+}
+
+pub struct Texture {
+    pub buffer: Buffer<u8>,
+    pub image: Image,
+    pub image_view: ::ash::vk::ImageView,
+    /// The index of the texture in the engine's texture table.
+    pub index: u32,
+}
+
+/// A physically based material. Textures which are `None` are replaced by the engine's default
+/// textures, see [MaterialDescriptor].
+pub struct Material {
+    pub descriptor_set: ::ash::vk::DescriptorSet,
+    pub blend_mode: BlendMode,
+    pub(crate) parameters_buffer: Buffer<MaterialUniformBufferObject>,
+    pub base_color_texture: Option<Texture>,
+    pub metallic_roughness_texture: Option<Texture>,
+    pub normal_texture: Option<Texture>,
+    pub occlusion_texture: Option<Texture>,
+    pub emissive_texture: Option<Texture>,
+}
+
+impl Material {
+
+    /// Returns the textures owned by this material.
+    pub fn textures(&self) -> impl Iterator<Item=&Texture> {
+        [
+            &self.base_color_texture,
+            &self.metallic_roughness_texture,
+            &self.normal_texture,
+            &self.occlusion_texture,
+            &self.emissive_texture,
+        ].into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::material::{BlendMode, MaterialDescriptor, MaterialUniformBufferObject};
+
+    #[test]
+    fn test_material_uniform_buffer_object_has_std140_layout() {
+        assert_that!(offset_of!(MaterialUniformBufferObject, base_color_factor), is(equal_to(0)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, emissive_factor), is(equal_to(16)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, metallic_factor), is(equal_to(32)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, roughness_factor), is(equal_to(36)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, normal_scale), is(equal_to(40)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, occlusion_strength), is(equal_to(44)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, base_color_texture), is(equal_to(48)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, metallic_roughness_texture), is(equal_to(52)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, normal_texture), is(equal_to(56)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, occlusion_texture), is(equal_to(60)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, emissive_texture), is(equal_to(64)));
+        assert_that!(offset_of!(MaterialUniformBufferObject, alpha_cutoff), is(equal_to(68)));
+        assert_that!(std::mem::size_of::<MaterialUniformBufferObject>(), is(equal_to(80)));
+    }
+
+    #[test]
+    fn test_material_descriptor_defaults() {
+
+        let gltf = MaterialUniformBufferObject::new(&MaterialDescriptor::default(), &[0, 1, 2, 3, 4]);
+        assert_that!(gltf.metallic_factor, is(equal_to(1.0)));
+        assert_that!(gltf.roughness_factor, is(equal_to(1.0)));
+        assert_that!(gltf.emissive_factor, is(equal_to([0.0, 0.0, 0.0, 0.0])));
+
+        let principled = MaterialUniformBufferObject::new(&MaterialDescriptor::principled(), &[0, 1, 2, 3, 4]);
+        assert_that!(principled.metallic_factor, is(equal_to(0.0)));
+        assert_that!(principled.roughness_factor, is(equal_to(0.5)));
+        assert_that!(principled.normal_texture, is(equal_to(2)));
+    }
+
+    #[test]
+    fn test_alpha_cutoff_of_blend_modes() {
+
+        let cutoff = |blend_mode| MaterialUniformBufferObject::new(&MaterialDescriptor {
+            blend_mode,
+            ..MaterialDescriptor::default()
+        }, &[0, 1, 2, 3, 4]).alpha_cutoff;
+
+        assert_that!(cutoff(BlendMode::Opaque), is(equal_to(0.0)));
+        assert_that!(cutoff(BlendMode::Mask { cutoff: 0.5 }), is(equal_to(0.5)));
+        assert_that!(cutoff(BlendMode::Blend), is(greater_than(0.0)));
+        assert_that!(cutoff(BlendMode::Additive), is(equal_to(0.0)));
+        assert_that!(BlendMode::Mask { cutoff: 0.5 }.is_transparent(), is(false));
+        assert_that!(BlendMode::Blend.is_transparent(), is(true));
+    }
+}
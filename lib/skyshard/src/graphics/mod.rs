@@ -1,10 +1,52 @@
 pub mod vulkan;
 
 mod camera;
+mod capture;
+mod culling;
+mod debug;
+mod environment;
+mod ibl;
+mod light;
+mod lod;
+mod material;
+mod overlay;
+mod post;
+mod selection;
+mod shadow;
+mod skin;
+mod transparency;
+mod vertex;
 
-use crate::engine::{InstanceData, Vertex};
+use crate::engine::InstanceData;
 pub use crate::graphics::camera::{Camera, Projection};
-use crate::graphics::vulkan::resources::{Buffer, Image};
+pub use crate::graphics::capture::{CaptureError, CapturedFrame};
+pub(crate) use crate::graphics::capture::{ChannelOrder, FrameSequence};
+pub use crate::graphics::culling::{BoundingBox, Frustum};
+pub use crate::graphics::debug::{DebugLines, DebugVertex, MAX_DEBUG_VERTICES};
+pub use crate::graphics::environment::{CubeFace, CubeMapData, EnvironmentError, HdrImage, Skybox};
+pub use crate::graphics::ibl::{BrdfLut, EnvironmentLighting, EnvironmentLightingData, EnvironmentLightingSettings, EnvironmentLightingShaders};
+pub(crate) use crate::graphics::ibl::EnvironmentLightingPushConstants;
+pub(crate) use crate::graphics::culling::partition_visible_instances;
+pub use crate::graphics::lod::{LevelOfDetail, LodBatch, LodMetric, LodSelection};
+pub(crate) use crate::graphics::lod::select_lods;
+pub use crate::graphics::light::{DirectionalLight, Light, MAX_LIGHTS, PointLight, SpotLight};
+pub(crate) use crate::graphics::light::LightsUniformBufferObject;
+pub use crate::graphics::material::{BlendMode, Material, MaterialDescriptor, Texture, TextureData};
+pub(crate) use crate::graphics::material::MaterialUniformBufferObject;
+pub use crate::graphics::overlay::{MAX_OVERLAY_VERTICES, Overlay, OverlayBatch, OverlayTexture, OverlayVertex};
+pub(crate) use crate::graphics::overlay::{FontAtlas, OverlayPushConstants};
+pub use crate::graphics::post::{BloomSettings, PostProcessingSettings, Tonemapping, VignetteSettings};
+pub(crate) use crate::graphics::post::{PostEffect, PostPass, PostPushConstants};
+pub use crate::graphics::selection::{MAX_SELECTED_OBJECTS, OutlineSettings};
+pub(crate) use crate::graphics::selection::SelectionUniformBufferObject;
+pub use crate::graphics::shadow::{MAX_SHADOW_CASTERS, SHADOW_CASCADES, SHADOW_MAP_RESOLUTION, ShadowSettings};
+pub(crate) use crate::graphics::shadow::{cascade_splits, shadow_casters, ShadowCascade, ShadowsUniformBufferObject};
+pub use crate::graphics::skin::Skin;
+pub(crate) use crate::graphics::transparency::{sort_back_to_front, transparent_draw_order};
+pub use crate::graphics::vertex::{HasVertexLayout, MAX_TEX_COORD_SET, VertexAttribute, VertexFormat, VertexLayout, VertexLayoutError, VertexSemantic};
+pub(crate) use crate::graphics::vertex::vertex_bytes;
+use crate::graphics::vulkan::resources::{Buffer, Resource};
+use crate::graphics::vulkan::VulkanObject;
 
 pub struct Renderer {
 
@@ -56,15 +98,80 @@ impl Default for Extent {
 }
 
 pub struct Geometry {
-    pub index_buffer: Buffer<u32>,
-    pub vertex_buffer: Buffer<Vertex>,
+    pub index_buffer: IndexBuffer,
+    /// The vertices laid out as described by `vertex_layout`.
+    pub vertex_buffer: Buffer<u8>,
+    pub vertex_layout: VertexLayout,
     pub instances_buffer: Buffer<InstanceData>,
+    /// The instances in the order of the instances buffer.
+    pub instances: Vec<InstanceData>,
+    /// The number of instances at the front of the instances buffer which intersected the
+    /// camera's frustum in the last frame.
+    pub visible_instances: usize,
     pub material: Material,
+    /// Whether the geometry is rendered into the shadow maps. The shadow pass has no fragment
+    /// stage, so geometries with `BlendMode::Mask` cast solid shadows through their cut-out
+    /// regions.
+    pub cast_shadows: bool,
+    /// Whether the geometry is darkened by the shadows of other geometries.
+    pub receive_shadows: bool,
+    /// Whether instances outside of the camera's frustum are skipped by the main pass.
+    pub frustum_culling: bool,
+    /// Coarser meshes of the geometry, ordered by ascending threshold.
+    pub lods: Vec<LevelOfDetail>,
+    pub lod_selection: LodSelection,
+    /// The ranges of the instances buffer drawn by the main pass in the last frame.
+    pub lod_batches: Vec<LodBatch>,
+    /// Bounding box of the vertices in model space.
+    pub bounding_box: BoundingBox,
+    /// Bounding sphere of the vertices in model space: xyz center, w radius.
+    pub bounding_sphere: [f32; 4],
+    /// Buffers used to cull and draw the geometry on the GPU, `None` if the engine has been
+    /// created without a culling shader. Transparent geometries and geometries with levels of
+    /// detail are culled on the CPU.
+    pub indirect_draw: Option<IndirectDraw>,
+    /// The skeleton deforming the vertices, geometries with skinned vertices are not drawn without.
+    pub skin: Option<Skin>,
 }
 
-pub struct Material {
+/// An index buffer with either 16 or 32 bits per index.
+pub enum IndexBuffer {
+    U16(Buffer<u16>),
+    U32(Buffer<u32>),
+}
+
+impl IndexBuffer {
+
+    pub fn handle(&self) -> &::ash::vk::Buffer {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.handle(),
+            IndexBuffer::U32(buffer) => buffer.handle(),
+        }
+    }
+
+    /// Returns the number of indices.
+    pub fn capacity(&self) -> usize {
+        match self {
+            IndexBuffer::U16(buffer) => buffer.capacity(),
+            IndexBuffer::U32(buffer) => buffer.capacity(),
+        }
+    }
+
+    pub fn index_type(&self) -> ::ash::vk::IndexType {
+        match self {
+            IndexBuffer::U16(_) => ::ash::vk::IndexType::UINT16,
+            IndexBuffer::U32(_) => ::ash::vk::IndexType::UINT32,
+        }
+    }
+}
+
+/// The buffers of a geometry written by the culling shader and consumed by
+/// `cmd_draw_indexed_indirect_count`.
+pub struct IndirectDraw {
+    /// The instances passing the frustum test, compacted to the front of the buffer.
+    pub visible_instances_buffer: Buffer<InstanceData>,
+    pub draw_command_buffer: Buffer<::ash::vk::DrawIndexedIndirectCommand>,
+    /// 1 if at least one instance is visible, otherwise 0.
+    pub draw_count_buffer: Buffer<u32>,
     pub descriptor_set: ::ash::vk::DescriptorSet,
-    pub texture_buffer: Buffer<u8>,
-    pub texture_image: Image,
-    pub texture_image_view: ::ash::vk::ImageView,
 }
@@ -0,0 +1,412 @@
+use crate::graphics::{Extent, HasVertexLayout, TextureData, VertexAttribute, VertexFormat, VertexLayout, VertexSemantic};
+use crate::graphics::vulkan::resources::Image;
+
+/// The maximum number of overlay vertices, i.e. six per quad, drawn per frame. Additional
+/// vertices are ignored.
+pub const MAX_OVERLAY_VERTICES: usize = 65536;
+
+/// The rows of the printable ASCII characters from `' '` to `'~'` followed by a filled block, one
+/// byte per row with the least significant bit being the leftmost pixel.
+const BUILTIN_GLYPHS: [[u8; 8]; 96] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // '#'
+    [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // '$'
+    [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // '%'
+    [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // '&'
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '''
+    [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // '('
+    [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // ')'
+    [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // '*'
+    [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ','
+    [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // '.'
+    [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // '/'
+    [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // '0'
+    [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // '1'
+    [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // '2'
+    [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // '3'
+    [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // '4'
+    [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // '5'
+    [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // '6'
+    [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // '7'
+    [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // '8'
+    [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // '9'
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // ':'
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ';'
+    [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // '<'
+    [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // '='
+    [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // '>'
+    [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // '?'
+    [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // '@'
+    [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // 'A'
+    [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // 'B'
+    [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // 'C'
+    [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // 'D'
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // 'E'
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // 'F'
+    [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // 'G'
+    [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // 'H'
+    [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'I'
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // 'J'
+    [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // 'K'
+    [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // 'L'
+    [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // 'M'
+    [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // 'N'
+    [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // 'O'
+    [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // 'P'
+    [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // 'Q'
+    [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // 'R'
+    [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // 'S'
+    [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'T'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // 'U'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'V'
+    [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // 'W'
+    [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // 'X'
+    [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // 'Y'
+    [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // 'Z'
+    [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // '['
+    [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // '\'
+    [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ']'
+    [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // '_'
+    [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // 'a'
+    [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // 'b'
+    [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // 'c'
+    [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // 'd'
+    [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // 'e'
+    [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // 'f'
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'g'
+    [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // 'h'
+    [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'i'
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // 'j'
+    [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // 'k'
+    [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'l'
+    [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // 'm'
+    [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+    [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // 'o'
+    [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // 'p'
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // 'q'
+    [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // 'r'
+    [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // 's'
+    [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // 'u'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'v'
+    [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // 'w'
+    [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // 'x'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'y'
+    [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // 'z'
+    [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // '{'
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+    [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // '}'
+    [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+    [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF], // filled block
+];
+
+/// A bitmap font whose glyphs are arranged in a grid, row by row in the order of their
+/// characters. The glyphs are white, their coverage is stored in the alpha channel.
+#[derive(Debug, Clone)]
+pub(crate) struct FontAtlas {
+    pub texture: TextureData,
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+    /// The character of the first glyph.
+    pub first: char,
+    /// The number of glyphs, the last one is an opaque block used to fill rectangles.
+    pub count: u32,
+}
+
+impl FontAtlas {
+
+    /// The 8x8 pixel font covering the printable ASCII characters.
+    pub(crate) fn builtin() -> FontAtlas {
+
+        const COLUMNS: usize = 16;
+        let rows = BUILTIN_GLYPHS.len() / COLUMNS;
+        let width = COLUMNS * 8;
+
+        let mut data = vec![0u8; width * rows * 8 * 4];
+
+        BUILTIN_GLYPHS.iter().enumerate().for_each(|(index, glyph)| {
+            let (left, top) = ((index % COLUMNS) * 8, (index / COLUMNS) * 8);
+            glyph.iter().enumerate().for_each(|(y, row)| {
+                (0..8).filter(|x| row & (1 << x) != 0).for_each(|x| {
+                    let offset = ((top + y) * width + left + x) * 4;
+                    data[offset..offset + 4].copy_from_slice(&[255; 4]);
+                });
+            });
+        });
+
+        FontAtlas {
+            texture: TextureData::new(data, Extent::from(width as u32, (rows * 8) as u32, 1)),
+            glyph_width: 8,
+            glyph_height: 8,
+            first: ' ',
+            count: BUILTIN_GLYPHS.len() as u32,
+        }
+    }
+}
+
+/// A corner of an overlay triangle. Positions are given in pixels from the top left corner of
+/// the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct OverlayVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl HasVertexLayout for OverlayVertex {
+    fn vertex_layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<OverlayVertex>() as u32, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float2, offset: offset_of!(OverlayVertex, position) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Color, format: VertexFormat::Float4, offset: offset_of!(OverlayVertex, color) as u32 },
+            VertexAttribute { semantic: VertexSemantic::TexCoord(0), format: VertexFormat::Float2, offset: offset_of!(OverlayVertex, uv) as u32 },
+        ])
+    }
+}
+
+/// Consecutive vertices of the overlay sampling the same texture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OverlayBatch {
+    /// The index of the texture in the engine's texture table.
+    pub texture: u32,
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+}
+
+/// A texture sampled by overlay triangles, e.g. the font atlas of an immediate-mode UI library.
+pub struct OverlayTexture {
+    pub image: Image,
+    pub image_view: ::ash::vk::ImageView,
+    /// The index of the texture in the engine's texture table.
+    pub index: u32,
+}
+
+/// Textured triangles in screen space drawn over the next frame after the post-processing, e.g.
+/// text and statistics. Like the debug lines, the overlay is collected anew every frame and
+/// cleared by the engine once it has been rendered. Colors are linear RGBA, multiplied with the
+/// texture and blended by alpha.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    glyph_size: [f32; 2],
+    atlas_size: [f32; 2],
+    atlas_columns: u32,
+    first: u32,
+    count: u32,
+    font_texture: u32,
+    vertices: Vec<OverlayVertex>,
+    batches: Vec<OverlayBatch>,
+}
+
+impl Overlay {
+
+    pub(crate) fn new(font: &FontAtlas, font_texture: u32) -> Overlay {
+        Overlay {
+            glyph_size: [font.glyph_width as f32, font.glyph_height as f32],
+            atlas_size: [font.texture.extent.width as f32, font.texture.extent.height as f32],
+            atlas_columns: font.texture.extent.width / font.glyph_width,
+            first: font.first as u32,
+            count: font.count,
+            font_texture,
+            vertices: Vec::new(),
+            batches: Vec::new(),
+        }
+    }
+
+    /// Draws the text with its top left corner at the position. Glyphs are `8 * scale` pixels
+    /// in size, lines are separated by `'\n'` and characters missing in the font are drawn as
+    /// `'?'`.
+    pub fn text(&mut self, position: [f32; 2], text: &str, scale: f32, color: [f32; 4]) -> &mut Overlay {
+
+        let [width, height] = self.glyph_size.map(|size| size * scale);
+
+        text.lines().enumerate().for_each(|(line, characters)| {
+            characters.chars().enumerate().for_each(|(column, character)| {
+                if character != ' ' {
+                    let glyph = self.glyph(character);
+                    let origin = [position[0] + column as f32 * width, position[1] + line as f32 * height];
+                    self.quad(origin, [width, height], self.glyph_uvs(glyph), color);
+                }
+            });
+        });
+
+        self
+    }
+
+    /// Returns the width and height of the text in pixels when drawn with the scale.
+    pub fn text_size(&self, text: &str, scale: f32) -> [f32; 2] {
+        let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+        let rows = text.lines().count();
+        [columns as f32 * self.glyph_size[0] * scale, rows as f32 * self.glyph_size[1] * scale]
+    }
+
+    /// Fills a rectangle with its top left corner at the position.
+    pub fn rect(&mut self, position: [f32; 2], size: [f32; 2], color: [f32; 4]) -> &mut Overlay {
+
+        // the center of the opaque block, so that no neighbouring glyph bleeds in
+        let [u, v] = self.glyph_uvs(self.count - 1);
+        let center = [(u[0] + v[0]) / 2.0, (u[1] + v[1]) / 2.0];
+
+        self.quad(position, size, [center, center], color);
+        self
+    }
+
+    /// Appends triangles sampling a texture of the texture table, three vertices per triangle.
+    /// This allows UI libraries to draw their meshes with their own textures.
+    pub fn triangles(&mut self, texture: u32, vertices: &[OverlayVertex]) -> &mut Overlay {
+
+        let first_vertex = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(vertices);
+
+        match self.batches.last_mut() {
+            Some(batch) if batch.texture == texture => {
+                batch.vertex_count += vertices.len() as u32;
+            }
+            _ => self.batches.push(OverlayBatch {
+                texture,
+                first_vertex,
+                vertex_count: vertices.len() as u32,
+            }),
+        }
+
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.batches.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[OverlayVertex] {
+        &self.vertices
+    }
+
+    pub fn batches(&self) -> &[OverlayBatch] {
+        &self.batches
+    }
+
+    /// Returns the index of the character's glyph, falling back to `'?'`.
+    fn glyph(&self, character: char) -> u32 {
+        let glyph = |character: char| (character as u32).checked_sub(self.first).filter(|index| *index < self.count - 1);
+        glyph(character)
+            .or_else(|| glyph('?'))
+            .unwrap_or(0)
+    }
+
+    /// Returns the top left and the bottom right texture coordinates of the glyph.
+    fn glyph_uvs(&self, glyph: u32) -> [[f32; 2]; 2] {
+        let left = (glyph % self.atlas_columns) as f32 * self.glyph_size[0] / self.atlas_size[0];
+        let top = (glyph / self.atlas_columns) as f32 * self.glyph_size[1] / self.atlas_size[1];
+        [[left, top], [left + self.glyph_size[0] / self.atlas_size[0], top + self.glyph_size[1] / self.atlas_size[1]]]
+    }
+
+    fn quad(&mut self, position: [f32; 2], size: [f32; 2], uvs: [[f32; 2]; 2], color: [f32; 4]) {
+
+        let [left, top] = position;
+        let [right, bottom] = [left + size[0], top + size[1]];
+        let [[u0, v0], [u1, v1]] = uvs;
+
+        let vertex = |position, uv| OverlayVertex { position, uv, color };
+
+        self.triangles(self.font_texture, &[
+            vertex([left, top], [u0, v0]),
+            vertex([left, bottom], [u0, v1]),
+            vertex([right, bottom], [u1, v1]),
+            vertex([left, top], [u0, v0]),
+            vertex([right, bottom], [u1, v1]),
+            vertex([right, top], [u1, v0]),
+        ]);
+    }
+}
+
+/// The push constants of an overlay batch.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(crate) struct OverlayPushConstants {
+    /// The size of the window in pixels.
+    pub screen_size: [f32; 2],
+    /// The index of the batch's texture in the texture table.
+    pub texture: u32,
+    /// Whether the swapchain encodes the colors to sRGB, otherwise the shader does.
+    pub srgb_output: u32,
+}
+
+impl OverlayPushConstants {
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::overlay::{FontAtlas, Overlay, OverlayBatch, OverlayPushConstants, OverlayVertex};
+
+    const WHITE: [f32; 4] = [1.0; 4];
+
+    #[test]
+    fn test_builtin_font_atlas_layout() {
+
+        let font = FontAtlas::builtin();
+        let texel = |x: usize, y: usize| font.texture.data[(y * font.texture.extent.width as usize + x) * 4 + 3];
+
+        assert_that!(font.texture.extent.width, is(equal_to(128)));
+        assert_that!(font.texture.extent.height, is(equal_to(48)));
+        // the top row of '1' (0x0C) starts with the third pixel, '1' is the 18th glyph
+        assert_that!(texel(8 + 1, 8), is(equal_to(0)));
+        assert_that!(texel(8 + 2, 8), is(equal_to(255)));
+        // the filled block in the bottom right corner
+        assert_that!(texel(127, 47), is(equal_to(255)));
+    }
+
+    #[test]
+    fn test_push_constants_layout() {
+        assert_that!(offset_of!(OverlayPushConstants, screen_size), is(equal_to(0)));
+        assert_that!(offset_of!(OverlayPushConstants, texture), is(equal_to(8)));
+        assert_that!(offset_of!(OverlayPushConstants, srgb_output), is(equal_to(12)));
+        assert_that!(std::mem::size_of::<OverlayPushConstants>(), is(equal_to(16)));
+    }
+
+    #[test]
+    fn test_text_emits_a_quad_per_visible_character() {
+
+        let mut overlay = Overlay::new(&FontAtlas::builtin(), 7);
+
+        overlay.text([10.0, 20.0], "a b\nc", 2.0, WHITE);
+
+        assert_that!(overlay.vertices().len(), is(equal_to(3 * 6)));
+        assert_that!(overlay.vertices()[6].position, is(equal_to([10.0 + 2.0 * 16.0, 20.0])));
+        assert_that!(overlay.vertices()[12].position, is(equal_to([10.0, 20.0 + 16.0])));
+        assert_that!(overlay.text_size("a b\nc", 2.0), is(equal_to([48.0, 32.0])));
+        assert_that!(overlay.batches(), is(equal_to(&[OverlayBatch { texture: 7, first_vertex: 0, vertex_count: 18 }][..])));
+    }
+
+    #[test]
+    fn test_triangles_of_another_texture_start_a_batch() {
+
+        let vertex = OverlayVertex { position: [0.0; 2], uv: [0.0; 2], color: WHITE };
+        let mut overlay = Overlay::new(&FontAtlas::builtin(), 0);
+
+        overlay.rect([0.0, 0.0], [4.0, 4.0], WHITE)
+            .triangles(3, &[vertex; 3])
+            .triangles(3, &[vertex; 3])
+            .text([0.0, 0.0], "\u{e9}", 1.0, WHITE);
+
+        assert_that!(overlay.batches().iter().map(|batch| (batch.texture, batch.vertex_count)).collect::<Vec<_>>(), is(equal_to(vec![(0, 6), (3, 6), (0, 6)])));
+        // the missing character is replaced by '?' in the first row
+        assert_that!(overlay.vertices()[12].uv, is(equal_to([15.0 * 8.0 / 128.0, 8.0 / 48.0])));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::graphics::selection::OutlineSettings;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// The fit of the ACES reference rendering transform by Krzysztof Narkowicz.
+    Aces,
+    /// John Hable's filmic curve, as used by Uncharted 2.
+    Filmic,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BloomSettings {
+    /// The brightness above which colors bleed into their neighborhood.
+    pub threshold: f32,
+    /// Factor of the blurred bright colors added to the scene.
+    pub intensity: f32,
+    /// Distance between the samples of the blur kernel in texels.
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            threshold: 1.0,
+            intensity: 0.5,
+            radius: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VignetteSettings {
+    /// How much the corners are darkened, from 0.0 (not at all) to 1.0 (black).
+    pub intensity: f32,
+    /// Distance from the center, relative to the corners, at which the darkening begins.
+    pub radius: f32,
+    /// Distance over which the darkening fades in.
+    pub softness: f32,
+}
+
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        VignetteSettings {
+            intensity: 0.4,
+            radius: 0.6,
+            softness: 0.5,
+        }
+    }
+}
+
+/// The fullscreen passes applied to the HDR target before it is presented, in the order bloom,
+/// exposure, tonemapping, gamma, outline, FXAA and vignette. Every pass is skipped, if it is
+/// disabled.
+#[derive(Debug, Copy, Clone)]
+pub struct PostProcessingSettings {
+    pub bloom: Option<BloomSettings>,
+    /// Exposure compensation in stops, the colors are scaled by `2^exposure`.
+    pub exposure: Option<f32>,
+    pub tonemapping: Option<Tonemapping>,
+    /// The colors are raised to `1 / gamma`. The pass is skipped if the swapchain's format is
+    /// sRGB, which encodes the colors already.
+    pub gamma: Option<f32>,
+    /// The outline of the engine's selected objects. The pass is skipped if nothing is selected.
+    pub outline: Option<OutlineSettings>,
+    pub fxaa: bool,
+    pub vignette: Option<VignetteSettings>,
+}
+
+impl Default for PostProcessingSettings {
+    fn default() -> Self {
+        PostProcessingSettings {
+            bloom: None,
+            exposure: None,
+            tonemapping: Some(Tonemapping::Aces),
+            gamma: Some(2.2),
+            outline: Some(OutlineSettings::default()),
+            fxaa: true,
+            vignette: None,
+        }
+    }
+}
+
+/// The effect of a pass, matching the constants of the post-processing fragment shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub(crate) enum PostEffect {
+    Copy = 0,
+    Bloom = 1,
+    Exposure = 2,
+    Aces = 3,
+    Filmic = 4,
+    Gamma = 5,
+    Fxaa = 6,
+    Vignette = 7,
+    Outline = 8,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct PostPass {
+    pub effect: PostEffect,
+    pub parameters: [f32; 4],
+}
+
+impl PostProcessingSettings {
+
+    /// Returns the enabled passes in the order they are applied.
+    pub(crate) fn passes(&self, srgb_output: bool, selection: bool) -> Vec<PostPass> {
+
+        let pass = |effect, parameters| PostPass { effect, parameters };
+
+        let bloom = self.bloom.map(|bloom| {
+            pass(PostEffect::Bloom, [bloom.threshold, bloom.intensity, bloom.radius, 0.0])
+        });
+        let exposure = self.exposure.map(|exposure| {
+            pass(PostEffect::Exposure, [2f32.powf(exposure), 0.0, 0.0, 0.0])
+        });
+        let tonemapping = self.tonemapping.map(|tonemapping| match tonemapping {
+            Tonemapping::Aces => pass(PostEffect::Aces, [0.0; 4]),
+            Tonemapping::Filmic => pass(PostEffect::Filmic, [0.0; 4]),
+        });
+        let gamma = self.gamma.filter(|_| !srgb_output).map(|gamma| {
+            pass(PostEffect::Gamma, [1.0 / gamma, 0.0, 0.0, 0.0])
+        });
+        // the outline is read from the selection's uniform buffer
+        let outline = self.outline.filter(|_| selection).map(|_| pass(PostEffect::Outline, [0.0; 4]));
+        let fxaa = self.fxaa.then(|| pass(PostEffect::Fxaa, [0.0; 4]));
+        let vignette = self.vignette.map(|vignette| {
+            pass(PostEffect::Vignette, [vignette.intensity, vignette.radius, vignette.softness, 0.0])
+        });
+
+        [bloom, exposure, tonemapping, gamma, outline, fxaa, vignette].into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// The push constants of a post-processing pass.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(crate) struct PostPushConstants {
+    pub parameters: [f32; 4],
+    /// The size of a texel of the input in texture coordinates.
+    pub texel_size: [f32; 2],
+    pub effect: u32,
+}
+
+impl PostPushConstants {
+
+    pub fn new(pass: &PostPass, width: u32, height: u32) -> PostPushConstants {
+        PostPushConstants {
+            parameters: pass.parameters,
+            texel_size: [1.0 / width as f32, 1.0 / height as f32],
+            effect: pass.effect as u32,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::post::{PostEffect, PostProcessingSettings, PostPushConstants, Tonemapping, VignetteSettings};
+
+    fn effects(settings: &PostProcessingSettings, srgb_output: bool, selection: bool) -> Vec<PostEffect> {
+        settings.passes(srgb_output, selection).iter().map(|pass| pass.effect).collect()
+    }
+
+    #[test]
+    fn test_passes_are_ordered_and_skipped_if_disabled() {
+
+        let settings = PostProcessingSettings {
+            exposure: Some(1.0),
+            tonemapping: Some(Tonemapping::Filmic),
+            vignette: Some(VignetteSettings::default()),
+            fxaa: false,
+            ..PostProcessingSettings::default()
+        };
+
+        assert_that!(effects(&settings, false, false), is(equal_to(vec![PostEffect::Exposure, PostEffect::Filmic, PostEffect::Gamma, PostEffect::Vignette])));
+        assert_that!(settings.passes(false, false)[0].parameters[0], is(equal_to(2.0)));
+    }
+
+    #[test]
+    fn test_gamma_is_skipped_for_srgb_output() {
+        assert_that!(effects(&PostProcessingSettings::default(), true, false), is(equal_to(vec![PostEffect::Aces, PostEffect::Fxaa])));
+    }
+
+    #[test]
+    fn test_outline_is_drawn_before_fxaa_if_objects_are_selected() {
+        assert_that!(effects(&PostProcessingSettings::default(), false, true), is(equal_to(vec![PostEffect::Aces, PostEffect::Gamma, PostEffect::Outline, PostEffect::Fxaa])));
+    }
+
+    #[test]
+    fn test_push_constants_layout() {
+        assert_that!(offset_of!(PostPushConstants, parameters), is(equal_to(0)));
+        assert_that!(offset_of!(PostPushConstants, texel_size), is(equal_to(16)));
+        assert_that!(offset_of!(PostPushConstants, effect), is(equal_to(24)));
+        assert_that!(std::mem::size_of::<PostPushConstants>(), is(equal_to(28)));
+    }
+}
@@ -0,0 +1,95 @@
+/// The maximum number of objects which are outlined at once. Additional ids of a selection are
+/// ignored.
+pub const MAX_SELECTED_OBJECTS: usize = 64;
+
+/// The appearance of the selected objects, drawn by a post-processing pass after tonemapping.
+#[derive(Debug, Copy, Clone)]
+pub struct OutlineSettings {
+    pub color: [f32; 3],
+    /// Width of the outline in pixels.
+    pub width: f32,
+    /// Opacity of the color blended over the selected objects, 0.0 draws the outline only.
+    pub highlight: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        OutlineSettings {
+            color: [1.0, 0.6, 0.1],
+            width: 2.0,
+            highlight: 0.15,
+        }
+    }
+}
+
+/// The selection as read by the post-processing fragment shader:
+///
+/// ```glsl
+/// layout(set = 1, binding = 1) uniform SelectionUniformBufferObject {
+///     vec4 color;     // rgb: outline color, a: highlight opacity
+///     float width;    // outline width in pixels
+///     uint count;     // number of valid ids
+///     uvec4 ids[16];  // see MAX_SELECTED_OBJECTS, packed four per element
+/// } selection;
+/// ```
+#[repr(C, align(256))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct SelectionUniformBufferObject {
+    color: [f32; 4],
+    width: f32,
+    count: u32,
+    _padding: [u32; 2],
+    ids: [[u32; 4]; MAX_SELECTED_OBJECTS / 4],
+}
+
+impl SelectionUniformBufferObject {
+
+    pub(crate) fn new(settings: &OutlineSettings, ids: &[u32]) -> SelectionUniformBufferObject {
+
+        let mut result = SelectionUniformBufferObject {
+            color: [settings.color[0], settings.color[1], settings.color[2], settings.highlight],
+            width: settings.width,
+            count: ids.len().min(MAX_SELECTED_OBJECTS) as u32,
+            _padding: [0; 2],
+            ids: [[0; 4]; MAX_SELECTED_OBJECTS / 4],
+        };
+
+        ids.iter()
+            .take(MAX_SELECTED_OBJECTS)
+            .enumerate()
+            .for_each(|(index, id)| {
+                result.ids[index / 4][index % 4] = *id;
+            });
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::selection::{MAX_SELECTED_OBJECTS, OutlineSettings, SelectionUniformBufferObject};
+
+    #[test]
+    fn test_selection_uniform_buffer_object_has_std140_layout() {
+        assert_that!(offset_of!(SelectionUniformBufferObject, color), is(equal_to(0)));
+        assert_that!(offset_of!(SelectionUniformBufferObject, width), is(equal_to(16)));
+        assert_that!(offset_of!(SelectionUniformBufferObject, count), is(equal_to(20)));
+        assert_that!(offset_of!(SelectionUniformBufferObject, ids), is(equal_to(32)));
+        assert_that!(std::mem::size_of::<SelectionUniformBufferObject>() % 256, is(equal_to(0)));
+    }
+
+    #[test]
+    fn test_selection_uniform_buffer_object_packs_and_truncates_ids() {
+
+        let ids = (1..=MAX_SELECTED_OBJECTS as u32 + 4).collect::<Vec<_>>();
+
+        let ubo = SelectionUniformBufferObject::new(&OutlineSettings::default(), &ids);
+
+        assert_that!(ubo.count, is(equal_to(MAX_SELECTED_OBJECTS as u32)));
+        assert_that!(ubo.ids[0], is(equal_to([1, 2, 3, 4])));
+        assert_that!(ubo.ids[1][1], is(equal_to(6)));
+        assert_that!(ubo.ids[MAX_SELECTED_OBJECTS / 4 - 1][3], is(equal_to(MAX_SELECTED_OBJECTS as u32)));
+    }
+}
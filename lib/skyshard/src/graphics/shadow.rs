@@ -0,0 +1,300 @@
+use nalgebra::{Matrix4, RowVector4, Vector3};
+
+use crate::graphics::{Camera, DirectionalLight, Light, MAX_LIGHTS, Projection};
+
+/// Number of cascades the view frustum is split into for every shadow casting light.
+pub const SHADOW_CASCADES: usize = 4;
+
+/// The maximum number of directional lights casting shadows. Further lights with `cast_shadows`
+/// set are rendered without shadows.
+pub const MAX_SHADOW_CASTERS: usize = 2;
+
+/// Resolution of a single cascade in texels. The cascades of a light are packed into a 2x2 atlas.
+pub const SHADOW_MAP_RESOLUTION: u32 = 1024;
+
+#[derive(Debug, Copy, Clone)]
+pub struct ShadowSettings {
+    /// Distance from the camera up to which shadows are rendered. Geometry between a cascade
+    /// and the light is captured up to the same distance.
+    pub distance: f32,
+    /// Blends between uniform (0.0) and logarithmic (1.0) distribution of the cascade splits.
+    pub split_lambda: f32,
+    /// Depth offset subtracted before the depth comparison to avoid shadow acne.
+    pub depth_bias: f32,
+    /// Distance between the samples of the 3x3 PCF kernel in texels.
+    pub filter_radius: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            distance: 50.0,
+            split_lambda: 0.75,
+            depth_bias: 0.0005,
+            filter_radius: 1.0,
+        }
+    }
+}
+
+/// Returns the lights casting shadows together with their index in the light buffer. The order
+/// of the result is the index of the light's shadow map.
+pub(crate) fn shadow_casters(lights: &[Light]) -> impl Iterator<Item=(usize, &DirectionalLight)> {
+    lights.iter()
+        .take(MAX_LIGHTS)
+        .enumerate()
+        .filter_map(|(index, light)| match light {
+            Light::Directional(light) if light.cast_shadows => Some((index, light)),
+            _ => None,
+        })
+        .take(MAX_SHADOW_CASTERS)
+}
+
+/// Computes the far distance of every cascade using the practical split scheme, which blends a
+/// logarithmic and a uniform distribution of the range between `near` and `far`.
+pub(crate) fn cascade_splits(near: f32, far: f32, lambda: f32) -> [f32; SHADOW_CASCADES] {
+    let mut result = [far; SHADOW_CASCADES];
+    result.iter_mut().enumerate().for_each(|(index, split)| {
+        let p = (index + 1) as f32 / SHADOW_CASCADES as f32;
+        let logarithmic = near * (far / near).powf(p);
+        let uniform = near + (far - near) * p;
+        *split = lambda * logarithmic + (1.0 - lambda) * uniform;
+    });
+    result
+}
+
+/// The light space transformation of a single cascade.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ShadowCascade {
+    pub(crate) view: Matrix4<f32>,
+    pub(crate) projection: Matrix4<f32>,
+}
+
+impl ShadowCascade {
+
+    /// Computes a cascade per split fitting the camera's frustum between the previous split
+    /// (respectively the near plane) and the split.
+    pub(crate) fn cascades(camera: &Camera, splits: &[f32; SHADOW_CASCADES], direction: &Vector3<f32>, margin: f32) -> [ShadowCascade; SHADOW_CASCADES] {
+        let inverse_view_projection = camera.as_matrix().try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        let mut from = camera.near();
+        splits.map(|to| {
+            let cascade = ShadowCascade::new(&inverse_view_projection, camera.near(), camera.far(), from, to, direction, margin);
+            from = to;
+            cascade
+        })
+    }
+
+    /// Fits an orthographic projection along `direction` around the bounding sphere of the
+    /// frustum slice between the view distances `from` and `to`. Using a sphere keeps the size of
+    /// the projection constant while the camera rotates, and snapping its origin to whole texels
+    /// avoids shimmering edges while the camera moves.
+    fn new(inverse_view_projection: &Matrix4<f32>, near: f32, far: f32, from: f32, to: f32, direction: &Vector3<f32>, margin: f32) -> ShadowCascade {
+
+        let corners = frustum_slice_corners(inverse_view_projection, near, far, from, to);
+
+        let center = corners.iter().sum::<Vector3<f32>>() / corners.len() as f32;
+        let radius = corners.iter()
+            .map(|corner| (corner - center).norm())
+            .fold(0.0f32, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let w: Vector3<f32> = direction.normalize();
+        let up = if w.y.abs() > 0.99 { Vector3::x() } else { Vector3::y() };
+        let u: Vector3<f32> = w.cross(&up).normalize();
+        let v: Vector3<f32> = w.cross(&u);
+        let eye: Vector3<f32> = center - w * (radius + margin);
+
+        let mut view = Matrix4::<f32>::identity();
+        view[(0, 0)] = u.x;
+        view[(1, 0)] = u.y;
+        view[(2, 0)] = u.z;
+        view[(0, 1)] = v.x;
+        view[(1, 1)] = v.y;
+        view[(2, 1)] = v.z;
+        view[(0, 2)] = w.x;
+        view[(1, 2)] = w.y;
+        view[(2, 2)] = w.z;
+        view[(3, 0)] = -u.dot(&eye);
+        view[(3, 1)] = -v.dot(&eye);
+        view[(3, 2)] = -w.dot(&eye);
+
+        let mut projection = Projection::OrthographicProjection {
+            left: -radius,
+            right: radius,
+            top: -radius,
+            bottom: radius,
+            near: 0.0,
+            far: 2.0 * radius + margin,
+        }.matrix();
+
+        let origin = RowVector4::new(0.0, 0.0, 0.0, 1.0) * view * projection;
+        let texels = SHADOW_MAP_RESOLUTION as f32 / 2.0;
+        projection[(3, 0)] += (origin.x * texels).round() / texels - origin.x;
+        projection[(3, 1)] += (origin.y * texels).round() / texels - origin.y;
+
+        ShadowCascade {
+            view,
+            projection,
+        }
+    }
+
+    pub(crate) fn view_projection(&self) -> Matrix4<f32> {
+        self.view * self.projection
+    }
+}
+
+/// Returns the world space corners of the camera's frustum between the view distances `from`
+/// and `to`, by interpolating along the edges of the frustum between the near and far plane.
+fn frustum_slice_corners(inverse_view_projection: &Matrix4<f32>, near: f32, far: f32, from: f32, to: f32) -> [Vector3<f32>; 8] {
+
+    let unproject = |x: f32, y: f32, z: f32| {
+        let corner = RowVector4::new(x, y, z, 1.0) * inverse_view_projection;
+        Vector3::new(corner.x, corner.y, corner.z) / corner.w
+    };
+
+    let start = (from - near) / (far - near);
+    let end = (to - near) / (far - near);
+
+    let mut result = [Vector3::zeros(); 8];
+    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter().enumerate().for_each(|(index, (x, y))| {
+        let near_corner = unproject(*x, *y, 0.0);
+        let far_corner = unproject(*x, *y, 1.0);
+        result[index] = near_corner + (far_corner - near_corner) * start;
+        result[index + 4] = near_corner + (far_corner - near_corner) * end;
+    });
+    result
+}
+
+/// Shadow parameters bound to `set = 0, binding = 3`. The layout matches the GLSL declaration:
+///
+/// ```glsl
+/// layout(set = 0, binding = 3) uniform ShadowsUniformBufferObject {
+///     mat4 cascades[8];   // light space transformation of cascade c of caster i at [i * 4 + c]
+///     vec4 splits;        // far view distance of each cascade
+///     vec4 parameters;    // x: depth bias, y: filter radius, z: texel size of the atlas, w: casters
+/// } shadows;
+/// ```
+///
+/// The shadow map of caster `i` is bound to `set = 0, binding = 2` as `sampler2DShadow[2]`. Cascade
+/// `c` is located in its quadrant `(c % 2, c / 2)`. A light's caster index is stored in the z
+/// component of its `cone`, -1 if the light casts no shadows.
+#[repr(C, align(256))]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ShadowsUniformBufferObject {
+    cascades: [Matrix4<f32>; MAX_SHADOW_CASTERS * SHADOW_CASCADES],
+    splits: [f32; SHADOW_CASCADES],
+    parameters: [f32; 4],
+}
+
+impl ShadowsUniformBufferObject {
+
+    pub(crate) fn new(splits: &[f32; SHADOW_CASCADES], casters: &[[ShadowCascade; SHADOW_CASCADES]], settings: &ShadowSettings) -> ShadowsUniformBufferObject {
+
+        let mut result = ShadowsUniformBufferObject {
+            cascades: [Matrix4::identity(); MAX_SHADOW_CASTERS * SHADOW_CASCADES],
+            splits: *splits,
+            parameters: [
+                settings.depth_bias,
+                settings.filter_radius,
+                1.0 / (2 * SHADOW_MAP_RESOLUTION) as f32,
+                casters.len().min(MAX_SHADOW_CASTERS) as f32,
+            ],
+        };
+
+        casters.iter()
+            .take(MAX_SHADOW_CASTERS)
+            .enumerate()
+            .for_each(|(caster, cascades)| {
+                cascades.iter().enumerate().for_each(|(index, cascade)| {
+                    result.cascades[caster * SHADOW_CASCADES + index] = cascade.view_projection();
+                })
+            });
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{RowVector4, Vector3};
+
+    use crate::graphics::{Camera, DirectionalLight, Light, PointLight, Projection};
+    use crate::graphics::shadow::{cascade_splits, MAX_SHADOW_CASTERS, shadow_casters, ShadowCascade, ShadowsUniformBufferObject};
+
+    #[test]
+    fn test_shadows_uniform_buffer_object_has_std140_layout() {
+        assert_that!(offset_of!(ShadowsUniformBufferObject, cascades), is(equal_to(0)));
+        assert_that!(offset_of!(ShadowsUniformBufferObject, splits), is(equal_to(512)));
+        assert_that!(offset_of!(ShadowsUniformBufferObject, parameters), is(equal_to(528)));
+        assert_that!(std::mem::size_of::<ShadowsUniformBufferObject>() % 256, is(equal_to(0)));
+    }
+
+    #[test]
+    fn test_cascade_splits() {
+
+        let uniform = cascade_splits(1.0, 101.0, 0.0);
+        assert_that!(uniform, is(equal_to([26.0, 51.0, 76.0, 101.0])));
+
+        let logarithmic = cascade_splits(1.0, 10000.0, 1.0);
+        assert_that!(logarithmic[0], is(close_to(10.0, 0.001)));
+        assert_that!(logarithmic[1], is(close_to(100.0, 0.01)));
+        assert_that!(logarithmic[3], is(close_to(10000.0, 1.0)));
+    }
+
+    #[test]
+    fn test_shadow_casters() {
+
+        let directional = |cast_shadows| Light::Directional(DirectionalLight {
+            direction: Vector3::new(0.0, 1.0, 0.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            cast_shadows,
+        });
+
+        let point = Light::Point(PointLight {
+            position: Vector3::zeros(),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            range: 1.0,
+        });
+
+        let lights = vec![point, directional(false), directional(true), point, directional(true), directional(true)];
+
+        let casters = shadow_casters(&lights).map(|(index, _)| index).collect::<Vec<_>>();
+
+        assert_that!(casters.len(), is(equal_to(MAX_SHADOW_CASTERS)));
+        assert_that!(casters, is(equal_to(vec![2, 4])));
+    }
+
+    #[test]
+    fn test_cascades_contain_their_frustum_slice() {
+
+        let mut camera = Camera::new(Projection::PerspectiveProjection {
+            fovy: 1.0,
+            aspect: 1.5,
+            near: 0.1,
+            far: 100.0,
+        });
+        camera.view_target(&Vector3::new(0.0, -2.0, -5.0), &Vector3::zeros(), &Vector3::new(0.0, -1.0, 0.0));
+
+        let splits = cascade_splits(camera.near(), 40.0, 0.75);
+        let direction = Vector3::new(0.3, 1.0, 0.2);
+        let cascades = ShadowCascade::cascades(&camera, &splits, &direction, 40.0);
+
+        let camera_position = camera.position();
+        let forward = (Vector3::zeros() - camera_position).normalize();
+
+        cascades.iter().enumerate().for_each(|(index, cascade)| {
+            // a point in the middle of the cascade's slice on the camera's axis
+            let from = if index == 0 { camera.near() } else { splits[index - 1] };
+            let point = camera_position + forward * (from + splits[index]) / 2.0;
+            let projected = RowVector4::new(point.x, point.y, point.z, 1.0) * cascade.view_projection();
+
+            assert_that!(projected.x.abs(), is(less_than(1.0)));
+            assert_that!(projected.y.abs(), is(less_than(1.0)));
+            assert_that!(projected.z, is(greater_than(0.0)));
+            assert_that!(projected.z, is(less_than(1.0)));
+        });
+    }
+}
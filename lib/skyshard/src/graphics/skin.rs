@@ -0,0 +1,15 @@
+use crate::animation::{Pose, Skeleton};
+use crate::graphics::vulkan::resources::Buffer;
+
+/// The skeleton deforming a geometry with skinned vertices.
+///
+/// The geometry is culled by the bounds of its vertices in the bind pose, animations moving the
+/// vertices far outside of them should be drawn with `frustum_culling` disabled.
+pub struct Skin {
+    pub skeleton: Skeleton,
+    /// The pose the geometry is drawn in, uploaded to the joint palette every frame.
+    pub pose: Pose,
+    /// The joint matrices of the pose in the order of the skeleton's joints, column-major.
+    pub joint_palette_buffer: Buffer<[f32; 16]>,
+    pub descriptor_set: ::ash::vk::DescriptorSet,
+}
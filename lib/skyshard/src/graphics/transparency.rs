@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+
+use nalgebra::{Matrix4, Vector3};
+
+use crate::engine::InstanceData;
+use crate::graphics::Geometry;
+
+/// Returns the distance from the camera to the center of the instance's bounding sphere.
+fn distance(instance: &InstanceData, bounding_sphere: &[f32; 4], camera_position: &Vector3<f32>) -> f32 {
+    let center = Vector3::new(bounding_sphere[0], bounding_sphere[1], bounding_sphere[2]);
+    let transformation = Matrix4::from_column_slice(&instance.transformation);
+    let world_center = (center.push(1.0).transpose() * transformation).transpose().xyz();
+    (world_center - camera_position).norm()
+}
+
+/// Returns the distance of the instance farthest from the camera, 0 if there is none.
+fn farthest_distance(instances: &[InstanceData], bounding_sphere: &[f32; 4], camera_position: &Vector3<f32>) -> f32 {
+    instances.iter()
+        .map(|instance| distance(instance, bounding_sphere, camera_position))
+        .fold(0.0, f32::max)
+}
+
+/// Sorts the instances from back to front, so that blended instances cover the ones behind them.
+/// Instances at the same distance keep their relative order.
+pub(crate) fn sort_back_to_front(instances: &mut [InstanceData], bounding_sphere: &[f32; 4], camera_position: &Vector3<f32>) {
+    instances.sort_by(|a, b| {
+        distance(b, bounding_sphere, camera_position)
+            .partial_cmp(&distance(a, bounding_sphere, camera_position))
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Returns the indices of the transparent geometries in the order they are drawn: the geometry
+/// whose farthest visible instance is the farthest first. The instances of different geometries
+/// are not interleaved, so that intersecting transparent geometries may be blended in the wrong
+/// order.
+pub(crate) fn transparent_draw_order(geometries: &[Geometry], camera_position: &Vector3<f32>) -> Vec<usize> {
+
+    let mut distances = geometries.iter().enumerate()
+        .filter(|(_, geometry)| geometry.material.blend_mode.is_transparent())
+        .map(|(index, geometry)| {
+            (index, farthest_distance(&geometry.instances[..geometry.visible_instances], &geometry.bounding_sphere, camera_position))
+        })
+        .collect::<Vec<_>>();
+
+    distances.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+    distances.into_iter().map(|(index, _)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::engine::InstanceData;
+    use crate::graphics::transparency::{farthest_distance, sort_back_to_front};
+
+    fn instance(id: u32, translation: Vector3<f32>) -> InstanceData {
+        InstanceData {
+            id,
+            transformation: Matrix4::<f32>::identity()
+                .append_translation(&translation)
+                .transpose()
+                .as_slice()
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_instances_are_sorted_back_to_front() {
+
+        let mut instances = vec![
+            instance(1, Vector3::new(0.0, 0.0, 2.0)),
+            instance(2, Vector3::new(0.0, 0.0, 8.0)),
+            instance(3, Vector3::new(0.0, 0.0, -4.0)),
+            instance(4, Vector3::new(0.0, 0.0, 8.0)),
+        ];
+
+        sort_back_to_front(&mut instances, &[0.0, 0.0, 0.0, 1.0], &Vector3::zeros());
+
+        assert_that!(instances.iter().map(|instance| instance.id).collect::<Vec<_>>(), is(equal_to(vec![2, 4, 3, 1])));
+    }
+
+    #[test]
+    fn test_farthest_distance_includes_the_bounding_sphere_center() {
+
+        let instances = [
+            instance(1, Vector3::new(0.0, 0.0, 2.0)),
+            instance(2, Vector3::new(3.0, 0.0, 0.0)),
+        ];
+
+        // the center is offset along z within the model
+        let distance = farthest_distance(&instances, &[0.0, 0.0, 2.0, 1.0], &Vector3::zeros());
+
+        assert_that!(distance, is(equal_to(4.0)));
+        assert_that!(farthest_distance(&[], &[0.0, 0.0, 0.0, 1.0], &Vector3::zeros()), is(equal_to(0.0)));
+    }
+}
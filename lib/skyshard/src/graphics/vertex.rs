@@ -0,0 +1,345 @@
+use std::ops::Range;
+
+use nalgebra::Vector3;
+use thiserror::Error;
+
+use crate::engine::{InstanceData, SkinnedVertex, Vertex};
+
+/// The meaning of a vertex attribute. Each semantic is bound to a fixed shader input location, so
+/// shaders can be written against the semantics without knowing the layout of the vertex buffer:
+///
+/// | semantic          | location |
+/// |-------------------|----------|
+/// | `Position`        | 0        |
+/// | `Normal`          | 1        |
+/// | `Color`           | 2        |
+/// | `TexCoord(0)`     | 3        |
+/// | instance data     | 4 - 8    |
+/// | `Tangent`         | 9        |
+/// | `JointIndices`    | 10       |
+/// | `JointWeights`    | 11       |
+/// | `TexCoord(1..=3)` | 12 - 14  |
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexSemantic {
+    Position,
+    Normal,
+    Tangent,
+    Color,
+    TexCoord(u8),
+    JointIndices,
+    JointWeights,
+}
+
+/// The highest supported index of `VertexSemantic::TexCoord`.
+pub const MAX_TEX_COORD_SET: u8 = 3;
+
+/// The locations of the instance data, the id followed by the four rows of the transformation.
+const INSTANCE_LOCATIONS: Range<u32> = 4..9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum VertexLayoutError {
+
+    #[error("The vertex shader reads location {location}, but the vertex layout has no attribute at it!")]
+    MissingInput { location: u32 },
+}
+
+impl VertexSemantic {
+
+    pub fn location(&self) -> u32 {
+        match self {
+            VertexSemantic::Position => 0,
+            VertexSemantic::Normal => 1,
+            VertexSemantic::Color => 2,
+            VertexSemantic::TexCoord(0) => 3,
+            VertexSemantic::Tangent => 9,
+            VertexSemantic::JointIndices => 10,
+            VertexSemantic::JointWeights => 11,
+            VertexSemantic::TexCoord(set) => 11 + *set as u32,
+        }
+    }
+}
+
+/// The data type of a vertex attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    UInt,
+    UInt2,
+    UInt3,
+    UInt4,
+    /// Four unsigned bytes, read as integers.
+    UByte4,
+    /// Four unsigned bytes, read as floats in `[0, 1]`.
+    UByte4Norm,
+    /// Four unsigned shorts, read as integers.
+    UShort4,
+}
+
+impl VertexFormat {
+
+    /// Returns the size of the attribute in bytes.
+    pub fn size(&self) -> u32 {
+        match self {
+            VertexFormat::Float | VertexFormat::UInt | VertexFormat::UByte4 | VertexFormat::UByte4Norm => 4,
+            VertexFormat::Float2 | VertexFormat::UInt2 | VertexFormat::UShort4 => 8,
+            VertexFormat::Float3 | VertexFormat::UInt3 => 12,
+            VertexFormat::Float4 | VertexFormat::UInt4 => 16,
+        }
+    }
+
+    pub(crate) fn vk_format(&self) -> ::ash::vk::Format {
+        match self {
+            VertexFormat::Float => ::ash::vk::Format::R32_SFLOAT,
+            VertexFormat::Float2 => ::ash::vk::Format::R32G32_SFLOAT,
+            VertexFormat::Float3 => ::ash::vk::Format::R32G32B32_SFLOAT,
+            VertexFormat::Float4 => ::ash::vk::Format::R32G32B32A32_SFLOAT,
+            VertexFormat::UInt => ::ash::vk::Format::R32_UINT,
+            VertexFormat::UInt2 => ::ash::vk::Format::R32G32_UINT,
+            VertexFormat::UInt3 => ::ash::vk::Format::R32G32B32_UINT,
+            VertexFormat::UInt4 => ::ash::vk::Format::R32G32B32A32_UINT,
+            VertexFormat::UByte4 => ::ash::vk::Format::R8G8B8A8_UINT,
+            VertexFormat::UByte4Norm => ::ash::vk::Format::R8G8B8A8_UNORM,
+            VertexFormat::UShort4 => ::ash::vk::Format::R16G16B16A16_UINT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    pub semantic: VertexSemantic,
+    pub format: VertexFormat,
+    /// Offset of the attribute from the start of a vertex in bytes.
+    pub offset: u32,
+}
+
+/// Describes how the attributes of a vertex are laid out in a vertex buffer. Geometries declare
+/// the layout of their vertices, the engine creates a set of pipelines for each distinct layout.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    stride: u32,
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+
+    /// Creates a layout of vertices `stride` bytes apart.
+    ///
+    /// Panics if a semantic is used twice, a texture coordinate set exceeds `MAX_TEX_COORD_SET`
+    /// or an attribute exceeds the stride.
+    pub fn new(stride: u32, attributes: Vec<VertexAttribute>) -> VertexLayout {
+
+        attributes.iter().enumerate().for_each(|(index, attribute)| {
+            if let VertexSemantic::TexCoord(set) = attribute.semantic {
+                assert!(set <= MAX_TEX_COORD_SET, "texture coordinate set {set} exceeds the maximum of {MAX_TEX_COORD_SET}");
+            }
+            assert!(attributes[..index].iter().all(|other| other.semantic != attribute.semantic),
+                "vertex semantic {:?} is used more than once", attribute.semantic);
+            assert!(attribute.offset + attribute.format.size() <= stride,
+                "vertex attribute {:?} exceeds the stride of {} bytes", attribute.semantic, stride);
+        });
+
+        VertexLayout { stride, attributes }
+    }
+
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, semantic: VertexSemantic) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|attribute| attribute.semantic == semantic)
+    }
+
+    /// Whether the vertices are deformed by a skeleton, i.e. have joint indices and weights.
+    pub fn is_skinned(&self) -> bool {
+        self.attribute(VertexSemantic::JointIndices).is_some() && self.attribute(VertexSemantic::JointWeights).is_some()
+    }
+
+    /// Checks that the layout has an attribute at each of the vertex shader's input locations,
+    /// apart from those of the instance data.
+    pub fn validate(&self, input_locations: &[u32]) -> Result<(), VertexLayoutError> {
+        input_locations.iter()
+            .filter(|location| !INSTANCE_LOCATIONS.contains(location))
+            .find(|location| self.attributes.iter().all(|attribute| attribute.semantic.location() != **location))
+            .map_or(Ok(()), |location| Err(VertexLayoutError::MissingInput { location: *location }))
+    }
+
+    /// Reads the `Position` attribute of all vertices, which must be `Float3`.
+    pub fn positions(&self, vertices: &[u8]) -> Vec<Vector3<f32>> {
+
+        let attribute = self.attribute(VertexSemantic::Position)
+            .filter(|attribute| attribute.format == VertexFormat::Float3)
+            .expect("vertex layout should have a Float3 position");
+
+        vertices.chunks_exact(self.stride as usize).map(|vertex| {
+            let position = &vertex[attribute.offset as usize..(attribute.offset + 12) as usize];
+            Vector3::from_fn(|index, _| {
+                f32::from_ne_bytes(position[index * 4..index * 4 + 4].try_into().unwrap())
+            })
+        }).collect()
+    }
+
+    /// Returns the binding and attribute descriptions of a pipeline reading the vertices from
+    /// binding 0 and the instance data from binding 1.
+    pub(crate) fn input_descriptions(&self) -> (Vec<::ash::vk::VertexInputBindingDescription>, Vec<::ash::vk::VertexInputAttributeDescription>) {
+
+        let bindings = vec![
+            ::ash::vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride(self.stride)
+                .input_rate(::ash::vk::VertexInputRate::VERTEX)
+                .build(),
+            ::ash::vk::VertexInputBindingDescription::builder()
+                .binding(1)
+                .stride(std::mem::size_of::<InstanceData>() as u32)
+                .input_rate(::ash::vk::VertexInputRate::INSTANCE)
+                .build(),
+        ];
+
+        let vertex_attributes = self.attributes.iter().map(|attribute| {
+            ::ash::vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(attribute.semantic.location())
+                .format(attribute.format.vk_format())
+                .offset(attribute.offset)
+                .build()
+        });
+
+        // the id followed by the four rows of the transformation
+        let instance_attributes = INSTANCE_LOCATIONS.enumerate().map(|(index, location)| {
+            ::ash::vk::VertexInputAttributeDescription::builder()
+                .binding(1)
+                .location(location)
+                .format(if index == 0 { ::ash::vk::Format::R32_UINT } else { ::ash::vk::Format::R32G32B32A32_SFLOAT })
+                .offset(match index {
+                    0 => offset_of!(InstanceData, id) as u32,
+                    row => (offset_of!(InstanceData, transformation) + (row - 1) * 16) as u32,
+                })
+                .build()
+        });
+
+        (bindings, vertex_attributes.chain(instance_attributes).collect())
+    }
+}
+
+/// Types which can be stored in a vertex buffer. Implementations for `#[repr(C)]` structs can be
+/// derived with `skyshard_shaders::HasVertexLayout`.
+pub trait HasVertexLayout: Copy {
+    fn vertex_layout() -> VertexLayout;
+}
+
+impl HasVertexLayout for Vertex {
+    fn vertex_layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<Vertex>() as u32, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: offset_of!(Vertex, position) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Normal, format: VertexFormat::Float3, offset: offset_of!(Vertex, normal) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Color, format: VertexFormat::Float3, offset: offset_of!(Vertex, color) as u32 },
+            VertexAttribute { semantic: VertexSemantic::TexCoord(0), format: VertexFormat::Float2, offset: offset_of!(Vertex, uv) as u32 },
+        ])
+    }
+}
+
+impl HasVertexLayout for SkinnedVertex {
+    fn vertex_layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<SkinnedVertex>() as u32, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: offset_of!(SkinnedVertex, position) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Normal, format: VertexFormat::Float3, offset: offset_of!(SkinnedVertex, normal) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Color, format: VertexFormat::Float3, offset: offset_of!(SkinnedVertex, color) as u32 },
+            VertexAttribute { semantic: VertexSemantic::TexCoord(0), format: VertexFormat::Float2, offset: offset_of!(SkinnedVertex, uv) as u32 },
+            VertexAttribute { semantic: VertexSemantic::JointIndices, format: VertexFormat::UInt4, offset: offset_of!(SkinnedVertex, joints) as u32 },
+            VertexAttribute { semantic: VertexSemantic::JointWeights, format: VertexFormat::Float4, offset: offset_of!(SkinnedVertex, weights) as u32 },
+        ])
+    }
+}
+
+/// Returns the raw bytes of the vertices, as they are copied into a vertex buffer.
+pub(crate) fn vertex_bytes<V: HasVertexLayout>(vertices: &[V]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(vertices.as_ptr() as *const u8, std::mem::size_of_val(vertices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::engine::{SkinnedVertex, Vertex};
+    use crate::graphics::{HasVertexLayout, VertexAttribute, VertexFormat, VertexLayout, VertexSemantic};
+    use crate::graphics::vertex::{vertex_bytes, VertexLayoutError};
+
+    #[test]
+    fn test_vertex_layout_matches_fixed_vertex_inputs() {
+
+        let layout = Vertex::vertex_layout();
+        let (bindings, attributes) = layout.input_descriptions();
+
+        assert_that!(bindings[0].stride, is(equal_to(std::mem::size_of::<Vertex>() as u32)));
+        assert_that!(attributes.iter().map(|attribute| attribute.location).collect::<Vec<_>>(), is(equal_to(vec![0, 1, 2, 3, 4, 5, 6, 7, 8])));
+        assert_that!(attributes[3].offset, is(equal_to(36)));
+        assert_that!(attributes[3].format, is(equal_to(::ash::vk::Format::R32G32_SFLOAT)));
+    }
+
+    #[test]
+    fn test_vertex_layout_reads_positions() {
+
+        let vertices = [
+            Vertex { position: [1.0, 2.0, 3.0], normal: [0.0; 3], color: [0.0; 3], uv: [0.0; 2] },
+            Vertex { position: [4.0, 5.0, 6.0], normal: [0.0; 3], color: [0.0; 3], uv: [0.0; 2] },
+        ];
+
+        let positions = Vertex::vertex_layout().positions(vertex_bytes(&vertices));
+
+        assert_that!(positions, is(equal_to(vec![Vector3::new(1.0, 2.0, 3.0), Vector3::new(4.0, 5.0, 6.0)])));
+    }
+
+    #[test]
+    fn test_skinned_vertex_layout() {
+
+        let layout = SkinnedVertex::vertex_layout();
+        let (_, attributes) = layout.input_descriptions();
+
+        assert_that!(layout.is_skinned(), is(true));
+        assert_that!(Vertex::vertex_layout().is_skinned(), is(false));
+        assert_that!(attributes.iter().map(|attribute| attribute.location).collect::<Vec<_>>(), is(equal_to(vec![0, 1, 2, 3, 10, 11, 4, 5, 6, 7, 8])));
+        assert_that!(attributes[4].format, is(equal_to(::ash::vk::Format::R32G32B32A32_UINT)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_vertex_layout_rejects_duplicate_semantics() {
+        VertexLayout::new(24, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: 0 },
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: 12 },
+        ]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_vertex_layout_rejects_unsupported_tex_coord_sets() {
+        VertexLayout::new(8, vec![
+            VertexAttribute { semantic: VertexSemantic::TexCoord(4), format: VertexFormat::Float2, offset: 0 },
+        ]);
+    }
+
+    #[test]
+    fn test_vertex_layout_is_validated_against_shader_inputs() {
+
+        // positions and a second set of texture coordinates, but no normals, colors or first set
+        let layout = VertexLayout::new(20, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: 0 },
+            VertexAttribute { semantic: VertexSemantic::TexCoord(1), format: VertexFormat::Float2, offset: 12 },
+        ]);
+
+        assert_that!(layout.validate(&[0, 4, 5, 12]), is(equal_to(Ok(()))));
+        assert_that!(layout.validate(&[0, 1, 2, 3, 4, 5]), is(equal_to(Err(VertexLayoutError::MissingInput { location: 1 }))));
+        assert_that!(Vertex::vertex_layout().validate(&[0, 1, 2, 3, 4, 5]), is(equal_to(Ok(()))));
+        assert_that!(SkinnedVertex::vertex_layout().validate(&[0, 1, 2, 3, 4, 5, 10, 11]), is(equal_to(Ok(()))));
+    }
+}
@@ -0,0 +1,134 @@
+use ash::vk::Handle;
+use log::info;
+
+use crate::graphics::vulkan::device::Device;
+use crate::graphics::vulkan::shaders::{ComputeShaderBinary, ShaderModule};
+use crate::graphics::vulkan::VulkanError;
+
+/// A compute pipeline together with its layout.
+pub struct ComputePipeline {
+    handle: ::ash::vk::Pipeline,
+    layout: ::ash::vk::PipelineLayout,
+    push_constants_size: u32,
+}
+
+impl ComputePipeline {
+
+    /// Creates a compute pipeline running the shader's `main` entrypoint.
+    ///
+    /// The descriptor set layouts are bound in the given order, the push constants (if any) are
+    /// visible to the compute stage starting at offset 0.
+    pub fn new(
+        device: &Device,
+        shader: ComputeShaderBinary,
+        descriptor_set_layouts: &[::ash::vk::DescriptorSetLayout],
+        push_constants_size: u32,
+    ) -> Result<ComputePipeline, VulkanError> {
+
+        let layout = {
+
+            let push_constant_ranges = [
+                ::ash::vk::PushConstantRange::builder()
+                    .stage_flags(::ash::vk::ShaderStageFlags::COMPUTE)
+                    .offset(0)
+                    .size(push_constants_size)
+                    .build()
+            ];
+
+            let pipeline_layout_create_info = ::ash::vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(descriptor_set_layouts)
+                .push_constant_ranges(if push_constants_size > 0 { &push_constant_ranges } else { &[] })
+                .build();
+
+            unsafe {
+                device.handle().create_pipeline_layout(&pipeline_layout_create_info, None)
+            }?
+        };
+
+        let shader_module = ShaderModule::create(shader, "main")
+            .and_then(|module| module.load(device))
+            .map_err(|_| VulkanError::InitializationFailedError)?;
+
+        let pipeline_create_info = ::ash::vk::ComputePipelineCreateInfo::builder()
+            .stage(shader_module.create_pipeline_shader_stage_create_info())
+            .layout(layout)
+            .build();
+
+        let result = unsafe {
+            device.handle().create_compute_pipelines(::ash::vk::PipelineCache::null(), &[pipeline_create_info], None)
+        };
+
+        // the module is not needed anymore, once the pipeline has been created
+        let _ = shader_module.unload(device);
+
+        let handle = result.map_err(|(_, result)| VulkanError::from(result))?[0];
+
+        info!("Vulkan compute pipeline <0x{:x?}> created.", handle.as_raw());
+
+        Ok(ComputePipeline {
+            handle,
+            layout,
+            push_constants_size,
+        })
+    }
+
+    pub fn handle(&self) -> &::ash::vk::Pipeline {
+        &self.handle
+    }
+
+    pub fn layout(&self) -> &::ash::vk::PipelineLayout {
+        &self.layout
+    }
+
+    /// Records the binding of the pipeline, its descriptor sets and push constants followed by a
+    /// dispatch of the given number of workgroups.
+    pub fn record_dispatch(
+        &self,
+        device: &Device,
+        command_buffer: &::ash::vk::CommandBuffer,
+        descriptor_sets: &[::ash::vk::DescriptorSet],
+        push_constants: &[u8],
+        group_counts: [u32; 3],
+    ) {
+        debug_assert!(push_constants.len() as u32 <= self.push_constants_size, "push constants exceed the pipeline's range");
+
+        unsafe {
+            device.handle().cmd_bind_pipeline(*command_buffer, ::ash::vk::PipelineBindPoint::COMPUTE, self.handle);
+            if !descriptor_sets.is_empty() {
+                device.handle().cmd_bind_descriptor_sets(*command_buffer, ::ash::vk::PipelineBindPoint::COMPUTE, self.layout, 0, descriptor_sets, &[]);
+            }
+            if !push_constants.is_empty() {
+                device.handle().cmd_push_constants(*command_buffer, self.layout, ::ash::vk::ShaderStageFlags::COMPUTE, 0, push_constants);
+            }
+            device.handle().cmd_dispatch(*command_buffer, group_counts[0], group_counts[1], group_counts[2]);
+        }
+    }
+
+    /// Destroys the pipeline and its layout. The pipeline must not be in use by the device.
+    pub fn destroy(self, device: &Device) {
+        unsafe {
+            device.handle().destroy_pipeline(self.handle, None);
+            device.handle().destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
+
+/// Returns the number of workgroups needed to cover `invocations` with workgroups of `size`.
+pub fn workgroup_count(invocations: u32, size: u32) -> u32 {
+    (invocations + size - 1) / size
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::vulkan::compute::workgroup_count;
+
+    #[test]
+    fn test_workgroup_count_rounds_up() {
+        assert_that!(workgroup_count(0, 64), is(equal_to(0)));
+        assert_that!(workgroup_count(1, 64), is(equal_to(1)));
+        assert_that!(workgroup_count(64, 64), is(equal_to(1)));
+        assert_that!(workgroup_count(65, 64), is(equal_to(2)));
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+
+use log::info;
+
+use crate::graphics::vulkan::VulkanError;
+
+type Result<T, E = VulkanError> = ::std::result::Result<T, E>;
+
+/// Allocates descriptor sets of arbitrary layouts from a growing list of pools.
+///
+/// Every pool is created with `FREE_DESCRIPTOR_SET`, so single descriptor sets can be returned
+/// with [free](DescriptorAllocator::free). If none of the pools can satisfy an allocation, a new
+/// pool twice the size of the previous one (up to [MAX_SETS_PER_POOL](Self::MAX_SETS_PER_POOL))
+/// is created.
+pub struct DescriptorAllocator {
+    device: ::ash::Device,
+    descriptors_per_set: Vec<(::ash::vk::DescriptorType, u32)>,
+    initial_sets_per_pool: u32,
+    pools: Vec<DescriptorPool>,
+    allocations: HashMap<::ash::vk::DescriptorSet, usize>,
+}
+
+struct DescriptorPool {
+    handle: ::ash::vk::DescriptorPool,
+    capacity: u32,
+    allocated: u32,
+    exhausted: bool,
+}
+
+impl DescriptorPool {
+
+    fn is_usable(&self) -> bool {
+        !self.exhausted && self.allocated < self.capacity
+    }
+}
+
+impl DescriptorAllocator {
+
+    pub const MAX_SETS_PER_POOL: u32 = 4096;
+
+    /// Creates an allocator whose pools provide the specified amount of descriptors per set,
+    /// e.g. `[(UNIFORM_BUFFER, 1)]` for sets consisting of a single uniform buffer.
+    pub fn new(device: &::ash::Device, descriptors_per_set: &[(::ash::vk::DescriptorType, u32)], initial_sets_per_pool: u32) -> DescriptorAllocator {
+        DescriptorAllocator {
+            device: Clone::clone(device),
+            descriptors_per_set: descriptors_per_set.to_vec(),
+            initial_sets_per_pool: initial_sets_per_pool.max(1),
+            pools: Vec::new(),
+            allocations: HashMap::new(),
+        }
+    }
+
+    pub fn allocate(&mut self, layout: ::ash::vk::DescriptorSetLayout) -> Result<::ash::vk::DescriptorSet> {
+
+        for index in 0..self.pools.len() {
+            if self.pools[index].is_usable() {
+                match self.allocate_from(index, layout) {
+                    Ok(descriptor_set) => return Ok(descriptor_set),
+                    Err(VulkanError::OutOfPoolMemoryError) | Err(VulkanError::FragmentedPoolError) => {
+                        self.pools[index].exhausted = true;
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        let index = self.grow()?;
+        self.allocate_from(index, layout)
+    }
+
+    /// Returns the descriptor set to its pool. The descriptor set must not be in use anymore.
+    pub fn free(&mut self, descriptor_set: ::ash::vk::DescriptorSet) -> Result<()> {
+
+        let index = self.allocations.remove(&descriptor_set)
+            .ok_or(VulkanError::Unknown)?;
+
+        let pool = &mut self.pools[index];
+
+        unsafe {
+            self.device.free_descriptor_sets(pool.handle, &[descriptor_set])
+        }?;
+
+        pool.allocated -= 1;
+        pool.exhausted = false;
+
+        Ok(())
+    }
+
+    /// Returns the number of allocated descriptor sets.
+    pub fn len(&self) -> usize {
+        self.allocations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocations.is_empty()
+    }
+
+    fn allocate_from(&mut self, index: usize, layout: ::ash::vk::DescriptorSetLayout) -> Result<::ash::vk::DescriptorSet> {
+
+        let pool = &mut self.pools[index];
+        let layouts = [layout];
+        let allocate_info = ::ash::vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool.handle)
+            .set_layouts(&layouts);
+
+        let descriptor_set = unsafe {
+            self.device.allocate_descriptor_sets(&allocate_info)
+        }?[0];
+
+        pool.allocated += 1;
+        self.allocations.insert(descriptor_set, index);
+
+        Ok(descriptor_set)
+    }
+
+    fn grow(&mut self) -> Result<usize> {
+
+        let capacity = pool_capacity(self.initial_sets_per_pool, self.pools.len());
+
+        let pool_sizes = self.descriptors_per_set.iter().map(|(descriptor_type, count)| {
+            ::ash::vk::DescriptorPoolSize::builder()
+                .ty(*descriptor_type)
+                .descriptor_count(count * capacity)
+                .build()
+        }).collect::<Vec<_>>();
+
+        let create_info = ::ash::vk::DescriptorPoolCreateInfo::builder()
+            .flags(::ash::vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(capacity)
+            .pool_sizes(&pool_sizes);
+
+        let handle = unsafe {
+            self.device.create_descriptor_pool(&create_info, None)
+        }?;
+
+        info!("Descriptor pool #{} for {} descriptor sets created.", self.pools.len(), capacity);
+
+        self.pools.push(DescriptorPool {
+            handle,
+            capacity,
+            allocated: 0,
+            exhausted: false,
+        });
+
+        Ok(self.pools.len() - 1)
+    }
+}
+
+/// Returns the number of descriptor sets of the pool at `index`.
+fn pool_capacity(initial: u32, index: usize) -> u32 {
+    initial.saturating_mul(1u32.checked_shl(index as u32).unwrap_or(u32::MAX))
+        .min(DescriptorAllocator::MAX_SETS_PER_POOL)
+}
+
+/// A bindless table of combined image samplers bound as `uniform sampler2D textures[]`.
+///
+/// Textures are referenced by their index in the table. The table occupies a single descriptor
+/// set with a variable descriptor count. Whenever the table is full it is reallocated with twice
+/// the capacity, up to `max_capacity`, hence the descriptor set must be queried each frame via
+/// [descriptor_set](TextureTable::descriptor_set) and the table must only be modified while the
+/// previous descriptor set is not in use by the device.
+pub struct TextureTable {
+    device: ::ash::Device,
+    layout: ::ash::vk::DescriptorSetLayout,
+    pool: ::ash::vk::DescriptorPool,
+    descriptor_set: ::ash::vk::DescriptorSet,
+    capacity: u32,
+    max_capacity: u32,
+    entries: Vec<Option<::ash::vk::DescriptorImageInfo>>,
+    slots: SlotAllocator,
+}
+
+impl TextureTable {
+
+    pub fn new(device: &::ash::Device, initial_capacity: u32, max_capacity: u32) -> Result<TextureTable> {
+
+        let layout = {
+            let bindings = [
+                ::ash::vk::DescriptorSetLayoutBinding::builder()
+                    .binding(0)
+                    .descriptor_count(max_capacity)
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .build()
+            ];
+
+            let binding_flags = [
+                ::ash::vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                    | ::ash::vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT
+            ];
+
+            let mut binding_flags_create_info = ::ash::vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder()
+                .binding_flags(&binding_flags);
+
+            let create_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+                .bindings(&bindings)
+                .push_next(&mut binding_flags_create_info);
+
+            unsafe {
+                device.create_descriptor_set_layout(&create_info, None)
+            }?
+        };
+
+        let capacity = initial_capacity.clamp(1, max_capacity);
+        let (pool, descriptor_set) = Self::allocate(device, layout, capacity)?;
+
+        Ok(TextureTable {
+            device: Clone::clone(device),
+            layout,
+            pool,
+            descriptor_set,
+            capacity,
+            max_capacity,
+            entries: Vec::new(),
+            slots: SlotAllocator::default(),
+        })
+    }
+
+    pub fn layout(&self) -> ::ash::vk::DescriptorSetLayout {
+        self.layout
+    }
+
+    pub fn descriptor_set(&self) -> ::ash::vk::DescriptorSet {
+        self.descriptor_set
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Returns the number of textures in the table.
+    pub fn len(&self) -> u32 {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a texture to the table and returns its index.
+    pub fn insert(&mut self, image_view: ::ash::vk::ImageView, sampler: ::ash::vk::Sampler) -> Result<u32> {
+
+        let index = self.slots.allocate();
+
+        if index >= self.capacity {
+            if let Err(error) = self.grow(index + 1) {
+                self.slots.free(index);
+                return Err(error);
+            }
+        }
+
+        let image_info = ::ash::vk::DescriptorImageInfo::builder()
+            .sampler(sampler)
+            .image_view(image_view)
+            .image_layout(::ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build();
+
+        if self.entries.len() <= index as usize {
+            self.entries.resize(index as usize + 1, None);
+        }
+        self.entries[index as usize] = Some(image_info);
+
+        self.write(&[(index, image_info)]);
+
+        Ok(index)
+    }
+
+    /// Removes the texture at `index` from the table. The index may be returned by a subsequent
+    /// [insert](TextureTable::insert).
+    pub fn remove(&mut self, index: u32) {
+        if let Some(entry) = self.entries.get_mut(index as usize) {
+            if entry.take().is_some() {
+                self.slots.free(index);
+            }
+        }
+    }
+
+    fn grow(&mut self, required: u32) -> Result<()> {
+
+        if required > self.max_capacity {
+            return Err(VulkanError::TooManyObjectsError);
+        }
+
+        let capacity = (self.capacity * 2).max(required).min(self.max_capacity);
+        let (pool, descriptor_set) = Self::allocate(&self.device, self.layout, capacity)?;
+
+        unsafe {
+            self.device.destroy_descriptor_pool(self.pool, None);
+        }
+
+        self.pool = pool;
+        self.descriptor_set = descriptor_set;
+        self.capacity = capacity;
+
+        let entries = self.entries.iter()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.map(|entry| (index as u32, entry)))
+            .collect::<Vec<_>>();
+
+        self.write(&entries);
+
+        info!("Texture table grown to {} textures.", capacity);
+
+        Ok(())
+    }
+
+    fn write(&self, entries: &[(u32, ::ash::vk::DescriptorImageInfo)]) {
+
+        let image_infos = entries.iter()
+            .map(|(_, image_info)| [*image_info])
+            .collect::<Vec<_>>();
+
+        let descriptor_writes = entries.iter().zip(&image_infos).map(|((index, _), image_info)| {
+            ::ash::vk::WriteDescriptorSet::builder()
+                .descriptor_type(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .dst_set(self.descriptor_set)
+                .dst_binding(0)
+                .dst_array_element(*index)
+                .image_info(image_info)
+                .build()
+        }).collect::<Vec<_>>();
+
+        let descriptor_copies: [::ash::vk::CopyDescriptorSet; 0] = [];
+
+        unsafe {
+            self.device.update_descriptor_sets(&descriptor_writes, &descriptor_copies)
+        }
+    }
+
+    fn allocate(device: &::ash::Device, layout: ::ash::vk::DescriptorSetLayout, capacity: u32) -> Result<(::ash::vk::DescriptorPool, ::ash::vk::DescriptorSet)> {
+
+        let pool = {
+            let pool_sizes = [
+                ::ash::vk::DescriptorPoolSize::builder()
+                    .ty(::ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(capacity)
+                    .build()
+            ];
+
+            let create_info = ::ash::vk::DescriptorPoolCreateInfo::builder()
+                .max_sets(1)
+                .pool_sizes(&pool_sizes);
+
+            unsafe {
+                device.create_descriptor_pool(&create_info, None)
+            }?
+        };
+
+        let descriptor_counts = [capacity];
+        let mut variable_count_info = ::ash::vk::DescriptorSetVariableDescriptorCountAllocateInfo::builder()
+            .descriptor_counts(&descriptor_counts);
+
+        let layouts = [layout];
+        let allocate_info = ::ash::vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts)
+            .push_next(&mut variable_count_info);
+
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(&allocate_info)
+        }?[0];
+
+        Ok((pool, descriptor_set))
+    }
+}
+
+/// Hands out consecutive indices, recycling freed indices before issuing new ones.
+#[derive(Debug, Default)]
+struct SlotAllocator {
+    next: u32,
+    free: Vec<u32>,
+}
+
+impl SlotAllocator {
+
+    fn allocate(&mut self) -> u32 {
+        self.free.pop().unwrap_or_else(|| {
+            self.next += 1;
+            self.next - 1
+        })
+    }
+
+    fn free(&mut self, slot: u32) {
+        debug_assert!(slot < self.next && !self.free.contains(&slot), "Slot {} is not allocated", slot);
+        self.free.push(slot);
+    }
+
+    fn len(&self) -> u32 {
+        self.next - self.free.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::vulkan::descriptors::{DescriptorAllocator, pool_capacity, SlotAllocator};
+
+    #[test]
+    fn test_pool_capacity_doubles_until_maximum() {
+        assert_that!(pool_capacity(64, 0), is(equal_to(64)));
+        assert_that!(pool_capacity(64, 1), is(equal_to(128)));
+        assert_that!(pool_capacity(64, 3), is(equal_to(512)));
+        assert_that!(pool_capacity(64, 10), is(equal_to(DescriptorAllocator::MAX_SETS_PER_POOL)));
+        assert_that!(pool_capacity(64, 40), is(equal_to(DescriptorAllocator::MAX_SETS_PER_POOL)));
+    }
+
+    #[test]
+    fn test_slot_allocator_recycles_freed_slots() {
+
+        let mut slots = SlotAllocator::default();
+
+        assert_that!(slots.allocate(), is(equal_to(0)));
+        assert_that!(slots.allocate(), is(equal_to(1)));
+        assert_that!(slots.allocate(), is(equal_to(2)));
+
+        slots.free(1);
+        assert_that!(slots.len(), is(equal_to(2)));
+
+        assert_that!(slots.allocate(), is(equal_to(1)));
+        assert_that!(slots.allocate(), is(equal_to(3)));
+        assert_that!(slots.len(), is(equal_to(4)));
+    }
+}
@@ -44,6 +44,8 @@ pub struct Device {
     handle: ash::Device,
     queues: Vec<DeviceQueueRef>,
     command_pool: Box<dyn CommandPool>,
+    compute_queue: Option<DeviceQueueRef>,
+    compute_command_pool: Option<ash::vk::CommandPool>,
 }
 
 impl fmt::Debug for Device {
@@ -51,6 +53,7 @@ impl fmt::Debug for Device {
         let mut formatter = f.debug_struct("Device");
         formatter.field("name", &self.device.name);
         formatter.field("queues", &self.queues);
+        formatter.field("compute_queue", &self.compute_queue);
         formatter.finish()
     }
 }
@@ -70,13 +73,20 @@ impl Device {
                 found: physical_device.queue_families(QueueCapabilities::ANY, 0)
             })?;
 
+        let compute_queue_family = physical_device.async_compute_queue_family();
+
         let queue_priorities = [1.0];
         let queue_create_infos = (0..queue_count).map(|index| {
             ash::vk::DeviceQueueCreateInfo::builder()
                 .queue_family_index(queue_family.index())
                 .queue_priorities(&queue_priorities)
                 .build()
-        }).collect::<Vec<_>>();
+        }).chain(compute_queue_family.iter().map(|family| {
+            ash::vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(family.index())
+                .queue_priorities(&queue_priorities)
+                .build()
+        })).collect::<Vec<_>>();
 
         let extension_names = [
             ash::extensions::khr::Swapchain::name().as_ptr(),
@@ -91,21 +101,19 @@ impl Device {
                 .sampler_anisotropy(true)
                 .build();
 
-            let mut buffer_features = ::ash::vk::PhysicalDeviceBufferDeviceAddressFeatures::builder()
+            // buffer device address, descriptor indexing and draw indirect count
+            let mut vulkan_12_features = ::ash::vk::PhysicalDeviceVulkan12Features::builder()
                 .buffer_device_address(true)
-                .build();
-
-            let mut descriptor_indexing_features = ::ash::vk::PhysicalDeviceDescriptorIndexingFeatures::builder()
                 .shader_sampled_image_array_non_uniform_indexing(true)
                 .runtime_descriptor_array(true)
                 .descriptor_binding_variable_descriptor_count(true)
                 .descriptor_binding_partially_bound(true)
+                .draw_indirect_count(true)
                 .build();
 
             ash::vk::PhysicalDeviceFeatures2::builder()
                 .features(device_features)
-                .push_next(&mut descriptor_indexing_features)
-                .push_next(&mut buffer_features)
+                .push_next(&mut vulkan_12_features)
                 .build()
         };
 
@@ -128,12 +136,29 @@ impl Device {
             .map(|(index, handle)| Rc::new(DeviceQueue::new(handle, index, *queue_family)))
             .collect();
 
+        let compute_queue = compute_queue_family.map(|family| unsafe {
+            Rc::new(DeviceQueue::new(device.get_device_queue(family.index(), 0), 0, family))
+        });
+
+        let compute_command_pool = compute_queue_family.map(|family| {
+
+            let command_pool_create_info = ash::vk::CommandPoolCreateInfo::builder()
+                .flags(ash::vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                .queue_family_index(family.index());
+
+            unsafe {
+                device.create_command_pool(&command_pool_create_info, None).unwrap()
+            }
+        });
+
         let device = Rc::new(RefCell::new(Device {
             instance: physical_device.instance.upgrade().expect("Valid instance."),
             device: physical_device,
             handle: device,
             queues,
             command_pool: Box::new(UninitializedCommandPool::new()),
+            compute_queue,
+            compute_command_pool,
         }));
 
         let command_pool = {
@@ -171,6 +196,30 @@ impl Device {
         &self.command_pool
     }
 
+    /// Returns the queue of a dedicated compute queue family, if the device provides one.
+    /// Work submitted to it runs asynchronously to the graphics queue.
+    pub fn compute_queue(&self) -> Option<&DeviceQueueRef> {
+        self.compute_queue.as_ref()
+    }
+
+    /// Returns the command pool for command buffers submitted to the compute queue.
+    pub fn compute_command_pool(&self) -> Option<ash::vk::CommandPool> {
+        self.compute_command_pool
+    }
+
+    /// Returns the indices of all queue families the device has created queues for.
+    pub fn queue_family_indices(&self) -> Vec<u32> {
+        self.queues.iter()
+            .chain(self.compute_queue.iter())
+            .map(|queue| queue.family().index())
+            .fold(Vec::new(), |mut indices, index| {
+                if !indices.contains(&index) {
+                    indices.push(index);
+                }
+                indices
+            })
+    }
+
     pub fn allocate_command_buffer(&mut self) -> CommandBuffer {
 
         let allocate_info = ash::vk::CommandBufferAllocateInfo::builder()
@@ -204,6 +253,9 @@ impl Drop for Device {
 
         unsafe {
             self.handle.destroy_command_pool(self.command_pool.handle(), None);
+            if let Some(compute_command_pool) = self.compute_command_pool {
+                self.handle.destroy_command_pool(compute_command_pool, None);
+            }
             self.handle.destroy_device(None);
         }
         info!("Vulkan device <{}> destroyed.", self.hex_id())
@@ -219,6 +271,7 @@ pub struct PhysicalDevice {
     name: String,
     queue_families: Vec<QueueFamily>,
     memory_properties: ash::vk::PhysicalDeviceMemoryProperties,
+    limits: ash::vk::PhysicalDeviceLimits,
 }
 
 impl PhysicalDevice {
@@ -259,7 +312,8 @@ impl PhysicalDevice {
             id,
             name,
             queue_families,
-            memory_properties
+            memory_properties,
+            limits: properties.limits,
         }
     }
 
@@ -267,6 +321,20 @@ impl PhysicalDevice {
         &self.handle
     }
 
+    pub fn limits(&self) -> &ash::vk::PhysicalDeviceLimits {
+        &self.limits
+    }
+
+    /// Returns a queue family supporting compute but no graphics operations, which is usually
+    /// served by dedicated hardware.
+    pub fn async_compute_queue_family(&self) -> Option<QueueFamily> {
+        self.queue_families.iter()
+            .find(|family| family.supports(QueueCapabilities::COMPUTE_OPERATIONS)
+                && !family.supports(QueueCapabilities::GRAPHICS_OPERATIONS)
+                && family.queues() > 0)
+            .cloned()
+    }
+
     pub fn queue_families(&self, capabilities: QueueCapabilities, queue_count: u32) -> Vec<QueueFamily> {
         self.queue_families.iter()
             .filter(|family| family.supports(capabilities) && family.queues() >= queue_count)
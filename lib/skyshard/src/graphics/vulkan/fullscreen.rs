@@ -0,0 +1,189 @@
+use ash::vk::Handle;
+use log::info;
+
+use crate::graphics::vulkan::device::Device;
+use crate::graphics::vulkan::shaders::{FragmentShaderBinary, LoadedShaderModule, VertexShaderBinary};
+use crate::graphics::vulkan::VulkanError;
+
+/// The attachments of the render pass a fullscreen pipeline draws into.
+#[derive(Debug, Copy, Clone)]
+pub struct FullscreenTarget {
+    /// The number of color attachments, all of them are written.
+    pub color_attachments: usize,
+    /// Whether the triangle is tested against the depth attachment, without writing it. The
+    /// vertex shader places the triangle at the desired depth, e.g. the far plane.
+    pub depth_test: bool,
+}
+
+impl Default for FullscreenTarget {
+    fn default() -> Self {
+        FullscreenTarget {
+            color_attachments: 1,
+            depth_test: false,
+        }
+    }
+}
+
+/// A graphics pipeline together with its layout, drawing a single triangle covering the whole
+/// viewport. The vertex shader is expected to derive the triangle's corners from `gl_VertexIndex`,
+/// the fragment shader usually samples the result of a previous pass.
+pub struct FullscreenPipeline {
+    handle: ::ash::vk::Pipeline,
+    layout: ::ash::vk::PipelineLayout,
+    push_constants_size: u32,
+}
+
+impl FullscreenPipeline {
+
+    /// Creates a pipeline for the first subpass of the render pass.
+    ///
+    /// The descriptor set layouts are bound in the given order, the push constants (if any) are
+    /// visible to the fragment stage starting at offset 0.
+    pub fn new(
+        device: &Device,
+        vertex_shader_module: &LoadedShaderModule<VertexShaderBinary>,
+        fragment_shader_module: &LoadedShaderModule<FragmentShaderBinary>,
+        descriptor_set_layouts: &[::ash::vk::DescriptorSetLayout],
+        push_constants_size: u32,
+        renderpass: ::ash::vk::RenderPass,
+        target: &FullscreenTarget,
+    ) -> Result<FullscreenPipeline, VulkanError> {
+
+        let layout = {
+
+            let push_constant_ranges = [
+                ::ash::vk::PushConstantRange::builder()
+                    .stage_flags(::ash::vk::ShaderStageFlags::FRAGMENT)
+                    .offset(0)
+                    .size(push_constants_size)
+                    .build()
+            ];
+
+            let pipeline_layout_create_info = ::ash::vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(descriptor_set_layouts)
+                .push_constant_ranges(if push_constants_size > 0 { &push_constant_ranges } else { &[] })
+                .build();
+
+            unsafe {
+                device.handle().create_pipeline_layout(&pipeline_layout_create_info, None)
+            }?
+        };
+
+        let shader_stage_create_infos = [
+            vertex_shader_module.create_pipeline_shader_stage_create_info(),
+            fragment_shader_module.create_pipeline_shader_stage_create_info(),
+        ];
+
+        let vertex_input_state_info = ::ash::vk::PipelineVertexInputStateCreateInfo::builder();
+
+        let input_assembly_state_info = ::ash::vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(::ash::vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        // viewport and scissor are dynamic state, only their count is needed
+        let viewport_state_info = ::ash::vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let rasterization_info = ::ash::vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(::ash::vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(::ash::vk::CullModeFlags::NONE)
+            .front_face(::ash::vk::FrontFace::COUNTER_CLOCKWISE);
+
+        let multisample_state_info = ::ash::vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(::ash::vk::SampleCountFlags::TYPE_1);
+
+        let depth_state_info = ::ash::vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(target.depth_test)
+            .depth_write_enable(false)
+            .depth_compare_op(::ash::vk::CompareOp::LESS_OR_EQUAL);
+
+        let color_blend_attachment_states = vec![
+            ::ash::vk::PipelineColorBlendAttachmentState::builder()
+                .color_write_mask(::ash::vk::ColorComponentFlags::RGBA)
+                .blend_enable(false)
+                .build();
+            target.color_attachments
+        ];
+
+        let color_blend_state = ::ash::vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachment_states);
+
+        let dynamic_state = [
+            ::ash::vk::DynamicState::VIEWPORT,
+            ::ash::vk::DynamicState::SCISSOR
+        ];
+
+        let dynamic_state_info = ::ash::vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&dynamic_state);
+
+        let pipeline_create_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(layout)
+            .render_pass(renderpass)
+            .subpass(0)
+            .build();
+
+        let result = unsafe {
+            device.handle().create_graphics_pipelines(::ash::vk::PipelineCache::null(), &[pipeline_create_info], None)
+        };
+
+        let handle = result.map_err(|(_, result)| VulkanError::from(result))?[0];
+
+        info!("Vulkan fullscreen pipeline <0x{:x?}> created.", handle.as_raw());
+
+        Ok(FullscreenPipeline {
+            handle,
+            layout,
+            push_constants_size,
+        })
+    }
+
+    pub fn handle(&self) -> &::ash::vk::Pipeline {
+        &self.handle
+    }
+
+    pub fn layout(&self) -> &::ash::vk::PipelineLayout {
+        &self.layout
+    }
+
+    /// Records the binding of the pipeline, its descriptor sets and push constants followed by
+    /// the draw of the fullscreen triangle. The render pass has to be begun already.
+    pub fn record_draw(
+        &self,
+        device: &Device,
+        command_buffer: &::ash::vk::CommandBuffer,
+        descriptor_sets: &[::ash::vk::DescriptorSet],
+        push_constants: &[u8],
+    ) {
+        debug_assert!(push_constants.len() as u32 <= self.push_constants_size, "push constants exceed the pipeline's range");
+
+        unsafe {
+            device.handle().cmd_bind_pipeline(*command_buffer, ::ash::vk::PipelineBindPoint::GRAPHICS, self.handle);
+            if !descriptor_sets.is_empty() {
+                device.handle().cmd_bind_descriptor_sets(*command_buffer, ::ash::vk::PipelineBindPoint::GRAPHICS, self.layout, 0, descriptor_sets, &[]);
+            }
+            if !push_constants.is_empty() {
+                device.handle().cmd_push_constants(*command_buffer, self.layout, ::ash::vk::ShaderStageFlags::FRAGMENT, 0, push_constants);
+            }
+            device.handle().cmd_draw(*command_buffer, 3, 1, 0, 0);
+        }
+    }
+
+    /// Destroys the pipeline and its layout. The pipeline must not be in use by the device.
+    pub fn destroy(self, device: &Device) {
+        unsafe {
+            device.handle().destroy_pipeline(self.handle, None);
+            device.handle().destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
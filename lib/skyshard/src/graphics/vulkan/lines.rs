@@ -0,0 +1,182 @@
+use ash::vk::Handle;
+use log::info;
+
+use crate::graphics::VertexLayout;
+use crate::graphics::vulkan::device::Device;
+use crate::graphics::vulkan::shaders::{FragmentShaderBinary, LoadedShaderModule, VertexShaderBinary};
+use crate::graphics::vulkan::VulkanError;
+
+/// A graphics pipeline together with its layout, drawing a list of lines read from the vertex
+/// buffer bound to binding 0. The lines are blended by alpha into the first color attachment,
+/// further color attachments (e.g. the object ids) are left untouched.
+pub struct LinePipeline {
+    handle: ::ash::vk::Pipeline,
+    layout: ::ash::vk::PipelineLayout,
+}
+
+impl LinePipeline {
+
+    /// Creates a pipeline for the first subpass of the render pass. If `depth_test` is set, the
+    /// lines are hidden behind the contents of the depth attachment, without writing it.
+    pub fn new(
+        device: &Device,
+        vertex_shader_module: &LoadedShaderModule<VertexShaderBinary>,
+        fragment_shader_module: &LoadedShaderModule<FragmentShaderBinary>,
+        descriptor_set_layouts: &[::ash::vk::DescriptorSetLayout],
+        vertex_layout: &VertexLayout,
+        renderpass: ::ash::vk::RenderPass,
+        color_attachments: usize,
+        depth_test: bool,
+    ) -> Result<LinePipeline, VulkanError> {
+
+        let layout = {
+
+            let pipeline_layout_create_info = ::ash::vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(descriptor_set_layouts)
+                .build();
+
+            unsafe {
+                device.handle().create_pipeline_layout(&pipeline_layout_create_info, None)
+            }?
+        };
+
+        let shader_stage_create_infos = [
+            vertex_shader_module.create_pipeline_shader_stage_create_info(),
+            fragment_shader_module.create_pipeline_shader_stage_create_info(),
+        ];
+
+        let vertex_input_binding_descriptions = [
+            ::ash::vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride(vertex_layout.stride())
+                .input_rate(::ash::vk::VertexInputRate::VERTEX)
+                .build(),
+        ];
+
+        let vertex_input_attribute_descriptions = vertex_layout.attributes().iter().map(|attribute| {
+            ::ash::vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(attribute.semantic.location())
+                .format(attribute.format.vk_format())
+                .offset(attribute.offset)
+                .build()
+        }).collect::<Vec<_>>();
+
+        let vertex_input_state_info = ::ash::vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&vertex_input_binding_descriptions)
+            .vertex_attribute_descriptions(&vertex_input_attribute_descriptions);
+
+        let input_assembly_state_info = ::ash::vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(::ash::vk::PrimitiveTopology::LINE_LIST)
+            .primitive_restart_enable(false);
+
+        // viewport and scissor are dynamic state, only their count is needed
+        let viewport_state_info = ::ash::vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let rasterization_info = ::ash::vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(::ash::vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(::ash::vk::CullModeFlags::NONE)
+            .front_face(::ash::vk::FrontFace::COUNTER_CLOCKWISE);
+
+        let multisample_state_info = ::ash::vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(::ash::vk::SampleCountFlags::TYPE_1);
+
+        let depth_state_info = ::ash::vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(depth_test)
+            .depth_write_enable(false)
+            .depth_compare_op(::ash::vk::CompareOp::LESS_OR_EQUAL);
+
+        let color_blend_attachment_states = (0..color_attachments).map(|index| {
+            ::ash::vk::PipelineColorBlendAttachmentState::builder()
+                .color_write_mask(if index == 0 { ::ash::vk::ColorComponentFlags::RGBA } else { ::ash::vk::ColorComponentFlags::empty() })
+                .blend_enable(index == 0)
+                .src_color_blend_factor(::ash::vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(::ash::vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .color_blend_op(::ash::vk::BlendOp::ADD)
+                .src_alpha_blend_factor(::ash::vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(::ash::vk::BlendFactor::ZERO)
+                .alpha_blend_op(::ash::vk::BlendOp::ADD)
+                .build()
+        }).collect::<Vec<_>>();
+
+        let color_blend_state = ::ash::vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachment_states);
+
+        let dynamic_state = [
+            ::ash::vk::DynamicState::VIEWPORT,
+            ::ash::vk::DynamicState::SCISSOR
+        ];
+
+        let dynamic_state_info = ::ash::vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&dynamic_state);
+
+        let pipeline_create_info = ::ash::vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(layout)
+            .render_pass(renderpass)
+            .subpass(0)
+            .build();
+
+        let result = unsafe {
+            device.handle().create_graphics_pipelines(::ash::vk::PipelineCache::null(), &[pipeline_create_info], None)
+        };
+
+        let handle = result.map_err(|(_, result)| VulkanError::from(result))?[0];
+
+        info!("Vulkan line pipeline <0x{:x?}> created.", handle.as_raw());
+
+        Ok(LinePipeline {
+            handle,
+            layout,
+        })
+    }
+
+    pub fn handle(&self) -> &::ash::vk::Pipeline {
+        &self.handle
+    }
+
+    pub fn layout(&self) -> &::ash::vk::PipelineLayout {
+        &self.layout
+    }
+
+    /// Records the binding of the pipeline, its descriptor sets and the vertex buffer followed by
+    /// the draw of `vertex_count` vertices starting at `first_vertex`. The render pass has to be
+    /// begun already.
+    pub fn record_draw(
+        &self,
+        device: &Device,
+        command_buffer: &::ash::vk::CommandBuffer,
+        descriptor_sets: &[::ash::vk::DescriptorSet],
+        vertex_buffer: &::ash::vk::Buffer,
+        first_vertex: u32,
+        vertex_count: u32,
+    ) {
+        unsafe {
+            device.handle().cmd_bind_pipeline(*command_buffer, ::ash::vk::PipelineBindPoint::GRAPHICS, self.handle);
+            if !descriptor_sets.is_empty() {
+                device.handle().cmd_bind_descriptor_sets(*command_buffer, ::ash::vk::PipelineBindPoint::GRAPHICS, self.layout, 0, descriptor_sets, &[]);
+            }
+            device.handle().cmd_bind_vertex_buffers(*command_buffer, 0, &[*vertex_buffer], &[0]);
+            device.handle().cmd_draw(*command_buffer, vertex_count, 1, first_vertex, 0);
+        }
+    }
+
+    /// Destroys the pipeline and its layout. The pipeline must not be in use by the device.
+    pub fn destroy(self, device: &Device) {
+        unsafe {
+            device.handle().destroy_pipeline(self.handle, None);
+            device.handle().destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
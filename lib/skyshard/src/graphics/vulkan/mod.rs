@@ -17,6 +17,12 @@ pub mod buffer;
 pub mod renderpass;
 pub mod resources;
 pub mod shaders;
+pub mod descriptors;
+pub mod compute;
+pub mod fullscreen;
+pub mod lines;
+pub mod overlay;
+pub mod billboards;
 
 pub trait VulkanObject {
 
@@ -68,6 +74,9 @@ pub enum VulkanError {
     #[error("A requested pool allocation has failed due to fragmentation of the pool\'s memory")]
     FragmentedPoolError,
 
+    #[error("A pool allocation has failed due to a lack of space in the pool")]
+    OutOfPoolMemoryError,
+
     #[error("An unknown error has occurred.")]
     Unknown
 }
@@ -88,6 +97,7 @@ impl From<ash::vk::Result> for VulkanError {
             ash::vk::Result::ERROR_TOO_MANY_OBJECTS => VulkanError::TooManyObjectsError,
             ash::vk::Result::ERROR_FORMAT_NOT_SUPPORTED => VulkanError::FormatNotSupportedError,
             ash::vk::Result::ERROR_FRAGMENTED_POOL => VulkanError::FragmentedPoolError,
+            ash::vk::Result::ERROR_OUT_OF_POOL_MEMORY => VulkanError::OutOfPoolMemoryError,
             ash::vk::Result::ERROR_UNKNOWN => VulkanError::Unknown,
             _ => VulkanError::Unknown
         }
@@ -39,6 +39,10 @@ impl DeviceQueue {
     pub fn index(&self) -> u32 {
         self.index
     }
+
+    pub fn family(&self) -> &QueueFamily {
+        &self.family
+    }
 }
 
 impl Drop for DeviceQueue {
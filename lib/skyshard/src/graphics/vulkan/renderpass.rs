@@ -1,23 +1,22 @@
 use crate::graphics::vulkan::device::DeviceRef;
-use crate::graphics::vulkan::surface::SurfaceRef;
 
-pub fn create_render_pass(device: DeviceRef, surface: SurfaceRef) -> ash::vk::RenderPass {
+/// Creates the render pass of the scene, rendering into a color attachment of the given format,
+/// which is left in a layout to be sampled by the post-processing passes. The object ids are left
+/// in the general layout, so that they can be sampled by the outline pass and copied when picking.
+pub fn create_render_pass(device: DeviceRef, color_format: ash::vk::Format) -> ash::vk::RenderPass {
 
     let _device = (*device).borrow();
-    let _surface = (*surface).borrow();
-    let surface_formats = _surface.get_formats(_device.physical_device()).unwrap();
-    let surface_format = surface_formats.first().unwrap();
 
     let attachments = [
         ash::vk::AttachmentDescription::builder()
-            .format(surface_format.format)
+            .format(color_format)
             .samples(ash::vk::SampleCountFlags::TYPE_1)
             .load_op(ash::vk::AttachmentLoadOp::CLEAR)
             .store_op(ash::vk::AttachmentStoreOp::STORE)
             .stencil_load_op(ash::vk::AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(ash::vk::AttachmentStoreOp::DONT_CARE)
             .initial_layout(ash::vk::ImageLayout::UNDEFINED)
-            .final_layout(ash::vk::ImageLayout::PRESENT_SRC_KHR)
+            .final_layout(ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
             .build(),
         ash::vk::AttachmentDescription::builder()
             .format(ash::vk::Format::D32_SFLOAT_S8_UINT) // TODO: check hardware support
@@ -37,7 +36,7 @@ pub fn create_render_pass(device: DeviceRef, surface: SurfaceRef) -> ash::vk::Re
             .stencil_load_op(ash::vk::AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(ash::vk::AttachmentStoreOp::DONT_CARE)
             .initial_layout(ash::vk::ImageLayout::UNDEFINED)
-            .final_layout(ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .final_layout(ash::vk::ImageLayout::GENERAL)
             .build(),
     ];
 
@@ -91,6 +90,14 @@ pub fn create_render_pass(device: DeviceRef, surface: SurfaceRef) -> ash::vk::Re
                 ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE
             )
             .build(),
+        ash::vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .src_stage_mask(ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_subpass(ash::vk::SUBPASS_EXTERNAL)
+            .dst_stage_mask(ash::vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(ash::vk::AccessFlags::SHADER_READ)
+            .build(),
     ];
 
     let subpasses = [
@@ -112,3 +119,137 @@ pub fn create_render_pass(device: DeviceRef, surface: SurfaceRef) -> ash::vk::Re
 
     renderpass
 }
+
+/// Creates a render pass with a single depth attachment, which is left in a layout to be sampled
+/// by subsequent passes (e.g. shadow maps).
+pub fn create_depth_only_render_pass(device: DeviceRef) -> ash::vk::RenderPass {
+
+    let _device = (*device).borrow();
+
+    let attachments = [
+        ash::vk::AttachmentDescription::builder()
+            .format(ash::vk::Format::D32_SFLOAT)
+            .samples(ash::vk::SampleCountFlags::TYPE_1)
+            .load_op(ash::vk::AttachmentLoadOp::CLEAR)
+            .store_op(ash::vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(ash::vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(ash::vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ash::vk::ImageLayout::UNDEFINED)
+            .final_layout(ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build(),
+    ];
+
+    let depth_attachment_ref = ash::vk::AttachmentReference {
+        attachment: 0,
+        layout: ash::vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    };
+
+    let dependencies = [
+        ash::vk::SubpassDependency::builder()
+            .src_subpass(ash::vk::SUBPASS_EXTERNAL)
+            .src_stage_mask(ash::vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .src_access_mask(ash::vk::AccessFlags::SHADER_READ)
+            .dst_subpass(0)
+            .dst_stage_mask(
+                ash::vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | ash::vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+            )
+            .dst_access_mask(ash::vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .build(),
+        ash::vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .src_stage_mask(ash::vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+            .src_access_mask(ash::vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .dst_subpass(ash::vk::SUBPASS_EXTERNAL)
+            .dst_stage_mask(ash::vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(ash::vk::AccessFlags::SHADER_READ)
+            .build(),
+    ];
+
+    let subpasses = [
+        ash::vk::SubpassDescription::builder()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .depth_stencil_attachment(&depth_attachment_ref)
+            .build(),
+    ];
+
+    let create_info = ash::vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses)
+        .dependencies(&dependencies);
+
+    let renderpass = unsafe {
+        _device.handle().create_render_pass(&create_info, None)
+    }.unwrap();
+
+    renderpass
+}
+
+/// Creates a render pass with a single color attachment, whose previous content is discarded,
+/// as drawn by fullscreen passes. The attachment is left in `final_layout`, e.g. to be sampled by
+/// the next pass or to be presented.
+pub fn create_fullscreen_render_pass(device: DeviceRef, format: ash::vk::Format, final_layout: ash::vk::ImageLayout) -> ash::vk::RenderPass {
+
+    let _device = (*device).borrow();
+
+    let attachments = [
+        ash::vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(ash::vk::SampleCountFlags::TYPE_1)
+            .load_op(ash::vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(ash::vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(ash::vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(ash::vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ash::vk::ImageLayout::UNDEFINED)
+            .final_layout(final_layout)
+            .build(),
+    ];
+
+    let color_attachment_refs = [
+        ash::vk::AttachmentReference {
+            attachment: 0,
+            layout: ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        },
+    ];
+
+    let dependencies = [
+        // the attachment may have been sampled by a previous pass
+        ash::vk::SubpassDependency::builder()
+            .src_subpass(ash::vk::SUBPASS_EXTERNAL)
+            .src_stage_mask(
+                ash::vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+            )
+            .src_access_mask(ash::vk::AccessFlags::default())
+            .dst_subpass(0)
+            .dst_stage_mask(ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .build(),
+        ash::vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .src_stage_mask(ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_subpass(ash::vk::SUBPASS_EXTERNAL)
+            .dst_stage_mask(ash::vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(ash::vk::AccessFlags::SHADER_READ)
+            .build(),
+    ];
+
+    let subpasses = [
+        ash::vk::SubpassDescription::builder()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_refs)
+            .build(),
+    ];
+
+    let create_info = ash::vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses)
+        .dependencies(&dependencies);
+
+    let renderpass = unsafe {
+        _device.handle().create_render_pass(&create_info, None)
+    }.unwrap();
+
+    renderpass
+}
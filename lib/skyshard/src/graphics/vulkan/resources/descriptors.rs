@@ -22,6 +22,7 @@ impl <const UsagesCount: usize> TryFrom<&BufferAllocationDescriptor<UsagesCount>
                     BufferUsage::VertexBuffer => ::ash::vk::BufferUsageFlags::VERTEX_BUFFER,
                     BufferUsage::IndexBuffer => ::ash::vk::BufferUsageFlags::INDEX_BUFFER,
                     BufferUsage::IndirectBuffer => ::ash::vk::BufferUsageFlags::INDIRECT_BUFFER,
+                    BufferUsage::StorageBuffer => ::ash::vk::BufferUsageFlags::STORAGE_BUFFER,
                     BufferUsage::TransferSourceBuffer => ::ash::vk::BufferUsageFlags::TRANSFER_SRC,
                     BufferUsage::TransferDestinationBuffer => ::ash::vk::BufferUsageFlags::TRANSFER_DST,
                 })
@@ -38,6 +39,12 @@ pub struct ImageAllocationDescriptor<const N: usize> {
     pub format: ImageFormat,
     pub tiling: ImageTiling,
     pub memory: MemoryLocation,
+    pub mip_levels: u32,
+    /// The number of layers, e.g. 6 for a cube map.
+    pub array_layers: u32,
+    /// Whether views of the type `ImageViewType::CUBE` can be created of the image, which
+    /// requires six square layers.
+    pub cube_compatible: bool,
 }
 
 impl <const N: usize> TryFrom<&ImageAllocationDescriptor<N>> for ::ash::vk::ImageCreateInfoBuilder<'_> {
@@ -48,8 +55,12 @@ impl <const N: usize> TryFrom<&ImageAllocationDescriptor<N>> for ::ash::vk::Imag
         let builder = ash::vk::ImageCreateInfo::builder()
             .image_type(::ash::vk::ImageType::TYPE_2D)
             .extent((&descriptor.extent).into())
-            .mip_levels(1)
-            .array_layers(1)
+            .flags(match descriptor.cube_compatible {
+                true => ::ash::vk::ImageCreateFlags::CUBE_COMPATIBLE,
+                false => ::ash::vk::ImageCreateFlags::empty(),
+            })
+            .mip_levels(descriptor.mip_levels)
+            .array_layers(descriptor.array_layers)
             .format(descriptor.format.into())
             .tiling(descriptor.tiling.into())
             .initial_layout(ash::vk::ImageLayout::UNDEFINED)
@@ -62,6 +73,7 @@ impl <const N: usize> TryFrom<&ImageAllocationDescriptor<N>> for ::ash::vk::Imag
                     ImageUsage::TransferDestination => ::ash::vk::ImageUsageFlags::TRANSFER_DST,
                     ImageUsage::TransferSource => ::ash::vk::ImageUsageFlags::TRANSFER_SRC,
                     ImageUsage::ColorAttachment => ::ash::vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                    ImageUsage::Storage => ::ash::vk::ImageUsageFlags::STORAGE,
                 })
             }));
         Ok(builder)
@@ -72,6 +84,7 @@ impl <const N: usize> TryFrom<&ImageAllocationDescriptor<N>> for ::ash::vk::Imag
 pub enum BufferUsage {
     IndexBuffer,
     IndirectBuffer,
+    StorageBuffer,
     UniformBuffer,
     VertexBuffer,
     TransferSourceBuffer,
@@ -85,6 +98,7 @@ pub enum ImageUsage {
     TransferDestination,
     TransferSource,
     ColorAttachment,
+    Storage,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -107,7 +121,13 @@ impl From<MemoryLocation> for gpu_allocator::MemoryLocation {
 #[derive(Debug, Copy, Clone)]
 pub enum ImageFormat {
     RGBA8,
+    SRGBA8,
+    /// Half floats per channel, e.g. for HDR render targets.
+    RGBA16F,
+    /// Single precision floats per channel, e.g. for environment maps.
+    RGBA32F,
     UInt32,
+    Depth,
     DepthStencil,
 }
 
@@ -115,7 +135,11 @@ impl From<ImageFormat> for ::ash::vk::Format {
     fn from(format: ImageFormat) -> Self {
         match format {
             ImageFormat::RGBA8 => ::ash::vk::Format::R8G8B8A8_UNORM,
+            ImageFormat::SRGBA8 => ::ash::vk::Format::R8G8B8A8_SRGB,
+            ImageFormat::RGBA16F => ::ash::vk::Format::R16G16B16A16_SFLOAT,
+            ImageFormat::RGBA32F => ::ash::vk::Format::R32G32B32A32_SFLOAT,
             ImageFormat::UInt32 => ::ash::vk::Format::R32_UINT,
+            ImageFormat::Depth => ::ash::vk::Format::D32_SFLOAT,
             ImageFormat::DepthStencil => ::ash::vk::Format::D32_SFLOAT_S8_UINT,
         }
     }
@@ -30,11 +30,14 @@ const NON_COHERENT_ATOM_SIZE: ::ash::vk::DeviceSize = 64; // TODO: Get value fro
 pub struct ResourceManager {
     device: Device,
     allocator: Allocator,
+    queue_family_indices: Vec<u32>,
 }
 
 impl ResourceManager {
 
-    pub fn new(instance: &Instance, device: &Device, physical_device: &PhysicalDevice) -> Result<ResourceManager> {
+    /// Creates a new resource manager. Resources are shared concurrently between the queue
+    /// families, if more than one queue family index is passed.
+    pub fn new(instance: &Instance, device: &Device, physical_device: &PhysicalDevice, queue_family_indices: &[u32]) -> Result<ResourceManager> {
         let result = ResourceManager {
             device: Clone::clone(device),
             allocator: Allocator::new(
@@ -45,6 +48,7 @@ impl ResourceManager {
                     debug_settings: Default::default(),
                     buffer_device_address: true,
                 }).expect("create allocator"),
+            queue_family_indices: queue_family_indices.to_vec(),
         };
         info!("ResourceManager created.");
         Ok(result)
@@ -60,8 +64,15 @@ impl ResourceManager {
             let buffer_create_info  = descriptor
                 .try_into()
                 .map(|builder: ::ash::vk::BufferCreateInfoBuilder| {
-                    builder.size(size as ash::vk::DeviceSize)
-                           .build()
+                    let builder = builder.size(size as ash::vk::DeviceSize);
+                    if self.queue_family_indices.len() > 1 {
+                        builder.sharing_mode(::ash::vk::SharingMode::CONCURRENT)
+                               .queue_family_indices(&self.queue_family_indices)
+                               .build()
+                    }
+                    else {
+                        builder.build()
+                    }
                 })?;
 
             unsafe { self.device.create_buffer(&buffer_create_info, None) }
@@ -107,7 +118,14 @@ impl ResourceManager {
             let image_create_info = descriptor
                 .try_into()
                 .map(|builder: ::ash::vk::ImageCreateInfoBuilder| {
-                    builder.build()
+                    if self.queue_family_indices.len() > 1 {
+                        builder.sharing_mode(::ash::vk::SharingMode::CONCURRENT)
+                               .queue_family_indices(&self.queue_family_indices)
+                               .build()
+                    }
+                    else {
+                        builder.build()
+                    }
                 })?;
 
             unsafe { self.device.create_image(&image_create_info, None) }
@@ -169,6 +187,22 @@ impl ResourceManager {
             .map_err(|error| ResourceManagerError::FlushMemoryError { name: String::from(resource.name()) } )
     }
 
+    /// Makes writes of the device visible to the host, before reading the resource's memory.
+    pub unsafe fn invalidate<A>(&self, resource: &A, offset: usize, count: usize) -> Result<()>
+        where A: Resource {
+
+        let ranges = [
+            ::ash::vk::MappedMemoryRange::builder()
+                .memory(resource.allocation().memory())
+                .offset(resource.allocation().offset() + resource.byte_offset(offset))
+                .size(resource.byte_size(count))
+                .build()
+        ];
+
+        self.device.invalidate_mapped_memory_ranges(&ranges)
+            .map_err(|_| ResourceManagerError::InvalidateMemoryError { name: String::from(resource.name()) } )
+    }
+
     pub fn free<A>(&mut self, mut resource: A) -> Result<()>
         where A: Resource {
 
@@ -232,6 +266,11 @@ pub enum ResourceManagerError {
         name: String
     },
 
+    #[error("Failed to invalidate memory of '{name}'!")]
+    InvalidateMemoryError {
+        name: String
+    },
+
     #[error("Failed to free memory of '{name}'!")]
     FreeMemoryError {
         name: String
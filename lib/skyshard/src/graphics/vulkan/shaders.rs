@@ -23,6 +23,44 @@ impl VertexShaderBinary {
             code: ::ash::util::read_spv(&mut Cursor::new(binary)).unwrap()
         }
     }
+
+    /// Returns the locations of the shader's inputs in ascending order, read from the `Location`
+    /// decorations of its `Input` variables. Built-in inputs have no location.
+    pub fn input_locations(&self) -> Vec<u32> {
+
+        const OP_DECORATE: u32 = 71;
+        const OP_VARIABLE: u32 = 59;
+        const DECORATION_LOCATION: u32 = 30;
+        const STORAGE_CLASS_INPUT: u32 = 1;
+
+        let mut locations = Vec::new();
+        let mut inputs = Vec::new();
+
+        // the instructions follow the header of five words
+        let mut offset = 5;
+        while offset < self.code.len() {
+
+            let word_count = ((self.code[offset] >> 16) as usize).max(1);
+            let opcode = self.code[offset] & 0xffff;
+            let operands = self.code.get(offset + 1..offset + word_count).unwrap_or(&[]);
+
+            match (opcode, operands) {
+                (OP_DECORATE, [target, DECORATION_LOCATION, location, ..]) => locations.push((*target, *location)),
+                (OP_VARIABLE, [_, result, STORAGE_CLASS_INPUT, ..]) => inputs.push(*result),
+                _ => {}
+            }
+
+            offset += word_count;
+        }
+
+        let mut locations = locations.into_iter()
+            .filter(|(target, _)| inputs.contains(target))
+            .map(|(_, location)| location)
+            .collect::<Vec<_>>();
+
+        locations.sort_unstable();
+        locations
+    }
 }
 
 impl ShaderBinary for VertexShaderBinary {
@@ -82,6 +120,29 @@ impl ShaderBinary for GeometryShaderBinary {
     }
 }
 
+pub struct ComputeShaderBinary {
+    code: Code,
+}
+
+impl ComputeShaderBinary {
+    pub fn new(binary: &[u8]) -> ComputeShaderBinary {
+        ComputeShaderBinary {
+            code: ::ash::util::read_spv(&mut Cursor::new(binary)).unwrap()
+        }
+    }
+}
+
+impl ShaderBinary for ComputeShaderBinary {
+
+    fn stage(&self) -> ash::vk::ShaderStageFlags {
+        ash::vk::ShaderStageFlags::COMPUTE
+    }
+
+    fn code(&self) -> &[u32] {
+        self.code.as_slice()
+    }
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum ShaderModuleError {
 
@@ -139,6 +200,10 @@ where A: ShaderBinary {
 impl <A> LoadedShaderModule<A>
 where A: ShaderBinary {
 
+    pub fn shader(&self) -> &A {
+        &self.inner.shader
+    }
+
     pub fn unload(self, device: &Device) -> Result<ShaderModule<A>, ShaderModuleError> {
 
         unsafe {
@@ -159,3 +224,37 @@ where A: ShaderBinary {
             .build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::graphics::vulkan::shaders::VertexShaderBinary;
+
+    fn instruction(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        [&[((operands.len() as u32 + 1) << 16) | opcode], operands].concat()
+    }
+
+    #[test]
+    fn test_input_locations_of_vertex_shader() {
+
+        let code = [
+            vec![0x07230203, 0x00010000, 0, 16, 0],
+            // OpDecorate %10 Location 3, OpDecorate %11 Location 0, OpDecorate %12 Location 1
+            instruction(71, &[10, 30, 3]),
+            instruction(71, &[11, 30, 0]),
+            instruction(71, &[12, 30, 1]),
+            // OpDecorate %13 BuiltIn VertexIndex
+            instruction(71, &[13, 11, 42]),
+            // %10, %11 and %13 are inputs, %12 an output
+            instruction(59, &[2, 10, 1]),
+            instruction(59, &[2, 11, 1]),
+            instruction(59, &[3, 12, 3]),
+            instruction(59, &[4, 13, 1]),
+        ].concat();
+
+        let bytes = code.iter().flat_map(|word| word.to_ne_bytes()).collect::<Vec<_>>();
+
+        assert_that!(VertexShaderBinary::new(&bytes).input_locations(), is(equal_to(vec![0, 3])));
+    }
+}
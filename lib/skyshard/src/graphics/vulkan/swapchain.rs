@@ -42,6 +42,9 @@ pub struct Swapchain {
     handle: vk::SwapchainKHR,
     images: Vec<vk::Image>,
     views: Vec<vk::ImageView>,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    capturable: bool,
     depth_image: vk::Image,
     depth_image_view: vk::ImageView,
     device: DeviceRef,
@@ -103,13 +106,21 @@ impl Swapchain {
                 .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
                 .unwrap_or(vk::PresentModeKHR::FIFO);
 
+            // the images are copied from, if the frames are captured
+            let capturable = capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC);
+
+            let image_usage = match capturable {
+                true => vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                false => vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            };
+
             let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
                 .surface(*_surface.handle())
                 .min_image_count(desired_image_count)
                 .image_color_space(format.color_space)
                 .image_format(format.format)
                 .image_extent(resolution)
-                .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+                .image_usage(image_usage)
                 .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
                 .pre_transform(pre_transform)
                 .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
@@ -165,7 +176,10 @@ impl Swapchain {
                     extent: Extent::from(resolution.width, resolution.height, 1),
                     format: ImageFormat::DepthStencil,
                     tiling: ImageTiling::Optimal,
-                    memory: MemoryLocation::GpuOnly
+                    memory: MemoryLocation::GpuOnly,
+                    mip_levels: 1,
+                    array_layers: 1,
+                    cube_compatible: false,
                 }).expect("depth image");
 
                 *image.handle()
@@ -200,6 +214,9 @@ impl Swapchain {
                 handle,
                 images,
                 views,
+                format: format.format,
+                extent: resolution,
+                capturable,
                 depth_image,
                 depth_image_view,
                 device: device.clone(),
@@ -223,6 +240,23 @@ impl Swapchain {
         &self.views
     }
 
+    pub fn images(&self) -> &Vec<ash::vk::Image> {
+        &self.images
+    }
+
+    pub fn format(&self) -> ash::vk::Format {
+        self.format
+    }
+
+    pub fn extent(&self) -> ash::vk::Extent2D {
+        self.extent
+    }
+
+    /// Whether the images can be copied from, see `capture_frame`.
+    pub fn is_capturable(&self) -> bool {
+        self.capturable
+    }
+
     pub fn depth_image_view(&self) -> &ash::vk::ImageView {
         &self.depth_image_view
     }
@@ -6,17 +6,46 @@ extern crate memoffset;
 mod engine;
 mod util;
 
+pub mod animation;
 pub mod assets;
+pub mod curve;
 pub mod entity;
 pub mod graphics;
+pub mod mesh;
+pub mod noise;
+pub mod particles;
 
 use engine::Engine;
 
 pub use engine::create;
+pub use engine::EngineShaders;
 pub use engine::create_geometry;
+pub use engine::create_geometry_with_material;
+pub use engine::create_geometry_lod;
+pub use engine::create_skin;
+pub use engine::create_material;
+pub use engine::create_compute_pipeline;
+pub use engine::dispatch_compute;
+pub use engine::destroy_material;
+pub use engine::create_skybox;
+pub use engine::destroy_skybox;
+pub use engine::create_environment_lighting;
+pub use engine::destroy_environment_lighting;
 pub use engine::update_geometry;
 pub use engine::pick_object;
+pub use engine::set_selection;
+pub use engine::selection;
+pub use engine::debug_lines;
+pub use engine::overlay;
+pub use engine::create_overlay_texture;
+pub use engine::destroy_overlay_texture;
+pub use engine::capture_frame;
+pub use engine::request_frame_capture;
+pub use engine::take_captured_frame;
+pub use engine::start_frame_sequence;
+pub use engine::stop_frame_sequence;
 pub use engine::render;
 pub use engine::prepare;
 pub use engine::Vertex;
+pub use engine::SkinnedVertex;
 pub use engine::InstanceData;
@@ -0,0 +1,167 @@
+use nalgebra::Vector3;
+
+use crate::engine::Vertex;
+use crate::mesh::IndexData;
+use crate::noise::{Fractal, Noise};
+
+/// Heights on a regular grid in the xz-plane, e.g. of terrain. As y points down, a height `h`
+/// places the surface at `y = -h`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    spacing: f32,
+    /// The heights with x increasing fastest.
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+
+    /// Panics if the number of heights does not match or the grid is smaller than 2x2 points.
+    pub fn new(width: usize, depth: usize, spacing: f32, heights: Vec<f32>) -> Heightmap {
+        assert!(width >= 2 && depth >= 2, "a heightmap needs at least 2x2 points, but was {width}x{depth}");
+        assert_eq!(heights.len(), width * depth, "the heights do not match the {width}x{depth} points of the heightmap");
+        Heightmap { width, depth, spacing, heights }
+    }
+
+    /// Samples the fractal at the points of the grid starting at the origin's x and z, scaled by
+    /// the amplitude. Heightmaps sampled at origins a multiple of the spacing apart share the
+    /// heights where they overlap.
+    pub fn from_noise(noise: &Noise, fractal: &Fractal, origin: [f32; 2], width: usize, depth: usize, spacing: f32, amplitude: f32) -> Heightmap {
+
+        let heights = noise.sample_2d(&Vector3::new(origin[0], origin[1], 0.0), spacing, [width, depth], fractal)
+            .into_iter()
+            .map(|height| height * amplitude)
+            .collect();
+
+        Heightmap::new(width, depth, spacing, heights)
+    }
+
+    /// Samples the chunk of terrain with `resolution` quads along each side, starting at
+    /// `chunk * resolution * spacing` on the xz-plane. The heightmap has a border of one point
+    /// around the chunk, so that [Heightmap::mesh] with a border of 1 yields chunks whose
+    /// normals match along their edges.
+    pub fn chunk(noise: &Noise, fractal: &Fractal, chunk: [i32; 2], resolution: usize, spacing: f32, amplitude: f32) -> Heightmap {
+
+        let size = resolution as f32 * spacing;
+        let origin = chunk.map(|coordinate| coordinate as f32 * size - spacing);
+
+        Heightmap::from_noise(noise, fractal, origin, resolution + 3, resolution + 3, spacing, amplitude)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    pub fn height(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.width + x]
+    }
+
+    /// Returns the unit normal pointing upwards at the point, from the differences to its
+    /// neighbours, or to the point itself at the edges.
+    pub fn normal(&self, x: usize, z: usize) -> Vector3<f32> {
+
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (back, front) = (z.saturating_sub(1), (z + 1).min(self.depth - 1));
+
+        let dx = (self.height(right, z) - self.height(left, z)) / ((right - left) as f32 * self.spacing);
+        let dz = (self.height(x, front) - self.height(x, back)) / ((front - back) as f32 * self.spacing);
+
+        Vector3::new(-dx, -1.0, -dz).normalize()
+    }
+
+    /// Triangulates the grid without the `border` outermost points, which only contribute to the
+    /// normals. The mesh starts at the origin with texture coordinates spanning it from 0.0 to
+    /// 1.0, its triangles face upwards.
+    pub fn mesh(&self, border: usize) -> (IndexData, Vec<Vertex>) {
+
+        assert!(self.width >= 2 * border + 2 && self.depth >= 2 * border + 2, "the border leaves no quads of the {}x{} heightmap", self.width, self.depth);
+
+        let (width, depth) = (self.width - 2 * border, self.depth - 2 * border);
+
+        let vertices = (0..depth).flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let (grid_x, grid_z) = (x + border, z + border);
+                Vertex {
+                    position: [x as f32 * self.spacing, -self.height(grid_x, grid_z), z as f32 * self.spacing],
+                    normal: self.normal(grid_x, grid_z).into(),
+                    color: [1.0, 1.0, 1.0],
+                    uv: [x as f32 / (width - 1) as f32, z as f32 / (depth - 1) as f32],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // counter-clockwise seen from above, i.e. from -y
+        let indices = (0..depth - 1).flat_map(|z| (0..width - 1).map(move |x| (x, z)))
+            .flat_map(|(x, z)| {
+                let index = |x: usize, z: usize| (z * width + x) as u32;
+                [
+                    index(x, z), index(x + 1, z), index(x, z + 1),
+                    index(x + 1, z), index(x + 1, z + 1), index(x, z + 1),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        (IndexData::compact(&indices), vertices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::mesh::{Heightmap, IndexData};
+    use crate::noise::{Fractal, Interpolation, Noise};
+
+    #[test]
+    fn test_mesh_of_a_slope_faces_upwards() {
+
+        // rising by 0.5 per point along x
+        let heightmap = Heightmap::new(3, 2, 2.0, vec![0.0, 0.5, 1.0, 0.0, 0.5, 1.0]);
+
+        let (indices, vertices) = heightmap.mesh(0);
+
+        assert_that!(vertices.len(), is(equal_to(6)));
+        assert_that!(vertices[5].position, is(equal_to([4.0, -1.0, 2.0])));
+        assert_that!(vertices[5].uv, is(equal_to([1.0, 1.0])));
+        assert_that!(indices, is(equal_to(IndexData::U16(vec![0, 1, 3, 1, 4, 3, 1, 2, 4, 2, 5, 4]))));
+
+        let expected = Vector3::new(-0.25, -1.0, 0.0).normalize();
+        assert_that!(Vector3::from(vertices[1].normal), is(equal_to(expected)));
+
+        // the winding's normal points up as well
+        let corner = |index: usize| Vector3::from(vertices[index].position);
+        let face = (corner(1) - corner(0)).cross(&(corner(3) - corner(0)));
+        assert_that!(face.y < 0.0, is(true));
+    }
+
+    #[test]
+    fn test_adjacent_chunks_share_their_edge() {
+
+        let noise = Noise::new("terrain", 64, Interpolation::Linear);
+        let fractal = Fractal { frequency: 0.1, ..Fractal::default() };
+
+        let (_, left) = Heightmap::chunk(&noise, &fractal, [0, 0], 8, 0.5, 4.0).mesh(1);
+        let (_, right) = Heightmap::chunk(&noise, &fractal, [1, 0], 8, 0.5, 4.0).mesh(1);
+
+        assert_that!(left.len(), is(equal_to(81)));
+        (0..9).for_each(|z| {
+            let (edge, start) = (&left[z * 9 + 8], &right[z * 9]);
+            assert_that!(edge.position[1], is(equal_to(start.position[1])));
+            assert_that!(edge.normal, is(equal_to(start.normal)));
+        });
+    }
+}
@@ -0,0 +1,18 @@
+mod heightmap;
+mod optimize;
+mod simplify;
+
+use crate::engine::Vertex;
+pub use crate::mesh::heightmap::Heightmap;
+pub use crate::mesh::optimize::{average_cache_miss_ratio, IndexData, optimize_mesh, optimize_vertex_cache, optimize_vertex_fetch, weld_vertices};
+pub use crate::mesh::simplify::{generate_lods, simplify, simplify_mesh, Simplification, SimplifyOptions};
+
+/// The bits of all attributes of a vertex, used to find identical vertices.
+pub(crate) fn vertex_key(vertex: &Vertex) -> [u32; 11] {
+    [
+        vertex.position[0], vertex.position[1], vertex.position[2],
+        vertex.normal[0], vertex.normal[1], vertex.normal[2],
+        vertex.color[0], vertex.color[1], vertex.color[2],
+        vertex.uv[0], vertex.uv[1],
+    ].map(f32::to_bits)
+}
@@ -0,0 +1,329 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::engine::Vertex;
+use crate::mesh::vertex_key;
+
+/// Size of the simulated post-transform cache used by `optimize_vertex_cache`.
+const CACHE_SIZE: usize = 32;
+
+/// Index data of a geometry, either with 16 or 32 bits per index.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexData {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl IndexData {
+
+    /// Returns 16-bit indices if all indices fit, otherwise 32-bit indices. `u16::MAX` is not
+    /// used, as it is reserved for primitive restart.
+    pub fn compact(indices: &[u32]) -> IndexData {
+        if indices.iter().all(|index| *index < u16::MAX as u32) {
+            IndexData::U16(indices.iter().map(|index| *index as u16).collect())
+        }
+        else {
+            IndexData::U32(indices.to_vec())
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            IndexData::U16(indices) => indices.len(),
+            IndexData::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_u32(&self) -> Vec<u32> {
+        match self {
+            IndexData::U16(indices) => indices.iter().map(|index| *index as u32).collect(),
+            IndexData::U32(indices) => indices.clone(),
+        }
+    }
+}
+
+impl From<Vec<u32>> for IndexData {
+    fn from(indices: Vec<u32>) -> Self {
+        IndexData::U32(indices)
+    }
+}
+
+/// Merges identical vertices and returns the remapped indices together with the unique vertices
+/// in the order of their first occurrence.
+pub fn weld_vertices(indices: &[u32], vertices: &[Vertex]) -> (Vec<u32>, Vec<Vertex>) {
+
+    let mut unique: HashMap<[u32; 11], u32> = HashMap::new();
+    let mut welded = Vec::new();
+
+    let remap = vertices.iter().map(|vertex| {
+        *unique.entry(vertex_key(vertex)).or_insert_with(|| {
+            welded.push(*vertex);
+            welded.len() as u32 - 1
+        })
+    }).collect::<Vec<_>>();
+
+    (indices.iter().map(|index| remap[*index as usize]).collect(), welded)
+}
+
+/// Reorders the triangles, so that consecutive triangles share vertices still in the
+/// post-transform cache (Tom Forsyth's linear-speed vertex cache optimisation).
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+
+    let triangle_count = indices.len() / 3;
+
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    (0..triangle_count).for_each(|triangle| {
+        (0..3).for_each(|corner| adjacency[indices[triangle * 3 + corner] as usize].push(triangle));
+    });
+
+    let mut cache_positions: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores = (0..vertex_count)
+        .map(|vertex| vertex_score(None, adjacency[vertex].len()))
+        .collect::<Vec<_>>();
+    let mut triangle_scores = (0..triangle_count)
+        .map(|triangle| (0..3).map(|corner| vertex_scores[indices[triangle * 3 + corner] as usize]).sum::<f32>())
+        .collect::<Vec<_>>();
+
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: VecDeque<u32> = VecDeque::with_capacity(CACHE_SIZE + 3);
+    let mut result = Vec::with_capacity(triangle_count * 3);
+    let mut best = best_triangle(&triangle_scores, &emitted, 0);
+    let mut scan_start = 0;
+
+    while let Some(triangle) = best {
+
+        emitted[triangle] = true;
+        triangle_scores[triangle] = f32::MIN;
+
+        let corners = [0, 1, 2].map(|corner| indices[triangle * 3 + corner]);
+        result.extend_from_slice(&corners);
+
+        corners.iter().for_each(|vertex| {
+            adjacency[*vertex as usize].retain(|adjacent| *adjacent != triangle);
+            cache.retain(|cached| cached != vertex);
+        });
+        corners.iter().rev().for_each(|vertex| cache.push_front(*vertex));
+
+        // vertices pushed out of the cache have to be rescored as well
+        let evicted = cache.iter().skip(CACHE_SIZE).copied().collect::<Vec<_>>();
+        cache.truncate(CACHE_SIZE);
+
+        evicted.iter().for_each(|vertex| cache_positions[*vertex as usize] = None);
+        cache.iter().enumerate().for_each(|(position, vertex)| cache_positions[*vertex as usize] = Some(position));
+
+        cache.iter().chain(evicted.iter()).for_each(|vertex| {
+            let vertex = *vertex as usize;
+            vertex_scores[vertex] = vertex_score(cache_positions[vertex], adjacency[vertex].len());
+        });
+
+        best = None;
+        let mut best_score = f32::MIN;
+
+        cache.iter().chain(evicted.iter()).for_each(|vertex| {
+            adjacency[*vertex as usize].iter().for_each(|adjacent| {
+                let score = (0..3).map(|corner| vertex_scores[indices[adjacent * 3 + corner] as usize]).sum::<f32>();
+                triangle_scores[*adjacent] = score;
+                if score > best_score {
+                    best_score = score;
+                    best = Some(*adjacent);
+                }
+            });
+        });
+
+        // no unemitted triangle shares a vertex with the cache, e.g. at the end of a connected
+        // component, triangles before the first unemitted one need not be scanned again
+        if best.is_none() {
+            while scan_start < triangle_count && emitted[scan_start] {
+                scan_start += 1;
+            }
+            best = best_triangle(&triangle_scores, &emitted, scan_start);
+        }
+    }
+
+    result
+}
+
+/// Reorders the vertices in the order of their first use by the indices, which improves the
+/// locality of vertex fetches. Unreferenced vertices are dropped.
+pub fn optimize_vertex_fetch(indices: &[u32], vertices: &[Vertex]) -> (Vec<u32>, Vec<Vertex>) {
+
+    let mut remap: Vec<Option<u32>> = vec![None; vertices.len()];
+    let mut reordered = Vec::with_capacity(vertices.len());
+
+    let indices = indices.iter().map(|index| {
+        *remap[*index as usize].get_or_insert_with(|| {
+            reordered.push(vertices[*index as usize]);
+            reordered.len() as u32 - 1
+        })
+    }).collect();
+
+    (indices, reordered)
+}
+
+/// Welds identical vertices and optimizes the triangle order for the post-transform cache and the
+/// vertex order for fetching. Returns 16-bit indices, if possible and requested.
+pub fn optimize_mesh(indices: &[u32], vertices: &[Vertex], allow_16_bit_indices: bool) -> (IndexData, Vec<Vertex>) {
+
+    let (indices, vertices) = weld_vertices(indices, vertices);
+    let indices = optimize_vertex_cache(&indices, vertices.len());
+    let (indices, vertices) = optimize_vertex_fetch(&indices, &vertices);
+
+    if allow_16_bit_indices {
+        (IndexData::compact(&indices), vertices)
+    }
+    else {
+        (IndexData::U32(indices), vertices)
+    }
+}
+
+/// Returns the average number of vertex shader invocations per triangle with a FIFO
+/// post-transform cache of the given size. Lower is better, `0.5` is the optimum for large grids.
+pub fn average_cache_miss_ratio(indices: &[u32], cache_size: usize) -> f32 {
+
+    let mut cache: VecDeque<u32> = VecDeque::with_capacity(cache_size);
+    let misses = indices.iter().filter(|index| {
+        if cache.contains(index) {
+            return false;
+        }
+        if cache.len() == cache_size {
+            cache.pop_back();
+        }
+        cache.push_front(**index);
+        true
+    }).count();
+
+    misses as f32 / (indices.len() / 3).max(1) as f32
+}
+
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        None => 0.0,
+        // the last triangle's vertices get a fixed score, so that strips are not preferred
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+    };
+
+    // vertices with few remaining triangles are preferred to get rid of them
+    cache_score + 2.0 * (remaining_triangles as f32).powf(-0.5)
+}
+
+fn best_triangle(scores: &[f32], emitted: &[bool], start: usize) -> Option<usize> {
+    (start..scores.len())
+        .filter(|triangle| !emitted[*triangle])
+        .max_by(|a, b| scores[*a].total_cmp(&scores[*b]))
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::engine::Vertex;
+    use crate::mesh::{average_cache_miss_ratio, IndexData, optimize_mesh, optimize_vertex_cache, optimize_vertex_fetch, weld_vertices};
+
+    fn vertex(x: f32, y: f32) -> Vertex {
+        Vertex {
+            position: [x, y, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            color: [1.0, 1.0, 1.0],
+            uv: [x, y],
+        }
+    }
+
+    /// A grid of quads with every triangle referencing its own vertices, like the demo's meshes.
+    fn unwelded_grid(size: u32) -> (Vec<u32>, Vec<Vertex>) {
+        let mut vertices = Vec::new();
+        (0..size).for_each(|y| {
+            (0..size).for_each(|x| {
+                let (x, y) = (x as f32, y as f32);
+                vertices.extend_from_slice(&[
+                    vertex(x, y), vertex(x + 1.0, y), vertex(x + 1.0, y + 1.0),
+                    vertex(x, y), vertex(x + 1.0, y + 1.0), vertex(x, y + 1.0),
+                ]);
+            });
+        });
+        ((0..vertices.len() as u32).collect(), vertices)
+    }
+
+    #[test]
+    fn test_weld_vertices_merges_identical_vertices() {
+
+        let (indices, vertices) = unwelded_grid(2);
+
+        let (indices, welded) = weld_vertices(&indices, &vertices);
+
+        assert_that!(welded.len(), is(equal_to(9)));
+        assert_that!(indices.len(), is(equal_to(24)));
+        indices.iter().enumerate().for_each(|(index, vertex)| {
+            assert_that!(welded[*vertex as usize].position, is(equal_to(vertices[index].position)));
+        });
+    }
+
+    #[test]
+    fn test_optimize_vertex_cache_improves_cache_reuse() {
+
+        let (indices, vertices) = unwelded_grid(16);
+        let (indices, welded) = weld_vertices(&indices, &vertices);
+
+        // a row-major triangle order already reuses some vertices, a shuffled one hardly any
+        let mut shuffled = indices.chunks_exact(3).collect::<Vec<_>>();
+        let count = shuffled.len();
+        (0..count).for_each(|index| shuffled.swap(index, (index * 7919) % count));
+        let shuffled = shuffled.concat();
+
+        let optimized = optimize_vertex_cache(&shuffled, welded.len());
+
+        assert_that!(optimized.len(), is(equal_to(shuffled.len())));
+        assert_that!(average_cache_miss_ratio(&optimized, 16), is(less_than(average_cache_miss_ratio(&shuffled, 16))));
+        assert_that!(average_cache_miss_ratio(&optimized, 16), is(less_than(0.9)));
+    }
+
+    #[test]
+    fn test_optimize_vertex_cache_keeps_all_triangles_of_disconnected_meshes() {
+
+        // a fan of six triangles around vertex 0 and two isolated triangles, which score higher
+        let indices = [
+            0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5, 0, 5, 6, 0, 6, 1,
+            7, 8, 9,
+            10, 11, 12,
+        ];
+
+        let optimized = optimize_vertex_cache(&indices, 13);
+
+        assert_that!(optimized.len(), is(equal_to(indices.len())));
+        let triangles = optimized.chunks_exact(3).collect::<Vec<_>>();
+        indices.chunks_exact(3).for_each(|triangle| {
+            assert_that!(triangles.contains(&triangle), is(true));
+        });
+    }
+
+    #[test]
+    fn test_optimize_vertex_fetch_orders_vertices_by_first_use() {
+
+        let vertices = vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(2.0, 0.0), vertex(3.0, 0.0)];
+
+        let (indices, reordered) = optimize_vertex_fetch(&[2, 0, 3, 3, 0, 2], &vertices);
+
+        assert_that!(indices, is(equal_to(vec![0, 1, 2, 2, 1, 0])));
+        assert_that!(reordered.iter().map(|vertex| vertex.position[0]).collect::<Vec<_>>(), is(equal_to(vec![2.0, 0.0, 3.0])));
+    }
+
+    #[test]
+    fn test_optimize_mesh_generates_16_bit_indices() {
+
+        let (indices, vertices) = unwelded_grid(4);
+
+        let (indices, vertices) = optimize_mesh(&indices, &vertices, true);
+
+        assert_that!(vertices.len(), is(equal_to(25)));
+        assert_that!(matches!(indices, IndexData::U16(_)), is(true));
+        assert_that!(IndexData::compact(&[0, u16::MAX as u32]), is(equal_to(IndexData::U32(vec![0, u16::MAX as u32]))));
+    }
+}
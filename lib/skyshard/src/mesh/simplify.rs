@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+
+use crate::assets::MeshData;
+use crate::engine::Vertex;
+use crate::mesh::vertex_key;
+
+/// Weight of the planes constraining border edges relative to the triangle planes.
+const BORDER_WEIGHT: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimplifyOptions {
+    /// The number of triangles to stop at.
+    pub target_triangles: usize,
+    /// The largest error a collapse may introduce, relative to the extent of the mesh.
+    pub max_error: f32,
+    /// Whether vertices on an open border must not move at all. Otherwise they only move along
+    /// the border.
+    pub lock_border: bool,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> Self {
+        SimplifyOptions {
+            target_triangles: 0,
+            max_error: f32::MAX,
+            lock_border: false,
+        }
+    }
+}
+
+/// The result of a simplification.
+#[derive(Clone, Debug)]
+pub struct Simplification {
+    /// Triangle list indexing the vertices passed to `simplify`.
+    pub indices: Vec<u32>,
+    /// The largest error of all collapses, relative to the extent of the mesh.
+    pub error: f32,
+}
+
+/// Reduces the number of triangles of a mesh by collapsing edges in the order of their quadric
+/// error. Vertices are only moved onto other vertices of the mesh, so the result indexes the
+/// given vertices and can be uploaded together with them.
+///
+/// Vertices sharing a position with vertices of different attributes (UV or normal seams) are
+/// never moved, vertices of open borders only along the border.
+pub fn simplify(indices: &[u32], vertices: &[Vertex], options: &SimplifyOptions) -> Simplification {
+
+    let positions = vertices.iter().map(|vertex| vertex.position).collect::<Vec<_>>();
+
+    // identical vertices are merged, vertices only sharing the position form a seam
+    let mut identical: HashMap<[u32; 11], u32> = HashMap::new();
+    let welded = vertices.iter().enumerate().map(|(index, vertex)| {
+        *identical.entry(vertex_key(vertex)).or_insert(index as u32)
+    }).collect::<Vec<_>>();
+
+    let indices = indices.iter().map(|index| welded[*index as usize]).collect::<Vec<_>>();
+
+    simplify_positions(&indices, &positions, &welded, options)
+}
+
+/// Simplifies an imported mesh like `simplify`, seams are formed by differing texture
+/// coordinates.
+pub fn simplify_mesh(mesh: &MeshData, options: &SimplifyOptions) -> Simplification {
+
+    let mut identical: HashMap<[u32; 5], u32> = HashMap::new();
+    let welded = mesh.positions.iter().enumerate().map(|(index, position)| {
+        let uv = mesh.texture_coordinates.get(index).copied().unwrap_or([0.0, 0.0]);
+        let key = [position[0], position[1], position[2], uv[0], uv[1]].map(f32::to_bits);
+        *identical.entry(key).or_insert(index as u32)
+    }).collect::<Vec<_>>();
+
+    let indices = mesh.indices.iter().map(|index| welded[*index as usize]).collect::<Vec<_>>();
+
+    simplify_positions(&indices, &mesh.positions, &welded, options)
+}
+
+/// Simplifies a mesh given by its positions only. `wedges` maps each vertex to its representative
+/// among all vertices with identical attributes, vertices sharing only their position with
+/// another representative are treated as seam.
+fn simplify_positions(indices: &[u32], positions: &[[f32; 3]], wedges: &[u32], options: &SimplifyOptions) -> Simplification {
+
+    let (scale, positions) = normalize(positions);
+
+    // every vertex is mapped to the first vertex at the same position
+    let mut first_at_position: HashMap<[u32; 3], u32> = HashMap::new();
+    let canonical = positions.iter().enumerate().map(|(index, position)| {
+        *first_at_position.entry(position.map(f32::to_bits)).or_insert(index as u32)
+    }).collect::<Vec<_>>();
+
+    let mut representatives: HashMap<u32, u32> = HashMap::new();
+    let mut seam = vec![false; positions.len()];
+    wedges.iter().enumerate()
+        .filter(|(index, wedge)| *index as u32 == **wedge)
+        .for_each(|(index, _)| {
+            let first = *representatives.entry(canonical[index]).or_insert(index as u32);
+            if first != index as u32 {
+                seam[index] = true;
+                seam[first as usize] = true;
+            }
+        });
+
+    let mut triangles = indices.chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|triangle| !is_degenerate(triangle, &canonical))
+        .collect::<Vec<_>>();
+
+    // edges used by a single triangle form the border, edges used by more than two are locked
+    let edges = count_edges(&triangles, &canonical);
+
+    let mut border = vec![false; positions.len()];
+    let mut locked = seam.clone();
+    edges.iter().for_each(|((a, b), count)| {
+        if *count == 1 {
+            border[*a as usize] = true;
+            border[*b as usize] = true;
+        }
+        if *count > 2 {
+            locked[*a as usize] = true;
+            locked[*b as usize] = true;
+        }
+    });
+
+    // the flags are looked up by the vertex' position
+    let is_border = |vertex: u32| border[canonical[vertex as usize] as usize];
+    let is_locked = |vertex: u32| {
+        let position = canonical[vertex as usize] as usize;
+        locked[position] || seam[vertex as usize] || (options.lock_border && border[position])
+    };
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    triangles.iter().for_each(|triangle| {
+        let corners = triangle.map(|vertex| positions[vertex as usize]);
+        let quadric = Quadric::from_triangle(&corners);
+        triangle.iter().for_each(|vertex| quadrics[canonical[*vertex as usize] as usize].add(&quadric));
+
+        (0..3).for_each(|corner| {
+            let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+            if edges[&edge_key(canonical[a as usize], canonical[b as usize])] == 1 {
+                let quadric = Quadric::from_border_edge(&corners[corner], &corners[(corner + 1) % 3], &corners[(corner + 2) % 3]);
+                quadrics[canonical[a as usize] as usize].add(&quadric);
+                quadrics[canonical[b as usize] as usize].add(&quadric);
+            }
+        });
+    });
+
+    let target_triangles = options.target_triangles;
+    // the quadrics measure squared distances
+    let max_error = (options.max_error as f64).powi(2);
+    let mut result_error: f64 = 0.0;
+
+    while triangles.len() > target_triangles {
+
+        let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+        triangles.iter().enumerate().for_each(|(index, triangle)| {
+            triangle.iter().for_each(|vertex| adjacency[*vertex as usize].push(index));
+        });
+
+        // border vertices may only move along the border edges of the current triangles
+        let current_edges = count_edges(&triangles, &canonical);
+
+        let mut candidates = Vec::new();
+        triangles.iter().for_each(|triangle| {
+            (0..3).for_each(|corner| {
+                let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+                [(a, b), (b, a)].into_iter().for_each(|(from, to)| {
+                    if is_locked(from) {
+                        return;
+                    }
+                    if is_border(from) && current_edges.get(&edge_key(canonical[from as usize], canonical[to as usize])) != Some(&1) {
+                        return;
+                    }
+                    let quadric = quadrics[canonical[from as usize] as usize].sum(&quadrics[canonical[to as usize] as usize]);
+                    candidates.push((quadric.error(&positions[to as usize]), from, to));
+                });
+            });
+        });
+
+        if candidates.is_empty() {
+            break;
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let collapse_goal = ((triangles.len() - target_triangles) / 2).max(1);
+        // cheap collapses blocked by others in this pass are retried in the next one instead of
+        // being replaced by much more expensive ones
+        let pass_limit = candidates[collapse_goal.min(candidates.len() - 1)].0 * 1.5 + f64::EPSILON;
+        let mut collapses = 0;
+        let mut removed = 0;
+        let mut touched = vec![false; positions.len()];
+
+        for (error, from, to) in candidates {
+
+            if error > max_error || error > pass_limit || collapses >= collapse_goal || triangles.len() - removed <= target_triangles {
+                break;
+            }
+
+            let (from_position, to_position) = (canonical[from as usize], canonical[to as usize]);
+
+            if touched[from_position as usize] || touched[to_position as usize] {
+                continue;
+            }
+
+            if flips(&triangles, &adjacency[from as usize], from, &positions[to as usize], &positions, &canonical, to_position) {
+                continue;
+            }
+
+            adjacency[from as usize].iter().for_each(|triangle| {
+                let triangle = &mut triangles[*triangle];
+                if triangle.iter().any(|vertex| canonical[*vertex as usize] == to_position) {
+                    removed += 1;
+                }
+                triangle.iter().for_each(|vertex| touched[canonical[*vertex as usize] as usize] = true);
+                triangle.iter_mut().filter(|vertex| **vertex == from).for_each(|vertex| *vertex = to);
+            });
+
+            let quadric = quadrics[from_position as usize];
+            quadrics[to_position as usize].add(&quadric);
+
+            result_error = result_error.max(error);
+            collapses += 1;
+        }
+
+        if collapses == 0 {
+            break;
+        }
+
+        triangles.retain(|triangle| !is_degenerate(triangle, &canonical));
+    }
+
+    Simplification {
+        indices: triangles.into_iter().flatten().collect(),
+        error: (result_error.sqrt() * scale) as f32,
+    }
+}
+
+/// Generates a chain of simplified meshes, each with the given fraction of the original
+/// triangles, simplified from the previous level. The chain ends early, if a level cannot be
+/// simplified any further within `max_error`.
+pub fn generate_lods(indices: &[u32], vertices: &[Vertex], ratios: &[f32], max_error: f32) -> Vec<Simplification> {
+
+    let triangles = indices.len() / 3;
+    let mut levels: Vec<Simplification> = Vec::new();
+
+    for ratio in ratios {
+
+        let previous = levels.last().map(|level| level.indices.as_slice()).unwrap_or(indices);
+        let level = simplify(previous, vertices, &SimplifyOptions {
+            target_triangles: (triangles as f32 * ratio) as usize,
+            max_error,
+            ..SimplifyOptions::default()
+        });
+
+        if level.indices.len() >= previous.len() {
+            break;
+        }
+
+        levels.push(level);
+    }
+
+    levels
+}
+
+/// A symmetric 4x4 matrix summing squared distances to planes, plus the sum of the planes'
+/// weights to normalize the error.
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric {
+    a: [f64; 10],
+    weight: f64,
+}
+
+impl Quadric {
+
+    fn from_plane(normal: &Vector3<f64>, distance: f64, weight: f64) -> Quadric {
+        let (x, y, z, d) = (normal.x, normal.y, normal.z, distance);
+        Quadric {
+            a: [x * x, x * y, x * z, x * d, y * y, y * z, y * d, z * z, z * d, d * d].map(|value| value * weight),
+            weight,
+        }
+    }
+
+    fn from_triangle(corners: &[[f32; 3]; 3]) -> Quadric {
+        let [p0, p1, p2] = corners.map(to_vector);
+        let normal = (p1 - p0).cross(&(p2 - p0));
+        let area = normal.norm();
+        if area == 0.0 {
+            return Quadric::default();
+        }
+        let normal = normal / area;
+        Quadric::from_plane(&normal, -normal.dot(&p0), area)
+    }
+
+    /// The plane through the edge perpendicular to its triangle keeps border vertices on the
+    /// border.
+    fn from_border_edge(a: &[f32; 3], b: &[f32; 3], opposite: &[f32; 3]) -> Quadric {
+        let (a, b, opposite) = (to_vector(*a), to_vector(*b), to_vector(*opposite));
+        let edge = b - a;
+        let face_normal = edge.cross(&(opposite - a));
+        let normal = edge.cross(&face_normal);
+        let length = normal.norm();
+        if length == 0.0 {
+            return Quadric::default();
+        }
+        let normal = normal / length;
+        Quadric::from_plane(&normal, -normal.dot(&a), edge.norm_squared() * BORDER_WEIGHT)
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        (0..10).for_each(|index| self.a[index] += other.a[index]);
+        self.weight += other.weight;
+    }
+
+    fn sum(&self, other: &Quadric) -> Quadric {
+        let mut result = *self;
+        result.add(other);
+        result
+    }
+
+    /// Returns the weighted mean of the squared distances of the position to all planes.
+    fn error(&self, position: &[f32; 3]) -> f64 {
+        let [x, y, z] = position.map(|value| value as f64);
+        let a = &self.a;
+        let error = a[0] * x * x + 2.0 * a[1] * x * y + 2.0 * a[2] * x * z + 2.0 * a[3] * x
+            + a[4] * y * y + 2.0 * a[5] * y * z + 2.0 * a[6] * y
+            + a[7] * z * z + 2.0 * a[8] * z
+            + a[9];
+        if self.weight > 0.0 { error.abs() / self.weight } else { 0.0 }
+    }
+}
+
+/// Returns whether moving `from` to `target` turns any of its remaining triangles over.
+fn flips(triangles: &[[u32; 3]], adjacent: &[usize], from: u32, target: &[f32; 3], positions: &[[f32; 3]], canonical: &[u32], target_position: u32) -> bool {
+    adjacent.iter()
+        .map(|triangle| &triangles[*triangle])
+        .filter(|triangle| !triangle.iter().any(|vertex| canonical[*vertex as usize] == target_position))
+        .any(|triangle| {
+            let before = triangle.map(|vertex| to_vector(positions[vertex as usize]));
+            let after = triangle.map(|vertex| if vertex == from { to_vector(*target) } else { to_vector(positions[vertex as usize]) });
+            let normal_before = (before[1] - before[0]).cross(&(before[2] - before[0]));
+            let normal_after = (after[1] - after[0]).cross(&(after[2] - after[0]));
+            normal_before.dot(&normal_after) <= 0.0
+        })
+}
+
+/// Scales the positions into the unit cube, so that errors are relative to the mesh' extent.
+fn normalize(positions: &[[f32; 3]]) -> (f64, Vec<[f32; 3]>) {
+
+    let (min, max) = positions.iter().fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), position| {
+        ([0, 1, 2].map(|axis| min[axis].min(position[axis])), [0, 1, 2].map(|axis| max[axis].max(position[axis])))
+    });
+
+    let extent = (0..3).map(|axis| max[axis] - min[axis]).fold(0.0f32, f32::max);
+    let scale = if extent > 0.0 { extent } else { 1.0 };
+
+    (scale as f64, positions.iter().map(|position| [0, 1, 2].map(|axis| (position[axis] - min[axis]) / scale)).collect())
+}
+
+fn to_vector(position: [f32; 3]) -> Vector3<f64> {
+    Vector3::new(position[0] as f64, position[1] as f64, position[2] as f64)
+}
+
+/// Counts the triangles using each edge, identified by the positions of its vertices.
+fn count_edges(triangles: &[[u32; 3]], canonical: &[u32]) -> HashMap<(u32, u32), u32> {
+    let mut edges = HashMap::new();
+    triangles.iter().for_each(|triangle| {
+        (0..3).for_each(|corner| {
+            *edges.entry(edge_key(canonical[triangle[corner] as usize], canonical[triangle[(corner + 1) % 3] as usize])).or_insert(0) += 1;
+        })
+    });
+    edges
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+fn is_degenerate(triangle: &[u32; 3], canonical: &[u32]) -> bool {
+    let [a, b, c] = triangle.map(|vertex| canonical[vertex as usize]);
+    a == b || b == c || a == c
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+
+    use crate::engine::Vertex;
+    use crate::mesh::{generate_lods, simplify, SimplifyOptions};
+
+    /// A flat grid of `size` x `size` quads in the xy plane.
+    fn grid(size: u32, uv_seam_at: Option<u32>) -> (Vec<u32>, Vec<Vertex>) {
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        let columns = size + 1 + uv_seam_at.map(|_| 1).unwrap_or(0);
+        let column_x = |column: u32| match uv_seam_at {
+            Some(seam) if column > seam => column - 1,
+            _ => column,
+        };
+
+        (0..=size).for_each(|y| {
+            (0..columns).for_each(|column| {
+                let x = column_x(column);
+                let u = match uv_seam_at {
+                    Some(seam) if column > seam => 1.0,
+                    _ => 0.0,
+                };
+                vertices.push(Vertex {
+                    position: [x as f32, y as f32, 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                    color: [1.0, 1.0, 1.0],
+                    uv: [u, y as f32],
+                });
+            });
+        });
+
+        (0..size).for_each(|y| {
+            (0..columns - 1).filter(|column| Some(*column) != uv_seam_at).for_each(|column| {
+                let a = y * columns + column;
+                let b = a + 1;
+                let c = a + columns;
+                let d = c + 1;
+                indices.extend_from_slice(&[a, b, d, a, d, c]);
+            });
+        });
+
+        (indices, vertices)
+    }
+
+    fn extent(indices: &[u32], vertices: &[Vertex]) -> ([f32; 2], [f32; 2]) {
+        indices.iter().fold(([f32::MAX; 2], [f32::MIN; 2]), |(min, max), index| {
+            let position = vertices[*index as usize].position;
+            ([min[0].min(position[0]), min[1].min(position[1])], [max[0].max(position[0]), max[1].max(position[1])])
+        })
+    }
+
+    #[test]
+    fn test_simplify_flat_grid_keeps_its_border() {
+
+        let (indices, vertices) = grid(8, None);
+
+        let result = simplify(&indices, &vertices, &SimplifyOptions {
+            target_triangles: 8,
+            ..SimplifyOptions::default()
+        });
+
+        assert_that!(result.indices.len() / 3, is(less_than_or_equal_to(16)));
+        assert_that!(result.indices.len() / 3, is(greater_than_or_equal_to(2)));
+        assert_that!(result.error, is(less_than(1.0e-3)));
+        assert_that!(extent(&result.indices, &vertices), is(equal_to(([0.0, 0.0], [8.0, 8.0]))));
+    }
+
+    #[test]
+    fn test_simplify_respects_max_error() {
+
+        let (indices, mut vertices) = grid(4, None);
+        vertices.iter_mut().for_each(|vertex| {
+            vertex.position[2] = (vertex.position[0] - 2.0).powi(2) + (vertex.position[1] - 2.0).powi(2);
+        });
+
+        let result = simplify(&indices, &vertices, &SimplifyOptions {
+            target_triangles: 0,
+            max_error: 1.0e-4,
+            lock_border: true,
+        });
+
+        assert_that!(result.indices.len(), is(equal_to(indices.len())));
+    }
+
+    #[test]
+    fn test_simplify_does_not_move_seam_vertices() {
+
+        let (indices, vertices) = grid(6, Some(3));
+
+        let result = simplify(&indices, &vertices, &SimplifyOptions {
+            target_triangles: 0,
+            ..SimplifyOptions::default()
+        });
+
+        // every vertex of the seam is still referenced on both sides
+        let seam = vertices.iter().enumerate()
+            .filter(|(_, vertex)| vertex.position[0] == 3.0)
+            .map(|(index, _)| index as u32)
+            .collect::<Vec<_>>();
+
+        assert_that!(result.indices.len(), is(less_than(indices.len())));
+        seam.iter().for_each(|vertex| {
+            assert_that!(result.indices.contains(vertex), is(true));
+        });
+    }
+
+    #[test]
+    fn test_generate_lods_reduces_each_level() {
+
+        let (indices, vertices) = grid(8, None);
+
+        let lods = generate_lods(&indices, &vertices, &[0.5, 0.25], f32::MAX);
+
+        assert_that!(lods.len(), is(equal_to(2)));
+        assert_that!(lods[0].indices.len(), is(less_than(indices.len())));
+        assert_that!(lods[1].indices.len(), is(less_than(lods[0].indices.len())));
+    }
+}
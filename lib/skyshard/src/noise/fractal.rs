@@ -0,0 +1,132 @@
+use nalgebra::Vector3;
+
+use crate::noise::Noise;
+
+/// Fractal Brownian motion: octaves of noise summed with increasing frequency and decreasing
+/// amplitude, adding detail at smaller scales.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fractal {
+    pub octaves: u32,
+    /// The frequency of the first octave, i.e. the noise's features per unit.
+    pub frequency: f32,
+    /// The factor of the frequency from one octave to the next.
+    pub lacunarity: f32,
+    /// The factor of the amplitude from one octave to the next.
+    pub gain: f32,
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Fractal {
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Noise {
+
+    /// Returns the sum of the octaves at the position, normalized by the sum of their amplitudes
+    /// so that it stays within the range of a single octave.
+    pub fn fbm(&self, position: Vector3<f32>, fractal: &Fractal) -> f32 {
+        self.octaves(position, fractal, |value| value)
+    }
+
+    /// Returns the normalized sum of the octaves' absolute values, in `[0, 1]`. The creases
+    /// where the noise changes its sign look like billowing clouds or marble veins.
+    pub fn turbulence(&self, position: Vector3<f32>, fractal: &Fractal) -> f32 {
+        self.octaves(position, fractal, f32::abs)
+    }
+
+    /// Returns the normalized sum of the octaves' inverted and squared absolute values, in
+    /// `[0, 1]`. The creases become sharp ridges, e.g. of mountains.
+    pub fn ridged(&self, position: Vector3<f32>, fractal: &Fractal) -> f32 {
+        self.octaves(position, fractal, |value| (1.0 - value.abs()).powi(2))
+    }
+
+    /// Samples the fractal on a grid of `size` points in the xy-plane starting at the origin, with
+    /// x increasing fastest.
+    pub fn sample_2d(&self, origin: &Vector3<f32>, spacing: f32, size: [usize; 2], fractal: &Fractal) -> Vec<f32> {
+        self.sample_3d(origin, spacing, [size[0], size[1], 1], fractal)
+    }
+
+    /// Samples the fractal on a grid of `size` points starting at the origin, with x increasing
+    /// fastest and z slowest.
+    pub fn sample_3d(&self, origin: &Vector3<f32>, spacing: f32, size: [usize; 3], fractal: &Fractal) -> Vec<f32> {
+
+        let [width, height, depth] = size;
+
+        (0..depth).flat_map(|z| (0..height).flat_map(move |y| (0..width).map(move |x| [x, y, z])))
+            .map(|[x, y, z]| {
+                self.fbm(origin + Vector3::new(x as f32, y as f32, z as f32) * spacing, fractal)
+            })
+            .collect()
+    }
+
+    fn octaves(&self, position: Vector3<f32>, fractal: &Fractal, shape: impl Fn(f32) -> f32) -> f32 {
+
+        let (sum, amplitudes, _, _) = (0..fractal.octaves.max(1)).fold((0.0, 0.0, fractal.frequency, 1.0), |(sum, amplitudes, frequency, amplitude), _| {
+            (
+                sum + shape(self.evaluate(position * frequency)) * amplitude,
+                amplitudes + amplitude,
+                frequency * fractal.lacunarity,
+                amplitude * fractal.gain,
+            )
+        });
+
+        sum / amplitudes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::noise::{Fractal, Interpolation, Noise};
+
+    #[test]
+    fn test_single_octave_is_the_scaled_noise() {
+
+        let noise = Noise::new("fbm", 64, Interpolation::Linear);
+        let fractal = Fractal { octaves: 1, frequency: 0.5, ..Fractal::default() };
+        let position = Vector3::new(1.3, 2.7, 0.4);
+
+        assert_that!(noise.fbm(position, &fractal), is(equal_to(noise.evaluate(position * 0.5))));
+    }
+
+    #[test]
+    fn test_turbulence_and_ridged_are_within_unit_range() {
+
+        let noise = Noise::new("variants", 64, Interpolation::Linear);
+        let fractal = Fractal::default();
+
+        (0..64).map(|index| Vector3::new(index as f32 * 0.37, index as f32 * 0.19, 0.5)).for_each(|position| {
+            let turbulence = noise.turbulence(position, &fractal);
+            let ridged = noise.ridged(position, &fractal);
+            assert_that!(turbulence >= 0.0 && turbulence <= 1.0, is(true));
+            assert_that!(ridged >= 0.0 && ridged <= 1.0, is(true));
+        });
+
+        // the noise is zero at integral positions, where the ridges peak
+        assert_that!(noise.ridged(Vector3::new(2.0, 3.0, 0.0), &Fractal { octaves: 1, ..fractal }), is(equal_to(1.0)));
+    }
+
+    #[test]
+    fn test_grids_are_sampled_with_x_fastest() {
+
+        let noise = Noise::new("grid", 64, Interpolation::Linear);
+        let fractal = Fractal::default();
+        let origin = Vector3::new(0.5, 0.25, 0.125);
+
+        let values = noise.sample_3d(&origin, 0.5, [3, 2, 2], &fractal);
+
+        assert_that!(values.len(), is(equal_to(12)));
+        assert_that!(values[1], is(equal_to(noise.fbm(origin + Vector3::new(0.5, 0.0, 0.0), &fractal))));
+        assert_that!(values[3], is(equal_to(noise.fbm(origin + Vector3::new(0.0, 0.5, 0.0), &fractal))));
+        assert_that!(values[6], is(equal_to(noise.fbm(origin + Vector3::new(0.0, 0.0, 0.5), &fractal))));
+        assert_that!(noise.sample_2d(&origin, 0.5, [3, 2], &fractal), is(equal_to(values[..6].to_vec())));
+    }
+}
@@ -1,11 +1,16 @@
 use std::f32::consts::PI;
 
-use itertools::Itertools;
 use nalgebra::Vector3;
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 use sha2::{Digest, Sha256};
 
+mod fractal;
+mod texture;
+mod worley;
+
+pub use crate::noise::fractal::Fractal;
+pub use crate::noise::texture::{generate_texture, NoiseFunction, TextureSettings};
 
 type InterpolationFunction = fn(f32, f32, f32) -> f32;
 
@@ -39,18 +44,20 @@ impl Interpolation {
     #[inline(always)]
     fn cosine(low: f32, high: f32, t: f32) -> f32 {
         debug_assert!(t >= 0f32 && t <= 1f32, "Expected 0 ≤ t ≤ 1 for cosine-interpolation, but was: {t}");
-        Self::linear(low, high, (1f32 - (t * PI).cos() * 0.5f32))
+        Self::linear(low, high, (1f32 - (t * PI).cos()) * 0.5f32)
     }
 }
 
-struct Noise {
+/// Seeded gradient noise in three dimensions. The noise is zero at integral positions and
+/// repeats every `size` units along each axis.
+#[derive(Debug, Clone)]
+pub struct Noise {
     gradients: Vec<Vector3<f32>>,
     gradients_modulo_mask: i32,
     permutation_table: Vec<usize>,
     interpolate: InterpolationFunction,
 }
 
-
 impl Noise {
 
     pub fn new(seed: &str, size: usize, interpolation: Interpolation) -> Self {
@@ -59,24 +66,18 @@ impl Noise {
         debug_assert!((size & (size - 1)) == 0,  "size has to be a power of 2, but was {}", size);
 
         let seed: [u8; 32] = Sha256::digest(seed).into();
-        let mut rng = StdRng::from_seed(seed);
+        let rng = StdRng::from_seed(seed);
+
+        let mut components = Clone::clone(&rng)
+            .sample_iter(rand::distributions::Uniform::new_inclusive(-1.0, 1.0));
 
         Self {
-            gradients: Clone::clone(&rng)
-                .sample_iter(rand::distributions::Uniform::new_inclusive(-1.0, 1.0))
-                .chunks(3)
-                .into_iter()
-                .map(|chunk| chunk
-                    .enumerate()
-                    .fold(Vector3::zeros(), |mut gradient: Vector3<f32>, (index, value)| {
-                        gradient.as_mut_slice()[index] = value;
-                        gradient
-                    }))
-                .map(|mut gradient| {
+            gradients: (0..size)
+                .map(|_| {
+                    let mut gradient: Vector3<f32> = Vector3::from_fn(|_, _| components.next().unwrap());
                     gradient.normalize_mut();
                     gradient
                 })
-                .take(size)
                 .collect(),
             gradients_modulo_mask: (size - 1) as i32,
             permutation_table: rng
@@ -141,6 +142,11 @@ impl Noise {
         (self.interpolate)(e, f, w)
     }
 
+    /// The number of units after which the noise repeats along each axis.
+    pub fn period(&self) -> usize {
+        self.gradients.len()
+    }
+
     #[inline(always)]
     fn to_index(&self, value: i32) -> i32 {
         value & self.gradients_modulo_mask
@@ -160,71 +166,42 @@ impl Noise {
     fn quintic(t: f32) -> f32 {
         t * t * t * (t * (t * 6f32 - 15f32) + 10f32)
     }
-
-    #[inline(always)]
-    fn quintic_derivative(t: f32) -> f32 {
-        30f32 * t * t * (t * (t - 2f32) + 1f32)
-    }
 }
 
 #[cfg(test)]
-mod test {
-    use std::fs::File;
-    use std::io::BufWriter;
-    use std::path::Path;
-
+mod tests {
+    use hamcrest2::prelude::*;
     use nalgebra::Vector3;
-    use png::ColorType;
 
     use crate::noise::{Interpolation, Noise};
 
     #[test]
-    fn test_noise() {
+    fn test_noise_is_seeded_periodic_and_bounded() {
 
         let noise = Noise::new("Elmar", 256, Interpolation::Linear);
+        let same = Noise::new("Elmar", 256, Interpolation::Linear);
+        let other = Noise::new("Schug", 256, Interpolation::Linear);
 
-        let image_width: usize = 256;
-        let image_height: usize = 256;
-        let mut noise_map = vec![0f32; image_width * image_height];
-        let mut frequency = 1.00f32;
-        let mut amplitude = 1.0f32;
-        let lacunarity = 0.25;
-        let gain = 1.8;
-        let layers = 5;
-
-        let mut min_value = f32::MAX;
-        let mut max_value = f32::MIN;
-
-        for j in 0..image_height {
-            for i in 0..image_width {
-                frequency = 0.5;
-                amplitude = 0.5;
-                for l in 0..layers {
-                    let value = noise.evaluate(Vector3::new(i as f32, j as f32, 0.0) * frequency) * amplitude;
-                    if value > max_value { max_value = value }
-                    if value < min_value { min_value = value }
-                    noise_map[j * image_width + i] += value;
-                    frequency *= lacunarity;
-                    amplitude *= gain;
-                }
-            }
-        }
+        let positions = (0..64).map(|index| Vector3::new(index as f32 * 0.37, index as f32 * 0.71, index as f32 * 0.13)).collect::<Vec<_>>();
 
-        println!("min={min_value}, max={max_value}");
+        positions.iter().for_each(|position| {
+            let value = noise.evaluate(*position);
+            assert_that!(value, is(equal_to(same.evaluate(*position))));
+            assert_that!(noise.evaluate(position + Vector3::repeat(256.0)), is(close_to(value, 1e-3)));
+            assert_that!(value.abs() <= 1.0, is(true));
+        });
 
-        let path = Path::new(r"tmp/image.png");
-        let file = File::create(path).unwrap();
-        let ref mut writer = BufWriter::new(file);
-        let mut encoder = ::png::Encoder::new(writer, 1 * image_width as u32, 1 * image_height as u32);
-        encoder.set_color(ColorType::Grayscale);
-        let mut png_writer = encoder.write_header().unwrap();
+        assert_that!(noise.evaluate(Vector3::new(3.0, 5.0, 7.0)), is(equal_to(0.0)));
+        assert_that!(positions.iter().any(|position| noise.evaluate(*position) != other.evaluate(*position)), is(true));
+    }
+
+    #[test]
+    fn test_cosine_interpolation_eases_between_values() {
 
-        let data: Vec<u8> = noise_map
-            .iter()
-            .map(|value| (value - min_value) / (max_value - min_value))
-            .map(|value| (255f32 - value * 255f32) as u8)
-            .collect();
+        let cosine = Interpolation::Cosine.function();
 
-        png_writer.write_image_data(data.as_slice()).unwrap();
+        assert_that!(cosine(2.0, 4.0, 0.0), is(equal_to(2.0)));
+        assert_that!(cosine(2.0, 4.0, 0.5), is(close_to(3.0, 1e-6)));
+        assert_that!(cosine(2.0, 4.0, 1.0), is(equal_to(4.0)));
     }
 }
@@ -0,0 +1,149 @@
+use nalgebra::Vector3;
+
+use crate::curve::Curve;
+use crate::graphics::{Extent, TextureData};
+use crate::noise::{Fractal, Noise};
+
+/// The function of a [Noise] sampled by [generate_texture].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NoiseFunction {
+    /// A single octave of the gradient noise, with the frequency's features per unit.
+    Gradient { frequency: f32 },
+    Fbm(Fractal),
+    Turbulence(Fractal),
+    Ridged(Fractal),
+    /// The distance to the nearest feature point, with the frequency's cells per unit.
+    Worley { frequency: f32 },
+}
+
+impl NoiseFunction {
+
+    /// Evaluates the function at the position, mapped to `[0, 1]`.
+    pub fn evaluate(&self, noise: &Noise, position: Vector3<f32>) -> f32 {
+        let value = match self {
+            NoiseFunction::Gradient { frequency } => noise.evaluate(position * *frequency) * 0.5 + 0.5,
+            NoiseFunction::Fbm(fractal) => noise.fbm(position, fractal) * 0.5 + 0.5,
+            NoiseFunction::Turbulence(fractal) => noise.turbulence(position, fractal),
+            NoiseFunction::Ridged(fractal) => noise.ridged(position, fractal),
+            NoiseFunction::Worley { frequency } => noise.worley(position * *frequency),
+        };
+        value.clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureSettings {
+    pub width: u32,
+    pub height: u32,
+    /// The position in noise space of the texture's top left corner, the texture spans the
+    /// xy-plane from there.
+    pub origin: Vector3<f32>,
+    /// The units of noise space spanned by the texture along each side.
+    pub size: f32,
+    /// Blends the noise with its copies one size to the left and above, so that opposite edges
+    /// of the texture match and it repeats seamlessly. Lowers the contrast towards the center.
+    pub tileable: bool,
+    /// Maps the function's values to colors, which are stored as they are, i.e. read as sRGB
+    /// by base color textures.
+    pub ramp: Curve<[f32; 4]>,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        TextureSettings {
+            width: 256,
+            height: 256,
+            origin: Vector3::zeros(),
+            size: 8.0,
+            tileable: false,
+            ramp: Curve::linear([0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]),
+        }
+    }
+}
+
+/// Samples the function at the texels' corners, row by row, and maps the values by the ramp to
+/// RGBA8 texture data, e.g. for [create_geometry](crate::create_geometry).
+pub fn generate_texture(noise: &Noise, function: &NoiseFunction, settings: &TextureSettings) -> TextureData {
+
+    let (width, height) = (settings.width, settings.height);
+
+    let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let value = sample(noise, function, settings, x as f32 / width as f32, y as f32 / height as f32);
+            settings.ramp.sample(value).map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect();
+
+    TextureData::new(data, Extent::from(width, height, 1))
+}
+
+/// Returns the value at the texture coordinates.
+fn sample(noise: &Noise, function: &NoiseFunction, settings: &TextureSettings, u: f32, v: f32) -> f32 {
+
+    let size = settings.size;
+    let position = settings.origin + Vector3::new(u, v, 0.0) * size;
+
+    if !settings.tileable {
+        return function.evaluate(noise, position);
+    }
+
+    // bilinear blend of the noise and its copies, weighted so that each takes over at the
+    // opposite edge
+    let value = |x: f32, y: f32| function.evaluate(noise, position - Vector3::new(x, y, 0.0) * size);
+
+    value(0.0, 0.0) * (1.0 - u) * (1.0 - v)
+        + value(1.0, 0.0) * u * (1.0 - v)
+        + value(0.0, 1.0) * (1.0 - u) * v
+        + value(1.0, 1.0) * u * v
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::curve::Curve;
+    use crate::noise::{Fractal, Interpolation, Noise};
+    use crate::noise::texture::{generate_texture, sample, NoiseFunction, TextureSettings};
+
+    #[test]
+    fn test_texture_maps_values_by_the_ramp() {
+
+        let noise = Noise::new("texture", 64, Interpolation::Linear);
+        let function = NoiseFunction::Gradient { frequency: 1.0 };
+        let settings = TextureSettings {
+            width: 4,
+            height: 2,
+            size: 2.0,
+            ramp: Curve::linear([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 0.5]),
+            ..TextureSettings::default()
+        };
+
+        let texture = generate_texture(&noise, &function, &settings);
+
+        assert_that!([texture.extent.width, texture.extent.height, texture.extent.depth], is(equal_to([4, 2, 1])));
+        assert_that!(texture.data.len(), is(equal_to(32)));
+        // the gradient noise is zero at integral positions, i.e. halfway along the ramp
+        assert_that!(&texture.data[8..12], is(equal_to(&[128u8, 0, 128, 191][..])));
+    }
+
+    #[test]
+    fn test_tileable_textures_match_at_opposite_edges() {
+
+        let noise = Noise::new("tiles", 64, Interpolation::Linear);
+        let settings = TextureSettings { origin: Vector3::new(0.3, 0.6, 0.5), size: 3.7, tileable: true, ..TextureSettings::default() };
+
+        [
+            NoiseFunction::Fbm(Fractal::default()),
+            NoiseFunction::Ridged(Fractal::default()),
+            NoiseFunction::Worley { frequency: 2.0 },
+        ].iter().for_each(|function| {
+            [0.0, 0.3, 0.8].iter().for_each(|t| {
+                let (left, right) = (sample(&noise, function, &settings, 0.0, *t), sample(&noise, function, &settings, 1.0, *t));
+                let (top, bottom) = (sample(&noise, function, &settings, *t, 0.0), sample(&noise, function, &settings, *t, 1.0));
+                assert_that!(left, is(close_to(right, 1e-5)));
+                assert_that!(top, is(close_to(bottom, 1e-5)));
+            });
+        });
+    }
+}
@@ -0,0 +1,51 @@
+use nalgebra::Vector3;
+
+use crate::noise::Noise;
+
+impl Noise {
+
+    /// Returns the distance to the nearest feature point, clamped to `[0, 1]`. Every unit cell
+    /// holds one feature point, placed by the noise's seed, so that the cells look like stones or
+    /// scales. Repeats like the gradient noise.
+    pub fn worley(&self, position: Vector3<f32>) -> f32 {
+
+        let cell = position.map(f32::floor);
+
+        (-1..=1).flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| Vector3::new(x as f32, y as f32, z as f32))))
+            .map(|offset| {
+                let neighbour = cell + offset;
+                (neighbour + self.feature_point(&neighbour) - position).norm()
+            })
+            .fold(1.0, f32::min)
+    }
+
+    /// Returns the position of the cell's feature point relative to the cell's corner.
+    fn feature_point(&self, cell: &Vector3<f32>) -> Vector3<f32> {
+        let [x, y, z] = [cell.x, cell.y, cell.z].map(|coordinate| self.to_index(coordinate as i32));
+        self.gradient_at(x, y, z).map(|component| component * 0.5 + 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::Vector3;
+
+    use crate::noise::{Interpolation, Noise};
+
+    #[test]
+    fn test_worley_is_zero_at_feature_points_and_periodic() {
+
+        let noise = Noise::new("worley", 16, Interpolation::Linear);
+        let cell = Vector3::new(3.0, 1.0, 0.0);
+        let feature = cell + noise.feature_point(&cell);
+
+        assert_that!(noise.worley(feature), is(close_to(0.0, 1e-6)));
+        assert_that!(noise.worley(feature + Vector3::new(16.0, 0.0, -16.0)), is(close_to(0.0, 1e-5)));
+
+        (0..64).map(|index| Vector3::new(index as f32 * 0.31, index as f32 * 0.17, 0.0)).for_each(|position| {
+            let value = noise.worley(position);
+            assert_that!(value >= 0.0 && value <= 1.0, is(true));
+        });
+    }
+}
@@ -0,0 +1,388 @@
+use std::f32::consts::PI;
+
+use nalgebra::{Matrix4, Vector3};
+
+use crate::curve::Curve;
+
+/// How the particles of an emitter are blended over the scene.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParticleBlend {
+    /// The particles are blended over what is behind by their alpha, sorted from back to front,
+    /// e.g. for smoke.
+    Alpha,
+    /// The particles' colors weighted by their alpha are added to what is behind, in any order,
+    /// e.g. for fire or sparks.
+    Additive,
+}
+
+/// The volume or surface in which the particles of an emitter are spawned, in the emitter's
+/// local space.
+#[derive(Debug, Clone)]
+pub enum EmitterShape {
+    Point,
+    Box { half_extents: Vector3<f32> },
+    Sphere { radius: f32 },
+    Surface(MeshSurface),
+}
+
+/// The triangles of a mesh, sampled uniformly by their area.
+#[derive(Debug, Clone)]
+pub struct MeshSurface {
+    triangles: Vec<[Vector3<f32>; 3]>,
+    /// The area of all triangles up to and including each one.
+    cumulative_areas: Vec<f32>,
+}
+
+impl MeshSurface {
+
+    /// Creates the surface of the triangle list, indices out of range are ignored.
+    pub fn new(positions: &[Vector3<f32>], indices: &[u32]) -> MeshSurface {
+
+        let triangles = indices.chunks_exact(3)
+            .filter_map(|triangle| {
+                let corner = |index: u32| positions.get(index as usize).copied();
+                Some([corner(triangle[0])?, corner(triangle[1])?, corner(triangle[2])?])
+            })
+            .collect::<Vec<_>>();
+
+        let cumulative_areas = triangles.iter()
+            .scan(0.0, |area, [a, b, c]| {
+                *area += (b - a).cross(&(c - a)).norm() / 2.0;
+                Some(*area)
+            })
+            .collect();
+
+        MeshSurface {
+            triangles,
+            cumulative_areas,
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+
+    fn sample(&self, random: &mut Random) -> Vector3<f32> {
+
+        let area = random.next() * self.area();
+        let index = self.cumulative_areas.partition_point(|cumulative_area| *cumulative_area <= area)
+            .min(self.triangles.len().saturating_sub(1));
+
+        let [a, b, c] = match self.triangles.get(index) {
+            Some(triangle) => triangle,
+            None => return Vector3::zeros(),
+        };
+
+        // reflects points of the parallelogram's other half back into the triangle
+        let (mut u, mut v) = (random.next(), random.next());
+        if u + v > 1.0 {
+            (u, v) = (1.0 - u, 1.0 - v);
+        }
+
+        a + (b - a) * u + (c - a) * v
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmitterSettings {
+    pub shape: EmitterShape,
+    /// The particles emitted per second while the emitter is enabled.
+    pub rate: f32,
+    /// The maximum number of living particles, no particles are emitted beyond.
+    pub max_particles: usize,
+    /// The range of the particles' lifetimes in seconds.
+    pub lifetime: [f32; 2],
+    /// The range of the particles' initial speeds in units per second.
+    pub speed: [f32; 2],
+    /// The direction in which the particles are emitted, in the emitter's local space.
+    pub direction: Vector3<f32>,
+    /// The half angle of the cone around the direction in radians, PI emits in all directions.
+    pub spread: f32,
+    /// Applied to the velocity of every particle, e.g. gravity along +y.
+    pub acceleration: Vector3<f32>,
+    /// The fraction of the velocity lost per second.
+    pub drag: f32,
+    pub color: Curve<[f32; 4]>,
+    /// The width and height of the billboards.
+    pub size: Curve<f32>,
+    pub blend: ParticleBlend,
+}
+
+impl Default for EmitterSettings {
+    fn default() -> Self {
+        EmitterSettings {
+            shape: EmitterShape::Point,
+            rate: 10.0,
+            max_particles: 1000,
+            lifetime: [1.0, 2.0],
+            speed: [1.0, 2.0],
+            direction: -Vector3::y(),
+            spread: 0.3,
+            acceleration: Vector3::zeros(),
+            drag: 0.0,
+            color: Curve::linear([1.0; 4], [1.0, 1.0, 1.0, 0.0]),
+            size: Curve::constant(0.1),
+            blend: ParticleBlend::Additive,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Particle {
+    /// The position in world space.
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    /// The time since the particle was emitted in seconds.
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+
+    /// Returns the age relative to the lifetime, from 0.0 (emitted) to 1.0 (expired).
+    pub fn normalized_age(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Emits particles into world space and simulates them on the CPU. Particles keep moving
+/// independently of the emitter, once emitted.
+///
+/// Emitters are advanced by a fixed time step, like the animation players, and drawn as
+/// billboards facing the camera once added to `World::particles`.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub settings: EmitterSettings,
+    /// Places the shape and the direction into the world, applied to row vectors like the
+    /// transformation of an instance.
+    pub transformation: Matrix4<f32>,
+    /// Whether particles are emitted continuously at the rate. Living particles are simulated
+    /// regardless.
+    pub enabled: bool,
+    particles: Vec<Particle>,
+    /// The fraction of a particle not emitted yet.
+    accumulator: f32,
+    random: Random,
+}
+
+impl ParticleEmitter {
+
+    /// Creates an enabled emitter, the seed makes its particles reproducible.
+    pub fn new(settings: EmitterSettings, seed: u64) -> ParticleEmitter {
+        ParticleEmitter {
+            settings,
+            transformation: Matrix4::identity(),
+            enabled: true,
+            particles: Vec::new(),
+            accumulator: 0.0,
+            random: Random::new(seed),
+        }
+    }
+
+    /// Emits the number of particles at once, limited by the maximum.
+    pub fn burst(&mut self, count: usize) {
+        (0..count.min(self.settings.max_particles.saturating_sub(self.particles.len()))).for_each(|_| {
+            self.emit();
+        });
+    }
+
+    /// Ages and moves the living particles, removes the expired ones and emits new ones.
+    pub fn advance(&mut self, delta: f32) {
+
+        let acceleration = self.settings.acceleration * delta;
+        let damping = (1.0 - self.settings.drag * delta).max(0.0);
+
+        self.particles.retain_mut(|particle| {
+            particle.age += delta;
+            particle.velocity = (particle.velocity + acceleration) * damping;
+            particle.position += particle.velocity * delta;
+            particle.age < particle.lifetime
+        });
+
+        if self.enabled {
+            self.accumulator += self.settings.rate.max(0.0) * delta;
+            let count = self.accumulator.floor();
+            self.accumulator -= count;
+            self.burst(count as usize);
+        }
+    }
+
+    /// Removes all particles.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.accumulator = 0.0;
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    fn emit(&mut self) {
+
+        let settings = &self.settings;
+        let random = &mut self.random;
+
+        let local_position = match &settings.shape {
+            EmitterShape::Point => Vector3::zeros(),
+            EmitterShape::Box { half_extents } => {
+                Vector3::new(random.range(-1.0, 1.0), random.range(-1.0, 1.0), random.range(-1.0, 1.0)).component_mul(half_extents)
+            }
+            EmitterShape::Sphere { radius } => random.unit_vector() * random.next().cbrt() * *radius,
+            EmitterShape::Surface(surface) => surface.sample(random),
+        };
+
+        let local_direction = random.cone(&settings.direction, settings.spread);
+        let speed = random.range(settings.speed[0], settings.speed[1]);
+        let lifetime = random.range(settings.lifetime[0], settings.lifetime[1]).max(f32::EPSILON);
+
+        let position = (local_position.push(1.0).transpose() * self.transformation).transpose();
+        let direction = (local_direction.push(0.0).transpose() * self.transformation).transpose().xyz();
+
+        self.particles.push(Particle {
+            position: position.xyz() / position.w,
+            velocity: direction.try_normalize(f32::EPSILON).unwrap_or(direction) * speed,
+            age: 0.0,
+            lifetime,
+        });
+    }
+}
+
+/// A xorshift generator, good enough for visual effects and reproducible by its seed.
+#[derive(Debug, Clone)]
+struct Random {
+    state: u64,
+}
+
+impl Random {
+
+    fn new(seed: u64) -> Random {
+        // the state must not be zero, the seed is scrambled so that close seeds diverge
+        Random { state: seed.wrapping_mul(0x9E3779B97F4A7C15) | 1 }
+    }
+
+    /// Returns a number in `[0, 1)`.
+    fn next(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    fn unit_vector(&mut self) -> Vector3<f32> {
+        let z = self.range(-1.0, 1.0);
+        let angle = self.range(0.0, 2.0 * PI);
+        let radius = (1.0 - z * z).sqrt();
+        Vector3::new(radius * angle.cos(), radius * angle.sin(), z)
+    }
+
+    /// Returns a unit vector within the cone of the half angle around the axis.
+    fn cone(&mut self, axis: &Vector3<f32>, half_angle: f32) -> Vector3<f32> {
+
+        let axis = match axis.try_normalize(f32::EPSILON) {
+            Some(axis) => axis,
+            None => return self.unit_vector(),
+        };
+
+        // uniform on the spherical cap, whose height is linear in the cosine
+        let cos_theta = 1.0 + (half_angle.clamp(0.0, PI).cos() - 1.0) * self.next();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = self.range(0.0, 2.0 * PI);
+
+        let tangent = match axis.x.abs() < 0.9 {
+            true => Vector3::x().cross(&axis).normalize(),
+            false => Vector3::y().cross(&axis).normalize(),
+        };
+        let bitangent = axis.cross(&tangent);
+
+        axis * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::particles::{EmitterSettings, EmitterShape, MeshSurface, ParticleEmitter};
+
+    #[test]
+    fn test_emitter_emits_at_its_rate_up_to_the_maximum() {
+
+        let mut emitter = ParticleEmitter::new(EmitterSettings {
+            rate: 10.0,
+            max_particles: 25,
+            lifetime: [10.0, 10.0],
+            ..EmitterSettings::default()
+        }, 7);
+
+        (0..10).for_each(|_| emitter.advance(0.125));
+        assert_that!(emitter.len(), is(equal_to(12)));
+
+        (0..20).for_each(|_| emitter.advance(0.125));
+        assert_that!(emitter.len(), is(equal_to(25)));
+
+        emitter.enabled = false;
+        emitter.clear();
+        emitter.advance(1.0);
+        assert_that!(emitter.is_empty(), is(true));
+    }
+
+    #[test]
+    fn test_particles_move_and_expire() {
+
+        let mut emitter = ParticleEmitter::new(EmitterSettings {
+            rate: 0.0,
+            lifetime: [1.0, 1.0],
+            speed: [2.0, 2.0],
+            direction: Vector3::x(),
+            spread: 0.0,
+            acceleration: Vector3::new(0.0, 4.0, 0.0),
+            ..EmitterSettings::default()
+        }, 7);
+        emitter.transformation = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 3.0)).transpose();
+
+        emitter.burst(2);
+        emitter.advance(0.5);
+
+        let particle = emitter.particles()[0];
+        assert_that!(particle.velocity, is(equal_to(Vector3::new(2.0, 2.0, 0.0))));
+        assert_that!(particle.position, is(equal_to(Vector3::new(1.0, 1.0, 3.0))));
+        assert_that!(particle.normalized_age(), is(equal_to(0.5)));
+
+        emitter.advance(0.5);
+        assert_that!(emitter.is_empty(), is(true));
+    }
+
+    #[test]
+    fn test_particles_are_emitted_within_the_shape() {
+
+        let shapes = [
+            (EmitterShape::Box { half_extents: Vector3::new(1.0, 2.0, 3.0) }, Vector3::new(1.0, 2.0, 3.0)),
+            (EmitterShape::Sphere { radius: 2.0 }, Vector3::repeat(2.0)),
+            (EmitterShape::Surface(MeshSurface::new(&[Vector3::zeros(), Vector3::x(), Vector3::z()], &[0, 1, 2])), Vector3::new(1.0, 0.0, 1.0)),
+        ];
+
+        shapes.into_iter().for_each(|(shape, bounds)| {
+
+            let mut emitter = ParticleEmitter::new(EmitterSettings { shape, speed: [0.0, 0.0], ..EmitterSettings::default() }, 3);
+            emitter.burst(100);
+
+            assert_that!(emitter.len(), is(equal_to(100)));
+            emitter.particles().iter().for_each(|particle| {
+                assert_that!(particle.position.abs().zip_fold(&bounds, true, |inside, position, bound| inside && position <= bound), is(true));
+            });
+        });
+    }
+}
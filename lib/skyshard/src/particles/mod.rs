@@ -0,0 +1,146 @@
+use std::cmp::Ordering;
+
+use nalgebra::Vector3;
+
+use crate::graphics::{HasVertexLayout, VertexAttribute, VertexFormat, VertexLayout, VertexSemantic};
+
+mod emitter;
+
+pub use crate::particles::emitter::{EmitterSettings, EmitterShape, MeshSurface, Particle, ParticleBlend, ParticleEmitter};
+
+/// The maximum number of particle vertices, i.e. six per particle, drawn per frame. Additional
+/// particles are ignored, the additively blended ones take precedence.
+pub const MAX_PARTICLE_VERTICES: usize = 6 * 16384;
+
+/// A corner of a particle's billboard, read by the particle vertex shader from binding 0. The
+/// texture coordinates span the billboard from (0, 0) to (1, 1).
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct ParticleVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl HasVertexLayout for ParticleVertex {
+    fn vertex_layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<ParticleVertex>() as u32, vec![
+            VertexAttribute { semantic: VertexSemantic::Position, format: VertexFormat::Float3, offset: offset_of!(ParticleVertex, position) as u32 },
+            VertexAttribute { semantic: VertexSemantic::Color, format: VertexFormat::Float4, offset: offset_of!(ParticleVertex, color) as u32 },
+            VertexAttribute { semantic: VertexSemantic::TexCoord(0), format: VertexFormat::Float2, offset: offset_of!(ParticleVertex, uv) as u32 },
+        ])
+    }
+}
+
+/// Writes the billboards of all particles into `vertices`, facing the camera spanned by its
+/// right and down axes. The additively blended particles come first, followed by the
+/// alpha-blended ones of all emitters sorted from back to front. Returns the number of vertices
+/// of both.
+pub(crate) fn billboards(
+    emitters: &[ParticleEmitter],
+    camera_position: &Vector3<f32>,
+    right: &Vector3<f32>,
+    down: &Vector3<f32>,
+    max_vertices: usize,
+    vertices: &mut Vec<ParticleVertex>,
+) -> (u32, u32) {
+
+    let max_particles = max_vertices / 6;
+
+    let particles = |blend: ParticleBlend| {
+        emitters.iter()
+            .filter(move |emitter| emitter.settings.blend == blend)
+            .flat_map(|emitter| emitter.particles().iter().map(move |particle| (emitter, particle)))
+    };
+
+    let mut blended = particles(ParticleBlend::Alpha)
+        .map(|(emitter, particle)| ((particle.position - camera_position).norm_squared(), emitter, particle))
+        .collect::<Vec<_>>();
+
+    blended.sort_by(|(a, _, _), (b, _, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+    let additive = particles(ParticleBlend::Additive).take(max_particles).collect::<Vec<_>>();
+    let blended = blended.into_iter()
+        .take(max_particles - additive.len())
+        .map(|(_, emitter, particle)| (emitter, particle));
+
+    vertices.clear();
+
+    additive.iter().copied().chain(blended).for_each(|(emitter, particle)| {
+
+        let age = particle.normalized_age();
+        let color = emitter.settings.color.sample(age);
+        let half_size = emitter.settings.size.sample(age) / 2.0;
+
+        let corner = |x: f32, y: f32| ParticleVertex {
+            position: (particle.position + (right * x + down * y) * half_size).into(),
+            uv: [(x + 1.0) / 2.0, (y + 1.0) / 2.0],
+            color,
+        };
+
+        let [top_left, bottom_left, bottom_right, top_right] = [
+            corner(-1.0, -1.0), corner(-1.0, 1.0), corner(1.0, 1.0), corner(1.0, -1.0),
+        ];
+
+        vertices.extend_from_slice(&[top_left, bottom_left, bottom_right, top_left, bottom_right, top_right]);
+    });
+
+    let additive_vertices = additive.len() as u32 * 6;
+
+    (additive_vertices, vertices.len() as u32 - additive_vertices)
+}
+
+#[cfg(test)]
+mod tests {
+    use hamcrest2::prelude::*;
+    use nalgebra::{Matrix4, Vector3};
+
+    use crate::curve::Curve;
+    use crate::particles::{billboards, EmitterSettings, ParticleBlend, ParticleEmitter};
+
+    fn emitter(blend: ParticleBlend, position: Vector3<f32>, count: usize) -> ParticleEmitter {
+        let mut emitter = ParticleEmitter::new(EmitterSettings {
+            speed: [0.0, 0.0],
+            size: Curve::constant(2.0),
+            blend,
+            ..EmitterSettings::default()
+        }, 1);
+        emitter.transformation = Matrix4::new_translation(&position).transpose();
+        emitter.burst(count);
+        emitter
+    }
+
+    #[test]
+    fn test_billboards_are_ordered_by_blend_and_distance() {
+
+        let emitters = [
+            emitter(ParticleBlend::Alpha, Vector3::new(0.0, 0.0, 2.0), 1),
+            emitter(ParticleBlend::Additive, Vector3::new(0.0, 0.0, 1.0), 2),
+            emitter(ParticleBlend::Alpha, Vector3::new(0.0, 0.0, 8.0), 1),
+        ];
+
+        let mut vertices = Vec::new();
+        let counts = billboards(&emitters, &Vector3::zeros(), &Vector3::x(), &Vector3::y(), 1000, &mut vertices);
+
+        assert_that!(counts, is(equal_to((12, 12))));
+        // the farther alpha-blended particle first, its top left corner spans to the camera's left and up
+        assert_that!(vertices[12].position, is(equal_to([-1.0, -1.0, 8.0])));
+        assert_that!(vertices[12].uv, is(equal_to([0.0, 0.0])));
+        assert_that!(vertices[18].position[2], is(equal_to(2.0)));
+    }
+
+    #[test]
+    fn test_billboards_are_truncated_to_whole_particles() {
+
+        let emitters = [
+            emitter(ParticleBlend::Alpha, Vector3::zeros(), 3),
+            emitter(ParticleBlend::Additive, Vector3::zeros(), 3),
+        ];
+
+        let mut vertices = Vec::new();
+        let counts = billboards(&emitters, &Vector3::zeros(), &Vector3::x(), &Vector3::y(), 6 * 4 + 5, &mut vertices);
+
+        assert_that!(counts, is(equal_to((18, 6))));
+        assert_that!(vertices.len(), is(equal_to(24)));
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use blend_rs::blend::{PointerLike, Reader, StringLike};
+use blend_rs::blender3_3::{bAction, bPose, bPoseChannel, AnimData, BezTriple, FCurve, Object, Scene, Void};
+use nalgebra::{Matrix3, Matrix4, Point3, Quaternion, Rotation3, UnitQuaternion, Vector3};
+use skyshard::animation::{AnimationClip, Channel, ChannelValues, Interpolation, Skeleton, Transform};
+
+/// The `type_` of a camera object.
+pub const OB_CAMERA: i16 = 11;
+
+const BEZT_IPO_CONST: i8 = 0;
+const BEZT_IPO_LIN: i8 = 1;
+
+/// Blender's z-up to y-up: x,y,z -> x,-z,y, the conversion applied to the vertices of meshes.
+pub fn z_up_to_y_up() -> Matrix4<f32> {
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 0.0, -1.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+/// Blender stores matrices column by column.
+pub fn matrix_of(matrix: &[[f32; 4]; 4]) -> Matrix4<f32> {
+    Matrix4::from_fn(|row, column| matrix[column][row])
+}
+
+/// The rotation of Blender's cameras, looking along -z with +y up, to the engine's camera looking
+/// along +z with +y down.
+fn camera_orientation() -> UnitQuaternion<f32> {
+    UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::PI)
+}
+
+fn z_up_to_y_up_rotation() -> UnitQuaternion<f32> {
+    let linear: Matrix3<f32> = z_up_to_y_up().fixed_view::<3, 3>(0, 0).into();
+    UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(linear))
+}
+
+/// Returns the transformation of the object in y-up coordinates, ignoring its parent. The
+/// transformation of a camera is suitable for `Camera::view_transform`.
+pub fn object_transform(object: &Object) -> Transform {
+    let conversion = z_up_to_y_up();
+    let mut transform = Transform::from_matrix(&(conversion * matrix_of(&object.obmat) * conversion.transpose()));
+    if object.type_ == OB_CAMERA {
+        transform.rotation *= z_up_to_y_up_rotation().inverse() * camera_orientation();
+    }
+    transform
+}
+
+pub fn frames_per_second(reader: &Reader) -> f32 {
+    reader.iter::<Scene>().ok()
+        .and_then(|mut scenes| scenes.next())
+        .map(|scene| scene.r.frs_sec as f32 / scene.r.frs_sec_base)
+        .unwrap_or(24.0)
+}
+
+/// Loads the action assigned to the object, animating the transform of target `0`. The clip is
+/// expressed in y-up coordinates like `object_transform`, so that cameras can be animated too.
+///
+/// Returns `None` if the object has no action.
+pub fn load_object_action(reader: &Reader, object: &Object) -> Option<AnimationClip> {
+
+    let action = action_of(reader, object)?;
+
+    let space = match object.type_ {
+        OB_CAMERA => ChannelSpace::camera(),
+        _ => ChannelSpace::object(),
+    };
+
+    Some(load_clip(reader, action, |path| {
+        let defaults = defaults(path, object.loc, object.rot, object.quat, object.size)?;
+        Some(Property { target: 0, name: String::from(path), defaults, space: space.clone() })
+    }))
+}
+
+/// Loads the action assigned to the armature object, animating the joints of the skeleton.
+///
+/// Returns `None` if the armature has no action.
+pub fn load_bone_action(reader: &Reader, armature_object: &Object, skeleton: &Skeleton) -> Option<AnimationClip> {
+
+    let action = action_of(reader, armature_object)?;
+
+    let pose_channels: HashMap<String, &bPoseChannel> = reader.deref_single(&armature_object.pose)
+        .map(|pose: &bPose| {
+            reader.traverse_double_linked(&pose.chanbase.first.as_instance_of::<bPoseChannel>())
+                .into_iter()
+                .flatten()
+                .map(|channel: &bPoseChannel| (String::from(channel.name.to_str_unchecked()), channel))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(load_clip(reader, action, |path| {
+        let (bone, name) = parse_rna_path(path)?;
+        let target = skeleton.joint_index(bone)?;
+        let defaults = match pose_channels.get(bone) {
+            Some(channel) => defaults(name, channel.loc, channel.eul, channel.quat, channel.size),
+            None => defaults(name, [0.0; 3], [0.0; 3], [1.0, 0.0, 0.0, 0.0], [1.0; 3]),
+        }?;
+        // the pose of a bone is relative to its rest pose
+        Some(Property { target, name: String::from(name), defaults, space: ChannelSpace::bone(&skeleton.joints()[target].rest) })
+    }))
+}
+
+fn action_of<'a>(reader: &'a Reader, object: &Object) -> Option<&'a bAction> {
+    let animation_data: &AnimData = reader.deref_single(&object.adt).ok()?;
+    reader.deref_single(&animation_data.action).ok()
+}
+
+/// Returns the values of the property, if it is animatable, used for the components without a
+/// curve.
+fn defaults(property: &str, location: [f32; 3], euler: [f32; 3], quaternion: [f32; 4], scale: [f32; 3]) -> Option<Vec<f32>> {
+    match property {
+        "location" => Some(location.to_vec()),
+        "rotation_euler" => Some(euler.to_vec()),
+        "rotation_quaternion" => Some(quaternion.to_vec()),
+        "scale" => Some(scale.to_vec()),
+        _ => None,
+    }
+}
+
+/// A property of a transform animated by the curves of an action.
+struct Property {
+    target: usize,
+    name: String,
+    defaults: Vec<f32>,
+    space: ChannelSpace,
+}
+
+/// Converts the values of Blender's properties into the space of the animated transform.
+#[derive(Clone)]
+struct ChannelSpace {
+    /// Transforms the locations, its linear part the tangents of locations.
+    translation: Matrix4<f32>,
+    /// Rotations `q` become `left * q * right`.
+    left: UnitQuaternion<f32>,
+    right: UnitQuaternion<f32>,
+    scale: Matrix3<f32>,
+}
+
+impl ChannelSpace {
+
+    fn bone(rest: &Transform) -> ChannelSpace {
+        ChannelSpace {
+            translation: Matrix4::new_translation(&rest.translation) * rest.rotation.to_homogeneous(),
+            left: rest.rotation,
+            right: UnitQuaternion::identity(),
+            scale: Matrix3::from_diagonal(&rest.scale),
+        }
+    }
+
+    fn object() -> ChannelSpace {
+        let rotation = z_up_to_y_up_rotation();
+        ChannelSpace {
+            translation: z_up_to_y_up(),
+            left: rotation,
+            right: rotation.inverse(),
+            // the axes are swapped, but not mirrored
+            scale: z_up_to_y_up().fixed_view::<3, 3>(0, 0).abs(),
+        }
+    }
+
+    fn camera() -> ChannelSpace {
+        ChannelSpace {
+            right: camera_orientation(),
+            ..ChannelSpace::object()
+        }
+    }
+
+    fn rotate(&self, quaternion: Quaternion<f32>) -> Quaternion<f32> {
+        self.left.into_inner() * quaternion * self.right.into_inner()
+    }
+}
+
+/// Loads the curves of the action, whose rna paths are resolved into animated properties. Curves
+/// of other properties are skipped.
+///
+/// Bezier keyframes become cubic splines, constant and linear ones step and linear keyframes.
+/// Euler rotations are converted into quaternions at their keyframes and interpolated linearly.
+fn load_clip(reader: &Reader, action: &bAction, resolve: impl Fn(&str) -> Option<Property>) -> AnimationClip {
+
+    let frames_per_second = frames_per_second(reader);
+
+    // the curves of each component of a property
+    let mut properties: HashMap<(usize, String), (Property, Vec<(usize, Vec<&BezTriple>)>)> = HashMap::new();
+
+    reader.traverse_double_linked(&action.curves.first.as_instance_of::<FCurve>())
+        .into_iter()
+        .flatten()
+        .for_each(|curve: &FCurve| {
+            let property = reader.deref_raw(&curve.rna_path.as_instance_of::<Void>()).ok()
+                .map(|data| {
+                    let end = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+                    String::from_utf8_lossy(&data[..end]).into_owned()
+                })
+                .and_then(|path| resolve(&path));
+            let keys = reader.deref(&curve.bezt).ok()
+                .map(|keys| keys.take(curve.totvert as usize).collect::<Vec<_>>());
+            if let (Some(property), Some(keys)) = (property, keys) {
+                properties.entry((property.target, property.name.clone()))
+                    .or_insert_with(|| (property, Vec::new()))
+                    .1
+                    .push((curve.array_index as usize, keys));
+            }
+        });
+
+    let frame_start = action.frame_start;
+    let time_of = |frame: f32| (frame - frame_start) / frames_per_second;
+
+    let channels = properties.into_values().map(|(property, curves)| {
+
+        let components = if property.name == "rotation_quaternion" { 4 } else { 3 };
+        let keyframes = Keyframes::new(&curves, components, &property.defaults, frames_per_second);
+        let times = keyframes.frames.iter().map(|frame| time_of(*frame)).collect::<Vec<_>>();
+
+        let space = &property.space;
+        let vector = |vector: &[f32]| Vector3::new(vector[0], vector[1], vector[2]);
+        let quaternion = |vector: &[f32]| space.rotate(Quaternion::new(vector[0], vector[1], vector[2], vector[3]));
+
+        let (interpolation, values) = match property.name.as_str() {
+            "location" => (keyframes.interpolation, ChannelValues::Translation(keyframes.map_values(
+                |location| space.translation.transform_point(&Point3::from(vector(location))).coords,
+                |tangent| space.translation.transform_vector(&vector(tangent)),
+            ))),
+            "scale" => (keyframes.interpolation, ChannelValues::Scale(keyframes.map_values(
+                |scale| space.scale * vector(scale),
+                |tangent| space.scale * vector(tangent),
+            ))),
+            "rotation_quaternion" => (keyframes.interpolation, ChannelValues::Rotation(keyframes.map_values(quaternion, quaternion))),
+            _ => {
+                let values = keyframes.values.iter().map(|euler| {
+                    space.rotate(UnitQuaternion::from_euler_angles(euler[0], euler[1], euler[2]).into_inner())
+                }).collect();
+                let interpolation = match keyframes.interpolation {
+                    Interpolation::Step => Interpolation::Step,
+                    _ => Interpolation::Linear,
+                };
+                (interpolation, ChannelValues::Rotation(values))
+            }
+        };
+
+        Channel { target: property.target, interpolation, times, values }
+    }).collect();
+
+    // the names of actions are prefixed with 'AC'
+    let name = action.id.name.to_str_unchecked();
+    AnimationClip::new(String::from(name.get(2..).unwrap_or(name)), channels)
+}
+
+/// Splits `pose.bones["name"].property` into the bone's name and the property.
+fn parse_rna_path(path: &str) -> Option<(&str, &str)> {
+    let path = path.strip_prefix("pose.bones[\"")?;
+    let (bone, property) = path.split_once("\"]")?;
+    Some((bone, property.strip_prefix('.')?))
+}
+
+/// The keyframes of a property with several components, e.g. the x, y and z of a location.
+struct Keyframes {
+    frames: Vec<f32>,
+    interpolation: Interpolation,
+    values: Vec<Vec<f32>>,
+    /// The incoming and the outgoing tangent of every keyframe per second, if cubic.
+    tangents: Vec<(Vec<f32>, Vec<f32>)>,
+}
+
+impl Keyframes {
+
+    /// Merges the curves of the components. Curves with different keyframes are resampled
+    /// linearly at the keyframes of all of them.
+    fn new(curves: &[(usize, Vec<&BezTriple>)], components: usize, defaults: &[f32], frames_per_second: f32) -> Keyframes {
+
+        let curve_of = |component: usize| curves.iter()
+            .find(|(index, keys)| *index == component && !keys.is_empty())
+            .map(|(_, keys)| keys);
+
+        let mut frames = curves.iter()
+            .flat_map(|(_, keys)| keys.iter().map(|key| key.vec[1][0]))
+            .collect::<Vec<_>>();
+        frames.sort_by(f32::total_cmp);
+        frames.dedup();
+
+        let aligned = (0..components).filter_map(curve_of).all(|keys| {
+            keys.len() == frames.len() && keys.iter().zip(&frames).all(|(key, frame)| key.vec[1][0] == *frame)
+        });
+
+        let keys = (0..components).filter_map(curve_of).flat_map(|keys| keys.iter()).collect::<Vec<_>>();
+
+        let interpolation = match aligned {
+            true if keys.iter().all(|key| key.ipo == BEZT_IPO_CONST) => Interpolation::Step,
+            true if keys.iter().any(|key| key.ipo != BEZT_IPO_CONST && key.ipo != BEZT_IPO_LIN) => Interpolation::CubicSpline,
+            _ => Interpolation::Linear,
+        };
+
+        let values = frames.iter().enumerate().map(|(key, frame)| {
+            (0..components).map(|component| match curve_of(component) {
+                Some(keys) if aligned => keys[key].vec[1][1],
+                Some(keys) => evaluate_linear(keys, *frame),
+                None => defaults[component],
+            }).collect()
+        }).collect();
+
+        let tangents = match interpolation {
+            Interpolation::CubicSpline => (0..frames.len()).map(|key| {
+                let slope = |component: usize, handle: usize| match curve_of(component) {
+                    Some(keys) => {
+                        let [handle, value] = [keys[key].vec[handle], keys[key].vec[1]];
+                        let frames = value[0] - handle[0];
+                        if frames.abs() > f32::EPSILON { (value[1] - handle[1]) / frames * frames_per_second } else { 0.0 }
+                    }
+                    None => 0.0,
+                };
+                ((0..components).map(|component| slope(component, 0)).collect(), (0..components).map(|component| slope(component, 2)).collect())
+            }).collect(),
+            _ => Vec::new(),
+        };
+
+        Keyframes { frames, interpolation, values, tangents }
+    }
+
+    /// Converts the values and, if cubic, the tangents in the order of a cubic spline channel.
+    fn map_values<T>(&self, value: impl Fn(&[f32]) -> T, tangent: impl Fn(&[f32]) -> T) -> Vec<T> {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.values.iter().zip(&self.tangents).flat_map(|(values, (in_tangent, out_tangent))| {
+                [tangent(in_tangent), value(values), tangent(out_tangent)]
+            }).collect(),
+            _ => self.values.iter().map(|values| value(values)).collect(),
+        }
+    }
+}
+
+fn evaluate_linear(keys: &[&BezTriple], frame: f32) -> f32 {
+    let next = keys.partition_point(|key| key.vec[1][0] <= frame);
+    match next {
+        0 => keys[0].vec[1][1],
+        next if next == keys.len() => keys[next - 1].vec[1][1],
+        next => {
+            let [from, to] = [keys[next - 1].vec[1], keys[next].vec[1]];
+            from[1] + (to[1] - from[1]) * (frame - from[0]) / (to[0] - from[0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hamcrest2::prelude::*;
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    use crate::action::{ChannelSpace, parse_rna_path, z_up_to_y_up};
+
+    #[test]
+    fn test_parse_rna_path() {
+        assert_that!(parse_rna_path("pose.bones[\"upper.L\"].rotation_quaternion"), is(equal_to(Some(("upper.L", "rotation_quaternion")))));
+        assert_that!(parse_rna_path("location"), is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_blender_up_is_engine_up() {
+        let up = z_up_to_y_up().transform_vector(&Vector3::z());
+        assert_that!(up, is(equal_to(Vector3::new(0.0, -1.0, 0.0))));
+        // x,y,z -> x,-z,y keeps the handedness, i.e. it is a rotation and not a mirroring
+        assert_that!(z_up_to_y_up().transform_vector(&Vector3::new(1.0, 2.0, 3.0)), is(equal_to(Vector3::new(1.0, -3.0, 2.0))));
+        assert_that!(z_up_to_y_up().determinant(), is(equal_to(1.0)));
+    }
+
+    #[test]
+    fn test_camera_space_looks_along_the_blender_camera() {
+
+        // a camera rotated by 90° about x looks along +y in Blender, which is +z in y-up coordinates
+        let rotation = UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0.0, 0.0);
+        let rotation = UnitQuaternion::new_normalize(ChannelSpace::camera().rotate(rotation.into_inner()));
+
+        assert_that!((rotation * Vector3::z() - Vector3::z()).norm(), is(less_than(1e-5)));
+        // the camera's up, +z in Blender, is -y
+        assert_that!((rotation * Vector3::y() - Vector3::y()).norm(), is(less_than(1e-5)));
+    }
+}
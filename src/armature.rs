@@ -0,0 +1,96 @@
+use blend_rs::blend::{PointerLike, Reader, StringLike};
+use blend_rs::blender3_3::{bArmature, bDeformGroup, Bone, ListBase, MDeformVert, MDeformWeight, Mesh, Object};
+use nalgebra::Matrix4;
+use skyshard::animation::{Joint, MAX_JOINT_INFLUENCES, Skeleton, Transform};
+
+use crate::action::{matrix_of, z_up_to_y_up};
+
+/// The `type_` of an armature object.
+pub const OB_ARMATURE: i16 = 25;
+
+/// Loads the bones of the armature as joints, in y-up coordinates and bound in the rest pose.
+/// The mesh is expected to be placed at the origin of the armature.
+pub fn load_skeleton(reader: &Reader, armature_object: &Object) -> Skeleton {
+
+    let armature: &bArmature = reader.deref_single(&armature_object.data.as_instance_of::<bArmature>())
+        .expect("armature object should have an armature");
+
+    let mut bones: Vec<(&Bone, Option<usize>)> = Vec::new();
+    collect_bones(reader, &armature.bonebase, None, &mut bones);
+
+    let globals = bones.iter().map(|(bone, _)| matrix_of(&bone.arm_mat)).collect::<Vec<_>>();
+
+    let mut skeleton = Skeleton::new(bones.iter().enumerate().map(|(index, (bone, parent))| {
+        // the conversion is part of the root joints, the bones keep their axes
+        let local = match parent {
+            Some(parent) => globals[*parent].try_inverse().unwrap_or_else(Matrix4::identity) * globals[index],
+            None => z_up_to_y_up() * globals[index],
+        };
+        Joint {
+            name: String::from(bone.name.to_str_unchecked()),
+            parent: *parent,
+            inverse_bind_matrix: Matrix4::identity(),
+            rest: Transform::from_matrix(&local),
+        }
+    }).collect());
+
+    skeleton.bind_rest_pose();
+    skeleton
+}
+
+fn collect_bones<'a>(reader: &'a Reader, list: &'a ListBase, parent: Option<usize>, bones: &mut Vec<(&'a Bone, Option<usize>)>) {
+    reader.traverse_double_linked(&list.first.as_instance_of::<Bone>())
+        .into_iter()
+        .flatten()
+        .for_each(|bone: &Bone| {
+            bones.push((bone, parent));
+            collect_bones(reader, &bone.childbase, Some(bones.len() - 1), bones);
+        });
+}
+
+/// Returns the joints and weights of every vertex of the mesh, the strongest influences of the
+/// vertex groups named after a joint, normalized. Vertices without influences are bound to the
+/// first joint.
+pub fn load_vertex_weights(reader: &Reader, mesh: &Mesh, skeleton: &Skeleton) -> Vec<([u32; 4], [f32; 4])> {
+
+    let groups = reader.traverse_double_linked(&mesh.vertex_group_names.first.as_instance_of::<bDeformGroup>())
+        .into_iter()
+        .flatten()
+        .map(|group: &bDeformGroup| skeleton.joint_index(group.name.to_str_unchecked()))
+        .collect::<Vec<_>>();
+
+    let vertices: Vec<&MDeformVert> = match reader.deref(&mesh.dvert) {
+        Ok(vertices) => vertices.take(mesh.totvert as usize).collect(),
+        Err(_) => return vec![([0; 4], [1.0, 0.0, 0.0, 0.0]); mesh.totvert as usize],
+    };
+
+    vertices.iter().map(|vertex| {
+
+        let mut influences = reader.deref(&vertex.dw).ok()
+            .into_iter()
+            .flat_map(|weights| weights.take(vertex.totweight as usize))
+            .filter_map(|weight: &MDeformWeight| {
+                groups.get(weight.def_nr as usize).copied().flatten().map(|joint| (joint as u32, weight.weight))
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect::<Vec<_>>();
+
+        influences.sort_by(|a, b| b.1.total_cmp(&a.1));
+        influences.truncate(MAX_JOINT_INFLUENCES);
+
+        let total: f32 = influences.iter().map(|(_, weight)| weight).sum();
+
+        if total <= 0.0 {
+            return ([0; 4], [1.0, 0.0, 0.0, 0.0])
+        }
+
+        let mut joints = [0; 4];
+        let mut weights = [0.0; 4];
+        influences.iter().enumerate().for_each(|(index, (joint, weight))| {
+            joints[index] = *joint;
+            weights[index] = weight / total;
+        });
+
+        (joints, weights)
+    }).collect()
+}
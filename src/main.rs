@@ -3,7 +3,8 @@ extern crate winit;
 
 use std::borrow::Borrow;
 use std::ops::{Deref, Mul};
-use std::time::{Duration, SystemTime};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::vec;
 
 use log::{info, LevelFilter};
@@ -23,20 +24,26 @@ use winit::window::{CursorGrabMode, WindowBuilder};
 use blend_rs::blend::{NameLike, PointerLike, StringLike};
 use blend_rs::blend::traverse::Named;
 use blend_rs::blender3_3::{bNode, bNodeTree, DrawDataList, Image, Material, Mesh, MLoop, MLoopUV, MVert, Object};
-use skyshard::{InstanceData, pick_object, Vertex};
+use skyshard::{debug_lines, EngineShaders, InstanceData, overlay, pick_object, request_frame_capture, set_selection, SkinnedVertex, start_frame_sequence, stop_frame_sequence, take_captured_frame, Vertex};
+use skyshard::animation::{AnimationPlayer, PlaybackMode, Transform};
 use skyshard::entity::World;
-use skyshard::graphics::{Camera, Extent};
+use skyshard::graphics::{BloomSettings, Camera, CubeMapData, DirectionalLight, EnvironmentError, EnvironmentLightingSettings, EnvironmentLightingShaders, Extent, HdrImage, Light, MaterialDescriptor, PointLight, VignetteSettings};
 use skyshard::graphics::Projection::PerspectiveProjection;
+use skyshard::mesh::Heightmap;
+use skyshard::noise::{Fractal, generate_texture, Interpolation, Noise, NoiseFunction, TextureSettings};
+use skyshard::curve::Curve;
+use skyshard::particles::{EmitterSettings, EmitterShape, ParticleEmitter};
 use crate::clock::Clock;
 
 use crate::input::{KeyAction, MovementController, MovementControllerSettings};
 use crate::movable::Movable;
 
+mod action;
+mod armature;
 mod clock;
 mod input;
 mod movable;
 mod shaders;
-mod noise;
 
 
 fn main() {
@@ -68,9 +75,8 @@ fn main() {
     let window_width: f32 = 800.0;
     let window_height: f32 = 600.0;
 
-    let window_title_prefix = "rust vulkan example: ";
     let window = WindowBuilder::new()
-        .with_title(window_title_prefix)
+        .with_title("rust vulkan example")
         .with_inner_size(winit::dpi::LogicalSize::new(window_width, window_height,))
         .build(&events_loop)
         .unwrap();
@@ -79,15 +85,27 @@ fn main() {
         let mut engine = skyshard::create(
             "Rust Vulkan Example",
             &window,
-            shaders::vs::shader(),
-            shaders::fs::shader(),
+            EngineShaders {
+                vertex: shaders::vs::shader(),
+                fragment: shaders::fs::shader(),
+                culling: Some(shaders::cs::shader()),
+                skinning: shaders::skinned_vs::shader(),
+                post_processing_vertex: shaders::post_vs::shader(),
+                post_processing_fragment: shaders::post_fs::shader(),
+                debug_vertex: shaders::debug_vs::shader(),
+                debug_fragment: shaders::debug_fs::shader(),
+                overlay_vertex: shaders::overlay_vs::shader(),
+                overlay_fragment: shaders::overlay_fs::shader(),
+                particle_vertex: shaders::particle_vs::shader(),
+                particle_fragment: shaders::particle_fs::shader(),
+            },
         ).unwrap();
 
         let asset_manager = engine.asset_manager();
         let mut clock = Clock::new(0.01);
         let mut world = World::new();
 
-        let cube = {
+        let (cube, animation, object_animation, camera_animation) = {
 
             let transformation1 = Matrix4::<f32>::identity()
                 .append_translation(&Vector3::new(0.0, 0.0, 0.0))
@@ -120,7 +138,13 @@ fn main() {
             let mesh: &Mesh = blend_reader.deref_single(&object.data.as_instance_of::<Mesh>())
                 .unwrap();
 
-            let vertices: Vec<Vertex> = {
+            // objects parented to an armature are deformed by its bones
+            let armature_object: Option<&Object> = blend_reader.deref_single(&object.parent)
+                .ok()
+                .filter(|parent: &&Object| parent.type_ == armature::OB_ARMATURE);
+
+            // the vertices and the index of the mesh's vertex each one originates from
+            let (vertices, vertex_sources): (Vec<Vertex>, Vec<usize>) = {
 
                 let mesh_polygons = blend_reader.deref(&mesh.mpoly)
                     .expect(format!("mesh of object '{}' should have polygons", object_name).as_str());
@@ -137,34 +161,74 @@ fn main() {
                     .expect(format!("mesh of object '{}' should have vertices", object_name).as_str())
                     .collect();
 
-                let mk_vert = |index| {
-                    let uv = mesh_uvs[index as usize].uv;
+                let position_of = |index: i32| {
                     let position = mesh_vertices[mesh_loops[index as usize].v as usize].co;
-                    Vertex {
-                        position: [position[0], position[2] * -1.0, position[1]], // # blender's z-up to y-up: x,y,z -> x,z,-y
+                    // the same conversion as the skeleton's, so that the mesh follows its bones
+                    action::z_up_to_y_up().transform_vector(&Vector3::from(position))
+                };
+
+                let mk_vert = |index, normal: &Vector3<f32>| {
+                    let uv = mesh_uvs[index as usize].uv;
+                    let position = position_of(index);
+                    (Vertex {
+                        position: [position.x, position.y, position.z],
+                        normal: [normal.x, normal.y, normal.z],
                         color: [0.0, 0.0, 0.0],
                         uv: [uv[0], (uv[1] * -1.0) + 1.0], // blender: u,v -> u,1-v
-                    }
+                    }, mesh_loops[index as usize].v as usize)
                 };
 
                 mesh_polygons.fold(Vec::new(), | mut vertices, polygon| {
 
-                        vertices.push(mk_vert(polygon.loopstart));
-                        vertices.push(mk_vert(polygon.loopstart + 1));
-                        vertices.push(mk_vert(polygon.loopstart + 2));
+                        let a = position_of(polygon.loopstart);
+                        let b = position_of(polygon.loopstart + 1);
+                        let c = position_of(polygon.loopstart + 2);
+                        let normal = (b - a).cross(&(c - a)).normalize();
+
+                        vertices.push(mk_vert(polygon.loopstart, &normal));
+                        vertices.push(mk_vert(polygon.loopstart + 1, &normal));
+                        vertices.push(mk_vert(polygon.loopstart + 2, &normal));
 
                         if polygon.totloop == 4 {
-                            vertices.push(mk_vert(polygon.loopstart));
-                            vertices.push(mk_vert(polygon.loopstart + 2));
-                            vertices.push(mk_vert(polygon.loopstart + 3));
+                            vertices.push(mk_vert(polygon.loopstart, &normal));
+                            vertices.push(mk_vert(polygon.loopstart + 2, &normal));
+                            vertices.push(mk_vert(polygon.loopstart + 3, &normal));
                         }
 
                         vertices
                     })
+                    .into_iter()
+                    .unzip()
             };
 
             let indices: Vec<u32> = (0u32..vertices.len() as u32).collect();
 
+            let skin = armature_object.map(|armature_object| {
+
+                let skeleton = armature::load_skeleton(&blend_reader, armature_object);
+                let animation = action::load_bone_action(&blend_reader, armature_object, &skeleton);
+                let weights = armature::load_vertex_weights(&blend_reader, mesh, &skeleton);
+
+                let vertices = vertices.iter().zip(&vertex_sources).map(|(vertex, source)| {
+                    let (joints, weights) = weights[*source];
+                    SkinnedVertex {
+                        position: vertex.position,
+                        normal: vertex.normal,
+                        color: vertex.color,
+                        uv: vertex.uv,
+                        joints,
+                        weights,
+                    }
+                }).collect::<Vec<_>>();
+
+                info!("Loaded armature '{}' with {} bones.", armature_object.id.get_name(), skeleton.joints().len());
+
+                (skeleton, animation, skyshard::mesh::IndexData::compact(&indices), vertices)
+            });
+
+            // the polygons don't share vertices yet, welding makes them reusable by the vertex cache
+            let (indices, vertices) = skyshard::mesh::optimize_mesh(&indices, &vertices, true);
+
             let (texture_extent, texture_data) = {
 
                 let material = blend_reader.deref_single(&mesh.mat.as_instance_of::<DrawDataList>())
@@ -199,41 +263,193 @@ fn main() {
                 (Extent::from(info.width, info.width, 1), Vec::from(bytes))
             };
 
-            skyshard::create_geometry(&mut engine,
-                &indices,
-                &vertices,
-                &texture_data,
-                texture_extent,
-                &vec![
-                    InstanceData {
-                        id: 1,
-                        transformation: transformation1.data
-                            .as_slice()
-                            .try_into()
-                            .expect("slice with incorect length")
-                    },
-                    InstanceData {
-                        id: 2,
-                        transformation: transformation2.data
-                            .as_slice()
-                            .try_into()
-                            .expect("slice with incorect length")
-                    },
+            let instances = vec![
+                InstanceData {
+                    id: 1,
+                    transformation: transformation1.data
+                        .as_slice()
+                        .try_into()
+                        .expect("slice with incorect length")
+                },
+                InstanceData {
+                    id: 2,
+                    transformation: transformation2.data
+                        .as_slice()
+                        .try_into()
+                        .expect("slice with incorect length")
+                },
+                InstanceData {
+                    id: 3,
+                    transformation: transformation3.data
+                        .as_slice()
+                        .try_into()
+                        .expect("slice with incorect length")
+                },
+            ];
+
+            // the object's action animates the first instance
+            let object_animation = action::load_object_action(&blend_reader, object);
+
+            // the first camera with an action replaces the movement controller while it plays
+            let camera_animation = blend_reader.iter::<Object>()
+                .unwrap()
+                .filter(|object| object.type_ == action::OB_CAMERA)
+                .find_map(|object| {
+                    action::load_object_action(&blend_reader, object)
+                        .map(|clip| (action::object_transform(object), clip))
+                });
+
+            match skin {
+                Some((skeleton, animation, indices, vertices)) => {
+                    let mut cube = skyshard::create_geometry(&mut engine, &indices, &vertices, &texture_data, texture_extent, &instances)
+                        .expect("Failed to create the skinned cube");
+                    skyshard::create_skin(&mut engine, &mut cube, skeleton);
+                    (cube, animation, object_animation, camera_animation)
+                }
+                None => {
+                    let cube = skyshard::create_geometry(&mut engine, &indices, &vertices, &texture_data, texture_extent, &instances)
+                        .expect("Failed to create the cube");
+                    (cube, None, object_animation, camera_animation)
+                }
+            }
+        };
+
+        let mut skin_player = AnimationPlayer::new();
+        if let Some(clip) = animation {
+            skin_player.play(clip, PlaybackMode::Loop);
+        }
+
+        let mut object_player = AnimationPlayer::new();
+        if let Some(clip) = object_animation {
+            object_player.play(clip, PlaybackMode::Loop);
+        }
+
+        let mut camera_player = AnimationPlayer::new();
+        let camera_transform = camera_animation.map(|(transform, clip)| {
+            camera_player.play(clip, PlaybackMode::Once);
+            transform
+        });
+
+        world.geometries.push(cube);
+
+        // rolling hills below the objects, one geometry per chunk
+        {
+            const RESOLUTION: usize = 32;
+            const SPACING: f32 = 0.5;
+
+            let noise = Noise::new("skyshard", 256, Interpolation::Linear);
+            let fractal = Fractal { frequency: 0.08, ..Fractal::default() };
+            let chunk_size = RESOLUTION as f32 * SPACING;
+
+            // grass with patches of dry soil, repeating seamlessly from chunk to chunk
+            let ground = generate_texture(&noise, &NoiseFunction::Turbulence(Fractal::default()), &TextureSettings {
+                size: 6.0,
+                tileable: true,
+                ramp: Curve::new(vec![
+                    (0.05, [0.55, 0.45, 0.3, 1.0]),
+                    (0.2, [0.6, 0.7, 0.4, 1.0]),
+                    (0.6, [0.45, 0.6, 0.3, 1.0]),
+                ]),
+                ..TextureSettings::default()
+            });
+
+            [[-1, -1], [0, -1], [-1, 0], [0, 0]].iter().enumerate().for_each(|(index, chunk)| {
+
+                let (indices, vertices) = Heightmap::chunk(&noise, &fractal, *chunk, RESOLUTION, SPACING, 3.0).mesh(1);
+
+                let material = skyshard::create_material(&mut engine, &MaterialDescriptor {
+                    base_color_texture: Some(ground.clone()),
+                    roughness_factor: 0.9,
+                    ..MaterialDescriptor::principled()
+                });
+
+                let transformation = Matrix4::<f32>::identity()
+                    .append_translation(&Vector3::new(chunk[0] as f32 * chunk_size, 3.0, chunk[1] as f32 * chunk_size))
+                    .transpose();
+
+                let instances = vec![
                     InstanceData {
-                        id: 3,
-                        transformation: transformation3.data
+                        id: 100 + index as u32,
+                        transformation: transformation.data
                             .as_slice()
                             .try_into()
-                            .expect("slice with incorect length")
+                            .expect("slice with incorrect length")
                     },
-                ]
-            )
-        };
-
-        world.geometries.push(cube);
+                ];
+
+                world.geometries.push(skyshard::create_geometry_with_material(&mut engine, &indices, &vertices, material, &instances)
+                    .expect("Failed to create a terrain chunk"));
+            });
+        }
+
+        world.post_processing.bloom = Some(BloomSettings::default());
+        world.post_processing.vignette = Some(VignetteSettings::default());
+
+        let environment = std::fs::File::open("assets/environment.hdr")
+            .map_err(EnvironmentError::from)
+            .and_then(|file| HdrImage::read_radiance(std::io::BufReader::new(file)))
+            .unwrap_or_else(|cause| {
+                info!("Using a gradient sky, as no environment could be loaded: {cause}");
+                gradient_sky()
+            });
+
+        let environment = CubeMapData::from_equirectangular(&environment, 256);
+
+        world.skybox = Some(skyshard::create_skybox(
+            &mut engine,
+            shaders::skybox_vs::shader(),
+            shaders::skybox_fs::shader(),
+            &environment,
+        ));
+
+        world.environment_lighting = Some(skyshard::create_environment_lighting(
+            &mut engine,
+            &environment,
+            &EnvironmentLightingSettings::default(),
+            Some(EnvironmentLightingShaders {
+                irradiance: shaders::ibl_irradiance_cs::shader(),
+                prefilter: shaders::ibl_prefilter_cs::shader(),
+                brdf: shaders::ibl_brdf_cs::shader(),
+            }),
+        ));
+
+        world.lights.push(Light::Directional(DirectionalLight {
+            direction: Vector3::new(-0.5, 1.0, 0.75),
+            color: [1.0, 0.95, 0.9],
+            intensity: 1.5,
+            cast_shadows: true,
+        }));
+
+        world.lights.push(Light::Point(PointLight {
+            position: Vector3::new(2.0, -2.0, -2.0),
+            color: [0.4, 0.6, 1.0],
+            intensity: 8.0,
+            range: 10.0,
+        }));
+
+        // sparks falling from the point light, y is down
+        world.particles.push({
+            let mut sparks = ParticleEmitter::new(EmitterSettings {
+                shape: EmitterShape::Sphere { radius: 0.1 },
+                rate: 60.0,
+                lifetime: [1.0, 2.0],
+                speed: [1.0, 2.5],
+                direction: -Vector3::y(),
+                spread: 0.6,
+                acceleration: Vector3::new(0.0, 4.0, 0.0),
+                drag: 0.5,
+                color: Curve::new(vec![(0.0, [4.0, 3.0, 1.5, 1.0]), (0.5, [2.0, 0.8, 0.2, 1.0]), (1.0, [0.5, 0.1, 0.0, 0.0])]),
+                size: Curve::linear(0.08, 0.02),
+                ..EmitterSettings::default()
+            }, 42);
+            sparks.transformation = Matrix4::new_translation(&Vector3::new(2.0, -2.0, -2.0)).transpose();
+            sparks
+        });
 
         let mut redraw_requested = true;
         let mut close_requested = false;
+        let mut recording = false;
+        let mut show_debug_lines = false;
 
         engine.reference_counts();
 
@@ -339,6 +555,38 @@ fn main() {
                                     } => {
                                         sleep_time_millis = 80;
                                     }
+                                    KeyboardInput {
+                                        state: ElementState::Pressed,
+                                        virtual_keycode: Some(VirtualKeyCode::F12),
+                                        ..
+                                    } => {
+                                        // the next rendered frame is saved
+                                        request_frame_capture(&mut engine)
+                                            .unwrap_or_else(|cause| println!("Failed to capture frame: {cause}"));
+                                    }
+                                    KeyboardInput {
+                                        state: ElementState::Pressed,
+                                        virtual_keycode: Some(VirtualKeyCode::F11),
+                                        ..
+                                    } => {
+                                        match recording {
+                                            true => {
+                                                println!("Recorded {} frames", stop_frame_sequence(&mut engine));
+                                                recording = false;
+                                            }
+                                            false => match start_frame_sequence(&mut engine, Path::new("frames")) {
+                                                Ok(()) => recording = true,
+                                                Err(cause) => println!("Failed to record frames: {cause}"),
+                                            },
+                                        }
+                                    }
+                                    KeyboardInput {
+                                        state: ElementState::Pressed,
+                                        virtual_keycode: Some(VirtualKeyCode::F3),
+                                        ..
+                                    } => {
+                                        show_debug_lines = !show_debug_lines;
+                                    }
                                     _ => {}
                                 }
                             }
@@ -351,6 +599,7 @@ fn main() {
                             let object_id: Option<u32> = pick_object(&mut engine, last_cursor_x, last_cursor_y);
                             println!("Picked: {object_id:?} at {last_cursor_x}/{last_cursor_y}");
                             grabbed_object_id = object_id;
+                            set_selection(&mut engine, object_id.as_slice());
 
                             // let ray: Vector4<f32> = {
 
@@ -404,8 +653,11 @@ fn main() {
 
                             let mut cube = &mut world.geometries[0];
 
+                            let mut object_transform = [Transform::identity()];
+                            object_player.apply(&mut object_transform);
+
                             let mut transformations: [Matrix4::<f32>; 3] = [
-                                Matrix4::<f32>::identity()
+                                object_transform[0].to_matrix()
                                     .append_translation(&Vector3::new(0.0, 0.0, 0.0))
                                     .append_translation(&translations[0])
                                     .transpose(),
@@ -448,10 +700,61 @@ fn main() {
                             while let Some(tick) = clock.consume() {
                                 // update all state with Tick(t, dt)
                                 movement_controller.apply(&tick, &mut movable);
-                                camera.view_yxz(movable.translation(), movable.rotation());
+
+                                skin_player.advance(tick.delta);
+                                object_player.advance(tick.delta);
+                                camera_player.advance(tick.delta);
+
+                                world.particles.iter_mut().for_each(|emitter| emitter.advance(tick.delta));
+
+                                match camera_transform {
+                                    Some(transform) if camera_player.is_playing() => {
+                                        let mut transform = [transform];
+                                        camera_player.apply(&mut transform);
+                                        camera.view_transform(&transform[0]);
+                                    }
+                                    _ => camera.view_yxz(movable.translation(), movable.rotation()),
+                                }
+
+                                if let Some(skin) = &mut world.geometries[0].skin {
+                                    skin.pose = skin.skeleton.rest_pose();
+                                    skin_player.apply(&mut skin.pose.local);
+                                }
                             };
 
+                            if show_debug_lines {
+                                let lines = debug_lines(&mut engine);
+                                lines.grid(&Vector3::zeros(), 20.0, 20, [0.5, 0.5, 0.5, 0.5]);
+                                world.geometries.iter().for_each(|geometry| {
+                                    geometry.instances.iter().for_each(|instance| {
+                                        let transformation = Matrix4::from_column_slice(&instance.transformation);
+                                        lines.aabb(&geometry.bounding_box.transform(&transformation), [1.0, 1.0, 0.0, 1.0]);
+                                    });
+                                });
+                                lines.depth_test(false)
+                                    .axes(&Matrix4::identity(), 1.0)
+                                    .depth_test(true);
+                            }
+
+                            {
+                                let stats = format!("{:>4} fps\n{:>4} ms", frames_per_second, clock.frame_time().as_millis());
+                                let overlay = overlay(&mut engine);
+                                let [width, height] = overlay.text_size(&stats, 2.0);
+                                overlay.rect([8.0, 8.0], [width + 16.0, height + 16.0], [0.0, 0.0, 0.0, 0.6])
+                                    .text([16.0, 16.0], &stats, 2.0, [1.0, 1.0, 1.0, 1.0]);
+                            }
+
                             skyshard::render(&mut engine, &mut world, &camera);
+
+                            if let Some(frame) = take_captured_frame(&mut engine) {
+                                let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                                let path = format!("screenshot-{timestamp}.png");
+                                match frame.save(&path) {
+                                    Ok(()) => println!("Captured: {path}"),
+                                    Err(cause) => println!("Failed to capture frame: {cause}"),
+                                }
+                            }
+
                             std::thread::sleep(Duration::from_millis(sleep_time_millis));
 
                             frame_count += 1;
@@ -465,8 +768,6 @@ fn main() {
                                 }
                                 Err(_) => {}
                             };
-
-                            window.set_title(format!("{} {} ms, {} fps", window_title_prefix, clock.frame_time().as_millis(), frames_per_second).as_str())
                         }
                         (_, true) => {
                             println!("Closing");
@@ -497,3 +798,26 @@ fn load_image(filepath: &'static str) -> (Extent, Vec<u8>) {
 mod test {
 
 }
+
+/// An equirectangular panorama of a sky fading from a bright horizon to a deep blue zenith over a
+/// dark ground.
+fn gradient_sky() -> HdrImage {
+
+    let (width, height) = (64, 32);
+
+    let pixels = (0..width * height).map(|index| {
+        // 1.0 at the zenith, 0.0 at the horizon and -1.0 at the nadir
+        let elevation = 1.0 - 2.0 * ((index / width) as f32 + 0.5) / height as f32;
+        match elevation >= 0.0 {
+            true => {
+                let horizon = [1.2, 1.1, 0.9];
+                let zenith = [0.15, 0.3, 0.8];
+                let t = elevation.sqrt();
+                [0, 1, 2].map(|channel| horizon[channel] + (zenith[channel] - horizon[channel]) * t)
+            }
+            false => [0.08, 0.07, 0.06],
+        }
+    }).map(|[r, g, b]| [r, g, b, 1.0]).collect();
+
+    HdrImage { width, height, pixels }
+}
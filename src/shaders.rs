@@ -1,4 +1,3 @@
-
 pub mod vs {
     skyshard_shaders::shader! {
         kind: "Vertex",
@@ -6,26 +5,99 @@ pub mod vs {
             #version 450
             #extension GL_ARB_separate_shader_objects : enable
 
-            layout(binding = 0) uniform UniformBufferObject {
-                mat4 mvp;
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
             } ubo;
 
             layout(location = 0) in vec3 inPosition;
-            layout(location = 1) in vec3 inColor;
-            layout(location = 2) in vec2 inTextCord;
+            layout(location = 1) in vec3 inNormal;
+            layout(location = 2) in vec3 inColor;
+            layout(location = 3) in vec2 inTextCord;
+
+            layout(location = 4) in uint inObjectId;
+            layout(location = 5) in mat4 transformation; // consumes location 5, 6, 7, 8
+
+            layout(location = 0) out uint outObjectId;
+            layout(location = 1) out vec3 outColor;
+            layout(location = 2) out vec2 outTextCord;
+            layout(location = 3) out vec3 outWorldPosition;
+            layout(location = 4) out vec3 outNormal;
+
+            void main() {
+                vec4 world_position = vec4(inPosition, 1.0) * transformation;
+                gl_Position = world_position * ubo.view_projection;
+                outObjectId = inObjectId;
+                outColor = inColor;
+                outTextCord = inTextCord;
+                outWorldPosition = world_position.xyz;
+                outNormal = normalize((vec4(inNormal, 0.0) * transformation).xyz);
+            }
+        "
+    }
+}
+
+pub mod skinned_vs {
+    skyshard_shaders::shader! {
+        kind: "Vertex",
+        src: "
+            #version 450
+            #extension GL_ARB_separate_shader_objects : enable
+
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
+            } ubo;
+
+            layout(set = 3, binding = 0) readonly buffer JointPalette {
+                mat4 joints[];
+            } palette;
+
+            layout(location = 0) in vec3 inPosition;
+            layout(location = 1) in vec3 inNormal;
+            layout(location = 2) in vec3 inColor;
+            layout(location = 3) in vec2 inTextCord;
+
+            layout(location = 4) in uint inObjectId;
+            layout(location = 5) in mat4 transformation; // consumes location 5, 6, 7, 8
 
-            layout(location = 3) in uint inObjectId;
-            layout(location = 4) in mat4 transformation; // consumes location 4, 5, 6, 7
+            layout(location = 10) in uvec4 inJoints;
+            layout(location = 11) in vec4 inWeights;
 
             layout(location = 0) out uint outObjectId;
             layout(location = 1) out vec3 outColor;
             layout(location = 2) out vec2 outTextCord;
+            layout(location = 3) out vec3 outWorldPosition;
+            layout(location = 4) out vec3 outNormal;
 
             void main() {
-                gl_Position = vec4(inPosition, 1.0) * transformation * ubo.mvp;
+                // the joint matrices transform column vectors, the instance transformation row vectors
+                mat4 skin = inWeights.x * palette.joints[inJoints.x]
+                    + inWeights.y * palette.joints[inJoints.y]
+                    + inWeights.z * palette.joints[inJoints.z]
+                    + inWeights.w * palette.joints[inJoints.w];
+
+                vec4 world_position = (skin * vec4(inPosition, 1.0)) * transformation;
+                gl_Position = world_position * ubo.view_projection;
                 outObjectId = inObjectId;
                 outColor = inColor;
                 outTextCord = inTextCord;
+                outWorldPosition = world_position.xyz;
+                outNormal = normalize(((skin * vec4(inNormal, 0.0)) * transformation).xyz);
             }
         "
     }
@@ -37,21 +109,1013 @@ pub mod fs {
         src: "
             #version 450
             #extension GL_ARB_separate_shader_objects : enable
+            #extension GL_EXT_nonuniform_qualifier : enable
+
+            const uint LIGHT_DIRECTIONAL = 0;
+            const uint LIGHT_POINT = 1;
+            const uint LIGHT_SPOT = 2;
+            const uint SHADOW_CASCADES = 4;
+            const float PI = 3.14159265359;
+
+            struct Light {
+                vec4 position;
+                vec4 direction;
+                vec4 color;
+                vec4 cone;
+            };
+
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
+            } ubo;
+
+            layout(set = 0, binding = 1) uniform LightsUniformBufferObject {
+                vec4 ambient;
+                uint count;
+                Light lights[16];
+            } lights;
+
+            layout(set = 0, binding = 2) uniform sampler2DShadow shadow_maps[2];
+
+            layout(set = 0, binding = 3) uniform ShadowsUniformBufferObject {
+                mat4 cascades[8];
+                vec4 splits;
+                vec4 parameters;
+            } shadows;
+
+            layout(set = 0, binding = 4) uniform samplerCube irradiance_map;
+            layout(set = 0, binding = 5) uniform samplerCube specular_map;
+            layout(set = 0, binding = 6) uniform sampler2D brdf_lut;
+
+            layout(push_constant) uniform GeometryConstants {
+                uint receive_shadows;
+                float lod_dither;
+            } geometry;
+
+            const float BAYER[16] = float[16](
+                 0.0 / 16.0,  8.0 / 16.0,  2.0 / 16.0, 10.0 / 16.0,
+                12.0 / 16.0,  4.0 / 16.0, 14.0 / 16.0,  6.0 / 16.0,
+                 3.0 / 16.0, 11.0 / 16.0,  1.0 / 16.0,  9.0 / 16.0,
+                15.0 / 16.0,  7.0 / 16.0, 13.0 / 16.0,  5.0 / 16.0
+            );
+
+            // cross-fades two levels of detail: a positive dither keeps the fragments below its
+            // threshold, a negative one the complementary fragments
+            bool dithered(float dither) {
+                if (dither == 0.0) {
+                    return false;
+                }
+                ivec2 pixel = ivec2(gl_FragCoord.xy) % 4;
+                float threshold = BAYER[pixel.y * 4 + pixel.x];
+                return dither > 0.0 ? threshold >= dither : threshold < -dither;
+            }
+
+            layout(set = 1, binding = 0) uniform MaterialUniformBufferObject {
+                vec4 base_color_factor;
+                vec4 emissive_factor;
+                float metallic_factor;
+                float roughness_factor;
+                float normal_scale;
+                float occlusion_strength;
+                uint base_color_texture;
+                uint metallic_roughness_texture;
+                uint normal_texture;
+                uint occlusion_texture;
+                uint emissive_texture;
+                float alpha_cutoff;
+            } material;
+
+            layout(set = 2, binding = 0) uniform sampler2D textures[];
 
             layout(location = 0) in flat uint inObjectId;
             layout(location = 1) in vec3 inColor;
             layout(location = 2) in vec2 inTextCord;
-
-            layout(set = 1, binding = 0) uniform sampler2D texture_sampler;
+            layout(location = 3) in vec3 inWorldPosition;
+            layout(location = 4) in vec3 inNormal;
 
             layout(location = 0) out vec4 outColor;
             layout(location = 1) out uint outObjectId;
 
+            // Perturbs the normal without precomputed tangents, see: http://www.thetenthplanet.de/archives/1180
+            vec3 perturb_normal(vec3 normal, vec3 view_direction) {
+                vec3 tangent_normal = texture(textures[material.normal_texture], inTextCord).xyz * 2.0 - 1.0;
+                tangent_normal.xy *= material.normal_scale;
+
+                vec3 dp1 = dFdx(-view_direction);
+                vec3 dp2 = dFdy(-view_direction);
+                vec2 duv1 = dFdx(inTextCord);
+                vec2 duv2 = dFdy(inTextCord);
+
+                vec3 dp2perp = cross(dp2, normal);
+                vec3 dp1perp = cross(normal, dp1);
+                vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+                vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+
+                float scale = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+                return normalize(mat3(tangent * scale, bitangent * scale, normal) * tangent_normal);
+            }
+
+            // Returns the fraction of light reaching the fragment, filtered with a 3x3 PCF kernel.
+            float shadow(Light light) {
+
+                int caster = int(light.cone.z);
+                if (caster < 0 || geometry.receive_shadows == 0) {
+                    return 1.0;
+                }
+
+                float depth = (vec4(inWorldPosition, 1.0) * ubo.view).z;
+                if (depth > shadows.splits[SHADOW_CASCADES - 1]) {
+                    return 1.0;
+                }
+
+                uint cascade = 0;
+                while (cascade < SHADOW_CASCADES - 1 && depth > shadows.splits[cascade]) {
+                    cascade++;
+                }
+
+                vec4 position = vec4(inWorldPosition, 1.0) * shadows.cascades[caster * SHADOW_CASCADES + cascade];
+                vec2 quadrant = vec2(cascade % 2, cascade / 2) * 0.5;
+                vec2 uv = quadrant + (position.xy * 0.5 + 0.5) * 0.5;
+                float reference = position.z - shadows.parameters.x;
+                float texel = shadows.parameters.z * shadows.parameters.y;
+
+                float result = 0.0;
+                for (int x = -1; x <= 1; x++) {
+                    for (int y = -1; y <= 1; y++) {
+                        vec2 sample_uv = clamp(uv + vec2(x, y) * texel, quadrant, quadrant + 0.5);
+                        result += texture(shadow_maps[caster], vec3(sample_uv, reference));
+                    }
+                }
+                return result / 9.0;
+            }
+
+            float attenuation(float distance, float range) {
+                float factor = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+                return factor * factor / max(distance * distance, 0.0001);
+            }
+
+            float distribution_ggx(float n_dot_h, float roughness) {
+                float a = roughness * roughness;
+                float a2 = a * a;
+                float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+                return a2 / (PI * denominator * denominator);
+            }
+
+            float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+                float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+                float ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+                float ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+                return ggx_v * ggx_l;
+            }
+
+            vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+                return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+            }
+
+            vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+                return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+            }
+
+            // The image based lighting of the environment with the split-sum approximation, see:
+            // Karis, Real Shading in Unreal Engine 4
+            vec3 environment_lighting(vec3 albedo, float metallic, float roughness, vec3 f0, vec3 normal, vec3 view_direction) {
+
+                float n_dot_v = max(dot(normal, view_direction), 0.0);
+                vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+
+                vec3 diffuse = (vec3(1.0) - fresnel) * (1.0 - metallic) * albedo * texture(irradiance_map, normal).rgb;
+
+                float level = roughness * float(textureQueryLevels(specular_map) - 1);
+                vec3 prefiltered = textureLod(specular_map, reflect(-view_direction, normal), level).rgb;
+                vec2 brdf = texture(brdf_lut, vec2(n_dot_v, roughness)).rg;
+
+                return diffuse + prefiltered * (fresnel * brdf.x + brdf.y);
+            }
+
+            vec3 radiance(Light light, vec3 albedo, float metallic, float roughness, vec3 f0, vec3 normal, vec3 view_direction) {
+
+                uint type = uint(light.position.w);
+                vec3 light_direction;
+                float intensity = light.color.a;
+
+                if (type == LIGHT_DIRECTIONAL) {
+                    light_direction = -light.direction.xyz;
+                    intensity *= shadow(light);
+                }
+                else {
+                    vec3 to_light = light.position.xyz - inWorldPosition;
+                    float distance = length(to_light);
+                    light_direction = to_light / distance;
+                    intensity *= attenuation(distance, light.direction.w);
+
+                    if (type == LIGHT_SPOT) {
+                        float theta = dot(light_direction, -light.direction.xyz);
+                        intensity *= smoothstep(light.cone.y, light.cone.x, theta);
+                    }
+                }
+
+                vec3 halfway = normalize(light_direction + view_direction);
+                float n_dot_l = max(dot(normal, light_direction), 0.0);
+                float n_dot_v = max(dot(normal, view_direction), 0.0001);
+                float n_dot_h = max(dot(normal, halfway), 0.0);
+
+                vec3 fresnel = fresnel_schlick(max(dot(halfway, view_direction), 0.0), f0);
+                float distribution = distribution_ggx(n_dot_h, roughness);
+                float geometry = geometry_smith(n_dot_v, n_dot_l, roughness);
+
+                vec3 specular = distribution * geometry * fresnel / max(4.0 * n_dot_v * n_dot_l, 0.0001);
+                vec3 diffuse = (vec3(1.0) - fresnel) * (1.0 - metallic) * albedo / PI;
+
+                return (diffuse + specular) * light.color.rgb * intensity * n_dot_l;
+            }
+
             void main() {
-                vec3 color = texture(texture_sampler, inTextCord).xyz;
-                outColor = vec4(color, 1.0f);
+                if (dithered(geometry.lod_dither)) {
+                    discard;
+                }
+
+                vec4 base_color = texture(textures[material.base_color_texture], inTextCord) * material.base_color_factor;
+                if (base_color.a < material.alpha_cutoff) {
+                    discard;
+                }
+
+                vec4 metallic_roughness = texture(textures[material.metallic_roughness_texture], inTextCord);
+                float metallic = clamp(metallic_roughness.b * material.metallic_factor, 0.0, 1.0);
+                float roughness = clamp(metallic_roughness.g * material.roughness_factor, 0.04, 1.0);
+                float occlusion = mix(1.0, texture(textures[material.occlusion_texture], inTextCord).r, material.occlusion_strength);
+                vec3 emissive = texture(textures[material.emissive_texture], inTextCord).rgb * material.emissive_factor.rgb;
+
+                vec3 view_direction = normalize(ubo.camera_position.xyz - inWorldPosition);
+                vec3 normal = perturb_normal(normalize(inNormal), view_direction);
+
+                vec3 albedo = base_color.rgb;
+                vec3 f0 = mix(vec3(0.04), albedo, metallic);
+
+                vec3 color = lights.ambient.rgb * albedo * occlusion;
+                color += environment_lighting(albedo, metallic, roughness, f0, normal, view_direction) * occlusion;
+                for (uint index = 0; index < lights.count; index++) {
+                    color += radiance(lights.lights[index], albedo, metallic, roughness, f0, normal, view_direction);
+                }
+                color += emissive;
+
+                outColor = vec4(color, base_color.a);
                 outObjectId = inObjectId;
             }
         "
     }
 }
+
+pub mod cs {
+    skyshard_shaders::shader! {
+        kind: "Compute",
+        src: "
+            #version 450
+
+            // InstanceData is read word by word: the id followed by the 16 floats of the
+            // transformation, padded to 20 words.
+            const uint INSTANCE_WORDS = 20;
+
+            layout(local_size_x = 64) in;
+
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
+            } ubo;
+
+            layout(set = 1, binding = 0) readonly buffer Instances {
+                uint instances[];
+            };
+
+            layout(set = 1, binding = 1) writeonly buffer VisibleInstances {
+                uint visible_instances[];
+            };
+
+            layout(set = 1, binding = 2) buffer DrawCommand {
+                uint index_count;
+                uint instance_count;
+                uint first_index;
+                int vertex_offset;
+                uint first_instance;
+            } command;
+
+            layout(set = 1, binding = 3) writeonly buffer DrawCount {
+                uint draw_count;
+            };
+
+            layout(push_constant) uniform PushConstants {
+                vec4 bounding_sphere;
+                uint instance_count;
+            } geometry;
+
+            mat4 transformation(uint base) {
+                mat4 result;
+                for (uint column = 0; column < 4; column++) {
+                    for (uint row = 0; row < 4; row++) {
+                        result[column][row] = uintBitsToFloat(instances[base + 1 + column * 4 + row]);
+                    }
+                }
+                return result;
+            }
+
+            bool visible(vec3 center, float radius) {
+                mat4 m = ubo.view_projection;
+                vec4 planes[6] = vec4[6](
+                    m[3] + m[0],
+                    m[3] - m[0],
+                    m[3] + m[1],
+                    m[3] - m[1],
+                    m[2],
+                    m[3] - m[2]
+                );
+                for (uint index = 0; index < 6; index++) {
+                    vec4 plane = planes[index] / length(planes[index].xyz);
+                    if (dot(plane.xyz, center) + plane.w < -radius) {
+                        return false;
+                    }
+                }
+                return true;
+            }
+
+            void main() {
+                uint instance = gl_GlobalInvocationID.x;
+                if (instance >= geometry.instance_count) {
+                    return;
+                }
+
+                uint base = instance * INSTANCE_WORDS;
+                mat4 m = transformation(base);
+
+                vec3 center = (vec4(geometry.bounding_sphere.xyz, 1.0) * m).xyz;
+                float scale = max(
+                    length(vec3(m[0][0], m[1][0], m[2][0])),
+                    max(length(vec3(m[0][1], m[1][1], m[2][1])), length(vec3(m[0][2], m[1][2], m[2][2])))
+                );
+
+                if (!visible(center, geometry.bounding_sphere.w * scale)) {
+                    return;
+                }
+
+                uint slot = atomicAdd(command.instance_count, 1u);
+                for (uint word = 0; word < INSTANCE_WORDS; word++) {
+                    visible_instances[slot * INSTANCE_WORDS + word] = instances[base + word];
+                }
+                draw_count = 1;
+            }
+        "
+    }
+}
+
+pub mod post_vs {
+    skyshard_shaders::shader! {
+        kind: "Vertex",
+        src: "
+            #version 450
+
+            layout(location = 0) out vec2 outUV;
+
+            // a single triangle covering the viewport, derived from the vertex index
+            void main() {
+                outUV = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+                gl_Position = vec4(outUV * 2.0 - 1.0, 0.0, 1.0);
+            }
+        "
+    }
+}
+
+pub mod post_fs {
+    skyshard_shaders::shader! {
+        kind: "Fragment",
+        src: "
+            #version 450
+
+            const uint COPY = 0;
+            const uint BLOOM = 1;
+            const uint EXPOSURE = 2;
+            const uint ACES = 3;
+            const uint FILMIC = 4;
+            const uint GAMMA = 5;
+            const uint FXAA = 6;
+            const uint VIGNETTE = 7;
+            const uint OUTLINE = 8;
+
+            layout(set = 0, binding = 0) uniform sampler2D inputImage;
+
+            layout(set = 1, binding = 0) uniform usampler2D objectIds;
+
+            layout(set = 1, binding = 1) uniform SelectionUniformBufferObject {
+                vec4 color;
+                float width;
+                uint count;
+                uvec4 ids[16];
+            } selection;
+
+            layout(push_constant) uniform PushConstants {
+                vec4 parameters;
+                vec2 texel_size;
+                uint effect;
+            } pass;
+
+            layout(location = 0) in vec2 inUV;
+
+            layout(location = 0) out vec4 outColor;
+
+            float luminance(vec3 color) {
+                return dot(color, vec3(0.2126, 0.7152, 0.0722));
+            }
+
+            // threshold, intensity, radius
+            vec3 bloom(vec3 color) {
+                const float weights[5] = float[5](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+                vec3 bright = vec3(0.0);
+                for (int x = -4; x <= 4; x++) {
+                    for (int y = -4; y <= 4; y++) {
+                        vec2 offset = vec2(x, y) * pass.texel_size * pass.parameters.z;
+                        vec3 sampled = texture(inputImage, inUV + offset).rgb;
+                        bright += max(sampled - vec3(pass.parameters.x), vec3(0.0)) * weights[abs(x)] * weights[abs(y)];
+                    }
+                }
+                return color + bright * pass.parameters.y;
+            }
+
+            vec3 aces(vec3 color) {
+                return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+            }
+
+            vec3 hable(vec3 x) {
+                return ((x * (0.15 * x + 0.05) + 0.004) / (x * (0.15 * x + 0.5) + 0.06)) - 0.02 / 0.3;
+            }
+
+            vec3 filmic(vec3 color) {
+                return clamp(hable(color * 2.0) / hable(vec3(11.2)), 0.0, 1.0);
+            }
+
+            vec3 fxaa(vec3 color) {
+                vec3 north_west = texture(inputImage, inUV + vec2(-1.0, -1.0) * pass.texel_size).rgb;
+                vec3 north_east = texture(inputImage, inUV + vec2(1.0, -1.0) * pass.texel_size).rgb;
+                vec3 south_west = texture(inputImage, inUV + vec2(-1.0, 1.0) * pass.texel_size).rgb;
+                vec3 south_east = texture(inputImage, inUV + vec2(1.0, 1.0) * pass.texel_size).rgb;
+
+                float luma_north_west = luminance(north_west);
+                float luma_north_east = luminance(north_east);
+                float luma_south_west = luminance(south_west);
+                float luma_south_east = luminance(south_east);
+                float luma_center = luminance(color);
+
+                float luma_min = min(luma_center, min(min(luma_north_west, luma_north_east), min(luma_south_west, luma_south_east)));
+                float luma_max = max(luma_center, max(max(luma_north_west, luma_north_east), max(luma_south_west, luma_south_east)));
+
+                vec2 direction = vec2(
+                    -((luma_north_west + luma_north_east) - (luma_south_west + luma_south_east)),
+                    (luma_north_west + luma_south_west) - (luma_north_east + luma_south_east)
+                );
+
+                float reduce = max((luma_north_west + luma_north_east + luma_south_west + luma_south_east) * 0.25 * 0.125, 1.0 / 128.0);
+                float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+                direction = clamp(direction * scale, vec2(-8.0), vec2(8.0)) * pass.texel_size;
+
+                vec3 a = 0.5 * (
+                    texture(inputImage, inUV + direction * (1.0 / 3.0 - 0.5)).rgb +
+                    texture(inputImage, inUV + direction * (2.0 / 3.0 - 0.5)).rgb);
+                vec3 b = a * 0.5 + 0.25 * (
+                    texture(inputImage, inUV + direction * -0.5).rgb +
+                    texture(inputImage, inUV + direction * 0.5).rgb);
+
+                float luma_b = luminance(b);
+                return (luma_b < luma_min || luma_b > luma_max) ? a : b;
+            }
+
+            // intensity, radius, softness
+            vec3 vignette(vec3 color) {
+                float distance = length(inUV - vec2(0.5)) * sqrt(2.0);
+                float darkening = smoothstep(pass.parameters.y, pass.parameters.y + pass.parameters.z, distance);
+                return color * mix(1.0, 1.0 - pass.parameters.x, darkening);
+            }
+
+            bool selected(uint id) {
+                if (id == 0) {
+                    return false;
+                }
+                for (uint i = 0; i < selection.count; i++) {
+                    if (selection.ids[i / 4][i % 4] == id) {
+                        return true;
+                    }
+                }
+                return false;
+            }
+
+            bool selected_at(ivec2 position) {
+                ivec2 size = textureSize(objectIds, 0);
+                return selected(texelFetch(objectIds, clamp(position, ivec2(0), size - 1), 0).r);
+            }
+
+            // outlines the pixels around the selected objects within the width and tints the selected ones
+            vec3 outline(vec3 color) {
+                ivec2 position = ivec2(gl_FragCoord.xy);
+                if (selected_at(position)) {
+                    return mix(color, selection.color.rgb, selection.color.a);
+                }
+                int radius = int(ceil(selection.width));
+                for (int x = -radius; x <= radius; x++) {
+                    for (int y = -radius; y <= radius; y++) {
+                        if (length(vec2(x, y)) <= selection.width && selected_at(position + ivec2(x, y))) {
+                            return selection.color.rgb;
+                        }
+                    }
+                }
+                return color;
+            }
+
+            void main() {
+                vec3 color = texture(inputImage, inUV).rgb;
+
+                switch (pass.effect) {
+                    case BLOOM:
+                        color = bloom(color);
+                        break;
+                    case EXPOSURE:
+                        color = color * pass.parameters.x;
+                        break;
+                    case ACES:
+                        color = aces(color);
+                        break;
+                    case FILMIC:
+                        color = filmic(color);
+                        break;
+                    case GAMMA:
+                        color = pow(max(color, vec3(0.0)), vec3(pass.parameters.x));
+                        break;
+                    case FXAA:
+                        color = fxaa(color);
+                        break;
+                    case VIGNETTE:
+                        color = vignette(color);
+                        break;
+                    case OUTLINE:
+                        color = outline(color);
+                        break;
+                    default:
+                        break;
+                }
+
+                outColor = vec4(color, 1.0);
+            }
+        "
+    }
+}
+
+pub mod debug_vs {
+    skyshard_shaders::shader! {
+        kind: "Vertex",
+        src: "
+            #version 450
+
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
+            } ubo;
+
+            layout(location = 0) in vec3 inPosition;
+            layout(location = 2) in vec4 inColor;
+
+            layout(location = 0) out vec4 outColor;
+
+            void main() {
+                gl_Position = vec4(inPosition, 1.0) * ubo.view_projection;
+                outColor = inColor;
+            }
+        "
+    }
+}
+
+pub mod debug_fs {
+    skyshard_shaders::shader! {
+        kind: "Fragment",
+        src: "
+            #version 450
+
+            layout(location = 0) in vec4 inColor;
+
+            layout(location = 0) out vec4 outColor;
+
+            void main() {
+                outColor = inColor;
+            }
+        "
+    }
+}
+
+pub mod particle_vs {
+    skyshard_shaders::shader! {
+        kind: "Vertex",
+        src: "
+            #version 450
+
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
+            } ubo;
+
+            layout(location = 0) in vec3 inPosition;
+            layout(location = 2) in vec4 inColor;
+            layout(location = 3) in vec2 inTexCoord;
+
+            layout(location = 0) out vec4 outColor;
+            layout(location = 1) out vec2 outTexCoord;
+
+            void main() {
+                gl_Position = vec4(inPosition, 1.0) * ubo.view_projection;
+                outColor = inColor;
+                outTexCoord = inTexCoord;
+            }
+        "
+    }
+}
+
+pub mod particle_fs {
+    skyshard_shaders::shader! {
+        kind: "Fragment",
+        src: "
+            #version 450
+
+            layout(location = 0) in vec4 inColor;
+            layout(location = 1) in vec2 inTexCoord;
+
+            layout(location = 0) out vec4 outColor;
+
+            void main() {
+                // a soft disc fading out towards the edges of the billboard
+                float falloff = 1.0 - smoothstep(0.25, 1.0, length(inTexCoord * 2.0 - 1.0));
+                float alpha = inColor.a * falloff;
+                if (alpha <= 0.0) {
+                    discard;
+                }
+                outColor = vec4(inColor.rgb, alpha);
+            }
+        "
+    }
+}
+
+pub mod overlay_vs {
+    skyshard_shaders::shader! {
+        kind: "Vertex",
+        src: "
+            #version 450
+
+            layout(push_constant) uniform PushConstants {
+                vec2 screen_size;
+                uint texture;
+                uint srgb_output;
+            } pc;
+
+            layout(location = 0) in vec2 inPosition;
+            layout(location = 2) in vec4 inColor;
+            layout(location = 3) in vec2 inTexCoord;
+
+            layout(location = 0) out vec4 outColor;
+            layout(location = 1) out vec2 outTexCoord;
+
+            void main() {
+                gl_Position = vec4(inPosition / pc.screen_size * 2.0 - 1.0, 0.0, 1.0);
+                outColor = inColor;
+                outTexCoord = inTexCoord;
+            }
+        "
+    }
+}
+
+pub mod overlay_fs {
+    skyshard_shaders::shader! {
+        kind: "Fragment",
+        src: "
+            #version 450
+            #extension GL_EXT_nonuniform_qualifier : enable
+
+            layout(push_constant) uniform PushConstants {
+                vec2 screen_size;
+                uint texture;
+                uint srgb_output;
+            } pc;
+
+            layout(set = 0, binding = 0) uniform sampler2D textures[];
+
+            layout(location = 0) in vec4 inColor;
+            layout(location = 1) in vec2 inTexCoord;
+
+            layout(location = 0) out vec4 outColor;
+
+            void main() {
+                vec4 color = inColor * texture(textures[pc.texture], inTexCoord);
+                if (pc.srgb_output == 0) {
+                    color.rgb = pow(color.rgb, vec3(1.0 / 2.2));
+                }
+                outColor = color;
+            }
+        "
+    }
+}
+
+pub mod skybox_vs {
+    skyshard_shaders::shader! {
+        kind: "Vertex",
+        src: "
+            #version 450
+
+            layout(set = 0, binding = 0) uniform UniformBufferObject {
+                mat4 view;
+                mat4 projection;
+                mat4 view_projection;
+                mat4 inverse_view_projection;
+                vec4 camera_position;
+                vec4 viewport;
+                float time;
+                float delta_time;
+                uint frame;
+            } ubo;
+
+            layout(location = 0) out vec3 outDirection;
+
+            // a single triangle covering the viewport at the far plane
+            void main() {
+                vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+                vec4 position = vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+                vec4 world_position = position * ubo.inverse_view_projection;
+                outDirection = world_position.xyz - ubo.camera_position.xyz * world_position.w;
+                gl_Position = position;
+            }
+        "
+    }
+}
+
+pub mod skybox_fs {
+    skyshard_shaders::shader! {
+        kind: "Fragment",
+        src: "
+            #version 450
+
+            layout(set = 1, binding = 0) uniform samplerCube environment;
+
+            layout(location = 0) in vec3 inDirection;
+
+            layout(location = 0) out vec4 outColor;
+            layout(location = 1) out uint outObjectId;
+
+            void main() {
+                outColor = vec4(texture(environment, normalize(inDirection)).rgb, 1.0);
+                outObjectId = 0;
+            }
+        "
+    }
+}
+
+pub mod ibl_irradiance_cs {
+    skyshard_shaders::shader! {
+        kind: "Compute",
+        src: "
+            #version 450
+
+            const float PI = 3.14159265359;
+            const float STEP = 0.025;
+
+            layout(local_size_x = 8, local_size_y = 8) in;
+
+            layout(set = 0, binding = 0) uniform samplerCube environment;
+            layout(set = 0, binding = 1, rgba32f) uniform writeonly image2DArray irradiance;
+
+            layout(push_constant) uniform PushConstants {
+                float roughness;
+                uint size;
+                uint samples;
+            } constants;
+
+            // the direction through (s, t) in [-1, 1] on the face, as defined by Vulkan
+            vec3 cube_direction(uint face, vec2 st) {
+                switch (face) {
+                    case 0: return normalize(vec3(1.0, -st.y, -st.x));
+                    case 1: return normalize(vec3(-1.0, -st.y, st.x));
+                    case 2: return normalize(vec3(st.x, 1.0, st.y));
+                    case 3: return normalize(vec3(st.x, -1.0, -st.y));
+                    case 4: return normalize(vec3(st.x, -st.y, 1.0));
+                    default: return normalize(vec3(-st.x, -st.y, -1.0));
+                }
+            }
+
+            void main() {
+                uvec3 texel = gl_GlobalInvocationID;
+                if (texel.x >= constants.size || texel.y >= constants.size) {
+                    return;
+                }
+
+                vec2 st = 2.0 * (vec2(texel.xy) + 0.5) / float(constants.size) - 1.0;
+                vec3 normal = cube_direction(texel.z, st);
+
+                vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+                vec3 tangent = normalize(cross(up, normal));
+                vec3 bitangent = cross(normal, tangent);
+
+                // integrates the hemisphere in uniform steps of the spherical angles
+                vec3 sum = vec3(0.0);
+                float count = 0.0;
+                for (float phi = 0.0; phi < 2.0 * PI; phi += STEP) {
+                    for (float theta = 0.0; theta < 0.5 * PI; theta += STEP) {
+                        vec3 direction = sin(theta) * (cos(phi) * tangent + sin(phi) * bitangent) + cos(theta) * normal;
+                        sum += textureLod(environment, direction, 0.0).rgb * cos(theta) * sin(theta);
+                        count += 1.0;
+                    }
+                }
+
+                // the irradiance divided by PI, as multiplied with the albedo by the lit shader
+                imageStore(irradiance, ivec3(texel), vec4(PI * sum / count, 1.0));
+            }
+        "
+    }
+}
+
+pub mod ibl_prefilter_cs {
+    skyshard_shaders::shader! {
+        kind: "Compute",
+        src: "
+            #version 450
+
+            const float PI = 3.14159265359;
+
+            layout(local_size_x = 8, local_size_y = 8) in;
+
+            layout(set = 0, binding = 0) uniform samplerCube environment;
+            layout(set = 0, binding = 1, rgba32f) uniform writeonly image2DArray specular;
+
+            layout(push_constant) uniform PushConstants {
+                float roughness;
+                uint size;
+                uint samples;
+            } constants;
+
+            // the direction through (s, t) in [-1, 1] on the face, as defined by Vulkan
+            vec3 cube_direction(uint face, vec2 st) {
+                switch (face) {
+                    case 0: return normalize(vec3(1.0, -st.y, -st.x));
+                    case 1: return normalize(vec3(-1.0, -st.y, st.x));
+                    case 2: return normalize(vec3(st.x, 1.0, st.y));
+                    case 3: return normalize(vec3(st.x, -1.0, -st.y));
+                    case 4: return normalize(vec3(st.x, -st.y, 1.0));
+                    default: return normalize(vec3(-st.x, -st.y, -1.0));
+                }
+            }
+
+            vec2 hammersley(uint i, uint n) {
+                return vec2(float(i) / float(n), float(bitfieldReverse(i)) * 2.3283064365386963e-10);
+            }
+
+            vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+                float a = roughness * roughness;
+
+                float phi = 2.0 * PI * xi.x;
+                float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+                float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+                vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+                vec3 tangent = normalize(cross(up, normal));
+                vec3 bitangent = cross(normal, tangent);
+
+                return normalize(tangent * cos(phi) * sin_theta + bitangent * sin(phi) * sin_theta + normal * cos_theta);
+            }
+
+            void main() {
+                uvec3 texel = gl_GlobalInvocationID;
+                if (texel.x >= constants.size || texel.y >= constants.size) {
+                    return;
+                }
+
+                vec2 st = 2.0 * (vec2(texel.xy) + 0.5) / float(constants.size) - 1.0;
+                vec3 normal = cube_direction(texel.z, st);
+
+                if (constants.roughness == 0.0) {
+                    imageStore(specular, ivec3(texel), vec4(textureLod(environment, normal, 0.0).rgb, 1.0));
+                    return;
+                }
+
+                // the view direction is assumed to equal the normal
+                vec3 sum = vec3(0.0);
+                float total_weight = 0.0;
+                for (uint index = 0; index < constants.samples; index++) {
+                    vec3 halfway = importance_sample_ggx(hammersley(index, constants.samples), normal, constants.roughness);
+                    vec3 light = 2.0 * dot(normal, halfway) * halfway - normal;
+                    float weight = dot(normal, light);
+                    if (weight > 0.0) {
+                        sum += textureLod(environment, light, 0.0).rgb * weight;
+                        total_weight += weight;
+                    }
+                }
+
+                imageStore(specular, ivec3(texel), vec4(sum / max(total_weight, 0.0001), 1.0));
+            }
+        "
+    }
+}
+
+pub mod ibl_brdf_cs {
+    skyshard_shaders::shader! {
+        kind: "Compute",
+        src: "
+            #version 450
+
+            const float PI = 3.14159265359;
+
+            layout(local_size_x = 8, local_size_y = 8) in;
+
+            layout(set = 0, binding = 1, rgba32f) uniform writeonly image2D brdf_lut;
+
+            layout(push_constant) uniform PushConstants {
+                float roughness;
+                uint size;
+                uint samples;
+            } constants;
+
+            vec2 hammersley(uint i, uint n) {
+                return vec2(float(i) / float(n), float(bitfieldReverse(i)) * 2.3283064365386963e-10);
+            }
+
+            vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+                float a = roughness * roughness;
+
+                float phi = 2.0 * PI * xi.x;
+                float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+                float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+                vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+                vec3 tangent = normalize(cross(up, normal));
+                vec3 bitangent = cross(normal, tangent);
+
+                return normalize(tangent * cos(phi) * sin_theta + bitangent * sin(phi) * sin_theta + normal * cos_theta);
+            }
+
+            // remapped for image based lighting
+            float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+                float k = roughness * roughness / 2.0;
+                float ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+                float ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+                return ggx_v * ggx_l;
+            }
+
+            void main() {
+                uvec2 texel = gl_GlobalInvocationID.xy;
+                if (texel.x >= constants.size || texel.y >= constants.size) {
+                    return;
+                }
+
+                float n_dot_v = (float(texel.x) + 0.5) / float(constants.size);
+                float roughness = (float(texel.y) + 0.5) / float(constants.size);
+
+                vec3 normal = vec3(0.0, 0.0, 1.0);
+                vec3 view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+                float scale = 0.0;
+                float bias = 0.0;
+                for (uint index = 0; index < constants.samples; index++) {
+                    vec3 halfway = importance_sample_ggx(hammersley(index, constants.samples), normal, roughness);
+                    vec3 light = 2.0 * dot(view, halfway) * halfway - view;
+
+                    float n_dot_l = max(light.z, 0.0);
+                    float n_dot_h = max(halfway.z, 0.0);
+                    float v_dot_h = max(dot(view, halfway), 0.0);
+
+                    if (n_dot_l > 0.0) {
+                        float visibility = geometry_smith(n_dot_v, n_dot_l, roughness) * v_dot_h / (n_dot_h * n_dot_v);
+                        float fresnel = pow(1.0 - v_dot_h, 5.0);
+                        scale += (1.0 - fresnel) * visibility;
+                        bias += fresnel * visibility;
+                    }
+                }
+
+                imageStore(brdf_lut, ivec2(texel), vec4(vec2(scale, bias) / float(constants.samples), 0.0, 1.0));
+            }
+        "
+    }
+}